@@ -3,6 +3,9 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::markdown::SanitizePolicy;
+use crate::search_scope::SearchDefaults;
+
 /// Theme configuration with all CSS variables
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThemeConfig {
@@ -26,6 +29,12 @@ pub struct AppConfig {
     pub confirm_folder_delete: bool,
     #[serde(default)]
     pub custom_settings: HashMap<String, serde_json::Value>,
+    /// Allowlist applied to raw HTML and link URLs in rendered notes
+    #[serde(default)]
+    pub sanitize: SanitizePolicy,
+    /// Files covered by directory search unless a search says otherwise
+    #[serde(default)]
+    pub search: SearchDefaults,
 }
 
 fn default_status_bar_visible() -> bool {
@@ -45,6 +54,8 @@ impl Default for AppConfig {
             confirm_file_delete: true,
             confirm_folder_delete: true,
             custom_settings: HashMap::new(),
+            sanitize: SanitizePolicy::default(),
+            search: SearchDefaults::default(),
         }
     }
 }
@@ -162,6 +173,22 @@ fn get_default_dark_theme() -> ThemeConfig {
     variables.insert("list-marker".to_string(), "#569cd6".to_string());
     variables.insert("hr-color".to_string(), "#3e3e42".to_string());
 
+    // Code token colors
+    variables.insert("token-keyword".to_string(), "#569cd6".to_string());
+    variables.insert("token-type".to_string(), "#4ec9b0".to_string());
+    variables.insert("token-constant".to_string(), "#4fc1ff".to_string());
+    variables.insert("token-string".to_string(), "#ce9178".to_string());
+    variables.insert("token-comment".to_string(), "#6a9955".to_string());
+    variables.insert("token-number".to_string(), "#b5cea8".to_string());
+    variables.insert("token-function".to_string(), "#dcdcaa".to_string());
+
+    // Callout colors
+    variables.insert("callout-note".to_string(), "#4493f8".to_string());
+    variables.insert("callout-tip".to_string(), "#3fb950".to_string());
+    variables.insert("callout-important".to_string(), "#ab7df8".to_string());
+    variables.insert("callout-warning".to_string(), "#d29922".to_string());
+    variables.insert("callout-caution".to_string(), "#f85149".to_string());
+
     ThemeConfig {
         name: "Dark".to_string(),
         author: Some("Loom.md".to_string()),
@@ -204,6 +231,22 @@ fn get_default_light_theme() -> ThemeConfig {
     variables.insert("list-marker".to_string(), "#0066cc".to_string());
     variables.insert("hr-color".to_string(), "#d4d4d4".to_string());
 
+    // Code token colors
+    variables.insert("token-keyword".to_string(), "#0000ff".to_string());
+    variables.insert("token-type".to_string(), "#267f99".to_string());
+    variables.insert("token-constant".to_string(), "#0070c1".to_string());
+    variables.insert("token-string".to_string(), "#a31515".to_string());
+    variables.insert("token-comment".to_string(), "#008000".to_string());
+    variables.insert("token-number".to_string(), "#098658".to_string());
+    variables.insert("token-function".to_string(), "#795e26".to_string());
+
+    // Callout colors
+    variables.insert("callout-note".to_string(), "#0969da".to_string());
+    variables.insert("callout-tip".to_string(), "#1a7f37".to_string());
+    variables.insert("callout-important".to_string(), "#8250df".to_string());
+    variables.insert("callout-warning".to_string(), "#9a6700".to_string());
+    variables.insert("callout-caution".to_string(), "#d1242f".to_string());
+
     ThemeConfig {
         name: "Light".to_string(),
         author: Some("Loom.md".to_string()),
@@ -0,0 +1,174 @@
+use crate::markdown::{extract_links, LinkKind};
+use crate::workspace::percent_decode;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How an export includes the local images a note shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageMode {
+    /// Inline each image as a `data:` URL, giving a single self-contained file
+    #[default]
+    Embed,
+    /// Copy the images into a `<name>_files` folder next to the exported file
+    Copy,
+    /// Keep image sources as written in the note
+    Link,
+}
+
+/// Where `Copy` mode puts images
+pub struct CopyTarget<'a> {
+    pub dir: &'a Path,
+    /// `dir` as referenced from the exported file
+    pub href: &'a str,
+}
+
+/// Image sources to use in the export, keyed by destination as written in
+/// the note, and warnings for images that couldn't be included
+#[derive(Debug, Default)]
+pub struct ExportImages {
+    pub sources: HashMap<String, String>,
+    pub warnings: Vec<String>,
+}
+
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let mime = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Whether an image destination points somewhere other than the local disk
+pub(super) fn is_remote(dest: &str) -> bool {
+    dest.starts_with("data:")
+        || dest
+            .split_once("://")
+            .is_some_and(|(scheme, _)| scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)))
+}
+
+/// File an image destination refers to; relative paths are relative to the note
+pub(super) fn image_path(dest: &str, note_dir: &Path) -> PathBuf {
+    let path = PathBuf::from(percent_decode(dest));
+    if path.is_absolute() {
+        path
+    } else {
+        note_dir.join(path)
+    }
+}
+
+/// File name for a copied image that no earlier image has taken
+fn unique_name(path: &Path, taken: &mut HashSet<String>) -> String {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let extension = path.extension().and_then(|s| s.to_str()).map(|e| format!(".{}", e)).unwrap_or_default();
+
+    let mut name = format!("{}{}", stem, extension);
+    let mut counter = 1;
+    while taken.contains(&name.to_lowercase()) {
+        name = format!("{}-{}{}", stem, counter, extension);
+        counter += 1;
+    }
+    taken.insert(name.to_lowercase());
+    name
+}
+
+/// Work out how each local image of a note is included in an export
+///
+/// `copy_to` is required for `ImageMode::Copy`; the images are copied there
+/// as part of this call. Remote and `data:` images are always left as they are.
+pub fn export_images(
+    content: &str,
+    note_dir: &Path,
+    mode: ImageMode,
+    copy_to: Option<CopyTarget>,
+) -> Result<ExportImages, String> {
+    let mut images = ExportImages::default();
+    if mode == ImageMode::Link {
+        return Ok(images);
+    }
+
+    let mut taken = HashSet::new();
+    for link in extract_links(content) {
+        let dest = link.target;
+        if link.kind != LinkKind::Image || is_remote(&dest) || images.sources.contains_key(&dest) {
+            continue;
+        }
+
+        let path = image_path(&dest, note_dir);
+        if !path.is_file() {
+            images.warnings.push(format!("Image not found: {}", dest));
+            continue;
+        }
+
+        let source = match (mode, &copy_to) {
+            (ImageMode::Copy, Some(target)) => {
+                fs::create_dir_all(target.dir)
+                    .map_err(|e| format!("Failed to create image folder: {}", e))?;
+                let name = unique_name(&path, &mut taken);
+                fs::copy(&path, target.dir.join(&name))
+                    .map_err(|e| format!("Failed to copy image {}: {}", dest, e))?;
+                format!("{}/{}", target.href, name.replace(' ', "%20"))
+            }
+            (ImageMode::Copy, None) => return Err("Copying images needs an output path".to_string()),
+            _ => {
+                let Some(mime) = mime_type(&path) else {
+                    images.warnings.push(format!("Unsupported image type: {}", dest));
+                    continue;
+                };
+                match fs::read(&path) {
+                    Ok(data) => format!("data:{};base64,{}", mime, general_purpose::STANDARD.encode(data)),
+                    Err(e) => {
+                        images.warnings.push(format!("Failed to read image {}: {}", dest, e));
+                        continue;
+                    }
+                }
+            }
+        };
+        images.sources.insert(dest, source);
+    }
+
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_images() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::create_dir_all(root.join("other")).unwrap();
+        fs::write(root.join("assets/my shot.png"), [1u8, 2, 3]).unwrap();
+        fs::write(root.join("other/my shot.png"), [4u8]).unwrap();
+
+        let content = "![a](assets/my%20shot.png) ![b](other/my%20shot.png)\n\
+                       ![c](missing.png) ![d](https://example.com/x.png)\n`![e](assets/no.png)`";
+
+        let embedded = export_images(content, root, ImageMode::Embed, None).unwrap();
+        assert_eq!(embedded.sources["assets/my%20shot.png"], "data:image/png;base64,AQID");
+        assert_eq!(embedded.sources.len(), 2);
+        assert_eq!(embedded.warnings, vec!["Image not found: missing.png"]);
+
+        let out = root.join("out/note_files");
+        let copied = export_images(content, root, ImageMode::Copy, Some(CopyTarget { dir: &out, href: "note_files" })).unwrap();
+        assert_eq!(copied.sources["assets/my%20shot.png"], "note_files/my%20shot.png");
+        assert_eq!(copied.sources["other/my%20shot.png"], "note_files/my shot-1.png".replace(' ', "%20"));
+        assert_eq!(fs::read(out.join("my shot-1.png")).unwrap(), vec![4u8]);
+
+        assert!(export_images(content, root, ImageMode::Link, None).unwrap().sources.is_empty());
+        assert!(export_images(content, root, ImageMode::Copy, None).is_err());
+    }
+}
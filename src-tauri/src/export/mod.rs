@@ -0,0 +1,220 @@
+mod images;
+mod pdf;
+
+pub use images::ImageMode;
+use images::{export_images, CopyTarget};
+pub use pdf::export_pdf;
+
+use crate::config::{
+    get_default_dark_theme_config, get_default_light_theme_config, load_app_config, load_theme, ThemeConfig,
+};
+use crate::markdown::{note_title, render_document, RenderContext, RenderContextHandle};
+use crate::write_file_atomic;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::State;
+
+/// Stylesheet of exported documents, written against the theme variables
+const EXPORT_CSS: &str = include_str!("export.css");
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    /// Theme to style the document with; the folder's current theme when unset
+    pub theme: Option<String>,
+    pub images: ImageMode,
+    /// Document title; the note's title or file name when unset
+    pub title: Option<String>,
+}
+
+/// A rendered standalone HTML document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlExport {
+    pub html: String,
+    /// Problems that didn't stop the export, such as missing images
+    pub warnings: Vec<String>,
+}
+
+/// `:root` rule declaring a theme's variables as CSS custom properties
+///
+/// Names and values that could break out of the declaration are skipped,
+/// since themes can be imported from anywhere.
+pub fn theme_css(theme: &ThemeConfig) -> String {
+    let mut variables: Vec<(&String, &String)> = theme
+        .variables
+        .iter()
+        .filter(|(name, value)| {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !value.contains([';', '{', '}', '<', '>'])
+        })
+        .collect();
+    variables.sort();
+
+    let declarations: String = variables
+        .iter()
+        .map(|(name, value)| format!("  --{}: {};\n", name, value.trim()))
+        .collect();
+    format!(":root {{\n{}}}\n", declarations)
+}
+
+/// Theme an export is styled with
+///
+/// Without a name, the folder's current theme is used. The built-in themes
+/// are available even when the folder has no `.loom` directory yet.
+pub fn theme_for_export(folder_path: Option<String>, name: Option<&str>) -> Result<ThemeConfig, String> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => load_app_config(folder_path.clone()).unwrap_or_default().current_theme,
+    };
+
+    match load_theme(folder_path, &name) {
+        Ok(theme) => Ok(theme),
+        Err(_) if name == "dark" => Ok(get_default_dark_theme_config()),
+        Err(_) if name == "light" => Ok(get_default_light_theme_config()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Title of an exported note: the one given for the export, else the note's
+/// own title, else its file name
+fn document_title(title: Option<&str>, content: &str, note_path: &Path) -> String {
+    title
+        .map(str::to_string)
+        .or_else(|| note_title(content))
+        .or_else(|| note_path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
+/// Content to export: the editor's when given, so unsaved edits are
+/// included without saving them, otherwise the note on disk
+pub fn note_content(note_path: &Path, content: Option<String>) -> Result<String, String> {
+    match content {
+        Some(content) => Ok(content),
+        None => fs::read_to_string(note_path).map_err(|e| format!("Failed to read file: {}", e)),
+    }
+}
+
+/// Render a note to a standalone HTML document
+///
+/// Math is converted to MathML and code blocks are highlighted, so the
+/// document needs no scripts. `output_path` is where the document will be
+/// written; copied images go into a `<name>_files` folder beside it.
+pub fn render_html_export(
+    note_path: &Path,
+    content: &str,
+    output_path: Option<&Path>,
+    theme: &ThemeConfig,
+    options: &ExportOptions,
+    context: &RenderContext,
+) -> Result<HtmlExport, String> {
+    let note_dir = note_path.parent().unwrap_or(Path::new(""));
+
+    let files_name = output_path
+        .and_then(|path| path.file_stem())
+        .map(|stem| format!("{}_files", stem.to_string_lossy()));
+    let copy_to = match (output_path.and_then(Path::parent), &files_name) {
+        (Some(dir), Some(name)) => Some((dir.join(name), name.replace(' ', "%20"))),
+        _ => None,
+    };
+    let images = export_images(
+        content,
+        note_dir,
+        options.images,
+        copy_to.as_ref().map(|(dir, href)| CopyTarget { dir, href }),
+    )?;
+
+    let context = RenderContext {
+        image_sources: images.sources,
+        ..context.clone()
+    };
+    let body = render_document(content, &context);
+
+    let title = document_title(options.title.as_deref(), content, note_path);
+
+    let html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n\
+         <meta name=\"generator\" content=\"Loom\">\n<title>{}</title>\n<style>\n{}\n{}</style>\n</head>\n\
+         <body>\n{}</body>\n</html>\n",
+        html_escape::encode_text(&title),
+        theme_css(theme),
+        EXPORT_CSS,
+        body
+    );
+
+    Ok(HtmlExport { html, warnings: images.warnings })
+}
+
+/// Export a note to a standalone HTML file
+///
+/// Returns the warnings collected along the way, such as images that
+/// couldn't be found.
+#[tauri::command]
+pub fn export_html(
+    path: String,
+    content: Option<String>,
+    output_path: String,
+    options: Option<ExportOptions>,
+    folder_path: Option<String>,
+    render_context: State<RenderContextHandle>,
+) -> Result<Vec<String>, String> {
+    let options = options.unwrap_or_default();
+    let context = render_context.lock()
+        .map(|context| context.clone())
+        .map_err(|e| format!("Failed to acquire render context lock: {}", e))?;
+    let theme = theme_for_export(folder_path, options.theme.as_deref())?;
+
+    let note_path = Path::new(&path);
+    let content = note_content(note_path, content)?;
+    let output_path = Path::new(&output_path);
+    let export = render_html_export(note_path, &content, Some(output_path), &theme, &options, &context)?;
+    write_file_atomic(output_path, &export.html)?;
+
+    Ok(export.warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_css() {
+        let mut theme = get_default_light_theme_config();
+        theme.variables.clear();
+        theme.variables.insert("bg-primary".to_string(), "#fff".to_string());
+        theme.variables.insert("accent-color".to_string(), " rgb(0, 1, 2) ".to_string());
+        theme.variables.insert("bad".to_string(), "red} body{display:none".to_string());
+        theme.variables.insert("bad name".to_string(), "red".to_string());
+
+        assert_eq!(theme_css(&theme), ":root {\n  --accent-color: rgb(0, 1, 2);\n  --bg-primary: #fff;\n}\n");
+    }
+
+    #[test]
+    fn test_render_html_export() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let note = root.join("Spec Draft.md");
+        fs::write(&note, "Intro with $e^x$\n\n![diagram](missing.png)\n").unwrap();
+        fs::write(root.join("Titled.md"), "---\ntitle: Design <Spec>\n---\n# Heading\n").unwrap();
+
+        let theme = get_default_dark_theme_config();
+        let options = ExportOptions::default();
+        let content = note_content(&note, None).unwrap();
+        let export = render_html_export(&note, &content, None, &theme, &options, &RenderContext::default()).unwrap();
+
+        assert!(export.html.starts_with("<!DOCTYPE html>"));
+        assert!(export.html.contains("<title>Spec Draft</title>"));
+        assert!(export.html.contains("--bg-primary: #1e1e1e;"));
+        assert!(export.html.contains("<msup><mi>e</mi><mi>x</mi></msup>"));
+        assert_eq!(export.warnings, vec!["Image not found: missing.png"]);
+
+        let titled_path = root.join("Titled.md");
+        let titled_content = note_content(&titled_path, None).unwrap();
+        let titled = render_html_export(&titled_path, &titled_content, None, &theme, &options, &RenderContext::default())
+            .unwrap();
+        assert!(titled.html.contains("<title>Design &lt;Spec&gt;</title>"));
+        assert!(!titled.html.contains("frontmatter"));
+    }
+}
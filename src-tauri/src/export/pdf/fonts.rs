@@ -0,0 +1,189 @@
+/// The standard PDF fonts used in exports
+///
+/// Every viewer ships these, so nothing has to be embedded. They only cover
+/// the WinAnsi character set; other characters are exported as `?`, see
+/// `unsupported_chars`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+    MonoBold,
+}
+
+/// Helvetica advance widths for ' '..='~', in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // ' '..='/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // '0'..='?'
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // '@'..='O'
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // 'P'..='_'
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // '`'..='o'
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p'..='~'
+];
+
+/// Helvetica-Bold advance widths for ' '..='~'
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, // ' '..='/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, // '0'..='?'
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, // '@'..='O'
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, // 'P'..='_'
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, // '`'..='o'
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584, // 'p'..='~'
+];
+
+impl Font {
+    pub const ALL: [Font; 6] = [
+        Font::Regular,
+        Font::Bold,
+        Font::Italic,
+        Font::BoldItalic,
+        Font::Mono,
+        Font::MonoBold,
+    ];
+
+    pub fn for_style(bold: bool, italic: bool, monospace: bool) -> Font {
+        match (monospace, bold, italic) {
+            (true, true, _) => Font::MonoBold,
+            (true, false, _) => Font::Mono,
+            (false, true, true) => Font::BoldItalic,
+            (false, true, false) => Font::Bold,
+            (false, false, true) => Font::Italic,
+            (false, false, false) => Font::Regular,
+        }
+    }
+
+    /// Name of the font in page resources
+    pub fn resource_name(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+            Font::BoldItalic => "F4",
+            Font::Mono => "F5",
+            Font::MonoBold => "F6",
+        }
+    }
+
+    pub fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Italic => "Helvetica-Oblique",
+            Font::BoldItalic => "Helvetica-BoldOblique",
+            Font::Mono => "Courier",
+            Font::MonoBold => "Courier-Bold",
+        }
+    }
+
+    fn is_bold(self) -> bool {
+        matches!(self, Font::Bold | Font::BoldItalic | Font::MonoBold)
+    }
+
+    fn char_width(self, c: char) -> u16 {
+        if matches!(self, Font::Mono | Font::MonoBold) {
+            return 600;
+        }
+
+        let widths = if self.is_bold() { &HELVETICA_BOLD_WIDTHS } else { &HELVETICA_WIDTHS };
+        match c {
+            ' '..='~' => widths[c as usize - 32],
+            '\u{2022}' => 350,
+            '\u{2014}' => 1000,
+            '\u{2026}' => 1000,
+            _ if self.is_bold() => 611,
+            _ => 556,
+        }
+    }
+
+    /// Width of a string set at `size` points
+    pub fn text_width(self, text: &str, size: f32) -> f32 {
+        let units: u32 = text.chars().map(|c| self.char_width(c) as u32).sum();
+        units as f32 * size / 1000.0
+    }
+}
+
+/// WinAnsi code of a character, `?` for characters the encoding lacks
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+        '\t' => b' ',
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => b'?',
+    }
+}
+
+/// Characters of `text` that the fonts can't show, which `encode_text`
+/// would turn into `?`
+pub fn unsupported_chars(text: &str) -> impl Iterator<Item = char> + '_ {
+    text.chars().filter(|&c| c != '?' && win_ansi(c) == b'?')
+}
+
+/// Text as a PDF string literal in WinAnsi encoding
+///
+/// Bytes outside printable ASCII are written as octal escapes, which keeps
+/// content streams plain ASCII.
+pub fn encode_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for byte in text.chars().map(win_ansi) {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x20..=0x7E => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push(')');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(Font::Regular.text_width("Hi", 10.0), (722.0 + 222.0) / 100.0);
+        assert_eq!(Font::Bold.text_width("Hi", 10.0), (722.0 + 278.0) / 100.0);
+        assert_eq!(Font::Mono.text_width("any text", 10.0), 48.0);
+        assert_eq!(Font::for_style(true, true, false), Font::BoldItalic);
+        assert_eq!(Font::for_style(true, true, true), Font::MonoBold);
+    }
+
+    #[test]
+    fn test_encode_text() {
+        assert_eq!(encode_text("f(x) \\ y"), "(f\\(x\\) \\\\ y)");
+        assert_eq!(encode_text("café – 日"), "(caf\\351 \\226 ?)");
+        assert_eq!(unsupported_chars("café – 日? ∑").collect::<String>(), "日∑");
+    }
+}
@@ -0,0 +1,152 @@
+use super::writer::PdfWriter;
+use std::fs;
+use std::path::Path;
+
+/// Pixel data of an image placed in a PDF
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageData {
+    /// JPEG files go into the PDF unchanged
+    Jpeg { data: Vec<u8>, components: u8 },
+    /// Decoded 8-bit samples, with a separate alpha channel if the image has one
+    Raw { samples: Vec<u8>, components: u8, alpha: Option<Vec<u8>> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: ImageData,
+}
+
+/// Size and color components from the start-of-frame marker of a JPEG
+fn jpeg_frame(data: &[u8]) -> Option<(u32, u32, u8)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    while pos + 9 < data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+
+        // SOF0..SOF15, except DHT, JPG and DAC which share the range
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16::from_be_bytes([data[pos + 5], data[pos + 6]]) as u32;
+            let width = u16::from_be_bytes([data[pos + 7], data[pos + 8]]) as u32;
+            return Some((width, height, data[pos + 9]));
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+fn decode_png(data: &[u8]) -> Result<PdfImage, String> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+    buffer.truncate(frame.buffer_size());
+
+    let (components, has_alpha) = match frame.color_type {
+        png::ColorType::Grayscale => (1, false),
+        png::ColorType::GrayscaleAlpha => (1, true),
+        png::ColorType::Rgb => (3, false),
+        png::ColorType::Rgba => (3, true),
+        png::ColorType::Indexed => return Err("unexpected indexed colors".to_string()),
+    };
+
+    let data = if has_alpha {
+        let stride = components as usize + 1;
+        let samples = buffer.chunks(stride).flat_map(|pixel| pixel[..stride - 1].to_vec()).collect();
+        let alpha = buffer.chunks(stride).map(|pixel| pixel[stride - 1]).collect();
+        ImageData::Raw { samples, components, alpha: Some(alpha) }
+    } else {
+        ImageData::Raw { samples: buffer, components, alpha: None }
+    };
+    Ok(PdfImage { width: frame.width, height: frame.height, data })
+}
+
+/// Load a JPEG or PNG file for placing in a PDF
+///
+/// The error is a warning for the export, naming the image as written in
+/// the note.
+pub fn load_image(path: &Path, dest: &str) -> Result<PdfImage, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read image {}: {}", dest, e))?;
+
+    if let Some((width, height, components)) = jpeg_frame(&data) {
+        return Ok(PdfImage { width, height, data: ImageData::Jpeg { data, components } });
+    }
+    if data.starts_with(b"\x89PNG") {
+        return decode_png(&data).map_err(|e| format!("Failed to decode image {}: {}", dest, e));
+    }
+    Err(format!("Unsupported image type in PDF: {}", dest))
+}
+
+fn color_space(components: u8) -> &'static str {
+    match components {
+        1 => "/DeviceGray",
+        4 => "/DeviceCMYK",
+        _ => "/DeviceRGB",
+    }
+}
+
+impl PdfImage {
+    /// Add the image to a PDF as an image XObject, returning its object number
+    pub fn write(&self, writer: &mut PdfWriter) -> usize {
+        let dict = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8",
+            self.width, self.height
+        );
+        match &self.data {
+            ImageData::Jpeg { data, components } => writer.add_encoded_stream(
+                &format!("{} /ColorSpace {} /Filter /DCTDecode", dict, color_space(*components)),
+                data,
+            ),
+            ImageData::Raw { samples, components, alpha } => {
+                let mask = alpha
+                    .as_ref()
+                    .map(|alpha| writer.add_stream(&format!("{} /ColorSpace /DeviceGray", dict), alpha))
+                    .map(|id| format!(" /SMask {} 0 R", id))
+                    .unwrap_or_default();
+                writer.add_stream(&format!("{} /ColorSpace {}{}", dict, color_space(*components), mask), samples)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jpeg_frame() {
+        // SOI, an APP0 segment, then a baseline SOF0 for a 3-component 640x480 image
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80, 0x03, 0x00, 0x00]);
+        assert_eq!(jpeg_frame(&jpeg), Some((640, 480, 3)));
+        assert_eq!(jpeg_frame(b"GIF89a"), None);
+    }
+
+    #[test]
+    fn test_decode_png_splits_alpha() {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 0, 255, 0, 0, 255, 128]).unwrap();
+        }
+
+        let image = decode_png(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(
+            image.data,
+            ImageData::Raw { samples: vec![255, 0, 0, 0, 0, 255], components: 3, alpha: Some(vec![255, 128]) }
+        );
+    }
+}
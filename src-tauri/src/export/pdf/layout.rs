@@ -0,0 +1,696 @@
+use super::fonts::{encode_text, unsupported_chars, Font};
+use super::images::{load_image, PdfImage};
+use super::palette::{Color, Palette};
+use crate::export::images::{image_path, is_remote};
+use crate::markdown::{Block, CodeToken, ColumnAlignment, TableBlockRow, TextRun, TextStyle};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
+
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.5;
+const TABLE_SIZE: f32 = 10.0;
+const HEADING_SIZES: [f32; 6] = [24.0, 20.0, 16.0, 14.0, 12.0, 11.0];
+const LINE_SPACING: f32 = 1.45;
+/// Indentation per list level and per quote
+const INDENT: f32 = 14.0;
+
+/// Page geometry, in points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSetup {
+    pub width: f32,
+    pub height: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl PageSetup {
+    fn content_width(&self) -> f32 {
+        self.width - self.left - self.right
+    }
+
+    fn content_height(&self) -> f32 {
+        self.height - self.top - self.bottom
+    }
+}
+
+/// A clickable area jumping to a position in the document
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkArea {
+    /// Left, bottom, right and top edge, in PDF coordinates
+    pub rect: [f32; 4],
+    pub page: usize,
+    /// Top of the target, in PDF coordinates
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    /// Content stream operators
+    pub content: String,
+    /// Indexes of the images drawn on the page
+    pub images: Vec<usize>,
+    pub links: Vec<LinkArea>,
+}
+
+/// Where a heading ended up, for the table of contents
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadingEntry {
+    pub level: u8,
+    pub title: String,
+    pub page: usize,
+    /// Top of the heading, in PDF coordinates
+    pub y: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct Layout {
+    pub pages: Vec<Page>,
+    pub images: Vec<PdfImage>,
+    pub headings: Vec<HeadingEntry>,
+    pub warnings: Vec<String>,
+    /// Characters drawn that the standard fonts can't show
+    pub unsupported: BTreeSet<char>,
+}
+
+/// Text of one style set on a line
+#[derive(Debug, Clone)]
+struct Fragment {
+    text: String,
+    font: Font,
+    size: f32,
+    style: TextStyle,
+    width: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TextLine {
+    fragments: Vec<Fragment>,
+    width: f32,
+}
+
+impl TextLine {
+    fn push(&mut self, text: &str, font: Font, size: f32, style: TextStyle) {
+        let width = font.text_width(text, size);
+        self.width += width;
+        match self.fragments.last_mut() {
+            Some(last) if last.font == font && last.size == size && last.style == style => {
+                last.text.push_str(text);
+                last.width += width;
+            }
+            _ => self.fragments.push(Fragment { text: text.to_string(), font, size, style, width }),
+        }
+    }
+
+    fn trim_end(&mut self) {
+        if let Some(last) = self.fragments.last_mut() {
+            let trimmed = last.text.trim_end().len();
+            last.text.truncate(trimmed);
+            let width = last.font.text_width(&last.text, last.size);
+            self.width -= last.width - width;
+            last.width = width;
+        }
+    }
+}
+
+/// Split text into alternating words and runs of whitespace
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (i, c) in text.char_indices() {
+        let space = c.is_whitespace();
+        if in_space.is_some_and(|in_space| in_space != space) {
+            words.push(&text[start..i]);
+            start = i;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Break styled runs into lines no wider than `max_width`
+///
+/// Lines break between words; a word wider than a whole line is broken
+/// between characters.
+fn wrap_runs(runs: &[TextRun], size: f32, bold: bool, max_width: f32) -> Vec<TextLine> {
+    let mut lines = Vec::new();
+    let mut line = TextLine::default();
+
+    for run in runs {
+        let style = run.style;
+        let monospace = style.code || style.math;
+        let font = Font::for_style(bold || style.bold, style.italic, monospace);
+        let size = if monospace { size * 0.9 } else { size };
+
+        for word in words(&run.text) {
+            if word.starts_with(char::is_whitespace) {
+                if !line.fragments.is_empty() {
+                    line.push(" ", font, size, style);
+                }
+                continue;
+            }
+
+            let width = font.text_width(word, size);
+            if line.width + width > max_width && !line.fragments.is_empty() {
+                line.trim_end();
+                lines.push(std::mem::take(&mut line));
+            }
+
+            if width <= max_width {
+                line.push(word, font, size, style);
+                continue;
+            }
+            for c in word.chars() {
+                let mut buf = [0; 4];
+                let c = c.encode_utf8(&mut buf);
+                if line.width + font.text_width(c, size) > max_width && !line.fragments.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c, font, size, style);
+            }
+        }
+    }
+
+    line.trim_end();
+    lines.push(line);
+    lines
+}
+
+fn plain_text(runs: &[TextRun]) -> String {
+    runs.iter().map(|run| run.text.as_str()).collect()
+}
+
+/// How a block of wrapped text is set
+struct TextBox {
+    indent: f32,
+    size: f32,
+    bold: bool,
+    color: Color,
+    /// Quote bars to draw beside each line, one per level, over a background
+    quotes: usize,
+}
+
+/// Lays blocks out onto pages, top to bottom
+pub struct Layouter<'a> {
+    setup: PageSetup,
+    palette: &'a Palette,
+    note_dir: &'a Path,
+    layout: Layout,
+    /// The page being filled; `layout.pages` holds the pages before it
+    current: Page,
+    /// Distance of the cursor from the top edge of the page
+    y: f32,
+}
+
+impl<'a> Layouter<'a> {
+    pub fn new(setup: PageSetup, palette: &'a Palette, note_dir: &'a Path) -> Self {
+        Self {
+            setup,
+            palette,
+            note_dir,
+            layout: Layout::default(),
+            current: Page::default(),
+            y: setup.top,
+        }
+    }
+
+    pub fn finish(mut self) -> Layout {
+        self.layout.pages.push(self.current);
+        self.layout
+    }
+
+    fn page_index(&self) -> usize {
+        self.layout.pages.len()
+    }
+
+    fn at_page_top(&self) -> bool {
+        self.y <= self.setup.top
+    }
+
+    /// Start a new page unless `height` fits below the cursor
+    fn reserve(&mut self, height: f32) {
+        if self.y + height > self.setup.height - self.setup.bottom && !self.at_page_top() {
+            self.layout.pages.push(std::mem::take(&mut self.current));
+            self.y = self.setup.top;
+        }
+    }
+
+    /// Convert a distance from the top edge to a PDF coordinate
+    fn pdf_y(&self, y: f32) -> f32 {
+        self.setup.height - y
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let bottom = self.pdf_y(y + height);
+        let [r, g, b] = color;
+        let _ = writeln!(self.current.content, "{:.3} {:.3} {:.3} rg {:.2} {:.2} {:.2} {:.2} re f", r, g, b, x, bottom, width, height);
+    }
+
+    fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let bottom = self.pdf_y(y + height);
+        let [r, g, b] = color;
+        let _ = writeln!(self.current.content, "{:.3} {:.3} {:.3} RG 0.5 w {:.2} {:.2} {:.2} {:.2} re S", r, g, b, x, bottom, width, height);
+    }
+
+    fn line(&mut self, x1: f32, x2: f32, y: f32, thickness: f32, color: Color) {
+        let y = self.pdf_y(y);
+        let [r, g, b] = color;
+        let _ = writeln!(
+            self.current.content,
+            "{:.3} {:.3} {:.3} RG {:.2} w {:.2} {:.2} m {:.2} {:.2} l S",
+            r, g, b, thickness, x1, y, x2, y
+        );
+    }
+
+    fn text(&mut self, x: f32, baseline: f32, font: Font, size: f32, color: Color, text: &str) {
+        let y = self.pdf_y(baseline);
+        let [r, g, b] = color;
+        self.layout.unsupported.extend(unsupported_chars(text));
+        let _ = writeln!(
+            self.current.content,
+            "BT /{} {:.2} Tf {:.3} {:.3} {:.3} rg {:.2} {:.2} Td {} Tj ET",
+            font.resource_name(), size, r, g, b, x, y, encode_text(text)
+        );
+    }
+
+    fn draw_line(&mut self, line: &TextLine, mut x: f32, baseline: f32, color: Color) {
+        for fragment in &line.fragments {
+            let style = fragment.style;
+            let size = fragment.size;
+            let color = if style.link {
+                self.palette.link
+            } else if style.code {
+                self.palette.code
+            } else {
+                color
+            };
+
+            if style.code {
+                let code_bg = self.palette.code_bg;
+                self.fill_rect(x - 1.0, baseline - size, fragment.width + 2.0, size * 1.3, code_bg);
+            }
+            self.text(x, baseline, fragment.font, size, color, &fragment.text);
+            if style.strikethrough {
+                self.line(x, x + fragment.width, baseline - size * 0.3, 0.6, color);
+            }
+            if style.link {
+                self.line(x, x + fragment.width, baseline + 1.5, 0.5, color);
+            }
+            x += fragment.width;
+        }
+    }
+
+    /// Set runs as wrapped lines, calling `first_line` with the baseline of
+    /// the first one so markers end up on the same page as it
+    fn text_box(&mut self, runs: &[TextRun], text_box: TextBox, mut first_line: impl FnMut(&mut Self, f32)) {
+        let left = self.setup.left + text_box.indent;
+        let lines = wrap_runs(runs, text_box.size, text_box.bold, self.setup.content_width() - text_box.indent);
+        let line_height = text_box.size * LINE_SPACING;
+
+        for (i, line) in lines.iter().enumerate() {
+            self.reserve(line_height);
+            if text_box.quotes > 0 {
+                let (bar, background) = (self.palette.quote_bar, self.palette.quote_bg);
+                let quote_left = left - INDENT * text_box.quotes as f32 + 2.0;
+                self.fill_rect(quote_left, self.y, self.setup.width - self.setup.right - quote_left, line_height, background);
+                for level in 0..text_box.quotes {
+                    self.fill_rect(quote_left + INDENT * level as f32, self.y, 3.0, line_height, bar);
+                }
+            }
+
+            let baseline = self.y + text_box.size * 1.1;
+            if i == 0 {
+                first_line(self, baseline);
+            }
+            self.draw_line(line, left, baseline, text_box.color);
+            self.y += line_height;
+        }
+    }
+
+    pub fn block(&mut self, block: &Block) {
+        match block {
+            Block::Heading { level, runs, .. } => self.heading(*level, runs),
+            Block::Paragraph(runs) => {
+                let text = TextBox { indent: 0.0, size: BODY_SIZE, bold: false, color: self.palette.text, quotes: 0 };
+                self.text_box(runs, text, |_, _| {});
+            }
+            Block::ListItem { indent, marker, task, runs } => self.list_item(*indent, marker, *task, runs),
+            Block::Quote { depth, runs } => {
+                let indent = INDENT * *depth as f32;
+                let text = TextBox { indent, size: BODY_SIZE, bold: false, color: self.palette.text, quotes: *depth };
+                self.text_box(runs, text, |_, _| {});
+            }
+            Block::Code { lines, .. } => self.code(lines),
+            Block::Math(latex) => self.math(latex),
+            Block::Table { alignments, rows } => self.table(alignments, rows),
+            Block::Image { dest, alt } => self.image(dest, alt),
+            Block::Rule => {
+                self.reserve(BODY_SIZE);
+                let (left, right, y, color) = (
+                    self.setup.left,
+                    self.setup.width - self.setup.right,
+                    self.y + BODY_SIZE / 2.0,
+                    self.palette.rule,
+                );
+                self.line(left, right, y, 1.0, color);
+                self.y += BODY_SIZE;
+            }
+            Block::Blank => {
+                if !self.at_page_top() {
+                    self.y += BODY_SIZE * 0.6;
+                }
+            }
+        }
+    }
+
+    fn heading(&mut self, level: u8, runs: &[TextRun]) {
+        let index = (level.clamp(1, 6) - 1) as usize;
+        let size = HEADING_SIZES[index];
+        if !self.at_page_top() {
+            self.y += size * 0.5;
+        }
+        // Keep the heading together with a line of what follows it
+        self.reserve(size * LINE_SPACING + BODY_SIZE * LINE_SPACING);
+
+        let entry = HeadingEntry {
+            level,
+            title: plain_text(runs),
+            page: self.page_index(),
+            y: self.pdf_y(self.y),
+        };
+        self.layout.headings.push(entry);
+
+        let text = TextBox { indent: 0.0, size, bold: true, color: self.palette.headings[index], quotes: 0 };
+        self.text_box(runs, text, |_, _| {});
+
+        if level <= 2 {
+            let (left, right, y, color) = (self.setup.left, self.setup.width - self.setup.right, self.y + 1.0, self.palette.border);
+            self.line(left, right, y, 0.75, color);
+            self.y += 6.0;
+        } else {
+            self.y += size * 0.25;
+        }
+    }
+
+    fn list_item(&mut self, indent: usize, marker: &str, task: Option<bool>, runs: &[TextRun]) {
+        let marker_x = self.setup.left + (indent / 2) as f32 * INDENT;
+        let text_indent = marker_x - self.setup.left + INDENT + if task.is_some() { INDENT } else { 0.0 };
+        let color = self.palette.list_marker;
+        let marker = marker.to_string();
+
+        let text = TextBox { indent: text_indent, size: BODY_SIZE, bold: false, color: self.palette.text, quotes: 0 };
+        self.text_box(runs, text, |layouter, baseline| {
+            let width = Font::Regular.text_width(&marker, BODY_SIZE);
+            layouter.text(marker_x + INDENT - width - 4.0, baseline, Font::Regular, BODY_SIZE, color, &marker);
+
+            if let Some(checked) = task {
+                let (x, size) = (marker_x + INDENT, BODY_SIZE * 0.75);
+                let top = baseline - size;
+                layouter.stroke_rect(x, top, size, size, color);
+                if checked {
+                    let bottom = layouter.pdf_y(top + size);
+                    let [r, g, b] = color;
+                    let _ = writeln!(
+                        layouter.current.content,
+                        "{:.3} {:.3} {:.3} RG 1 w {:.2} {:.2} m {:.2} {:.2} l {:.2} {:.2} l S",
+                        r, g, b,
+                        x + size * 0.2, bottom + size * 0.5,
+                        x + size * 0.45, bottom + size * 0.2,
+                        x + size * 0.8, bottom + size * 0.8
+                    );
+                }
+            }
+        });
+    }
+
+    fn code(&mut self, lines: &[Vec<CodeToken>]) {
+        let padding = 6.0;
+        let line_height = CODE_SIZE * 1.4;
+        let columns = ((self.setup.content_width() - 2.0 * padding) / Font::Mono.text_width(" ", CODE_SIZE)).max(1.0) as usize;
+        let (left, width, background) = (self.setup.left, self.setup.content_width(), self.palette.code_bg);
+
+        self.y += 4.0;
+        self.reserve(padding + line_height);
+        self.fill_rect(left, self.y, width, padding / 2.0, background);
+        self.y += padding / 2.0;
+
+        for tokens in lines {
+            // Break the line every `columns` characters, keeping token colors
+            let mut rows: Vec<Vec<(Color, String)>> = vec![Vec::new()];
+            let mut column = 0;
+            for token in tokens {
+                let color = self.palette.token_color(token.kind);
+                for c in token.text.chars() {
+                    if column == columns {
+                        rows.push(Vec::new());
+                        column = 0;
+                    }
+                    let row = rows.last_mut().unwrap();
+                    match row.last_mut() {
+                        Some((last, text)) if *last == color => text.push(c),
+                        _ => row.push((color, c.to_string())),
+                    }
+                    column += 1;
+                }
+            }
+
+            for row in rows {
+                self.reserve(line_height);
+                self.fill_rect(left, self.y, width, line_height, background);
+                let mut x = left + padding;
+                let baseline = self.y + CODE_SIZE * 1.05;
+                for (color, text) in row {
+                    self.text(x, baseline, Font::Mono, CODE_SIZE, color, &text);
+                    x += Font::Mono.text_width(&text, CODE_SIZE);
+                }
+                self.y += line_height;
+            }
+        }
+
+        self.fill_rect(left, self.y, width, padding / 2.0, background);
+        self.y += padding / 2.0 + 4.0;
+    }
+
+    /// Math blocks show their LaTeX source, centered
+    fn math(&mut self, latex: &str) {
+        let line_height = BODY_SIZE * LINE_SPACING;
+        let color = self.palette.text;
+        self.y += 4.0;
+        for line in latex.lines() {
+            let line = line.trim();
+            self.reserve(line_height);
+            let width = Font::Mono.text_width(line, BODY_SIZE);
+            let x = self.setup.left + ((self.setup.content_width() - width) / 2.0).max(0.0);
+            let baseline = self.y + BODY_SIZE * 1.1;
+            self.text(x, baseline, Font::Mono, BODY_SIZE, color, line);
+            self.y += line_height;
+        }
+        self.y += 4.0;
+    }
+
+    fn table(&mut self, alignments: &[ColumnAlignment], rows: &[TableBlockRow]) {
+        let padding = 4.0;
+        let line_height = TABLE_SIZE * LINE_SPACING;
+        let column_width = self.setup.content_width() / alignments.len().max(1) as f32;
+
+        self.y += 4.0;
+        for row in rows {
+            let cells: Vec<Vec<TextLine>> = row
+                .cells
+                .iter()
+                .map(|cell| wrap_runs(cell, TABLE_SIZE, row.header, column_width - 2.0 * padding))
+                .collect();
+            let line_count = cells.iter().map(Vec::len).max().unwrap_or(1).max(1);
+            let height = line_count as f32 * line_height + 2.0 * padding;
+
+            self.reserve(height);
+            if row.header {
+                let (left, width, background) = (self.setup.left, self.setup.content_width(), self.palette.table_header_bg);
+                self.fill_rect(left, self.y, width, height, background);
+            }
+
+            for (column, lines) in cells.iter().enumerate() {
+                let x = self.setup.left + column as f32 * column_width;
+                let border = self.palette.border;
+                self.stroke_rect(x, self.y, column_width, height, border);
+
+                for (i, line) in lines.iter().enumerate() {
+                    let line_x = match alignments.get(column) {
+                        Some(ColumnAlignment::Right) => x + column_width - padding - line.width,
+                        Some(ColumnAlignment::Center) => x + (column_width - line.width) / 2.0,
+                        _ => x + padding,
+                    };
+                    let baseline = self.y + padding + i as f32 * line_height + TABLE_SIZE * 1.1;
+                    let color = self.palette.text;
+                    self.draw_line(line, line_x, baseline, color);
+                }
+            }
+            self.y += height;
+        }
+        self.y += 6.0;
+    }
+
+    fn image(&mut self, dest: &str, alt: &str) {
+        let path = image_path(dest, self.note_dir);
+        let image = if is_remote(dest) {
+            Err(format!("Remote image left out of PDF: {}", dest))
+        } else if !path.is_file() {
+            Err(format!("Image not found: {}", dest))
+        } else {
+            load_image(&path, dest)
+        };
+
+        let image = match image {
+            Ok(image) => image,
+            Err(warning) => {
+                self.layout.warnings.push(warning);
+                let placeholder = TextRun {
+                    text: format!("[Image: {}]", if alt.is_empty() { dest } else { alt }),
+                    style: TextStyle { italic: true, ..TextStyle::default() },
+                };
+                let text = TextBox { indent: 0.0, size: BODY_SIZE, bold: false, color: self.palette.muted, quotes: 0 };
+                self.text_box(&[placeholder], text, |_, _| {});
+                return;
+            }
+        };
+
+        // Pixels at 96 dpi, shrunk to fit on a page
+        let natural = (image.width as f32 * 0.75, image.height as f32 * 0.75);
+        let scale = (self.setup.content_width() / natural.0)
+            .min(self.setup.content_height() / natural.1)
+            .min(1.0);
+        let (width, height) = (natural.0 * scale, natural.1 * scale);
+
+        self.y += 4.0;
+        self.reserve(height);
+        let index = self.layout.images.len();
+        self.layout.images.push(image);
+        let (x, bottom) = (self.setup.left, self.pdf_y(self.y + height));
+        let page = &mut self.current;
+        page.images.push(index);
+        let _ = writeln!(page.content, "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q", width, height, x, bottom, index);
+        self.y += height + 4.0;
+    }
+
+    /// Lay out a table of contents for `headings`, whose pages come after
+    /// `page_offset` pages; entries link to their headings
+    pub fn contents(&mut self, headings: &[HeadingEntry], page_offset: usize) {
+        let title = [TextRun { text: "Contents".to_string(), style: TextStyle::default() }];
+        let text = TextBox { indent: 0.0, size: HEADING_SIZES[1], bold: true, color: self.palette.headings[1], quotes: 0 };
+        self.text_box(&title, text, |_, _| {});
+        self.y += 8.0;
+
+        let line_height = BODY_SIZE * 1.6;
+        let right = self.setup.width - self.setup.right;
+        let dot_width = Font::Regular.text_width(".", BODY_SIZE);
+
+        for heading in headings {
+            self.reserve(line_height);
+            let bold = heading.level == 1;
+            let font = if bold { Font::Bold } else { Font::Regular };
+            let x = self.setup.left + (heading.level.saturating_sub(1)) as f32 * INDENT;
+            let number = (heading.page + page_offset + 1).to_string();
+            let number_width = font.text_width(&number, BODY_SIZE);
+
+            // Shorten titles that would run into the page number
+            let available = right - x - number_width - 4.0 * dot_width;
+            let mut title = heading.title.clone();
+            if font.text_width(&title, BODY_SIZE) > available {
+                while !title.is_empty() && font.text_width(&format!("{}…", title), BODY_SIZE) > available {
+                    title.pop();
+                }
+                title = format!("{}…", title.trim_end());
+            }
+
+            let baseline = self.y + BODY_SIZE * 1.1;
+            let (text_color, muted) = (self.palette.text, self.palette.muted);
+            self.text(x, baseline, font, BODY_SIZE, text_color, &title);
+
+            let dots_start = x + font.text_width(&title, BODY_SIZE) + dot_width;
+            let dots_end = right - number_width - dot_width;
+            let dots = ((dots_end - dots_start) / dot_width).max(0.0) as usize;
+            if dots > 0 {
+                self.text(dots_end - dots as f32 * dot_width, baseline, Font::Regular, BODY_SIZE, muted, &".".repeat(dots));
+            }
+            self.text(right - number_width, baseline, font, BODY_SIZE, text_color, &number);
+
+            let link = LinkArea {
+                rect: [x, self.pdf_y(self.y + line_height), right, self.pdf_y(self.y)],
+                page: heading.page + page_offset,
+                y: heading.y,
+            };
+            self.current.links.push(link);
+            self.y += line_height;
+        }
+    }
+}
+
+/// Add a header or footer line to a page, centered in the top or bottom margin
+pub fn running_text(page: &mut Page, setup: &PageSetup, color: Color, text: &str, top: bool) {
+    let size = 9.0;
+    let width = Font::Regular.text_width(text, size);
+    let x = ((setup.width - width) / 2.0).max(setup.left);
+    let baseline = if top {
+        setup.height - setup.top / 2.0
+    } else {
+        setup.bottom / 2.0 - size / 2.0
+    };
+    let [r, g, b] = color;
+    let _ = writeln!(
+        page.content,
+        "BT /{} {:.2} Tf {:.3} {:.3} {:.3} rg {:.2} {:.2} Td {} Tj ET",
+        Font::Regular.resource_name(), size, r, g, b, x, baseline, encode_text(text)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::document_blocks;
+
+    const SETUP: PageSetup = PageSetup { width: 300.0, height: 200.0, top: 20.0, right: 20.0, bottom: 20.0, left: 20.0 };
+
+    fn run(text: &str) -> TextRun {
+        TextRun { text: text.to_string(), style: TextStyle::default() }
+    }
+
+    #[test]
+    fn test_wrap_runs() {
+        let lines = wrap_runs(&[run("alpha beta gamma "), run("supercalifragilistic")], 10.0, false, 60.0);
+        let text: Vec<String> = lines
+            .iter()
+            .map(|line| line.fragments.iter().map(|f| f.text.as_str()).collect())
+            .collect();
+
+        assert_eq!(text, vec!["alpha beta", "gamma", "supercalifragi", "listic"]);
+        assert!(lines.iter().all(|line| line.width <= 60.0));
+    }
+
+    #[test]
+    fn test_layout_breaks_pages_and_records_headings() {
+        let mut content = "# Intro\n".to_string();
+        for i in 0..20 {
+            content.push_str(&format!("Line {}\n", i));
+        }
+        content.push_str("## Details\nMore\n");
+
+        let palette = Palette::default();
+        let mut layouter = Layouter::new(SETUP, &palette, Path::new(""));
+        for block in document_blocks(&content) {
+            layouter.block(&block);
+        }
+        let layout = layouter.finish();
+
+        assert!(layout.pages.len() >= 2);
+        assert_eq!(layout.headings.len(), 2);
+        assert_eq!((layout.headings[0].page, layout.headings[0].y), (0, 180.0));
+        assert_eq!(layout.headings[1].page, layout.pages.len() - 1);
+        assert!(layout.pages[0].content.contains("(Intro) Tj"));
+    }
+}
@@ -0,0 +1,337 @@
+mod fonts;
+mod images;
+mod layout;
+mod palette;
+mod writer;
+
+use fonts::{unsupported_chars, Font};
+use layout::{running_text, Layouter, Page, PageSetup};
+use palette::Palette;
+use writer::{text_string, PdfWriter};
+
+use super::{document_title, note_content, theme_for_export};
+use crate::config::ThemeConfig;
+use crate::markdown::document_blocks;
+use crate::write_file_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+    /// Width and height in millimetres
+    Custom { width: f32, height: f32 },
+}
+
+impl PageSize {
+    /// Width and height in millimetres, in portrait orientation
+    fn dimensions(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+            PageSize::Custom { width, height } => (width, height),
+        }
+    }
+}
+
+/// Page margins in millimetres
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Margins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Self { top: 20.0, right: 20.0, bottom: 20.0, left: 20.0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    pub landscape: bool,
+    pub margins: Margins,
+    /// Text at the top of every page; `{title}`, `{page}` and `{pages}` are
+    /// filled in, and an empty string leaves the header out
+    pub header: String,
+    /// Text at the bottom of every page, with the same placeholders
+    pub footer: String,
+    /// Start the document with a table of contents linking to its headings
+    pub toc: bool,
+    /// Theme to take colors from; the folder's current theme when unset
+    pub theme: Option<String>,
+    /// Document title; the note's title or file name when unset
+    pub title: Option<String>,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::default(),
+            landscape: false,
+            margins: Margins::default(),
+            header: "{title}".to_string(),
+            footer: "Page {page} of {pages}".to_string(),
+            toc: false,
+            theme: None,
+            title: None,
+        }
+    }
+}
+
+impl PdfOptions {
+    fn page_setup(&self) -> Result<PageSetup, String> {
+        let (width, height) = self.page_size.dimensions();
+        let (width, height) = if self.landscape { (height, width) } else { (width, height) };
+        let margins = self.margins;
+
+        let setup = PageSetup {
+            width: width * POINTS_PER_MM,
+            height: height * POINTS_PER_MM,
+            top: margins.top.max(0.0) * POINTS_PER_MM,
+            right: margins.right.max(0.0) * POINTS_PER_MM,
+            bottom: margins.bottom.max(0.0) * POINTS_PER_MM,
+            left: margins.left.max(0.0) * POINTS_PER_MM,
+        };
+        // At least an inch of room for content either way
+        if setup.width - setup.left - setup.right < 72.0 || setup.height - setup.top - setup.bottom < 72.0 {
+            return Err("Page is too small for its margins".to_string());
+        }
+        Ok(setup)
+    }
+}
+
+/// A rendered PDF document
+#[derive(Debug, Clone)]
+pub struct PdfExport {
+    pub bytes: Vec<u8>,
+    /// Problems that didn't stop the export, such as missing images
+    pub warnings: Vec<String>,
+}
+
+fn fill_placeholders(template: &str, title: &str, page: usize, pages: usize) -> String {
+    template
+        .replace("{title}", title)
+        .replace("{page}", &page.to_string())
+        .replace("{pages}", &pages.to_string())
+}
+
+/// Write laid out pages as a PDF file
+fn write_pdf(pages: &[Page], images: &[images::PdfImage], setup: &PageSetup, title: &str) -> Vec<u8> {
+    let mut writer = PdfWriter::new();
+    let catalog = writer.reserve();
+    let page_tree = writer.reserve();
+
+    let fonts: String = Font::ALL
+        .iter()
+        .map(|font| {
+            let id = writer.add(format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font.base_font()
+            ));
+            format!("/{} {} 0 R ", font.resource_name(), id)
+        })
+        .collect();
+    let image_ids: Vec<usize> = images.iter().map(|image| image.write(&mut writer)).collect();
+    let page_ids: Vec<usize> = pages.iter().map(|_| writer.reserve()).collect();
+
+    for (page, id) in pages.iter().zip(&page_ids) {
+        let content = writer.add_stream("", page.content.as_bytes());
+        let x_objects: String = page
+            .images
+            .iter()
+            .map(|&index| format!("/Im{} {} 0 R ", index, image_ids[index]))
+            .collect();
+        let annotations: Vec<String> = page
+            .links
+            .iter()
+            .map(|link| {
+                let [x1, y1, x2, y2] = link.rect;
+                let annotation = writer.add(format!(
+                    "<< /Type /Annot /Subtype /Link /Rect [{:.2} {:.2} {:.2} {:.2}] /Border [0 0 0] /Dest [{} 0 R /XYZ null {:.2} null] >>",
+                    x1, y1, x2, y2, page_ids[link.page], link.y
+                ));
+                format!("{} 0 R", annotation)
+            })
+            .collect();
+
+        writer.set(
+            *id,
+            format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << {}>> /XObject << {}>> >> /Contents {} 0 R /Annots [{}] >>",
+                page_tree, setup.width, setup.height, fonts, x_objects, content, annotations.join(" ")
+            ),
+        );
+    }
+
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    writer.set(page_tree, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_ids.len()));
+    writer.set(catalog, format!("<< /Type /Catalog /Pages {} 0 R >>", page_tree));
+    let info = writer.add(format!("<< /Title {} /Producer (Loom) >>", text_string(title)));
+
+    writer.finish(catalog, info)
+}
+
+/// Warning for characters the standard fonts can't show, which are
+/// exported as `?`
+fn font_warning(unsupported: &BTreeSet<char>) -> Option<String> {
+    if unsupported.is_empty() {
+        return None;
+    }
+    let mut shown: String = unsupported.iter().take(20).collect();
+    if unsupported.len() > 20 {
+        shown.push('…');
+    }
+    Some(format!(
+        "The PDF fonts only cover Latin characters, so these are shown as ?: {}. Export to HTML to keep them.",
+        shown
+    ))
+}
+
+/// Render a note to a PDF document
+///
+/// Layout is done here rather than by a browser, so this works without a
+/// window. Colors come from `theme` where they suit a white page.
+pub fn render_pdf(
+    note_path: &Path,
+    content: &str,
+    theme: &ThemeConfig,
+    options: &PdfOptions,
+) -> Result<PdfExport, String> {
+    let note_dir = note_path.parent().unwrap_or(Path::new(""));
+    let title = document_title(options.title.as_deref(), content, note_path);
+    let setup = options.page_setup()?;
+    let palette = Palette::from_theme(theme);
+
+    let mut body = Layouter::new(setup, &palette, note_dir);
+    for block in document_blocks(content) {
+        body.block(&block);
+    }
+    let body = body.finish();
+    let mut unsupported = body.unsupported.clone();
+
+    let mut pages = Vec::new();
+    if options.toc && !body.headings.is_empty() {
+        // The contents' own length shifts the page numbers it lists
+        let mut draft = Layouter::new(setup, &palette, note_dir);
+        draft.contents(&body.headings, 0);
+        let offset = draft.finish().pages.len();
+
+        let mut contents = Layouter::new(setup, &palette, note_dir);
+        contents.contents(&body.headings, offset);
+        let contents = contents.finish();
+        unsupported.extend(contents.unsupported);
+        pages.extend(contents.pages);
+    }
+    pages.extend(body.pages);
+
+    let total = pages.len();
+    for (index, page) in pages.iter_mut().enumerate() {
+        for (template, top) in [(&options.header, true), (&options.footer, false)] {
+            if !template.is_empty() {
+                let text = fill_placeholders(template, &title, index + 1, total);
+                unsupported.extend(unsupported_chars(&text));
+                running_text(page, &setup, palette.muted, &text, top);
+            }
+        }
+    }
+
+    let mut warnings = body.warnings;
+    warnings.extend(font_warning(&unsupported));
+
+    Ok(PdfExport {
+        bytes: write_pdf(&pages, &body.images, &setup, &title),
+        warnings,
+    })
+}
+
+/// Export a note to a PDF file
+///
+/// Returns the warnings collected along the way, such as images that
+/// couldn't be included.
+#[tauri::command]
+pub fn export_pdf(
+    path: String,
+    content: Option<String>,
+    output_path: String,
+    options: Option<PdfOptions>,
+    folder_path: Option<String>,
+) -> Result<Vec<String>, String> {
+    let options = options.unwrap_or_default();
+    let theme = theme_for_export(folder_path, options.theme.as_deref())?;
+
+    let note_path = Path::new(&path);
+    let content = note_content(note_path, content)?;
+    let export = render_pdf(note_path, &content, &theme, &options)?;
+    write_file_atomic(Path::new(&output_path), &export.bytes)?;
+
+    Ok(export.warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_light_theme_config;
+
+    fn count(pdf: &[u8], needle: &str) -> usize {
+        pdf.windows(needle.len()).filter(|window| *window == needle.as_bytes()).count()
+    }
+
+    #[test]
+    fn test_page_setup() {
+        let options = PdfOptions { page_size: PageSize::Letter, landscape: true, ..PdfOptions::default() };
+        let setup = options.page_setup().unwrap();
+        assert_eq!((setup.width.round(), setup.height.round()), (792.0, 612.0));
+        assert!((setup.left - 56.69).abs() < 0.01);
+
+        let cramped = PdfOptions { margins: Margins { left: 100.0, right: 100.0, ..Margins::default() }, ..PdfOptions::default() };
+        assert!(cramped.page_setup().is_err());
+    }
+
+    #[test]
+    fn test_render_pdf() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let note = root.join("Spec.md");
+        let mut content = "# Overview\n\nIntro with **bold** and `code`.\n\n![chart](chart.gif)\n\n".to_string();
+        for i in 0..80 {
+            content.push_str(&format!("- Item {}\n", i));
+        }
+        content.push_str("## Details\n```rust\nfn main() {}\n```\n");
+
+        let theme = get_default_light_theme_config();
+        let options = PdfOptions { toc: true, ..PdfOptions::default() };
+        let export = render_pdf(&note, &content, &theme, &options).unwrap();
+        let pdf = &export.bytes;
+
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        let pages = count(pdf, "/Type /Page ");
+        assert!(pages >= 3);
+        assert_eq!(count(pdf, "/Subtype /Link"), 2);
+        assert_eq!(count(pdf, "/Title (Overview)"), 1);
+        assert_eq!(export.warnings, vec!["Image not found: chart.gif"]);
+
+        let plain = render_pdf(&note, &content, &theme, &PdfOptions::default()).unwrap();
+        assert_eq!(count(&plain.bytes, "/Type /Page "), pages - 1);
+
+        let export = render_pdf(&note, "# 概要\n\nNaïve café ∑\n", &theme, &PdfOptions::default()).unwrap();
+        assert_eq!(export.warnings.len(), 1);
+        assert!(export.warnings[0].contains("shown as ?: ∑概要."), "{}", export.warnings[0]);
+    }
+}
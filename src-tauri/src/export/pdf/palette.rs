@@ -0,0 +1,195 @@
+use crate::config::ThemeConfig;
+use crate::markdown::TokenKind;
+
+/// An RGB color with components between 0 and 1
+pub type Color = [f32; 3];
+
+const WHITE: Color = [1.0, 1.0, 1.0];
+
+/// Colors a PDF is drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub text: Color,
+    pub muted: Color,
+    pub accent: Color,
+    pub headings: [Color; 6],
+    pub link: Color,
+    pub code: Color,
+    pub code_bg: Color,
+    pub quote_bar: Color,
+    pub quote_bg: Color,
+    pub border: Color,
+    pub table_header_bg: Color,
+    pub list_marker: Color,
+    pub rule: Color,
+    /// Colors of highlighted code, in the order of `TokenKind::ALL`
+    pub tokens: [Color; 7],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let blue = rgb(0x0066cc);
+        Self {
+            text: rgb(0x1e1e1e),
+            muted: rgb(0x6e6e6e),
+            accent: rgb(0x007acc),
+            headings: [blue, rgb(0x267f99), rgb(0x795e26), blue, rgb(0xaf00db), rgb(0x6e6e6e)],
+            link: blue,
+            code: rgb(0xa31515),
+            code_bg: rgb(0xf5f5f5),
+            quote_bar: rgb(0x007acc),
+            quote_bg: rgb(0xf5f5f5),
+            border: rgb(0xd4d4d4),
+            table_header_bg: rgb(0xe8e8e8),
+            list_marker: blue,
+            rule: rgb(0xd4d4d4),
+            tokens: [0x0000ff, 0x267f99, 0x0070c1, 0xa31515, 0x008000, 0x098658, 0x795e26].map(rgb),
+        }
+    }
+}
+
+fn rgb(hex: u32) -> Color {
+    [(hex >> 16) as u8, (hex >> 8) as u8, hex as u8].map(|c| c as f32 / 255.0)
+}
+
+/// Parse a `#rgb`, `#rrggbb`, `#rrggbbaa` or `rgb()` color; alpha is ignored
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let hex = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 | 8 => hex[..6].to_string(),
+            _ => return None,
+        };
+        return u32::from_str_radix(&hex, 16).ok().map(rgb);
+    }
+
+    let inner = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))?
+        .strip_suffix(')')?;
+    let components: Vec<f32> = inner
+        .split(',')
+        .take(3)
+        .map(|c| c.trim().parse::<f32>().ok().map(|c| c.clamp(0.0, 255.0) / 255.0))
+        .collect::<Option<_>>()?;
+    match components.as_slice() {
+        [r, g, b] => Some([*r, *g, *b]),
+        _ => None,
+    }
+}
+
+/// Relative luminance as defined by WCAG
+fn luminance(color: Color) -> f32 {
+    let [r, g, b] = color.map(|c| {
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn contrast(a: Color, b: Color) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+impl Palette {
+    /// Palette taking the theme's colors where they work on paper
+    ///
+    /// Pages are always white, so a dark theme's light text colors would be
+    /// unreadable. Text colors are only taken when they contrast well enough
+    /// with white, and background colors only when they are light; the rest
+    /// keep the defaults.
+    pub fn from_theme(theme: &ThemeConfig) -> Self {
+        let defaults = Palette::default();
+        let color = |name: &str| theme.variables.get(name).and_then(|value| parse_color(value));
+        let text = |name: &str, fallback: Color| color(name).filter(|c| contrast(*c, WHITE) >= 3.0).unwrap_or(fallback);
+        let background = |name: &str, fallback: Color| color(name).filter(|c| luminance(*c) >= 0.85).unwrap_or(fallback);
+
+        let mut headings = defaults.headings;
+        for (level, heading) in headings.iter_mut().enumerate() {
+            *heading = text(&format!("h{}-color", level + 1), *heading);
+        }
+
+        // Themes from before token colors existed get the colors the HTML
+        // export falls back to
+        let tokens = TokenKind::ALL.map(|kind| {
+            let fallback = match kind {
+                TokenKind::Keyword => text("accent-color", defaults.accent),
+                TokenKind::Type => headings[1],
+                TokenKind::Constant => headings[4],
+                TokenKind::String => headings[2],
+                TokenKind::Comment => text("text-secondary", defaults.muted),
+                TokenKind::Number => headings[3],
+                TokenKind::Function => text("link-color", defaults.link),
+            };
+            text(kind.theme_variable(), fallback)
+        });
+
+        Self {
+            text: color("text-primary").filter(|c| contrast(*c, WHITE) >= 4.5).unwrap_or(defaults.text),
+            muted: text("text-secondary", defaults.muted),
+            accent: text("accent-color", defaults.accent),
+            headings,
+            link: text("link-color", defaults.link),
+            code: text("code-color", defaults.code),
+            code_bg: background("code-bg", defaults.code_bg),
+            quote_bar: text("blockquote-border", defaults.quote_bar),
+            quote_bg: background("blockquote-bg", defaults.quote_bg),
+            border: background("table-border", defaults.border),
+            table_header_bg: background("table-header-bg", defaults.table_header_bg),
+            list_marker: text("list-marker", defaults.list_marker),
+            rule: background("hr-color", defaults.rule),
+            tokens,
+        }
+    }
+
+    /// Color of highlighted code
+    pub fn token_color(&self, kind: Option<TokenKind>) -> Color {
+        match kind {
+            None => self.text,
+            Some(kind) => TokenKind::ALL
+                .iter()
+                .position(|&k| k == kind)
+                .map_or(self.text, |index| self.tokens[index]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_default_dark_theme_config, get_default_light_theme_config};
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#fff"), Some(WHITE));
+        assert_eq!(parse_color(" #0066ccff "), Some(rgb(0x0066cc)));
+        assert_eq!(parse_color("rgba(255, 0, 0, 0.5)"), Some([1.0, 0.0, 0.0]));
+        assert_eq!(parse_color("var(--accent)"), None);
+    }
+
+    #[test]
+    fn test_palette_from_theme() {
+        let light = Palette::from_theme(&get_default_light_theme_config());
+        assert_eq!(light, Palette::default());
+
+        // Light text and dark backgrounds of a dark theme fall back to the defaults
+        let mut dark = get_default_dark_theme_config();
+        dark.variables.insert("h1-color".to_string(), "#8b0000".to_string());
+        let palette = Palette::from_theme(&dark);
+        assert_eq!(palette.text, Palette::default().text);
+        assert_eq!(palette.code_bg, Palette::default().code_bg);
+        assert_eq!(palette.headings[0], rgb(0x8b0000));
+
+        // Themes without token colors fall back to their accent and heading colors
+        let mut older = get_default_light_theme_config();
+        older.variables.retain(|name, _| !name.starts_with("token-"));
+        let palette = Palette::from_theme(&older);
+        assert_eq!(palette.token_color(Some(TokenKind::Keyword)), palette.accent);
+        assert_eq!(palette.token_color(Some(TokenKind::String)), palette.headings[2]);
+    }
+}
@@ -0,0 +1,122 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// Assembles the objects of a PDF file and its cross-reference table
+///
+/// Object numbers can be reserved before their content is known, so pages
+/// and their parent can refer to each other.
+#[derive(Default)]
+pub struct PdfWriter {
+    objects: Vec<Option<Vec<u8>>>,
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number for an object whose content is set later
+    pub fn reserve(&mut self) -> usize {
+        self.objects.push(None);
+        self.objects.len()
+    }
+
+    pub fn set(&mut self, id: usize, body: impl Into<Vec<u8>>) {
+        self.objects[id - 1] = Some(body.into());
+    }
+
+    pub fn add(&mut self, body: impl Into<Vec<u8>>) -> usize {
+        let id = self.reserve();
+        self.set(id, body);
+        id
+    }
+
+    /// Add a stream whose data is already encoded; `dict` holds its filter
+    pub fn add_encoded_stream(&mut self, dict: &str, data: &[u8]) -> usize {
+        let dict = format!("{} /Length {}", dict, data.len());
+        let mut body = format!("<< {} >>\nstream\n", dict.trim_start()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.add(body)
+    }
+
+    /// Add a stream, compressing its data
+    pub fn add_stream(&mut self, dict: &str, data: &[u8]) -> usize {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder.write_all(data).and_then(|_| encoder.finish());
+        match compressed {
+            Ok(compressed) => self.add_encoded_stream(&format!("{} /Filter /FlateDecode", dict), &compressed),
+            Err(_) => self.add_encoded_stream(dict, data),
+        }
+    }
+
+    /// The complete file, with `root` as the catalog and `info` as the
+    /// document information dictionary
+    pub fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+
+        for (index, object) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            out.extend_from_slice(object.as_deref().unwrap_or(b"null"));
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1).as_bytes());
+        for offset in offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+                self.objects.len() + 1,
+                root,
+                info,
+                xref
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+/// A text string for the document outside page content, such as its title
+///
+/// Plain ASCII stays readable; anything else is written as UTF-16.
+pub fn text_string(text: &str) -> String {
+    if text.chars().all(|c| (' '..='~').contains(&c)) {
+        let escaped = text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+        return format!("({})", escaped);
+    }
+
+    let hex: String = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+    format!("<FEFF{}>", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pdf: &[u8], needle: &str) -> usize {
+        pdf.windows(needle.len()).position(|window| window == needle.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_finish_writes_xref() {
+        let mut writer = PdfWriter::new();
+        let root = writer.reserve();
+        let info = writer.add(format!("<< /Title {} >>", text_string("Spec (v2)")));
+        writer.set(root, "<< /Type /Catalog >>");
+        let pdf = writer.finish(root, info);
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.contains(&format!("{:010} 00000 n \n", find(&pdf, "2 0 obj"))));
+        assert!(text.contains("<< /Title (Spec \\(v2\\)) >>"));
+        assert!(text.contains("/Size 3 /Root 1 0 R /Info 2 0 R"));
+        assert!(text.ends_with(&format!("startxref\n{}\n%%EOF\n", find(&pdf, "xref\n"))));
+        assert_eq!(text_string("Café"), "<FEFF00430061006600E9>");
+    }
+}
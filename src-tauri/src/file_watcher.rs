@@ -1,8 +1,10 @@
 use notify::{Watcher, RecursiveMode, Event};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Emitter};
 use serde::{Serialize, Deserialize};
+use crate::search_index::sync_search_index;
+use crate::workspace::sync_changed_paths;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileSystemEvent {
@@ -38,48 +40,57 @@ impl FileWatcherState {
         }
 
         // Create a new watcher
+        let root = path.clone();
         let watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
             match res {
                 Ok(event) => {
                     // Filter out events we don't care about
-                    match event.kind {
-                        notify::EventKind::Create(_) |
-                        notify::EventKind::Remove(_) |
-                        notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => {
-                            // Determine the event type
-                            let event_type = match event.kind {
-                                notify::EventKind::Create(_) => "create",
-                                notify::EventKind::Remove(_) => "delete",
-                                notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => "rename",
-                                _ => "modify",
-                            };
-
-                            // Get the first path from the event
-                            if let Some(path) = event.paths.first() {
-                                let path_str = path.to_string_lossy().to_string();
-
-                                // Skip hidden files/folders (starting with .)
-                                if let Some(name) = path.file_name() {
-                                    let name_str = name.to_string_lossy();
-                                    if name_str.starts_with('.') {
-                                        return;
-                                    }
-                                }
-
-                                let fs_event = FileSystemEvent {
-                                    event_type: event_type.to_string(),
-                                    path: path_str,
-                                };
-
-                                // Emit the event to the frontend
-                                if let Err(e) = app_handle.emit("file-system-change", fs_event) {
-                                    eprintln!("Failed to emit file system event: {}", e);
-                                }
-                            }
-                        }
+                    let event_type = match event.kind {
+                        notify::EventKind::Create(_) => "create",
+                        notify::EventKind::Remove(_) => "delete",
+                        notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => "rename",
+                        notify::EventKind::Modify(notify::event::ModifyKind::Data(_)) |
+                        notify::EventKind::Modify(notify::event::ModifyKind::Any) => "modify",
                         _ => {
-                            // Ignore other event types (metadata changes, content modifications, etc.)
+                            // Ignore other event types (metadata changes, access, etc.)
+                            return;
                         }
+                    };
+
+                    // Skip hidden files/folders (starting with .) and anything inside
+                    // them, such as .loom, but keep the other side of a rename such
+                    // as a temp file replacing a note
+                    let paths: Vec<PathBuf> = event.paths.iter()
+                        .filter(|path| {
+                            !path.strip_prefix(&root)
+                                .unwrap_or(path)
+                                .components()
+                                .any(|part| part.as_os_str().to_string_lossy().starts_with('.'))
+                        })
+                        .cloned()
+                        .collect();
+
+                    let Some(path) = paths.first() else {
+                        return;
+                    };
+
+                    // Keep the workspace indexes current before the frontend hears about it
+                    sync_changed_paths(&app_handle, &paths);
+                    sync_search_index(&app_handle, &paths);
+
+                    // Content changes only matter to the indexes, not the file tree
+                    if event_type == "modify" {
+                        return;
+                    }
+
+                    let fs_event = FileSystemEvent {
+                        event_type: event_type.to_string(),
+                        path: path.to_string_lossy().to_string(),
+                    };
+
+                    // Emit the event to the frontend
+                    if let Err(e) = app_handle.emit("file-system-change", fs_event) {
+                        eprintln!("Failed to emit file system event: {}", e);
                     }
                 }
                 Err(e) => eprintln!("File watcher error: {:?}", e),
@@ -0,0 +1,287 @@
+use super::{html, is_url, Conversion, ImageSource, ImportTarget, ImportedNote, Origin, Resources};
+use crate::markdown::{extract_links, LinkKind};
+use crate::workspace::percent_decode;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Remove the 32 digit id Notion appends to exported page and folder names
+fn strip_notion_id(name: &str) -> &str {
+    match name.len().checked_sub(33).map(|at| name.split_at(at)) {
+        Some((base, suffix))
+            if suffix.starts_with(' ') && suffix[1..].chars().all(|c| c.is_ascii_hexdigit()) && !base.is_empty() =>
+        {
+            base
+        }
+        _ => name,
+    }
+}
+
+fn is_page(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".html") || lower.ends_with(".htm") || lower.ends_with(".md")
+}
+
+/// A Notion or Obsidian export: pages plus the files they refer to
+#[derive(Debug, Default)]
+pub struct Bundle {
+    /// File contents by `/` separated path within the export
+    pub files: BTreeMap<String, Vec<u8>>,
+    /// Name of the note written for each page, for linking to it
+    notes: HashMap<String, String>,
+}
+
+impl Bundle {
+    /// Read an export zip; Notion splits large exports into zips inside the zip
+    pub fn from_zip_file(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let mut bundle = Bundle::default();
+        bundle.add_zip(&data, "")?;
+        Ok(bundle)
+    }
+
+    fn add_zip(&mut self, data: &[u8], prefix: &str) -> Result<(), String> {
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|e| format!("Failed to open zip file: {}", e))?;
+
+        for index in 0..archive.len() {
+            let mut file = archive
+                .by_index(index)
+                .map_err(|e| format!("Failed to read zip file: {}", e))?;
+            // Entries that would land outside the export are skipped
+            let Some(name) = file.enclosed_name().filter(|_| file.is_file()) else {
+                continue;
+            };
+            let name = format!("{}{}", prefix, super::slash_path(&name));
+
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)
+                .map_err(|e| format!("Failed to read zip file: {}", e))?;
+            drop(file);
+
+            if name.to_lowercase().ends_with(".zip") {
+                let folder = name.rsplit_once('/').map(|(folder, _)| format!("{}/", folder)).unwrap_or_default();
+                self.add_zip(&content, &folder)?;
+            } else {
+                self.files.insert(name, content);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an export that was unpacked into a folder
+    pub fn from_dir(path: &Path) -> Result<Self, String> {
+        let mut bundle = Bundle::default();
+        let mut pending = vec![(path.to_path_buf(), String::new())];
+
+        while let Some((dir, prefix)) = pending.pop() {
+            let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let path: PathBuf = entry.path();
+                if path.is_dir() {
+                    pending.push((path, format!("{}{}/", prefix, name)));
+                } else {
+                    let content = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+                    bundle.files.insert(format!("{}{}", prefix, name), content);
+                }
+            }
+        }
+        Ok(bundle)
+    }
+
+    /// Path within the export that a link on `page` points to, if it's a
+    /// file of the export
+    pub fn resolve(&self, page: &str, href: &str) -> Option<String> {
+        if href.is_empty() || href.starts_with('#') || is_url(href) || href.starts_with("mailto:") {
+            return None;
+        }
+        let href = percent_decode(href.split(['#', '?']).next().unwrap_or(href));
+
+        let mut parts: Vec<&str> = page.split('/').collect();
+        parts.pop();
+        for part in href.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop();
+                }
+                part => parts.push(part),
+            }
+        }
+
+        let path = parts.join("/");
+        self.files.contains_key(&path).then_some(path)
+    }
+
+    /// Links between pages become wiki links to the imported notes
+    pub fn link(&self, page: &str, href: &str, text: &str) -> Option<String> {
+        let file = self.resolve(page, href)?;
+        if let Some(name) = self.notes.get(&file) {
+            return Some(if text.is_empty() || text == name {
+                format!("[[{}]]", name)
+            } else {
+                format!("[[{}|{}]]", name, text.replace(['|', ']'], " "))
+            });
+        }
+
+        // Notion wraps images in links to the full-size file; the image is enough
+        if text.starts_with("![") && text.ends_with(')') {
+            return Some(text.to_string());
+        }
+        None
+    }
+
+    /// Rewrite the links of a markdown page: page links become wiki links
+    /// and images are copied into the image folder
+    fn convert_markdown(&self, page: &str, content: &str, resources: &mut dyn Resources) -> Conversion {
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let mut warnings = Vec::new();
+
+        // Later links first, so earlier ranges on the same line stay valid
+        for link in extract_links(content).into_iter().rev() {
+            let line = &mut lines[link.line];
+            match link.kind {
+                LinkKind::Image => match resources.image(ImageSource::Url(&link.target)) {
+                    Ok(dest) => {
+                        if let Some(range) = link.destination.clone() {
+                            line.replace_range(range, &dest);
+                        }
+                    }
+                    Err(warning) => warnings.push(warning),
+                },
+                LinkKind::Markdown => {
+                    let source = link.text();
+                    let text = source.rfind("](").map_or("", |end| &source[1..end]);
+                    if let Some(markdown) = self.link(page, &link.target, text) {
+                        line.replace_range(link.range.clone(), &markdown);
+                    }
+                }
+                LinkKind::Wiki => {}
+            }
+        }
+
+        warnings.reverse();
+        let mut markdown = lines.join("\n");
+        markdown.push('\n');
+        Conversion { markdown, warnings }
+    }
+
+    /// Write a note for every page of the export, keeping its folder structure
+    pub fn import(mut self, source: &str, target: &mut ImportTarget) -> Vec<ImportedNote> {
+        let pages: Vec<String> = self.files.keys().filter(|path| is_page(path)).cloned().collect();
+        if pages.is_empty() {
+            return vec![ImportedNote::failed(source.to_string(), "No pages found in export".to_string())];
+        }
+
+        // Name every note first so pages can link to ones imported after them
+        let mut paths = Vec::new();
+        for page in &pages {
+            let (folder, file) = page.rsplit_once('/').unwrap_or(("", page));
+            let dir = folder
+                .split('/')
+                .filter(|part| !part.is_empty())
+                .fold(target.notes_dir.clone(), |dir, part| dir.join(strip_notion_id(part)));
+            let stem = Path::new(file).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let path = target.note_path(&dir, strip_notion_id(&stem));
+
+            let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            self.notes.insert(page.clone(), name);
+            paths.push(path);
+        }
+
+        let mut imported: Vec<ImportedNote> = self
+            .files
+            .keys()
+            .filter(|path| path.to_lowercase().ends_with(".csv"))
+            .map(|path| ImportedNote {
+                source: format!("{}/{}", source, path),
+                path: None,
+                warnings: vec!["Database tables are not imported".to_string()],
+                error: None,
+            })
+            .collect();
+
+        for (page, path) in pages.iter().zip(paths) {
+            let content = String::from_utf8_lossy(&self.files[page]);
+            let origin = Origin::Bundle { bundle: &self, page };
+            let note = target.write_note(format!("{}/{}", source, page), path, origin, |resources| {
+                if page.to_lowercase().ends_with(".md") {
+                    Ok(self.convert_markdown(page, &content, resources))
+                } else {
+                    Ok(html::html_to_markdown(&content, resources))
+                }
+            });
+            imported.push(note);
+        }
+        imported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_strip_notion_id() {
+        assert_eq!(strip_notion_id("Roadmap 0123456789abcdef0123456789abcdef"), "Roadmap");
+        assert_eq!(strip_notion_id("Roadmap"), "Roadmap");
+        assert_eq!(strip_notion_id(" 0123456789abcdef0123456789abcdef"), " 0123456789abcdef0123456789abcdef");
+    }
+
+    #[test]
+    fn test_import_notion_zip() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let id = "0123456789abcdef0123456789abcdef";
+        let mut inner = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let files = [
+            (
+                format!("Home {id}.html"),
+                format!(r#"<h1>Home</h1><p>See <a href="Home%20{id}/Roadmap%20{id}.md">the roadmap</a>.</p>"#),
+            ),
+            (
+                format!("Home {id}/Roadmap {id}.md"),
+                format!("# Roadmap\n\n![Plan](plan.png) back to [Home](../Home%20{id}.html)\n"),
+            ),
+            (format!("Home {id}/plan.png"), "png".to_string()),
+            (format!("Home {id}/Tasks {id}.csv"), "Name,Done".to_string()),
+        ];
+        for (name, content) in &files {
+            inner.start_file(name.as_str(), SimpleFileOptions::default()).unwrap();
+            inner.write_all(content.as_bytes()).unwrap();
+        }
+        let inner = inner.finish().unwrap().into_inner();
+
+        let mut outer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        outer.start_file("Export-Part-1.zip", SimpleFileOptions::default()).unwrap();
+        outer.write_all(&inner).unwrap();
+        let export = root.join("Export.zip");
+        fs::write(&export, outer.finish().unwrap().into_inner()).unwrap();
+
+        let workspace = root.join("workspace");
+        let mut target = ImportTarget::new(workspace.clone(), workspace.join("Notion"), workspace.join("assets"));
+        let bundle = Bundle::from_zip_file(&export).unwrap();
+        let imported = bundle.import("Export.zip", &mut target);
+
+        assert_eq!(imported.len(), 3);
+        assert_eq!(imported[0].warnings, vec!["Database tables are not imported"]);
+        assert_eq!(
+            fs::read_to_string(workspace.join("Notion/Home.md")).unwrap(),
+            "# Home\n\nSee [[Roadmap|the roadmap]].\n"
+        );
+        assert_eq!(
+            fs::read_to_string(workspace.join("Notion/Home/Roadmap.md")).unwrap(),
+            "# Roadmap\n\n![Plan](../../assets/plan.png) back to [[Home]]\n"
+        );
+        assert_eq!(fs::read(workspace.join("assets/plan.png")).unwrap(), b"png");
+    }
+}
@@ -0,0 +1,600 @@
+use super::{code_span, escape_line_start, escape_markdown, link_destination, Conversion, ImageSource, Resources};
+use roxmltree::{Document, Node};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+const W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const A: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const M: &str = "http://schemas.openxmlformats.org/officeDocument/2006/math";
+const V: &str = "urn:schemas-microsoft-com:vml";
+const WP: &str = "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing";
+const PACKAGE: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+
+/// What a paragraph style makes of its paragraphs
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StyleKind {
+    Heading(usize),
+    Quote,
+    Code,
+}
+
+/// A relationship target of the document, such as a hyperlink or image part
+struct Relationship {
+    target: String,
+    external: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct RunStyle {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+}
+
+enum Inline {
+    Text(String, RunStyle),
+    /// Markdown written as is, such as links and images
+    Markdown(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ParagraphKind {
+    Text,
+    Heading(usize),
+    Quote,
+    Code,
+    ListItem { depth: usize, ordered: bool, number: usize },
+}
+
+fn read_part(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Option<String> {
+    let mut file = archive.by_name(name).ok()?;
+    let mut text = String::new();
+    file.read_to_string(&mut text).ok()?;
+    Some(text)
+}
+
+fn read_bytes(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Option<Vec<u8>> {
+    let mut file = archive.by_name(name).ok()?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+fn attr<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute((W, name))
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|child| child.has_tag_name((W, name)))
+}
+
+/// Whether an on/off property such as `<w:b/>` is set
+fn toggle(properties: Option<Node>, name: &str) -> bool {
+    properties
+        .and_then(|properties| child(properties, name))
+        .is_some_and(|node| !matches!(attr(node, "val"), Some("0" | "false" | "off" | "none")))
+}
+
+fn parse_relationships(xml: &str) -> HashMap<String, Relationship> {
+    let Ok(document) = Document::parse(xml) else {
+        return HashMap::new();
+    };
+    document
+        .descendants()
+        .filter(|node| node.has_tag_name((PACKAGE, "Relationship")))
+        .filter_map(|node| {
+            let relationship = Relationship {
+                target: node.attribute("Target")?.to_string(),
+                external: node.attribute("TargetMode") == Some("External"),
+            };
+            Some((node.attribute("Id")?.to_string(), relationship))
+        })
+        .collect()
+}
+
+fn parse_styles(xml: &str) -> HashMap<String, StyleKind> {
+    let Ok(document) = Document::parse(xml) else {
+        return HashMap::new();
+    };
+    document
+        .descendants()
+        .filter(|node| node.has_tag_name((W, "style")))
+        .filter_map(|style| {
+            let id = attr(style, "styleId")?;
+            let name = child(style, "name").and_then(|name| attr(name, "val")).unwrap_or(id).to_lowercase();
+            let kind = if name == "title" {
+                StyleKind::Heading(1)
+            } else if let Some(level) = name.strip_prefix("heading ").and_then(|level| level.parse().ok()) {
+                StyleKind::Heading(level)
+            } else if name.contains("quote") {
+                StyleKind::Quote
+            } else if name.contains("code") || name == "html preformatted" {
+                StyleKind::Code
+            } else {
+                return None;
+            };
+            Some((id.to_string(), kind))
+        })
+        .collect()
+}
+
+/// Whether each list level is numbered, by numbering id and level
+fn parse_numbering(xml: &str) -> HashMap<(String, usize), bool> {
+    let Ok(document) = Document::parse(xml) else {
+        return HashMap::new();
+    };
+    let abstract_levels: HashMap<&str, Vec<(usize, bool)>> = document
+        .descendants()
+        .filter(|node| node.has_tag_name((W, "abstractNum")))
+        .filter_map(|abstract_num| {
+            let levels = abstract_num
+                .children()
+                .filter(|node| node.has_tag_name((W, "lvl")))
+                .filter_map(|level| {
+                    let index = attr(level, "ilvl")?.parse().ok()?;
+                    let format = child(level, "numFmt").and_then(|format| attr(format, "val")).unwrap_or("bullet");
+                    Some((index, !matches!(format, "bullet" | "none")))
+                })
+                .collect();
+            Some((attr(abstract_num, "abstractNumId")?, levels))
+        })
+        .collect();
+
+    let mut numbering = HashMap::new();
+    for num in document.descendants().filter(|node| node.has_tag_name((W, "num"))) {
+        let levels = attr(num, "numId").zip(
+            child(num, "abstractNumId")
+                .and_then(|id| attr(id, "val"))
+                .and_then(|id| abstract_levels.get(id)),
+        );
+        if let Some((id, levels)) = levels {
+            for &(level, ordered) in levels {
+                numbering.insert((id.to_string(), level), ordered);
+            }
+        }
+    }
+    numbering
+}
+
+/// Text with bold, italic and strikethrough markers around it, keeping
+/// surrounding spaces outside them
+fn styled(text: &str, style: RunStyle) -> String {
+    if style.code {
+        return if text.trim().is_empty() { text.to_string() } else { code_span(text) };
+    }
+
+    let escaped = escape_markdown(text);
+    let trimmed = escaped.trim();
+    let mut markers = String::new();
+    if style.strike {
+        markers.push_str("~~");
+    }
+    if style.bold {
+        markers.push_str("**");
+    }
+    if style.italic {
+        markers.push('*');
+    }
+    if trimmed.is_empty() || markers.is_empty() {
+        return escaped;
+    }
+
+    let closing: String = markers.chars().rev().collect();
+    let leading = &escaped[..escaped.len() - escaped.trim_start().len()];
+    let trailing = &escaped[escaped.trim_end().len()..];
+    format!("{leading}{markers}{trimmed}{closing}{trailing}")
+}
+
+/// Markdown for the inline content of a paragraph
+fn render_inlines(inlines: Vec<Inline>) -> String {
+    let mut merged: Vec<Inline> = Vec::new();
+    for inline in inlines {
+        match (merged.last_mut(), inline) {
+            (Some(Inline::Text(text, style)), Inline::Text(more, more_style)) if *style == more_style => {
+                text.push_str(&more);
+            }
+            (_, inline) => merged.push(inline),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|inline| match inline {
+            // Markers can't span a line break, so style each line separately
+            Inline::Text(text, style) => {
+                text.split('\n').map(|line| styled(line, style)).collect::<Vec<_>>().join("\n")
+            }
+            Inline::Markdown(markdown) => markdown,
+        })
+        .collect()
+}
+
+struct Converter<'d, 'r> {
+    archive: ZipArchive<Cursor<&'d [u8]>>,
+    relationships: HashMap<String, Relationship>,
+    styles: HashMap<String, StyleKind>,
+    numbering: HashMap<(String, usize), bool>,
+    /// Next number for each list and level
+    counters: HashMap<(String, usize), usize>,
+    resources: &'r mut dyn Resources,
+    warnings: BTreeSet<String>,
+}
+
+impl Converter<'_, '_> {
+    fn image(&mut self, id: &str, alt: &str) -> Option<String> {
+        let relationship = self.relationships.get(id)?;
+        let result = if relationship.external {
+            self.resources.image(ImageSource::Url(&relationship.target))
+        } else {
+            let target = relationship.target.trim_start_matches('/');
+            let part = if target.starts_with("word/") { target.to_string() } else { format!("word/{}", target) };
+            let name = part.clone();
+            match read_bytes(&mut self.archive, &part) {
+                Some(data) => self.resources.image(ImageSource::Embedded { data: &data, name: &name }),
+                None => Err(format!("Image not found: {}", relationship.target)),
+            }
+        };
+
+        match result {
+            Ok(dest) => Some(format!("![{}]({})", escape_markdown(alt), dest)),
+            Err(warning) => {
+                self.warnings.insert(warning);
+                None
+            }
+        }
+    }
+
+    fn run(&mut self, run: Node, out: &mut Vec<Inline>) {
+        let properties = child(run, "rPr");
+        let font = properties
+            .and_then(|properties| child(properties, "rFonts"))
+            .and_then(|fonts| attr(fonts, "ascii"))
+            .unwrap_or("")
+            .to_lowercase();
+        let character_style = properties
+            .and_then(|properties| child(properties, "rStyle"))
+            .and_then(|style| attr(style, "val"))
+            .unwrap_or("")
+            .to_lowercase();
+        let style = RunStyle {
+            bold: toggle(properties, "b"),
+            italic: toggle(properties, "i"),
+            strike: toggle(properties, "strike") || toggle(properties, "dstrike"),
+            code: ["courier", "consolas", "mono", "menlo"].iter().any(|name| font.contains(name))
+                || character_style.contains("code"),
+        };
+
+        for node in run.children().filter(|node| node.is_element()) {
+            let text = match node.tag_name().name() {
+                "t" => node.text().unwrap_or("").to_string(),
+                "tab" => " ".to_string(),
+                "br" | "cr" if attr(node, "type") != Some("page") => "\n".to_string(),
+                "drawing" | "pict" => {
+                    let alt = node
+                        .descendants()
+                        .find(|n| n.has_tag_name((WP, "docPr")))
+                        .and_then(|n| n.attribute("descr"))
+                        .unwrap_or("");
+                    let id = node.descendants().find_map(|n| {
+                        if n.has_tag_name((A, "blip")) {
+                            n.attribute((R, "embed")).or_else(|| n.attribute((R, "link")))
+                        } else if n.has_tag_name((V, "imagedata")) {
+                            n.attribute((R, "id"))
+                        } else {
+                            None
+                        }
+                    });
+                    if let Some(image) = id.and_then(|id| self.image(id, alt)) {
+                        out.push(Inline::Markdown(image));
+                    }
+                    continue;
+                }
+                "footnoteReference" | "endnoteReference" => {
+                    self.warnings.insert("Footnotes are not imported".to_string());
+                    continue;
+                }
+                _ => continue,
+            };
+            out.push(Inline::Text(text, style));
+        }
+    }
+
+    /// Collect the inline content of a paragraph or hyperlink
+    fn inlines(&mut self, parent: Node, out: &mut Vec<Inline>) {
+        for node in parent.children().filter(|node| node.is_element()) {
+            if node.tag_name().namespace() == Some(M) {
+                self.warnings.insert("Equations are not imported".to_string());
+                continue;
+            }
+            match node.tag_name().name() {
+                "r" => self.run(node, out),
+                "hyperlink" => {
+                    let mut inner = Vec::new();
+                    self.inlines(node, &mut inner);
+                    let text = render_inlines(inner);
+                    let href = node
+                        .attribute((R, "id"))
+                        .and_then(|id| self.relationships.get(id))
+                        .map(|relationship| relationship.target.as_str());
+                    let link = href.and_then(|href| self.resources.link(href, &text)).or_else(|| {
+                        href.filter(|_| !text.trim().is_empty())
+                            .map(|href| format!("[{}]({})", text.trim(), link_destination(href)))
+                    });
+                    out.push(Inline::Markdown(link.unwrap_or(text)));
+                }
+                // Tracked insertions count, tracked deletions don't
+                "del" | "pPr" | "moveFrom" => {}
+                _ => self.inlines(node, out),
+            }
+        }
+    }
+
+    fn paragraph_kind(&mut self, paragraph: Node) -> ParagraphKind {
+        let properties = child(paragraph, "pPr");
+        let style = properties
+            .and_then(|properties| child(properties, "pStyle"))
+            .and_then(|style| attr(style, "val"))
+            .and_then(|id| self.styles.get(id).copied());
+
+        let numbering = properties.and_then(|properties| child(properties, "numPr")).and_then(|numbering| {
+            let id = child(numbering, "numId").and_then(|id| attr(id, "val"))?;
+            let level = child(numbering, "ilvl").and_then(|level| attr(level, "val")).and_then(|l| l.parse().ok());
+            (id != "0").then(|| (id.to_string(), level.unwrap_or(0)))
+        });
+
+        match (style, numbering) {
+            (Some(StyleKind::Heading(level)), _) => ParagraphKind::Heading(level.clamp(1, 6)),
+            (_, Some((id, depth))) => {
+                let ordered = self.numbering.get(&(id.clone(), depth)).copied().unwrap_or(false);
+                // A new item restarts the numbering of levels below it
+                self.counters.retain(|(list, level), _| list != &id || *level <= depth);
+                let counter = self.counters.entry((id, depth)).or_insert(0);
+                *counter += 1;
+                ParagraphKind::ListItem { depth, ordered, number: *counter }
+            }
+            (Some(StyleKind::Quote), _) => ParagraphKind::Quote,
+            (Some(StyleKind::Code), _) => ParagraphKind::Code,
+            (None, None) => ParagraphKind::Text,
+        }
+    }
+
+    fn paragraph(&mut self, paragraph: Node) -> Option<(ParagraphKind, String)> {
+        let kind = self.paragraph_kind(paragraph);
+        let mut inlines = Vec::new();
+        self.inlines(paragraph, &mut inlines);
+
+        let text = if kind == ParagraphKind::Code {
+            inlines
+                .into_iter()
+                .map(|inline| match inline {
+                    Inline::Text(text, _) | Inline::Markdown(text) => text,
+                })
+                .collect()
+        } else {
+            render_inlines(inlines)
+        };
+        if text.trim().is_empty() && kind != ParagraphKind::Code {
+            return None;
+        }
+
+        let text = match kind {
+            ParagraphKind::Text => text.lines().map(|line| escape_line_start(line.trim())).collect::<Vec<_>>().join("\n"),
+            ParagraphKind::Heading(level) => format!("{} {}", "#".repeat(level), text.split_whitespace().collect::<Vec<_>>().join(" ")),
+            ParagraphKind::Quote => text.lines().map(|line| format!("> {}", line.trim())).collect::<Vec<_>>().join("\n"),
+            ParagraphKind::ListItem { depth, ordered, number } => {
+                let marker = if ordered { format!("{}.", number) } else { "-".to_string() };
+                format!("{}{} {}", "  ".repeat(depth), marker, text.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            ParagraphKind::Code => text,
+        };
+        Some((kind, text))
+    }
+
+    fn table(&mut self, table: Node) -> Option<String> {
+        let rows: Vec<Vec<String>> = table
+            .children()
+            .filter(|node| node.has_tag_name((W, "tr")))
+            .map(|row| {
+                row.children()
+                    .filter(|node| node.has_tag_name((W, "tc")))
+                    .map(|cell| {
+                        let mut inlines = Vec::new();
+                        for paragraph in cell.descendants().filter(|node| node.has_tag_name((W, "p"))) {
+                            self.inlines(paragraph, &mut inlines);
+                            inlines.push(Inline::Markdown(" ".to_string()));
+                        }
+                        let text = render_inlines(inlines);
+                        text.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|")
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return None;
+        }
+        let line = |cells: &[String]| {
+            let padded: Vec<&str> = (0..columns).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+            format!("| {} |", padded.join(" | "))
+        };
+        let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+        lines.extend(rows[1..].iter().map(|row| line(row)));
+        Some(lines.join("\n"))
+    }
+
+    /// Convert the block content of the body, or of a content control in it
+    fn body(&mut self, body: Node, blocks: &mut Vec<(ParagraphKind, String)>) {
+        for node in body.children().filter(|node| node.is_element()) {
+            match node.tag_name().name() {
+                "p" => blocks.extend(self.paragraph(node)),
+                "tbl" => blocks.extend(self.table(node).map(|table| (ParagraphKind::Text, table))),
+                "sdt" | "sdtContent" | "customXml" => self.body(node, blocks),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Join converted paragraphs, grouping list items and code lines
+fn join_blocks(blocks: Vec<(ParagraphKind, String)>) -> String {
+    let mut grouped: Vec<(ParagraphKind, String)> = Vec::new();
+    for (kind, text) in blocks {
+        match grouped.last_mut() {
+            Some((last_kind, last))
+                if matches!(
+                    (&*last_kind, &kind),
+                    (ParagraphKind::ListItem { .. }, ParagraphKind::ListItem { .. })
+                        | (ParagraphKind::Code, ParagraphKind::Code)
+                ) =>
+            {
+                last.push('\n');
+                last.push_str(&text);
+            }
+            _ => grouped.push((kind, text)),
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(kind, text)| {
+            if kind == ParagraphKind::Code {
+                let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+                let fence = "`".repeat((longest + 1).max(3));
+                format!("{fence}\n{text}\n{fence}")
+            } else {
+                text
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Convert a Word document to markdown
+pub fn docx_to_markdown(data: &[u8], resources: &mut dyn Resources) -> Result<Conversion, String> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| format!("Failed to open document: {}", e))?;
+    let document = read_part(&mut archive, "word/document.xml")
+        .ok_or_else(|| "Failed to open document: word/document.xml is missing".to_string())?;
+    let relationships = read_part(&mut archive, "word/_rels/document.xml.rels")
+        .map(|xml| parse_relationships(&xml))
+        .unwrap_or_default();
+    let styles = read_part(&mut archive, "word/styles.xml").map(|xml| parse_styles(&xml)).unwrap_or_default();
+    let numbering = read_part(&mut archive, "word/numbering.xml")
+        .map(|xml| parse_numbering(&xml))
+        .unwrap_or_default();
+
+    let document = Document::parse(&document).map_err(|e| format!("Failed to parse document: {}", e))?;
+    let body = document
+        .descendants()
+        .find(|node| node.has_tag_name((W, "body")))
+        .ok_or_else(|| "Failed to parse document: no body".to_string())?;
+
+    let mut converter = Converter {
+        archive,
+        relationships,
+        styles,
+        numbering,
+        counters: HashMap::new(),
+        resources,
+        warnings: BTreeSet::new(),
+    };
+    let mut blocks = Vec::new();
+    converter.body(body, &mut blocks);
+
+    let mut markdown = join_blocks(blocks);
+    markdown.push('\n');
+    Ok(Conversion { markdown, warnings: converter.warnings.into_iter().collect() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    struct Saved(Vec<(String, Vec<u8>)>);
+
+    impl Resources for Saved {
+        fn image(&mut self, source: ImageSource) -> Result<String, String> {
+            match source {
+                ImageSource::Embedded { data, name } => {
+                    self.0.push((name.to_string(), data.to_vec()));
+                    Ok("assets/image.png".to_string())
+                }
+                _ => Err("Unexpected image source".to_string()),
+            }
+        }
+    }
+
+    fn docx(parts: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in parts {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_docx_to_markdown() {
+        let document = r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"
+            xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"
+            xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"
+            xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing"
+            xmlns:m="http://schemas.openxmlformats.org/officeDocument/2006/math"><w:body>
+            <w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Plan</w:t></w:r></w:p>
+            <w:p><w:r><w:t xml:space="preserve">Costs </w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t xml:space="preserve">$5 </w:t></w:r><w:r><w:t>per </w:t></w:r><w:hyperlink r:id="rId2"><w:r><w:t>seat</w:t></w:r></w:hyperlink><w:del><w:r><w:delText>gone</w:delText></w:r></w:del><m:oMath/></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>First</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Nested</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Second</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="CodeBlock"/></w:pPr><w:r><w:t>let x = 1;</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="CodeBlock"/></w:pPr><w:r><w:t>x *= 2;</w:t></w:r></w:p>
+            <w:tbl><w:tr><w:tc><w:p><w:r><w:t>A</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>B|C</w:t></w:r></w:p></w:tc></w:tr>
+                <w:tr><w:tc><w:p><w:r><w:t>1</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>2</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+            <w:p><w:r><w:drawing><wp:inline><wp:docPr id="1" descr="Chart"/><a:graphic><a:blip r:embed="rId3"/></a:graphic></wp:inline></w:drawing></w:r></w:p>
+        </w:body></w:document>"#;
+        let relationships = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+            <Relationship Id="rId2" Type="hyperlink" Target="https://example.com/seat" TargetMode="External"/>
+            <Relationship Id="rId3" Type="image" Target="media/image1.png"/></Relationships>"#;
+        let styles = r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+            <w:style w:styleId="Heading2"><w:name w:val="heading 2"/></w:style>
+            <w:style w:styleId="CodeBlock"><w:name w:val="Code Block"/></w:style></w:styles>"#;
+        let numbering = r#"<w:numbering xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+            <w:abstractNum w:abstractNumId="0"><w:lvl w:ilvl="0"><w:numFmt w:val="decimal"/></w:lvl><w:lvl w:ilvl="1"><w:numFmt w:val="bullet"/></w:lvl></w:abstractNum>
+            <w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num></w:numbering>"#;
+
+        let data = docx(&[
+            ("word/document.xml", document),
+            ("word/_rels/document.xml.rels", relationships),
+            ("word/styles.xml", styles),
+            ("word/numbering.xml", numbering),
+            ("word/media/image1.png", "png bytes"),
+        ]);
+        let mut saved = Saved(Vec::new());
+        let conversion = docx_to_markdown(&data, &mut saved).unwrap();
+
+        assert_eq!(
+            conversion.markdown,
+            "## Plan\n\n\
+             Costs **\\$5** per [seat](https://example.com/seat)\n\n\
+             1. First\n  - Nested\n2. Second\n\n\
+             ```\nlet x = 1;\nx *= 2;\n```\n\n\
+             | A | B\\|C |\n| --- | --- |\n| 1 | 2 |\n\n\
+             ![Chart](assets/image.png)\n"
+        );
+        assert_eq!(conversion.warnings, vec!["Equations are not imported"]);
+        assert_eq!(saved.0, vec![("word/media/image1.png".to_string(), b"png bytes".to_vec())]);
+    }
+
+    #[test]
+    fn test_not_a_document() {
+        let mut saved = Saved(Vec::new());
+        assert!(docx_to_markdown(b"plain text", &mut saved).is_err());
+        let empty = docx(&[("other.xml", "<x/>")]);
+        assert!(docx_to_markdown(&empty, &mut saved).unwrap_err().contains("word/document.xml"));
+    }
+}
@@ -0,0 +1,246 @@
+use super::{html, ImportTarget, ImportedNote, Origin};
+use crate::markdown::replace_properties;
+use base64::{engine::general_purpose, Engine as _};
+use md5::{Digest, Md5};
+use roxmltree::{Document, Node, ParsingOptions};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A file attached to an Evernote note
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub data: Vec<u8>,
+    pub mime: String,
+    pub name: String,
+}
+
+/// Attachments of a note by the hex MD5 of their data, which is how
+/// `<en-media>` refers to them
+pub type Attachments = HashMap<String, Attachment>;
+
+/// A note read from an Evernote export
+#[derive(Debug, Clone, Default)]
+pub struct EnexNote {
+    pub title: String,
+    /// The note body in ENML, Evernote's restricted XHTML
+    pub content: String,
+    pub tags: Vec<String>,
+    /// Creation time as an ISO 8601 timestamp
+    pub created: Option<String>,
+    pub source_url: Option<String>,
+    pub attachments: Attachments,
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Turn Evernote's `20240102T030405Z` timestamps into `2024-01-02T03:04:05Z`
+fn iso_timestamp(stamp: &str) -> String {
+    let digits = stamp.len() == 16 && stamp.chars().enumerate().all(|(i, c)| match i {
+        8 => c == 'T',
+        15 => c == 'Z',
+        _ => c.is_ascii_digit(),
+    });
+    if !digits {
+        return stamp.to_string();
+    }
+    format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &stamp[0..4],
+        &stamp[4..6],
+        &stamp[6..8],
+        &stamp[9..11],
+        &stamp[11..13],
+        &stamp[13..15]
+    )
+}
+
+fn attachment(resource: Node) -> Result<(String, Attachment), String> {
+    let encoded: String = resource
+        .children()
+        .find(|child| child.has_tag_name("data"))
+        .and_then(|data| data.text())
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let data = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Failed to decode attachment: {}", e))?;
+
+    let hash: String = Md5::digest(&data).iter().map(|byte| format!("{:02x}", byte)).collect();
+    let name = resource
+        .children()
+        .find(|child| child.has_tag_name("resource-attributes"))
+        .and_then(|attributes| child_text(attributes, "file-name"))
+        .unwrap_or_else(|| "attachment".to_string());
+    let mime = child_text(resource, "mime").unwrap_or_default();
+
+    Ok((hash, Attachment { data, mime, name }))
+}
+
+/// Read the notes of an `.enex` export; attachments that can't be decoded
+/// are left out with a warning
+pub fn parse_enex(xml: &str) -> Result<(Vec<EnexNote>, Vec<String>), String> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(xml, options)
+        .map_err(|e| format!("Failed to parse Evernote export: {}", e))?;
+
+    let mut warnings = Vec::new();
+    let notes = document
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("note"))
+        .map(|note| {
+            let mut attachments = Attachments::new();
+            for resource in note.children().filter(|child| child.has_tag_name("resource")) {
+                match attachment(resource) {
+                    Ok((hash, attachment)) => {
+                        attachments.insert(hash, attachment);
+                    }
+                    Err(warning) => warnings.push(warning),
+                }
+            }
+
+            EnexNote {
+                title: child_text(note, "title").unwrap_or_default(),
+                content: child_text(note, "content").unwrap_or_default(),
+                tags: note
+                    .children()
+                    .filter(|child| child.has_tag_name("tag"))
+                    .filter_map(|tag| tag.text())
+                    .map(|tag| tag.trim().to_string())
+                    .collect(),
+                created: child_text(note, "created").map(|stamp| iso_timestamp(&stamp)),
+                source_url: note
+                    .children()
+                    .find(|child| child.has_tag_name("note-attributes"))
+                    .and_then(|attributes| child_text(attributes, "source-url")),
+                attachments,
+            }
+        })
+        .collect();
+
+    Ok((notes, warnings))
+}
+
+/// Frontmatter for the note's tags, creation time and source page
+fn properties(note: &EnexNote) -> Map<String, Value> {
+    let mut properties = Map::new();
+    if !note.tags.is_empty() {
+        properties.insert("tags".to_string(), note.tags.iter().cloned().map(Value::String).collect());
+    }
+    if let Some(created) = &note.created {
+        properties.insert("created".to_string(), Value::String(created.clone()));
+    }
+    if let Some(url) = &note.source_url {
+        properties.insert("source".to_string(), Value::String(url.clone()));
+    }
+    properties
+}
+
+/// Import every note of an Evernote export
+pub fn import_enex(path: &Path, target: &mut ImportTarget) -> Vec<ImportedNote> {
+    let source = path.to_string_lossy().to_string();
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file: {}", e))
+        .and_then(|xml| parse_enex(&xml));
+    let (notes, warnings) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => return vec![ImportedNote::failed(source, error)],
+    };
+
+    let notes_dir = target.notes_dir.clone();
+    let mut imported: Vec<ImportedNote> = notes
+        .iter()
+        .map(|note| {
+            let path = target.note_path(&notes_dir, &note.title);
+            let source = format!("{}/{}", source, note.title);
+            target.write_note(source, path, Origin::Enex(&note.attachments), |resources| {
+                let mut conversion = html::html_to_markdown(&note.content, resources);
+                if !note.title.is_empty() {
+                    conversion.markdown = format!("# {}\n\n{}", note.title, conversion.markdown);
+                }
+                conversion.markdown = replace_properties(&conversion.markdown, &properties(note))?;
+                Ok(conversion)
+            })
+        })
+        .collect();
+
+    if let Some(first) = imported.first_mut() {
+        first.warnings.extend(warnings);
+    }
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-export SYSTEM "http://xml.evernote.com/pub/evernote-export4.dtd">
+<en-export export-date="20240301T120000Z" application="Evernote">
+  <note>
+    <title>Trip plan</title>
+    <content><![CDATA[<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><div>Pack <b>light</b></div><div><en-todo checked="false"/>Book hotel</div><en-media hash="8b1a9953c4611296a827abf8c47804d7" type="image/gif"/></en-note>]]></content>
+    <created>20240102T030405Z</created>
+    <tag>travel</tag>
+    <tag>2024</tag>
+    <note-attributes><source-url>https://example.com/trip</source-url></note-attributes>
+    <resource>
+      <data encoding="base64">
+        SGVs
+        bG8=
+      </data>
+      <mime>image/gif</mime>
+      <resource-attributes><file-name>map.gif</file-name></resource-attributes>
+    </resource>
+  </note>
+</en-export>"#;
+
+    #[test]
+    fn test_parse_enex() {
+        let (notes, warnings) = parse_enex(EXPORT).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(notes.len(), 1);
+
+        let note = &notes[0];
+        assert_eq!(note.title, "Trip plan");
+        assert_eq!(note.tags, vec!["travel", "2024"]);
+        assert_eq!(note.created.as_deref(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(note.source_url.as_deref(), Some("https://example.com/trip"));
+        assert_eq!(
+            note.attachments.get("8b1a9953c4611296a827abf8c47804d7"),
+            Some(&Attachment { data: b"Hello".to_vec(), mime: "image/gif".to_string(), name: "map.gif".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_import_enex() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let export = root.join("Travel.enex");
+        fs::write(&export, EXPORT).unwrap();
+
+        let mut target = ImportTarget::new(root.to_path_buf(), root.to_path_buf(), root.join("assets"));
+        let imported = import_enex(&export, &mut target);
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].error, None);
+        assert!(imported[0].warnings.is_empty());
+
+        let note = fs::read_to_string(root.join("Trip plan.md")).unwrap();
+        assert!(note.starts_with("---\n"));
+        assert!(note.contains("created: 2024-01-02T03:04:05Z\n"));
+        assert!(note.ends_with("# Trip plan\n\nPack **light**\n\n- [ ] Book hotel\n\n![](assets/map.gif)\n"));
+        assert_eq!(fs::read(root.join("assets/map.gif")).unwrap(), b"Hello");
+    }
+}
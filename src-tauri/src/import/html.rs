@@ -0,0 +1,400 @@
+use super::{code_span, escape_markdown, finish_paragraph, link_destination, Conversion, ImageSource, Resources};
+use scraper::{ElementRef, Html, Node};
+use std::collections::BTreeSet;
+
+/// Elements whose content is never part of the note
+const SKIPPED: [&str; 8] = ["head", "script", "style", "title", "meta", "link", "noscript", "template"];
+
+/// Elements that have no markdown form; their content is dropped with a warning
+const DROPPED: [&str; 12] = [
+    "iframe", "video", "audio", "object", "embed", "canvas", "svg", "form", "select", "button", "textarea", "math",
+];
+
+/// Elements that hold blocks rather than inline content
+const CONTAINERS: [&str; 22] = [
+    "html", "body", "div", "section", "article", "main", "header", "footer", "nav", "aside", "address", "center",
+    "details", "summary", "dialog", "figure", "figcaption", "fieldset", "dl", "dt", "dd", "en-note",
+];
+
+fn is_block(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "ul" | "ol" | "blockquote" | "pre" | "table" | "hr")
+        || CONTAINERS.contains(&name)
+        || SKIPPED.contains(&name)
+        || DROPPED.contains(&name)
+}
+
+/// Append text with its whitespace collapsed the way a browser shows it
+fn push_text(out: &mut String, text: &str) {
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !out.ends_with([' ', '\n']) {
+                out.push(' ');
+            }
+        } else {
+            out.push_str(&escape_markdown(c.encode_utf8(&mut [0; 4])));
+        }
+    }
+}
+
+/// Text of an element as written, with `<br>` as a line break
+fn raw_text(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(e) if e.name() == "br" => out.push('\n'),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    raw_text(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Converter<'r> {
+    resources: &'r mut dyn Resources,
+    warnings: Vec<String>,
+    dropped: BTreeSet<String>,
+}
+
+impl Converter<'_> {
+    fn drop_element(&mut self, name: &str) {
+        if self.dropped.insert(name.to_string()) {
+            self.warnings.push(format!("Dropped unsupported <{}> content", name));
+        }
+    }
+
+    fn blocks(&mut self, element: ElementRef, out: &mut Vec<String>) {
+        let mut paragraph = String::new();
+        for child in element.children() {
+            match (child.value(), ElementRef::wrap(child)) {
+                (Node::Text(text), _) => push_text(&mut paragraph, text),
+                (_, Some(child)) if is_block(child.value().name()) => {
+                    finish_paragraph(&mut paragraph, out);
+                    self.block(child, out);
+                }
+                (_, Some(child)) => self.inline(child, &mut paragraph),
+                _ => {}
+            }
+        }
+        finish_paragraph(&mut paragraph, out);
+    }
+
+    fn block(&mut self, element: ElementRef, out: &mut Vec<String>) {
+        let name = element.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let text = self.inline_text(element).replace('\n', " ");
+                if !text.is_empty() {
+                    let level = name[1..].parse().unwrap_or(1);
+                    out.push(format!("{} {}", "#".repeat(level), text));
+                }
+            }
+            "p" => {
+                let mut paragraph = String::new();
+                self.inline_children(element, &mut paragraph);
+                finish_paragraph(&mut paragraph, out);
+            }
+            "ul" | "ol" => {
+                let mut lines = Vec::new();
+                self.list(element, 0, &mut lines);
+                if !lines.is_empty() {
+                    out.push(lines.join("\n"));
+                }
+            }
+            "blockquote" => {
+                let mut inner = Vec::new();
+                self.blocks(element, &mut inner);
+                if !inner.is_empty() {
+                    let quoted: Vec<String> = inner
+                        .join("\n\n")
+                        .lines()
+                        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                        .collect();
+                    out.push(quoted.join("\n"));
+                }
+            }
+            "pre" => out.push(self.code_block(element)),
+            "table" => {
+                if let Some(table) = self.table(element) {
+                    out.push(table);
+                }
+            }
+            "hr" => out.push("---".to_string()),
+            _ if SKIPPED.contains(&name) => {}
+            _ if DROPPED.contains(&name) => self.drop_element(name),
+            _ => self.blocks(element, out),
+        }
+    }
+
+    /// Inline content of an element on one trimmed line
+    fn inline_text(&mut self, element: ElementRef) -> String {
+        let mut text = String::new();
+        self.inline_children(element, &mut text);
+        text.trim().to_string()
+    }
+
+    fn inline_children(&mut self, element: ElementRef, out: &mut String) {
+        for child in element.children() {
+            match (child.value(), ElementRef::wrap(child)) {
+                (Node::Text(text), _) => push_text(out, text),
+                (_, Some(child)) => self.inline(child, out),
+                _ => {}
+            }
+        }
+    }
+
+    /// Wrap inline content in emphasis markers, keeping surrounding spaces outside them
+    fn emphasis(&mut self, element: ElementRef, marker: &str, out: &mut String) {
+        let mut inner = String::new();
+        self.inline_children(element, &mut inner);
+        let trimmed = inner.trim();
+        if trimmed.is_empty() {
+            push_text(out, &inner);
+            return;
+        }
+
+        if inner.starts_with(' ') {
+            push_text(out, " ");
+        }
+        out.push_str(&format!("{}{}{}", marker, trimmed, marker));
+        if inner.ends_with(' ') {
+            out.push(' ');
+        }
+    }
+
+    fn checkbox(out: &mut String, checked: bool) {
+        out.push_str(if checked { "[x] " } else { "[ ] " });
+    }
+
+    fn inline(&mut self, element: ElementRef, out: &mut String) {
+        let value = element.value();
+        let name = value.name();
+        match name {
+            "br" => out.push('\n'),
+            "strong" | "b" => self.emphasis(element, "**", out),
+            "em" | "i" => self.emphasis(element, "*", out),
+            "del" | "s" | "strike" => self.emphasis(element, "~~", out),
+            "code" | "kbd" | "samp" | "tt" => {
+                let mut text = String::new();
+                raw_text(element, &mut text);
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !text.is_empty() {
+                    out.push_str(&code_span(&text));
+                }
+            }
+            "a" => {
+                let href = value.attr("href").unwrap_or("").trim();
+                let text = self.inline_text(element).replace('\n', " ");
+                if let Some(markdown) = self.resources.link(href, &text) {
+                    out.push_str(&markdown);
+                } else if href.is_empty() || href.starts_with("javascript:") {
+                    out.push_str(&text);
+                } else if text.is_empty() {
+                    out.push_str(&format!("<{}>", href));
+                } else {
+                    out.push_str(&format!("[{}]({})", text, link_destination(href)));
+                }
+            }
+            "img" => {
+                let src = value.attr("src").unwrap_or("").trim();
+                let alt = escape_markdown(value.attr("alt").unwrap_or("").trim());
+                if src.is_empty() {
+                    return;
+                }
+                match self.resources.image(ImageSource::Url(src)) {
+                    Ok(dest) => out.push_str(&format!("![{}]({})", alt, dest)),
+                    Err(warning) => {
+                        self.warnings.push(warning);
+                        if !src.starts_with("data:") {
+                            out.push_str(&format!("![{}]({})", alt, link_destination(src)));
+                        }
+                    }
+                }
+            }
+            // Evernote attachments and checkboxes
+            "en-media" => {
+                let hash = value.attr("hash").unwrap_or("");
+                match self.resources.image(ImageSource::Media(hash)) {
+                    Ok(dest) => out.push_str(&format!("![]({})", dest)),
+                    Err(warning) => self.warnings.push(warning),
+                }
+                // HTML parsing ignores the self-closing slash, so what follows ends up inside
+                self.inline_children(element, out);
+            }
+            "en-todo" => {
+                Self::checkbox(out, value.attr("checked") == Some("true"));
+                self.inline_children(element, out);
+            }
+            "input" if value.attr("type") == Some("checkbox") => Self::checkbox(out, value.attr("checked").is_some()),
+            _ if SKIPPED.contains(&name) => {}
+            _ if DROPPED.contains(&name) => self.drop_element(name),
+            _ => self.inline_children(element, out),
+        }
+    }
+
+    fn list(&mut self, element: ElementRef, depth: usize, lines: &mut Vec<String>) {
+        let ordered = element.value().name() == "ol";
+        let mut number: usize = element.value().attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+
+        for item in element.child_elements() {
+            match item.value().name() {
+                "li" => {}
+                "ul" | "ol" => {
+                    self.list(item, depth + 1, lines);
+                    continue;
+                }
+                _ => continue,
+            }
+
+            let mut text = String::new();
+            let mut nested = Vec::new();
+            for child in item.children() {
+                let Some(child) = ElementRef::wrap(child) else {
+                    if let Node::Text(t) = child.value() {
+                        push_text(&mut text, t);
+                    }
+                    continue;
+                };
+                let value = child.value();
+                match value.name() {
+                    "ul" | "ol" => self.list(child, depth + 1, &mut nested),
+                    // Notion marks to-do items with a styled div
+                    "div" if value.classes().any(|class| class == "checkbox") => {
+                        Self::checkbox(&mut text, value.classes().any(|class| class == "checkbox-on"));
+                    }
+                    name if is_block(name) => {
+                        push_text(&mut text, " ");
+                        self.inline_children(child, &mut text);
+                        push_text(&mut text, " ");
+                    }
+                    _ => self.inline(child, &mut text),
+                }
+            }
+
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let marker = if ordered { format!("{}.", number) } else { "-".to_string() };
+            number += 1;
+            lines.push(format!("{}{} {}", "  ".repeat(depth), marker, text).trim_end().to_string());
+            lines.extend(nested);
+        }
+    }
+
+    fn code_block(&mut self, element: ElementRef) -> String {
+        let language = element
+            .child_elements()
+            .find(|child| child.value().name() == "code")
+            .and_then(|code| {
+                code.value()
+                    .classes()
+                    .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+                    .map(str::to_string)
+            })
+            .unwrap_or_default();
+
+        let mut text = String::new();
+        raw_text(element, &mut text);
+        let text = text.trim_end_matches('\n');
+
+        // The fence must be longer than any run of backticks in the code
+        let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat((longest + 1).max(3));
+        format!("{fence}{language}\n{text}\n{fence}")
+    }
+
+    fn table(&mut self, element: ElementRef) -> Option<String> {
+        let rows: Vec<Vec<String>> = element
+            .descendent_elements()
+            .filter(|row| row.value().name() == "tr")
+            .map(|row| {
+                row.child_elements()
+                    .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                    .map(|cell| self.inline_text(cell).replace('\n', " ").replace('|', "\\|"))
+                    .collect()
+            })
+            .collect();
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return None;
+        }
+
+        let line = |cells: &[String]| {
+            let padded: Vec<&str> = (0..columns).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+            format!("| {} |", padded.join(" | "))
+        };
+        let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+        lines.extend(rows[1..].iter().map(|row| line(row)));
+        Some(lines.join("\n"))
+    }
+}
+
+/// Convert an HTML document (or Evernote's ENML) to markdown
+pub fn html_to_markdown(html: &str, resources: &mut dyn Resources) -> Conversion {
+    let document = Html::parse_document(html);
+    let mut converter = Converter { resources, warnings: Vec::new(), dropped: BTreeSet::new() };
+
+    let mut blocks = Vec::new();
+    converter.blocks(document.root_element(), &mut blocks);
+
+    let mut markdown = blocks.join("\n\n");
+    markdown.push('\n');
+    Conversion { markdown, warnings: converter.warnings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps image sources as they are, refusing `data:` URLs
+    struct Passthrough;
+
+    impl Resources for Passthrough {
+        fn image(&mut self, source: ImageSource) -> Result<String, String> {
+            match source {
+                ImageSource::Url(src) if !src.starts_with("data:") => Ok(format!("assets/{}", src)),
+                _ => Err("Image not imported".to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"<html><head><title>T</title><style>p{}</style></head><body>
+            <h1>Release <em>notes</em></h1>
+            <p>Costs $5 with <b>bold </b>and <a href="https://x.y/a b">a link</a>.<br>Second line</p>
+            <ul><li>One<ul><li><input type="checkbox" checked> Done</li></ul></li><li>Two</li></ul>
+            <ol start="3"><li><p>Three</p></li></ol>
+            <blockquote><p>Quoted</p><p># not a heading</p></blockquote>
+            <pre><code class="language-rust">fn main() {
+    println!("`hi`");
+}</code></pre>
+            <table><thead><tr><th>A</th><th>B|C</th></tr></thead><tbody><tr><td>1</td></tr></tbody></table>
+            <img src="chart.png" alt="Chart"><img src="data:image/png;base64,AAAA">
+            <iframe src="https://example.com"></iframe><hr>
+        </body></html>"#;
+
+        let conversion = html_to_markdown(html, &mut Passthrough);
+        assert_eq!(
+            conversion.markdown,
+            "# Release *notes*\n\n\
+             Costs \\$5 with **bold** and [a link](https://x.y/a%20b).\nSecond line\n\n\
+             - One\n  - [x] Done\n- Two\n\n\
+             3. Three\n\n\
+             > Quoted\n>\n> \\# not a heading\n\n\
+             ```rust\nfn main() {\n    println!(\"`hi`\");\n}\n```\n\n\
+             | A | B\\|C |\n| --- | --- |\n| 1 |  |\n\n\
+             ![Chart](assets/chart.png)\n\n---\n"
+        );
+        assert_eq!(conversion.warnings, vec!["Image not imported", "Dropped unsupported <iframe> content"]);
+    }
+
+    #[test]
+    fn test_enml_todos_and_media() {
+        let enml = r#"<en-note><div><en-todo checked="true"/>Ship it</div><div><en-media hash="abc" type="image/png"/></div></en-note>"#;
+        let conversion = html_to_markdown(enml, &mut Passthrough);
+        assert_eq!(conversion.markdown, "- [x] Ship it\n");
+        assert_eq!(conversion.warnings, vec!["Image not imported"]);
+    }
+}
@@ -0,0 +1,108 @@
+use md5::{Digest, Md5};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extension for image data, from its leading bytes
+fn sniff_extension(data: &[u8]) -> Option<&'static str> {
+    let extension = if data.starts_with(b"\x89PNG") {
+        "png"
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else if data.starts_with(b"GIF8") {
+        "gif"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        "webp"
+    } else if data.starts_with(b"BM") {
+        "bmp"
+    } else if data.starts_with(b"<svg") || data.starts_with(b"<?xml") {
+        "svg"
+    } else {
+        return None;
+    };
+    Some(extension)
+}
+
+/// File name characters that aren't allowed on every platform
+pub(super) fn clean_stem(stem: &str) -> String {
+    let cleaned: String = stem
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|#[]^".contains(c) { '-' } else { c })
+        .collect();
+    cleaned.trim().trim_matches('.').chars().take(80).collect()
+}
+
+/// Writes the images of imported notes into the image folder
+///
+/// Identical images are written once, however many notes use them, and
+/// existing files are never overwritten.
+pub struct ImageStore {
+    dir: PathBuf,
+    /// Files written so far, by MD5 of their content
+    saved: HashMap<[u8; 16], PathBuf>,
+}
+
+impl ImageStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, saved: HashMap::new() }
+    }
+
+    /// Save image data, named after `name` where possible; returns the file written
+    pub fn save(&mut self, data: &[u8], name: &str) -> Result<PathBuf, String> {
+        let digest: [u8; 16] = Md5::digest(data).into();
+        if let Some(path) = self.saved.get(&digest) {
+            return Ok(path.clone());
+        }
+
+        let name = Path::new(name);
+        let stem = name
+            .file_stem()
+            .map(|stem| clean_stem(&stem.to_string_lossy()))
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or_else(|| "imported".to_string());
+        let extension = sniff_extension(data)
+            .map(str::to_string)
+            .or_else(|| name.extension().map(|e| e.to_string_lossy().to_lowercase()))
+            .unwrap_or_else(|| "png".to_string());
+
+        if !self.dir.exists() {
+            fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+
+        let mut path = self.dir.join(format!("{}.{}", stem, extension));
+        let mut counter = 1;
+        while path.exists() {
+            path = self.dir.join(format!("{}-{}.{}", stem, counter, extension));
+            counter += 1;
+        }
+
+        fs::write(&path, data)
+            .map_err(|e| format!("Failed to write image file: {}", e))?;
+        self.saved.insert(digest, path.clone());
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_images() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let mut store = ImageStore::new(root.join("assets"));
+
+        let png = b"\x89PNG\r\n\x1a\nrest";
+        let first = store.save(png, "media/image1.jpeg").unwrap();
+        assert_eq!(first, root.join("assets/image1.png"));
+        assert_eq!(store.save(png, "other.png").unwrap(), first);
+
+        let second = store.save(b"GIF89a", "image1").unwrap();
+        assert_eq!(second, root.join("assets/image1.gif"));
+        let third = store.save(b"GIF89a!", "dir/image1.gif").unwrap();
+        assert_eq!(third, root.join("assets/image1-1.gif"));
+        assert_eq!(store.save(b"????", "what?.dat").unwrap(), root.join("assets/what-.dat"));
+    }
+}
@@ -0,0 +1,462 @@
+mod bundle;
+mod docx;
+mod enex;
+mod html;
+mod images;
+
+use images::ImageStore;
+
+use crate::config::{load_app_config, AppConfig};
+use crate::link_updates::relative_path_between;
+use crate::workspace::percent_decode;
+use crate::write_file_atomic;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Where an image in a converted document comes from
+pub enum ImageSource<'a> {
+    /// An `<img>` source: a URL, a `data:` URL or a path relative to the document
+    Url(&'a str),
+    /// An Evernote attachment, by the MD5 hash of its data
+    Media(&'a str),
+    /// Image data stored inside the document, with its file name there
+    Embedded { data: &'a [u8], name: &'a str },
+}
+
+/// What a converter needs from outside the document it converts
+pub trait Resources {
+    /// Store an image where the note can show it, returning the link
+    /// destination to write; an error is a warning for the conversion
+    fn image(&mut self, source: ImageSource) -> Result<String, String>;
+
+    /// Markdown for a link, when it should be something other than `[text](href)`
+    fn link(&mut self, _href: &str, _text: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Markdown converted from another format
+#[derive(Debug, Clone, Default)]
+pub struct Conversion {
+    pub markdown: String,
+    /// Content that was dropped or couldn't be converted faithfully
+    pub warnings: Vec<String>,
+}
+
+/// Outcome of importing one note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedNote {
+    /// File the note came from; notes of a bundle are named `bundle/page`
+    pub source: String,
+    /// Note that was written, if any
+    pub path: Option<String>,
+    pub warnings: Vec<String>,
+    /// Why nothing was written
+    pub error: Option<String>,
+}
+
+impl ImportedNote {
+    fn failed(source: String, error: String) -> Self {
+        Self { source, path: None, warnings: Vec::new(), error: Some(error) }
+    }
+}
+
+/// Escape characters that would otherwise be read as markdown syntax
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\*_`[]$<".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape the start of a paragraph line that would read as a heading, quote
+/// or list item
+fn escape_line_start(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    let marker = match rest.chars().next() {
+        Some('.' | ')') if digits > 0 => digits,
+        Some('#' | '>') if digits == 0 => 0,
+        Some('-' | '+') if digits == 0 && (rest.len() == 1 || rest[1..].starts_with(' ')) => 0,
+        _ => return line.to_string(),
+    };
+    format!("{}\\{}", &line[..marker], &line[marker..])
+}
+
+/// Add collected inline text to `blocks` as a paragraph, then clear it
+///
+/// Text starting with a checkbox becomes a task item, which is how
+/// Evernote and Notion lay out their to-do lists.
+fn finish_paragraph(paragraph: &mut String, blocks: &mut Vec<String>) {
+    let lines: Vec<&str> = paragraph.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if let Some(first) = lines.first() {
+        let text = if first.starts_with("[ ] ") || first.starts_with("[x] ") {
+            format!("- {}", lines.join(" "))
+        } else {
+            lines.iter().map(|line| escape_line_start(line)).collect::<Vec<_>>().join("\n")
+        };
+        blocks.push(text);
+    }
+    paragraph.clear();
+}
+
+/// Code span with enough backticks to hold the text
+fn code_span(text: &str) -> String {
+    let fence = if text.contains('`') { "``" } else { "`" };
+    let padding = if text.starts_with('`') || text.ends_with('`') { " " } else { "" };
+    format!("{fence}{padding}{text}{padding}{fence}")
+}
+
+/// Link destination that can be written bare in `[text](dest)`
+fn link_destination(dest: &str) -> String {
+    dest.trim().replace(' ', "%20").replace('(', "%28").replace(')', "%29")
+}
+
+/// Whether an image source points at another site rather than a file
+fn is_url(src: &str) -> bool {
+    src.starts_with("//")
+        || src
+            .split_once("://")
+            .is_some_and(|(scheme, _)| scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)))
+}
+
+/// Bytes of a `data:` URL
+fn decode_data_url(url: &str) -> Result<Vec<u8>, String> {
+    let (header, data) = url
+        .strip_prefix("data:")
+        .and_then(|url| url.split_once(','))
+        .ok_or_else(|| "Invalid data URL in image".to_string())?;
+
+    if header.ends_with(";base64") {
+        let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+        general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("Failed to decode base64: {}", e))
+    } else {
+        Ok(percent_decode(data).into_bytes())
+    }
+}
+
+/// Workspace-relative path with `/` separators
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `path` with symlinks and `..` resolved; the part of it that doesn't exist
+/// yet may only name plain folders and files
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let existing = path.ancestors().find(|dir| dir.exists())?;
+    let rest = path.strip_prefix(existing).ok()?;
+    if !rest.components().all(|part| matches!(part, Component::Normal(_))) {
+        return None;
+    }
+    existing.canonicalize().ok().map(|existing| existing.join(rest))
+}
+
+/// Whether `path` stays inside `root` once both are resolved
+fn is_inside(path: &Path, root: &Path) -> bool {
+    matches!((resolve(path), resolve(root)), (Some(path), Some(root)) if path.starts_with(&root))
+}
+
+/// Folder that pasted and imported images are saved to, from the
+/// `imageSaveFolder` setting
+fn image_folder(root: &Path, config: &AppConfig) -> PathBuf {
+    let folder = config
+        .custom_settings
+        .get("imageSaveFolder")
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .unwrap_or(".");
+    let folder = folder.strip_prefix("./").unwrap_or(folder);
+
+    if folder.is_empty() || folder == "." {
+        root.to_path_buf()
+    } else {
+        root.join(folder)
+    }
+}
+
+/// Where imported notes and their images are written
+pub struct ImportTarget {
+    root: PathBuf,
+    notes_dir: PathBuf,
+    images: ImageStore,
+    /// Note paths handed out that may not have been written yet
+    reserved: HashSet<PathBuf>,
+}
+
+impl ImportTarget {
+    fn new(root: PathBuf, notes_dir: PathBuf, image_dir: PathBuf) -> Self {
+        Self { root, notes_dir, images: ImageStore::new(image_dir), reserved: HashSet::new() }
+    }
+
+    /// Path for a new note named after `name` in `dir`, not taken by any
+    /// existing file or earlier note of this import
+    fn note_path(&mut self, dir: &Path, name: &str) -> PathBuf {
+        let stem = images::clean_stem(name);
+        let stem = if stem.is_empty() { "Untitled".to_string() } else { stem };
+
+        let mut path = dir.join(format!("{}.md", stem));
+        let mut counter = 1;
+        while path.exists() || self.reserved.contains(&path) {
+            path = dir.join(format!("{}-{}.md", stem, counter));
+            counter += 1;
+        }
+        self.reserved.insert(path.clone());
+        path
+    }
+
+    /// Link destination for a saved image, relative to the note when both
+    /// are inside the workspace
+    fn image_link(&self, note: &Path, image: &Path) -> String {
+        let dest = match (note.strip_prefix(&self.root), image.strip_prefix(&self.root)) {
+            (Ok(note), Ok(image)) => relative_path_between(&slash_path(note), &slash_path(image)),
+            _ => image.to_string_lossy().to_string(),
+        };
+        link_destination(&dest)
+    }
+
+    /// Convert one note and write it, reporting how that went
+    fn write_note(
+        &mut self,
+        source: String,
+        note: PathBuf,
+        origin: Origin,
+        convert: impl FnOnce(&mut dyn Resources) -> Result<Conversion, String>,
+    ) -> ImportedNote {
+        let conversion = convert(&mut NoteResources { target: self, note: &note, origin });
+        let result = conversion.and_then(|conversion| {
+            // Folder names inside an export bundle must not lead out of the workspace
+            if !is_inside(&note, &self.root) {
+                return Err(format!("Note path is outside the workspace: {}", note.display()));
+            }
+            if let Some(dir) = note.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+            }
+            write_file_atomic(&note, &conversion.markdown)?;
+            Ok(conversion.warnings)
+        });
+
+        match result {
+            Ok(warnings) => ImportedNote {
+                source,
+                path: Some(note.to_string_lossy().to_string()),
+                warnings,
+                error: None,
+            },
+            Err(error) => ImportedNote::failed(source, error),
+        }
+    }
+}
+
+/// What relative image sources and links of a note are relative to
+enum Origin<'a> {
+    /// A file on disk
+    File(&'a Path),
+    /// An Evernote note, whose images are its attachments
+    Enex(&'a enex::Attachments),
+    /// A page of an export bundle
+    Bundle { bundle: &'a bundle::Bundle, page: &'a str },
+}
+
+/// Saves the images of a note being imported and resolves its links
+struct NoteResources<'a> {
+    target: &'a mut ImportTarget,
+    note: &'a Path,
+    origin: Origin<'a>,
+}
+
+impl Resources for NoteResources<'_> {
+    fn image(&mut self, source: ImageSource) -> Result<String, String> {
+        let (data, name) = match source {
+            ImageSource::Url(src) if src.starts_with("data:") => (decode_data_url(src)?, "image".to_string()),
+            ImageSource::Url(src) if is_url(src) => return Ok(link_destination(src)),
+            ImageSource::Url(src) => match &self.origin {
+                Origin::File(path) => {
+                    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                    let file = dir
+                        .join(percent_decode(src.split(['?', '#']).next().unwrap_or(src)))
+                        .canonicalize()
+                        .map_err(|_| format!("Image not found: {}", src))?;
+                    // Only images next to or below the document are imported
+                    if !is_inside(&file, dir) {
+                        return Err(format!("Image outside the imported folder: {}", src));
+                    }
+                    let data = fs::read(&file).map_err(|_| format!("Image not found: {}", src))?;
+                    (data, file.to_string_lossy().to_string())
+                }
+                Origin::Bundle { bundle, page } => {
+                    let file = bundle.resolve(page, src).ok_or_else(|| format!("Image not found: {}", src))?;
+                    (bundle.files[&file].clone(), file)
+                }
+                Origin::Enex(_) => return Err(format!("Image not found: {}", src)),
+            },
+            ImageSource::Media(hash) => {
+                let Origin::Enex(attachments) = &self.origin else {
+                    return Err(format!("Attachment not found: {}", hash));
+                };
+                let attachment = attachments.get(hash).ok_or_else(|| format!("Attachment not found: {}", hash))?;
+                if !attachment.mime.starts_with("image/") {
+                    return Err(format!("Attachment left out: {}", attachment.name));
+                }
+                (attachment.data.clone(), attachment.name.clone())
+            }
+            ImageSource::Embedded { data, name } => (data.to_vec(), name.to_string()),
+        };
+
+        let path = self.target.images.save(&data, &name)?;
+        Ok(self.target.image_link(self.note, &path))
+    }
+
+    fn link(&mut self, href: &str, text: &str) -> Option<String> {
+        let Origin::Bundle { bundle, page } = &self.origin else {
+            return None;
+        };
+        bundle.link(page, href, text)
+    }
+}
+
+/// Import a single file or bundle folder
+fn import_path(path: &Path, target: &mut ImportTarget) -> Vec<ImportedNote> {
+    let source = path.to_string_lossy().to_string();
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let notes_dir = target.notes_dir.clone();
+
+    match extension.as_str() {
+        _ if path.is_dir() => match bundle::Bundle::from_dir(path) {
+            Ok(bundle) => bundle.import(&source, target),
+            Err(error) => vec![ImportedNote::failed(source, error)],
+        },
+        "html" | "htm" => {
+            let note = target.note_path(&notes_dir, &name);
+            let imported = target.write_note(source, note, Origin::File(path), |resources| {
+                let data = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+                Ok(html::html_to_markdown(&String::from_utf8_lossy(&data), resources))
+            });
+            vec![imported]
+        }
+        "docx" => {
+            let note = target.note_path(&notes_dir, &name);
+            let imported = target.write_note(source, note, Origin::File(path), |resources| {
+                let data = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+                docx::docx_to_markdown(&data, resources)
+            });
+            vec![imported]
+        }
+        "enex" => enex::import_enex(path, target),
+        "zip" => match bundle::Bundle::from_zip_file(path) {
+            Ok(bundle) => bundle.import(&source, target),
+            Err(error) => vec![ImportedNote::failed(source, error)],
+        },
+        _ => vec![ImportedNote::failed(source, "Unsupported file type".to_string())],
+    }
+}
+
+/// Import HTML pages, Word documents, Evernote exports and Notion or
+/// Obsidian export bundles (zip files or folders) as markdown notes
+///
+/// Notes are written to `target_dir`, relative to the workspace and the
+/// workspace root when unset; images they contain are saved to the image
+/// folder from the settings. Returns the outcome for every note, with
+/// anything lost in conversion as warnings.
+#[tauri::command]
+pub fn import_files(
+    paths: Vec<String>,
+    folder_path: String,
+    target_dir: Option<String>,
+) -> Result<Vec<ImportedNote>, String> {
+    let root = PathBuf::from(&folder_path);
+    let notes_dir = match target_dir {
+        Some(dir) => root.join(dir),
+        None => root.clone(),
+    };
+    if !is_inside(&notes_dir, &root) {
+        return Err(format!("Import folder is outside the workspace: {}", notes_dir.display()));
+    }
+    fs::create_dir_all(&notes_dir)
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let config = load_app_config(Some(folder_path))?;
+    let image_dir = image_folder(&root, &config);
+    let mut target = ImportTarget::new(root, notes_dir, image_dir);
+
+    Ok(paths
+        .iter()
+        .flat_map(|path| import_path(Path::new(path), &mut target))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape_markdown("a*b_[c]`$5`"), "a\\*b\\_\\[c\\]\\`\\$5\\`");
+        assert_eq!(escape_line_start("# title"), "\\# title");
+        assert_eq!(escape_line_start("2024. A year"), "2024\\. A year");
+        assert_eq!(escape_line_start("- item"), "\\- item");
+        assert_eq!(escape_line_start("-5 degrees"), "-5 degrees");
+        assert_eq!(link_destination("my notes/a (1).png"), "my%20notes/a%20%281%29.png");
+        assert_eq!(decode_data_url("data:image/png;base64,iVBO Rw==").unwrap(), b"\x89PNG");
+    }
+
+    #[test]
+    fn test_import_html_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let source = root.join("source");
+        fs::create_dir_all(source.join("files")).unwrap();
+        fs::write(source.join("files/chart one.png"), b"\x89PNG\r\n\x1a\nchart").unwrap();
+        fs::write(
+            source.join("Report.html"),
+            r#"<h1>Report</h1><p><img src="files/chart%20one.png"><img src="missing.png"><img src="../secret.png"></p>"#,
+        )
+        .unwrap();
+
+        fs::write(root.join("secret.png"), b"\x89PNG\r\n\x1a\nsecret").unwrap();
+
+        let workspace = root.join("workspace");
+        fs::create_dir_all(workspace.join("Existing")).unwrap();
+        fs::write(workspace.join("Existing/Report.md"), "taken").unwrap();
+        let mut target = ImportTarget::new(workspace.clone(), workspace.join("Existing"), workspace.join("assets"));
+
+        let imported = import_path(&source.join("Report.html"), &mut target);
+        assert_eq!(imported.len(), 1);
+        let note = workspace.join("Existing/Report-1.md");
+        assert_eq!(imported[0].path.as_deref(), Some(note.to_string_lossy().as_ref()));
+        assert_eq!(
+            imported[0].warnings,
+            vec!["Image not found: missing.png", "Image outside the imported folder: ../secret.png"]
+        );
+        assert_eq!(
+            fs::read_to_string(&note).unwrap(),
+            "# Report\n\n![](../assets/chart%20one.png)![](missing.png)![](../secret.png)\n"
+        );
+        assert!(workspace.join("assets/chart one.png").is_file());
+
+        let unsupported = import_path(&source.join("notes.txt"), &mut target);
+        assert_eq!(unsupported[0].error.as_deref(), Some("Unsupported file type"));
+
+        let outside = target.write_note("x".to_string(), workspace.join("../escaped.md"), Origin::File(root), |_| {
+            Ok(Conversion::default())
+        });
+        assert!(outside.error.unwrap().starts_with("Note path is outside the workspace"));
+        assert!(!root.join("escaped.md").exists());
+        let folder = workspace.to_string_lossy().to_string();
+        assert!(import_files(Vec::new(), folder, Some("../elsewhere".to_string())).is_err());
+        assert!(!root.join("elsewhere").exists());
+    }
+}
@@ -1,16 +1,41 @@
 mod markdown;
 mod config;
+mod export;
+mod import;
 mod file_watcher;
 mod search;
-
-use markdown::{render_markdown_line, LineRenderResult, RenderRequest};
+mod search_index;
+mod search_scope;
+mod link_updates;
+mod tasks;
+mod outline;
+mod properties;
+mod quick_open;
+mod tags;
+mod replace;
+mod workspace;
+
+use markdown::{render_markdown_line, LineRenderResult, RenderRequest,
+               create_document_store, DocumentStoreHandle, DocumentUpdate, LineEdit,
+               create_render_context, RenderContext, RenderContextHandle};
 use config::{ThemeConfig, AppConfig, initialize_loom_dir, load_app_config, save_app_config,
              load_theme, list_themes, import_theme, export_theme, get_loom_dir,
              get_default_dark_theme_config, get_default_light_theme_config};
 use file_watcher::{FileWatcherStateHandle, create_watcher_state};
-use search::{search_in_content, replace_in_content, search_in_directory};
+use workspace::{WorkspaceStateHandle, create_workspace_state};
+use search_index::{SearchIndexHandle, create_search_index_state, open_search_index};
+use link_updates::{plan_link_updates, move_with_link_updates, FileLinkUpdate};
+use search::{search_in_content, replace_in_content, search_in_directory, start_directory_search, cancel_directory_search, create_search_tasks, FileSearchResult};
+use tasks::{get_content_hash, toggle_task, find_open_tasks_in_directory};
+use properties::{get_note_properties, update_note_properties};
+use tags::{list_tags, get_files_for_tag, rename_tag};
+use quick_open::quick_open;
+use export::{export_html, export_pdf};
+use import::import_files;
+use outline::{get_outline, get_file_outline, refresh_toc, resolve_anchor_link};
+use replace::{preview_replace_in_directory, replace_in_directory, undo_replace};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use base64::{engine::general_purpose, Engine as _};
@@ -24,24 +49,169 @@ struct FileEntry {
     children: Option<Vec<FileEntry>>,
 }
 
+// Snapshot the render settings so the lock isn't held while rendering
+fn current_render_context(render_context: &RenderContextHandle) -> Result<RenderContext, String> {
+    render_context.lock()
+        .map(|context| context.clone())
+        .map_err(|e| format!("Failed to acquire render context lock: {}", e))
+}
+
+// Apply the rendering-related parts of a folder's config
+fn apply_render_settings(config: &AppConfig, render_context: &RenderContextHandle) -> Result<(), String> {
+    let mut context = render_context.lock()
+        .map_err(|e| format!("Failed to acquire render context lock: {}", e))?;
+
+    if context.sanitize != config.sanitize {
+        context.sanitize = config.sanitize.clone();
+        context.revision += 1;
+    }
+    Ok(())
+}
+
+// Hidden sibling a file's new content is staged in before replacing it
+pub(crate) fn atomic_temp_path(path: &Path) -> Result<PathBuf, String> {
+    let file_name = path.file_name()
+        .ok_or_else(|| "Invalid file path".to_string())?
+        .to_string_lossy();
+    Ok(path.with_file_name(format!(".{}.loom-tmp", file_name)))
+}
+
+// Write a file via a temporary sibling and a rename, so a crash mid-write
+// never leaves a truncated note behind
+pub(crate) fn write_file_atomic(path: &Path, content: impl AsRef<[u8]>) -> Result<(), String> {
+    let temp_path = atomic_temp_path(path)?;
+
+    fs::write(&temp_path, content)
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to replace file: {}", e)
+    })
+}
+
+/// New content for one of several files rewritten together
+pub(crate) struct FileRewrite<'a> {
+    pub path: &'a Path,
+    /// Content the file must still have, so edits made elsewhere aren't lost
+    pub original: &'a str,
+    pub updated: &'a str,
+}
+
+// Rewrite several files as one step: every new content is staged next to its
+// file first, then they are all swapped in. If a swap fails, the files already
+// replaced get their original content back.
+pub(crate) fn rewrite_files_atomic(files: &[FileRewrite]) -> Result<(), String> {
+    let mut staged: Vec<PathBuf> = Vec::with_capacity(files.len());
+    for file in files {
+        let result = fs::read_to_string(file.path)
+            .map_err(|e| format!("Failed to read {}: {}", file.path.display(), e))
+            .and_then(|current| {
+                if current == file.original {
+                    Ok(())
+                } else {
+                    Err(format!("{} changed on disk", file.path.display()))
+                }
+            })
+            .and_then(|_| atomic_temp_path(file.path))
+            .and_then(|temp_path| {
+                fs::write(&temp_path, file.updated)
+                    .map(|_| temp_path)
+                    .map_err(|e| format!("Failed to write {}: {}", file.path.display(), e))
+            });
+
+        match result {
+            Ok(temp_path) => staged.push(temp_path),
+            Err(error) => {
+                for temp_path in &staged {
+                    let _ = fs::remove_file(temp_path);
+                }
+                return Err(error);
+            }
+        }
+    }
+
+    for (i, (file, temp_path)) in files.iter().zip(&staged).enumerate() {
+        if let Err(e) = fs::rename(temp_path, file.path) {
+            for done in &files[..i] {
+                let _ = fs::write(done.path, done.original);
+            }
+            for temp_path in &staged[i..] {
+                let _ = fs::remove_file(temp_path);
+            }
+            return Err(format!("Failed to update {}: {}", file.path.display(), e));
+        }
+    }
+
+    Ok(())
+}
+
 // Markdown rendering commands
 #[tauri::command]
-fn render_markdown(request: RenderRequest) -> LineRenderResult {
-    render_markdown_line(request)
+fn render_markdown(
+    request: RenderRequest,
+    render_context: State<RenderContextHandle>,
+) -> Result<LineRenderResult, String> {
+    let context = current_render_context(&render_context)?;
+    Ok(render_markdown_line(request, &context))
 }
 
-// Batch rendering for multiple lines (parallelized for performance)
+// Batch rendering for the lines of one document; superseded by the document
+// session commands below, which only send edits
 #[tauri::command]
-fn render_markdown_batch(requests: Vec<RenderRequest>) -> Vec<LineRenderResult> {
-    use rayon::prelude::*;
+fn render_markdown_batch(
+    requests: Vec<RenderRequest>,
+    render_context: State<RenderContextHandle>,
+) -> Result<Vec<LineRenderResult>, String> {
+    let context = current_render_context(&render_context)?;
+    Ok(markdown::render_markdown_batch(requests, &context))
+}
 
-    // Use parallel iterator for large batches (>50 lines)
-    if requests.len() > 50 {
-        requests.into_par_iter().map(render_markdown_line).collect()
-    } else {
-        // For small batches, sequential is faster (no thread overhead)
-        requests.into_iter().map(render_markdown_line).collect()
-    }
+// Incremental document rendering commands
+
+/// Open a document session and render all of its lines once
+#[tauri::command]
+fn open_document(
+    doc_id: String,
+    lines: Vec<String>,
+    editing_line: Option<usize>,
+    document_store: State<DocumentStoreHandle>,
+    render_context: State<RenderContextHandle>,
+) -> Result<Vec<LineRenderResult>, String> {
+    let context = current_render_context(&render_context)?;
+    let mut store = document_store.lock()
+        .map_err(|e| format!("Failed to acquire document lock: {}", e))?;
+
+    Ok(store.open(doc_id, lines, editing_line, &context))
+}
+
+/// Apply line edits to an open document and return only the lines whose HTML changed
+#[tauri::command]
+fn update_document(
+    doc_id: String,
+    edits: Vec<LineEdit>,
+    editing_line: Option<usize>,
+    document_store: State<DocumentStoreHandle>,
+    render_context: State<RenderContextHandle>,
+) -> Result<DocumentUpdate, String> {
+    let context = current_render_context(&render_context)?;
+    let mut store = document_store.lock()
+        .map_err(|e| format!("Failed to acquire document lock: {}", e))?;
+
+    store.update(&doc_id, &edits, editing_line, &context)
+}
+
+/// Close a document session and release its cached render state
+#[tauri::command]
+fn close_document(
+    doc_id: String,
+    document_store: State<DocumentStoreHandle>,
+) -> Result<(), String> {
+    let mut store = document_store.lock()
+        .map_err(|e| format!("Failed to acquire document lock: {}", e))?;
+
+    store.close(&doc_id);
+    Ok(())
 }
 
 // Read directory contents recursively
@@ -249,9 +419,57 @@ fn count_folder_contents(path: String) -> Result<(usize, usize), String> {
     Ok((file_count, folder_count))
 }
 
+// Move a path, rewriting links to and from it across the workspace when asked
+fn move_and_update_links(
+    old_path: &Path,
+    new_path: &Path,
+    update_links: bool,
+    workspace: &WorkspaceStateHandle,
+    render_context: &RenderContextHandle,
+) -> Result<(), String> {
+    if !update_links {
+        return fs::rename(old_path, new_path)
+            .map_err(|e| format!("Failed to move: {}", e));
+    }
+
+    let mut workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    let plan = plan_link_updates(&workspace, old_path, new_path)?;
+    move_with_link_updates(old_path, new_path, &plan)?;
+
+    // Don't wait for the watcher, so a follow-up query sees the new state
+    workspace.refresh_path(old_path);
+    workspace.refresh_path(new_path);
+    for path in plan.updated_paths() {
+        workspace.refresh_path(path);
+    }
+    workspace.publish(render_context)
+}
+
+/// Preview the link edits a rename or move would make, without changing anything
+#[tauri::command]
+fn preview_link_updates(
+    old_path: String,
+    new_path: String,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<Vec<FileLinkUpdate>, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    let plan = plan_link_updates(&workspace, Path::new(&old_path), Path::new(&new_path))?;
+    Ok(plan.preview())
+}
+
 // Rename a file or folder
 #[tauri::command]
-fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
+fn rename_path(
+    old_path: String,
+    new_name: String,
+    update_links: Option<bool>,
+    workspace: State<WorkspaceStateHandle>,
+    render_context: State<RenderContextHandle>,
+) -> Result<String, String> {
     let old_path_buf = PathBuf::from(&old_path);
 
     // Check if path exists
@@ -271,9 +489,8 @@ fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
         return Err("A file or folder with that name already exists".to_string());
     }
 
-    // Rename
-    fs::rename(&old_path_buf, &new_path_buf)
-        .map_err(|e| format!("Failed to rename: {}", e))?;
+    // Rename (errors already say what failed)
+    move_and_update_links(&old_path_buf, &new_path_buf, update_links.unwrap_or(false), &workspace, &render_context)?;
 
     let new_path = new_path_buf.to_string_lossy().to_string();
     println!("Renamed {:?} to {:?}", old_path, new_path);
@@ -282,7 +499,13 @@ fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
 
 // Move a file or folder to a different directory
 #[tauri::command]
-fn move_path(source_path: String, dest_dir_path: String) -> Result<String, String> {
+fn move_path(
+    source_path: String,
+    dest_dir_path: String,
+    update_links: Option<bool>,
+    workspace: State<WorkspaceStateHandle>,
+    render_context: State<RenderContextHandle>,
+) -> Result<String, String> {
     let source_path_buf = PathBuf::from(&source_path);
     let dest_dir_buf = PathBuf::from(&dest_dir_path);
 
@@ -314,8 +537,7 @@ fn move_path(source_path: String, dest_dir_path: String) -> Result<String, Strin
     }
 
     // Move (rename) the file/folder
-    fs::rename(&source_path_buf, &new_path_buf)
-        .map_err(|e| format!("Failed to move: {}", e))?;
+    move_and_update_links(&source_path_buf, &new_path_buf, update_links.unwrap_or(false), &workspace, &render_context)?;
 
     let new_path = new_path_buf.to_string_lossy().to_string();
     println!("Moved {:?} to {:?}", source_path, new_path);
@@ -476,13 +698,54 @@ fn start_watching_directory(
     path: String,
     app_handle: tauri::AppHandle,
     watcher_state: State<FileWatcherStateHandle>,
+    workspace: State<WorkspaceStateHandle>,
+    render_context: State<RenderContextHandle>,
+    search_index: State<SearchIndexHandle>,
 ) -> Result<(), String> {
+    // Index the folder's notes before any events for it can arrive
+    {
+        let mut workspace = workspace.lock()
+            .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+        workspace.open(&PathBuf::from(&path));
+        workspace.publish(&render_context)?;
+    }
+    // The full-text index can take a while, so it is built in the background
+    open_search_index(&search_index, Path::new(&path))?;
+
     let mut state = watcher_state.lock()
         .map_err(|e| format!("Failed to acquire watcher lock: {}", e))?;
 
     state.start_watching(&path, app_handle)
 }
 
+/// Find the notes linking to a note, with the line containing each link
+#[tauri::command]
+fn get_backlinks(
+    path: String,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<Vec<FileSearchResult>, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    Ok(workspace.backlinks(&PathBuf::from(&path)))
+}
+
+/// Resolve a wiki-link target to the absolute path of a note
+#[tauri::command]
+fn resolve_wiki_link(
+    target: String,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<Option<String>, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    let notes = workspace.notes();
+    Ok(notes.resolve(&target)
+        .and_then(|relative_path| notes.absolute_path(relative_path))
+        .map(|path| path.to_string_lossy().to_string()))
+}
+
 /// Stop watching the current directory
 #[tauri::command]
 fn stop_watching_directory(
@@ -511,14 +774,24 @@ fn get_loom_directory(folder_path: Option<String>) -> Result<String, String> {
 
 /// Load application configuration
 #[tauri::command]
-fn load_config(folder_path: Option<String>) -> Result<AppConfig, String> {
-    load_app_config(folder_path)
+fn load_config(
+    folder_path: Option<String>,
+    render_context: State<RenderContextHandle>,
+) -> Result<AppConfig, String> {
+    let config = load_app_config(folder_path)?;
+    apply_render_settings(&config, &render_context)?;
+    Ok(config)
 }
 
 /// Save application configuration
 #[tauri::command]
-fn save_config(folder_path: Option<String>, config: AppConfig) -> Result<(), String> {
-    save_app_config(folder_path, &config)
+fn save_config(
+    folder_path: Option<String>,
+    config: AppConfig,
+    render_context: State<RenderContextHandle>,
+) -> Result<(), String> {
+    save_app_config(folder_path, &config)?;
+    apply_render_settings(&config, &render_context)
 }
 
 /// Set the current theme
@@ -531,14 +804,24 @@ fn set_theme(folder_path: Option<String>, theme_name: String) -> Result<(), Stri
 
 /// Get the full config (simplified version without folder requirement for better compatibility)
 #[tauri::command]
-fn get_config(folder_path: Option<String>) -> Result<AppConfig, String> {
-    load_app_config(folder_path)
+fn get_config(
+    folder_path: Option<String>,
+    render_context: State<RenderContextHandle>,
+) -> Result<AppConfig, String> {
+    let config = load_app_config(folder_path)?;
+    apply_render_settings(&config, &render_context)?;
+    Ok(config)
 }
 
 /// Update the full config (simplified version that can update any config field)
 #[tauri::command]
-fn update_config(folder_path: Option<String>, config: AppConfig) -> Result<(), String> {
-    save_app_config(folder_path, &config)
+fn update_config(
+    folder_path: Option<String>,
+    config: AppConfig,
+    render_context: State<RenderContextHandle>,
+) -> Result<(), String> {
+    save_app_config(folder_path, &config)?;
+    apply_render_settings(&config, &render_context)
 }
 
 /// Get the current theme configuration
@@ -603,9 +886,17 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(create_watcher_state())
+        .manage(create_document_store())
+        .manage(create_render_context())
+        .manage(create_workspace_state())
+        .manage(create_search_index_state())
+        .manage(create_search_tasks())
         .invoke_handler(tauri::generate_handler![
             render_markdown,
             render_markdown_batch,
+            open_document,
+            update_document,
+            close_document,
             read_directory,
             read_file_from_path,
             create_file,
@@ -615,11 +906,14 @@ pub fn run() {
             count_folder_contents,
             rename_path,
             move_path,
+            preview_link_updates,
             copy_path,
             save_image_from_clipboard,
             is_image_file,
             start_watching_directory,
             stop_watching_directory,
+            resolve_wiki_link,
+            get_backlinks,
             init_loom_dir,
             get_loom_directory,
             load_config,
@@ -635,6 +929,27 @@ pub fn run() {
             search_in_content,
             replace_in_content,
             search_in_directory,
+            start_directory_search,
+            cancel_directory_search,
+            preview_replace_in_directory,
+            replace_in_directory,
+            undo_replace,
+            get_content_hash,
+            toggle_task,
+            find_open_tasks_in_directory,
+            get_note_properties,
+            update_note_properties,
+            list_tags,
+            get_files_for_tag,
+            rename_tag,
+            quick_open,
+            get_outline,
+            get_file_outline,
+            refresh_toc,
+            resolve_anchor_link,
+            export_html,
+            export_pdf,
+            import_files,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
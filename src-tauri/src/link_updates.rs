@@ -0,0 +1,371 @@
+use crate::{rewrite_files_atomic, FileRewrite};
+use crate::markdown::{extract_links, LinkKind, NoteIndex};
+use crate::workspace::{resolve_relative_link, WorkspaceState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkLineEdit {
+    pub line: usize,
+    pub line_text: String,
+    pub new_line_text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileLinkUpdate {
+    pub file_path: String,
+    pub edits: Vec<LinkLineEdit>,
+}
+
+/// A note whose links change when a path moves
+struct PlannedFile {
+    /// Where the note is before the move
+    old_path: PathBuf,
+    /// Where the note is after the move (differs for notes inside a moved folder)
+    new_path: PathBuf,
+    original: String,
+    updated: String,
+    edits: Vec<LinkLineEdit>,
+}
+
+/// Link rewrites needed to move one file or folder
+pub struct LinkUpdatePlan {
+    files: Vec<PlannedFile>,
+}
+
+impl LinkUpdatePlan {
+    pub fn preview(&self) -> Vec<FileLinkUpdate> {
+        self.files
+            .iter()
+            .map(|file| FileLinkUpdate {
+                file_path: file.old_path.to_string_lossy().to_string(),
+                edits: file.edits.clone(),
+            })
+            .collect()
+    }
+
+    /// Paths of every note the plan rewrites, after the move
+    pub fn updated_paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|file| file.new_path.as_path())
+    }
+}
+
+/// Where a workspace-relative path ends up after `old` is moved to `new`
+fn map_moved(path: &str, old: &str, new: &str) -> String {
+    if path == old {
+        return new.to_string();
+    }
+    match path.strip_prefix(old).and_then(|rest| rest.strip_prefix('/')) {
+        Some(rest) => format!("{}/{}", new, rest),
+        None => path.to_string(),
+    }
+}
+
+/// Relative path from the folder containing `source` to `target`
+pub(crate) fn relative_path_between(source: &str, target: &str) -> String {
+    let from: Vec<&str> = source.split('/').collect();
+    let from = &from[..from.len() - 1];
+    let to: Vec<&str> = target.split('/').collect();
+
+    let common = from
+        .iter()
+        .zip(&to[..to.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<&str> = std::iter::repeat_n("..", from.len() - common).collect();
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
+
+/// Write a new link destination in the same style as the one it replaces
+fn format_destination(original: &str, in_angle_brackets: bool, source: &str, target: &str) -> String {
+    let mut path = if original.starts_with('/') {
+        format!("/{}", target)
+    } else {
+        relative_path_between(source, target)
+    };
+
+    // Keep extensionless note links extensionless
+    if Path::new(original).extension().is_none() {
+        if let Some(stripped) = path.strip_suffix(".md") {
+            path = stripped.to_string();
+        }
+    }
+
+    if original.starts_with("./") && !path.starts_with("../") {
+        path = format!("./{}", path);
+    }
+
+    if in_angle_brackets {
+        path
+    } else {
+        // Bare destinations can't contain spaces or unbalanced parentheses
+        path.replace('%', "%25")
+            .replace(' ', "%20")
+            .replace('(', "%28")
+            .replace(')', "%29")
+    }
+}
+
+/// Wiki-link target text after the note it resolves to moves
+fn rewrite_wiki_target(written: &str, old_target: &str, new_target: &str, after: &NoteIndex) -> String {
+    if old_target == new_target || after.resolve(written) == Some(new_target) {
+        return written.to_string();
+    }
+
+    let new_without_ext = new_target.strip_suffix(".md").unwrap_or(new_target);
+    let name = new_without_ext.rsplit('/').next().unwrap_or(new_without_ext);
+
+    // Bare names stay bare unless another note would now win the name
+    let rewritten = if !written.contains('/') && after.resolve(name) == Some(new_target) {
+        name
+    } else {
+        new_without_ext
+    };
+
+    if written.to_lowercase().ends_with(".md") {
+        format!("{}.md", rewritten)
+    } else {
+        rewritten.to_string()
+    }
+}
+
+/// Work out every link edit needed to move `old_path` to `new_path`
+///
+/// Notes are re-read from disk rather than taken from the index, so the
+/// edits always apply to their current content.
+pub fn plan_link_updates(
+    workspace: &WorkspaceState,
+    old_path: &Path,
+    new_path: &Path,
+) -> Result<LinkUpdatePlan, String> {
+    let notes = workspace.notes();
+    let outside = || "Path is outside the open folder".to_string();
+    let old = notes.relative_path(old_path).ok_or_else(outside)?;
+    let new = notes.relative_path(new_path).ok_or_else(outside)?;
+
+    // Name index as it will be after the move, for checking wiki-links
+    let mut after = notes.clone();
+    after.remove(&old);
+    for (source, _) in workspace.link_sources() {
+        let moved = map_moved(source, &old, &new);
+        if moved != *source {
+            after.insert(&moved);
+        }
+    }
+
+    let is_moved = |path: &str| map_moved(path, &old, &new) != path;
+
+    // Only notes that moved, or link to something that moved, need a look
+    let mut candidates: Vec<&String> = workspace
+        .link_sources()
+        .filter(|(source, links)| {
+            is_moved(source)
+                || links.iter().any(|link| match link.kind {
+                    LinkKind::Wiki => notes.resolve(&link.target).is_some_and(is_moved),
+                    _ => resolve_relative_link(source, &link.target).is_some_and(|t| is_moved(&t)),
+                })
+        })
+        .map(|(source, _)| source)
+        .collect();
+    candidates.sort();
+
+    let mut files = Vec::new();
+
+    for source in candidates {
+        let new_source = map_moved(source, &old, &new);
+        let file_path = notes.absolute_path(source).ok_or_else(outside)?;
+        let original = fs::read_to_string(&file_path)
+            .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+
+        // Replacements per line, as (destination range, new text)
+        let mut replacements: BTreeMap<usize, Vec<(std::ops::Range<usize>, String)>> = BTreeMap::new();
+
+        for link in extract_links(&original) {
+            let Some(range) = link.destination.clone() else {
+                continue;
+            };
+            let written = &link.line_text[range.clone()];
+
+            let replacement = match link.kind {
+                LinkKind::Wiki => {
+                    let Some(old_target) = notes.resolve(&link.target) else {
+                        continue;
+                    };
+                    let new_target = map_moved(old_target, &old, &new);
+                    rewrite_wiki_target(written, old_target, &new_target, &after)
+                }
+                LinkKind::Markdown | LinkKind::Image => {
+                    let path_end = written.find(['#', '?']).unwrap_or(written.len());
+                    let (path_part, suffix) = written.split_at(path_end);
+                    let Some(old_target) = resolve_relative_link(source, path_part) else {
+                        continue;
+                    };
+                    let new_target = map_moved(&old_target, &old, &new);
+                    if new_source == *source && new_target == old_target {
+                        continue;
+                    }
+
+                    let in_angle_brackets = link.line_text[..range.start].ends_with('<');
+                    let destination = format_destination(path_part, in_angle_brackets, &new_source, &new_target);
+                    format!("{}{}", destination, suffix)
+                }
+            };
+
+            if replacement != written {
+                replacements.entry(link.line).or_default().push((range, replacement));
+            }
+        }
+
+        if replacements.is_empty() {
+            continue;
+        }
+
+        let mut updated = String::with_capacity(original.len());
+        let mut edits = Vec::new();
+
+        for (i, raw_line) in original.split_inclusive('\n').enumerate() {
+            let line = raw_line.trim_end_matches(['\n', '\r']);
+            let ending = &raw_line[line.len()..];
+
+            match replacements.get(&i) {
+                Some(line_replacements) => {
+                    let mut new_line = line.to_string();
+                    // Right to left, so earlier ranges stay valid
+                    for (range, text) in line_replacements.iter().rev() {
+                        new_line.replace_range(range.clone(), text);
+                    }
+                    updated.push_str(&new_line);
+                    edits.push(LinkLineEdit {
+                        line: i + 1,
+                        line_text: line.to_string(),
+                        new_line_text: new_line,
+                    });
+                }
+                None => updated.push_str(line),
+            }
+            updated.push_str(ending);
+        }
+
+        files.push(PlannedFile {
+            old_path: file_path,
+            new_path: notes.absolute_path(&new_source).ok_or_else(outside)?,
+            original,
+            updated,
+            edits,
+        });
+    }
+
+    Ok(LinkUpdatePlan { files })
+}
+
+/// Move a path and apply its link updates, all or nothing
+///
+/// New contents are staged next to each note before anything is replaced.
+/// If staging or replacing fails, written notes are restored and the move
+/// is undone.
+pub fn move_with_link_updates(old_path: &Path, new_path: &Path, plan: &LinkUpdatePlan) -> Result<(), String> {
+    fs::rename(old_path, new_path)
+        .map_err(|e| format!("Failed to move: {}", e))?;
+
+    let undo_move = |error: String| -> String {
+        match fs::rename(new_path, old_path) {
+            Ok(()) => error,
+            Err(e) => format!("{} (and failed to undo the move: {})", error, e),
+        }
+    };
+
+    let rewrites: Vec<FileRewrite> = plan
+        .files
+        .iter()
+        .map(|file| FileRewrite {
+            path: &file.new_path,
+            original: &file.original,
+            updated: &file.updated,
+        })
+        .collect();
+
+    rewrite_files_atomic(&rewrites).map_err(undo_move)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_paths() {
+        assert_eq!(relative_path_between("a/Note.md", "a/Other.md"), "Other.md");
+        assert_eq!(relative_path_between("a/b/Note.md", "c/img.png"), "../../c/img.png");
+        assert_eq!(relative_path_between("Note.md", "a/b/Other.md"), "a/b/Other.md");
+
+        assert_eq!(format_destination("./Plan", false, "Inbox.md", "archive/My Plan.md"), "./archive/My%20Plan");
+        assert_eq!(format_destination("/Plan.md", true, "x/Inbox.md", "archive/My Plan.md"), "/archive/My Plan.md");
+    }
+
+    #[test]
+    fn test_move_folder_rewrites_links() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("projects/assets")).unwrap();
+        fs::create_dir_all(root.join("archive")).unwrap();
+        fs::write(root.join("Inbox.md"), "[plan](projects/Plan.md#goals) and [[Plan]]\r\n![](projects/assets/chart.png)\r\n").unwrap();
+        fs::write(root.join("projects/Plan.md"), "![chart](assets/chart.png)\n[home](../Inbox.md)\n").unwrap();
+        fs::write(root.join("projects/assets/chart.png"), "").unwrap();
+
+        let mut workspace = WorkspaceState::new();
+        workspace.open(root);
+
+        let old_path = root.join("projects");
+        let new_path = root.join("archive/projects");
+        let plan = plan_link_updates(&workspace, &old_path, &new_path).unwrap();
+
+        let preview = plan.preview();
+        assert_eq!(preview.len(), 2);
+        assert_eq!(preview[0].edits.len(), 2);
+        assert_eq!(preview[0].edits[0].new_line_text, "[plan](archive/projects/Plan.md#goals) and [[Plan]]");
+
+        move_with_link_updates(&old_path, &new_path, &plan).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(root.join("Inbox.md")).unwrap(),
+            "[plan](archive/projects/Plan.md#goals) and [[Plan]]\r\n![](archive/projects/assets/chart.png)\r\n"
+        );
+        // Links to files that moved along are unchanged, links out of the folder are rewritten
+        assert_eq!(
+            fs::read_to_string(root.join("archive/projects/Plan.md")).unwrap(),
+            "![chart](assets/chart.png)\n[home](../../Inbox.md)\n"
+        );
+    }
+
+    #[test]
+    fn test_rename_updates_wiki_links_and_rolls_back() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::write(root.join("Plan.md"), "").unwrap();
+        fs::write(root.join("Inbox.md"), "See [[Plan#Goals|goals]] and [p](Plan)").unwrap();
+
+        let mut workspace = WorkspaceState::new();
+        workspace.open(root);
+
+        let plan = plan_link_updates(&workspace, &root.join("Plan.md"), &root.join("Road Map.md")).unwrap();
+
+        // A concurrent edit makes the whole operation back out
+        fs::write(root.join("Inbox.md"), "edited elsewhere").unwrap();
+        assert!(move_with_link_updates(&root.join("Plan.md"), &root.join("Road Map.md"), &plan).is_err());
+        assert!(root.join("Plan.md").exists());
+        assert!(!root.join("Road Map.md").exists());
+        assert_eq!(fs::read_to_string(root.join("Inbox.md")).unwrap(), "edited elsewhere");
+
+        fs::write(root.join("Inbox.md"), "See [[Plan#Goals|goals]] and [p](Plan)").unwrap();
+        move_with_link_updates(&root.join("Plan.md"), &root.join("Road Map.md"), &plan).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("Inbox.md")).unwrap(),
+            "See [[Road Map#Goals|goals]] and [p](Road%20Map)"
+        );
+    }
+}
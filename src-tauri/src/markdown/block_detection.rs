@@ -1,60 +1,483 @@
 /**
  * Block detection utilities for markdown rendering
  *
- * This module handles detection of code blocks and math blocks
- * to ensure proper context-aware rendering.
+ * This module handles detection of code blocks, math blocks, tables,
+ * frontmatter and quotes to ensure proper context-aware rendering. It also
+ * assigns heading anchors, which depend on the headings above them, and
+ * tracks the callouts (`> [!NOTE]`) a quote line belongs to.
  */
 
-/// Check if a line is inside a code block
+use super::diagram::DiagramKind;
+use super::highlight::Highlighter;
+use super::outline::{heading_text, slugify, SlugGenerator};
+use super::{CALLOUT_RE, LANG_RE};
+
+/// Position of a line relative to a fenced block (``` or $$)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockPosition {
+    pub in_block: bool,
+    pub is_start: bool,
+    pub is_end: bool,
+}
+
+/// Horizontal alignment of a table column, taken from the delimiter row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnAlignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// Role of a line within a GFM table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableRow {
+    Header,
+    Delimiter,
+    Body,
+}
+
+/// Table context of a line that belongs to a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLine {
+    pub row: TableRow,
+    pub alignments: Vec<ColumnAlignment>,
+}
+
+/// Syntax of a frontmatter block, chosen by its opening delimiter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontmatterFormat {
+    /// `---` ... `---` (or `...`)
+    Yaml,
+    /// `+++` ... `+++`
+    Toml,
+}
+
+impl FrontmatterFormat {
+    /// Format opened by a document's first line, if it is a frontmatter delimiter
+    pub fn from_opening_line(line: &str) -> Option<Self> {
+        match line.trim_end() {
+            "---" => Some(Self::Yaml),
+            "+++" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn is_closing_line(self, line: &str) -> bool {
+        match self {
+            Self::Yaml => matches!(line.trim_end(), "---" | "..."),
+            Self::Toml => line.trim_end() == "+++",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+        }
+    }
+
+    pub fn delimiter(self) -> &'static str {
+        match self {
+            Self::Yaml => "---",
+            Self::Toml => "+++",
+        }
+    }
+}
+
+/// Fold state of a callout, from the sign after its type: `[!NOTE]-`
+/// starts collapsed, `[!NOTE]+` expanded, and without one it can't fold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalloutFold {
+    None,
+    Expanded,
+    Collapsed,
+}
+
+/// A callout opened by a `> [!TYPE] Title` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Callout {
+    /// Type as written, lowercased, e.g. `warning`
+    pub kind: String,
+    pub fold: CalloutFold,
+    /// Quote level of its header
+    pub depth: usize,
+}
+
+/// Quote context of a line starting with `>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteLine {
+    /// Number of `>` markers
+    pub depth: usize,
+    /// Callouts the line is inside, outermost first
+    pub callouts: Vec<Callout>,
+    /// The line is the header of the innermost callout
+    pub is_callout_start: bool,
+    /// The line is hidden inside a collapsed callout
+    pub folded: bool,
+}
+
+/// Block context of a single line, as needed by the line renderer
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LineBlockState {
+    /// Frontmatter can only open on the first line of a document
+    pub frontmatter: Option<(FrontmatterFormat, BlockPosition)>,
+    pub code: BlockPosition,
+    pub math: BlockPosition,
+    pub table: Option<TableLine>,
+    /// Anchor id of a heading line, unique within the document
+    pub heading_id: Option<String>,
+    /// Highlighter of a line inside a code block, in the state the lines
+    /// above it left it in
+    pub highlight: Option<Highlighter>,
+    /// LaTeX of a math block, on the line that closes it
+    pub math_source: Option<String>,
+    /// Language and source of a diagram block, on the line that closes it
+    pub diagram: Option<(DiagramKind, String)>,
+    pub quote: Option<QuoteLine>,
+}
+
+/// Incremental scanner over document lines
+///
+/// Holds the block state *before* a line. Feeding lines in order through
+/// `advance` yields the same results as `block_state_at` without rescanning
+/// from the top of the document for every line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BlockScanner {
+    structure: StructureScanner,
+    /// Anchors of the headings seen so far
+    slugs: SlugGenerator,
+}
+
+impl BlockScanner {
+    /// Scanner positioned before the first line of a document
+    pub fn for_document<S: AsRef<str>>(lines: &[S]) -> Self {
+        Self {
+            structure: StructureScanner::for_document(lines),
+            slugs: SlugGenerator::default(),
+        }
+    }
+
+    /// Compute the block state of `line` and move the scanner past it
+    ///
+    /// `next_line` is needed because a table header is only recognized
+    /// when it is followed by a delimiter row.
+    pub fn advance(&mut self, line: &str, next_line: Option<&str>) -> LineBlockState {
+        let mut state = self.structure.advance(line, next_line);
+        state.heading_id = state.heading_id.map(|base| self.slugs.unique(base));
+        state
+    }
+}
+
+/// The part of `BlockScanner` that doesn't depend on the headings above
 ///
-/// Returns a tuple of (in_block, is_start, is_end)
-/// - in_block: true if the line is inside a code block
-/// - is_start: true if this line starts a code block
-/// - is_end: true if this line ends a code block
-pub fn is_in_code_block(line_index: usize, all_lines: &[String]) -> (bool, bool, bool) {
-    let mut in_block = false;
-
-    for (i, line) in all_lines.iter().enumerate() {
-        if i > line_index {
-            break;
-        }
-
-        if line.trim().starts_with("```") {
-            if i == line_index {
-                // This line is a code block boundary
-                return (true, !in_block, in_block);
+/// Its `advance` leaves `heading_id` as the heading's slug before it is made
+/// unique in the document. Unlike the anchors seen so far, this state stays
+/// small, so editors can keep copies of it to restart scans from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StructureScanner {
+    /// False until the first line has been scanned
+    past_first_line: bool,
+    /// The first line opens frontmatter that a later line closes
+    frontmatter_closed: bool,
+    in_frontmatter: Option<FrontmatterFormat>,
+    in_code: bool,
+    /// LaTeX of the math block the scanner is currently inside, so far
+    math: Option<String>,
+    /// Column alignments of the table the scanner is currently inside
+    table: Option<Vec<ColumnAlignment>>,
+    /// Set after a table header row, whose delimiter row comes next
+    expect_delimiter: bool,
+    /// Highlighter of the code block the scanner is currently inside
+    highlighter: Option<Highlighter>,
+    /// Language and source so far of the diagram block the scanner is inside
+    diagram: Option<(DiagramKind, String)>,
+    /// Quote level of the line before, 0 outside quotes
+    quote_depth: usize,
+    /// Callouts the scanner is currently inside, outermost first
+    callouts: Vec<Callout>,
+}
+
+impl StructureScanner {
+    /// Scanner positioned before the first line of a document
+    ///
+    /// An opening `---` or `+++` only starts frontmatter when a closing
+    /// delimiter follows, so that is looked up front.
+    pub fn for_document<S: AsRef<str>>(lines: &[S]) -> Self {
+        let frontmatter_closed = lines.split_first().is_some_and(|(first, rest)| {
+            FrontmatterFormat::from_opening_line(first.as_ref())
+                .is_some_and(|format| rest.iter().any(|line| format.is_closing_line(line.as_ref())))
+        });
+
+        Self {
+            frontmatter_closed,
+            ..Default::default()
+        }
+    }
+
+    /// Compute the block state of `line` and move the scanner past it
+    pub fn advance(&mut self, line: &str, next_line: Option<&str>) -> LineBlockState {
+        let is_first_line = !self.past_first_line;
+        self.past_first_line = true;
+
+        if let Some(format) = self.in_frontmatter {
+            let is_end = format.is_closing_line(line);
+            if is_end {
+                self.in_frontmatter = None;
+            }
+            return LineBlockState {
+                frontmatter: Some((format, BlockPosition { in_block: true, is_start: false, is_end })),
+                ..Default::default()
+            };
+        }
+
+        if let Some(format) = FrontmatterFormat::from_opening_line(line).filter(|_| is_first_line && self.frontmatter_closed) {
+            self.in_frontmatter = Some(format);
+            return LineBlockState {
+                frontmatter: Some((format, BlockPosition { in_block: true, is_start: true, is_end: false })),
+                ..Default::default()
+            };
+        }
+
+        let trimmed = line.trim();
+
+        let mut diagram = None;
+        let code = if trimmed.starts_with("```") {
+            let position = BlockPosition {
+                in_block: true,
+                is_start: !self.in_code,
+                is_end: self.in_code,
+            };
+            self.in_code = !self.in_code;
+            let lang = LANG_RE.captures(trimmed).and_then(|cap| cap.get(1)).map_or("", |m| m.as_str());
+            self.highlighter = self.in_code.then(|| Highlighter::new(lang));
+            diagram = self.diagram.take();
+            if self.in_code {
+                self.diagram = DiagramKind::from_lang(lang).map(|kind| (kind, String::new()));
+            }
+            position
+        } else {
+            if let Some((_, source)) = self.diagram.as_mut() {
+                source.push_str(line);
+                source.push('\n');
+            }
+            BlockPosition { in_block: self.in_code, ..Default::default() }
+        };
+
+        // Block comments and multi-line strings carry over to the next line
+        let highlight = self.highlighter.filter(|_| code.in_block && !code.is_start);
+        if let Some(highlighter) = self.highlighter.as_mut().filter(|_| highlight.is_some()) {
+            highlighter.skip_line(line);
+        }
+
+        let mut math_source = None;
+        let math = if trimmed == "$$" {
+            let position = BlockPosition {
+                in_block: true,
+                is_start: self.math.is_none(),
+                is_end: self.math.is_some(),
+            };
+            math_source = self.math.take();
+            if position.is_start {
+                self.math = Some(String::new());
+            }
+            position
+        } else {
+            if let Some(latex) = self.math.as_mut() {
+                if !latex.is_empty() {
+                    latex.push('\n');
+                }
+                latex.push_str(line);
+            }
+            BlockPosition { in_block: self.math.is_some(), ..Default::default() }
+        };
+
+        let table = if code.in_block || math.in_block {
+            self.table = None;
+            self.expect_delimiter = false;
+            None
+        } else {
+            self.advance_table(line, next_line)
+        };
+
+        let heading_id = if code.in_block || math.in_block || table.is_some() {
+            None
+        } else {
+            heading_text(line).map(|(_, text)| slugify(&text))
+        };
+
+        let quote = if code.in_block || math.in_block || table.is_some() {
+            self.quote_depth = 0;
+            self.callouts.clear();
+            None
+        } else {
+            self.advance_quote(line)
+        };
+
+        LineBlockState { frontmatter: None, code, math, table, heading_id, highlight, math_source, diagram, quote }
+    }
+
+    fn advance_quote(&mut self, line: &str) -> Option<QuoteLine> {
+        let Some((depth, text)) = parse_quote(line) else {
+            self.quote_depth = 0;
+            self.callouts.clear();
+            return None;
+        };
+
+        self.callouts.retain(|callout| callout.depth <= depth);
+        let folded = self.callouts.iter().any(|callout| callout.fold == CalloutFold::Collapsed);
+
+        // Only the first line of a quote level can be a callout header
+        let header = parse_callout_header(text).filter(|_| depth > self.quote_depth);
+        self.quote_depth = depth;
+        if let Some((kind, fold, _)) = header {
+            self.callouts.push(Callout { kind: kind.to_lowercase(), fold, depth });
+        }
+
+        Some(QuoteLine { depth, callouts: self.callouts.clone(), is_callout_start: header.is_some(), folded })
+    }
+
+    fn advance_table(&mut self, line: &str, next_line: Option<&str>) -> Option<TableLine> {
+        if self.expect_delimiter {
+            self.expect_delimiter = false;
+            return self.table.clone().map(|alignments| TableLine { row: TableRow::Delimiter, alignments });
+        }
+
+        if self.table.is_some() && is_table_row(line) {
+            return self.table.clone().map(|alignments| TableLine { row: TableRow::Body, alignments });
+        }
+
+        self.table = None;
+
+        if !is_table_row(line) {
+            return None;
+        }
+
+        let alignments = next_line.and_then(parse_delimiter_row)?;
+        if alignments.len() != table_cell_ranges(line).len() {
+            return None;
+        }
+
+        self.table = Some(alignments.clone());
+        self.expect_delimiter = true;
+        Some(TableLine { row: TableRow::Header, alignments })
+    }
+}
+
+/// Compute the block states of all lines in one pass over the document
+pub fn block_states<S: AsRef<str>>(lines: &[S]) -> Vec<LineBlockState> {
+    let mut scanner = BlockScanner::for_document(lines);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| scanner.advance(line.as_ref(), lines.get(i + 1).map(AsRef::as_ref)))
+        .collect()
+}
+
+/// Compute the block state of a single line by scanning the lines before it
+pub fn block_state_at(line_index: usize, all_lines: &[String]) -> LineBlockState {
+    let mut scanner = BlockScanner::for_document(all_lines);
+    let mut state = LineBlockState::default();
+
+    for (i, line) in all_lines.iter().enumerate().take(line_index + 1) {
+        state = scanner.advance(line, all_lines.get(i + 1).map(String::as_str));
+    }
+
+    state
+}
+
+/// Quote level of a line and its text after the markers, e.g. 2 and
+/// `text` for `> > text`
+pub fn parse_quote(line: &str) -> Option<(usize, &str)> {
+    let mut rest = line.strip_prefix('>')?;
+    let mut depth = 1;
+    loop {
+        let text = rest.trim_start();
+        match text.strip_prefix('>') {
+            Some(after) => {
+                depth += 1;
+                rest = after;
             }
-            in_block = !in_block;
+            None => return Some((depth, text)),
         }
     }
+}
 
-    (in_block, false, false)
+/// Type, fold state and title of a callout header such as `[!TIP]- Title`
+pub fn parse_callout_header(text: &str) -> Option<(&str, CalloutFold, &str)> {
+    let cap = CALLOUT_RE.captures(text)?;
+    let fold = match cap.get(2).map(|m| m.as_str()) {
+        Some("-") => CalloutFold::Collapsed,
+        Some(_) => CalloutFold::Expanded,
+        None => CalloutFold::None,
+    };
+    let title = cap.get(3).map_or("", |m| m.as_str().trim());
+    Some((cap.get(1).unwrap().as_str(), fold, title))
 }
 
-/// Check if a line is inside a math block
+/// A table row candidate: non-blank and containing an unescaped pipe
+fn is_table_row(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && (trimmed.starts_with('|') || table_cell_ranges(line).len() > 1)
+}
+
+/// Byte ranges of the cells of a table row, excluding the pipes
 ///
-/// Returns a tuple of (in_block, is_start, is_end)
-/// - in_block: true if the line is inside a math block
-/// - is_start: true if this line starts a math block
-/// - is_end: true if this line ends a math block
-pub fn is_in_math_block(line_index: usize, all_lines: &[String]) -> (bool, bool, bool) {
-    let mut in_block = false;
-
-    for (i, line) in all_lines.iter().enumerate() {
-        if i > line_index {
-            break;
-        }
-
-        if line.trim() == "$$" {
-            if i == line_index {
-                // This line is a math block boundary
-                return (true, !in_block, in_block);
+/// Leading and trailing pipes are optional, and `\|` does not split cells.
+pub fn table_cell_ranges(line: &str) -> Vec<std::ops::Range<usize>> {
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '|' => {
+                cells.push(start..i);
+                start = i + 1;
             }
-            in_block = !in_block;
+            _ => {}
         }
     }
+    cells.push(start..line.len());
+
+    if cells.len() > 1 && line[cells[0].clone()].trim().is_empty() && line.trim_start().starts_with('|') {
+        cells.remove(0);
+    }
+    if cells.len() > 1 && line[cells[cells.len() - 1].clone()].trim().is_empty() {
+        cells.pop();
+    }
+
+    cells
+}
+
+/// Parse a delimiter row such as `| :--- | :---: | ---: |`
+pub fn parse_delimiter_row(line: &str) -> Option<Vec<ColumnAlignment>> {
+    if !line.contains('-') {
+        return None;
+    }
+
+    table_cell_ranges(line)
+        .into_iter()
+        .map(|range| {
+            let cell = line[range].trim();
+            let left = cell.starts_with(':');
+            let right = cell.ends_with(':');
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
 
-    (in_block, false, false)
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+
+            Some(match (left, right) {
+                (true, true) => ColumnAlignment::Center,
+                (true, false) => ColumnAlignment::Left,
+                (false, true) => ColumnAlignment::Right,
+                (false, false) => ColumnAlignment::None,
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -71,19 +494,19 @@ mod tests {
             "More text".to_string(),
         ];
 
-        let (in_block, is_start, is_end) = is_in_code_block(0, &lines);
+        let BlockPosition { in_block, is_start, is_end } = block_state_at(0, &lines).code;
         assert!(!in_block && !is_start && !is_end);
 
-        let (_in_block, is_start, is_end) = is_in_code_block(1, &lines);
+        let BlockPosition { is_start, is_end, .. } = block_state_at(1, &lines).code;
         assert!(is_start && !is_end);
 
-        let (in_block, is_start, is_end) = is_in_code_block(2, &lines);
+        let BlockPosition { in_block, is_start, is_end } = block_state_at(2, &lines).code;
         assert!(in_block && !is_start && !is_end);
 
-        let (_in_block, is_start, is_end) = is_in_code_block(3, &lines);
+        let BlockPosition { is_start, is_end, .. } = block_state_at(3, &lines).code;
         assert!(is_end && !is_start);
 
-        let (in_block, is_start, is_end) = is_in_code_block(4, &lines);
+        let BlockPosition { in_block, is_start, is_end } = block_state_at(4, &lines).code;
         assert!(!in_block && !is_start && !is_end);
     }
 
@@ -97,19 +520,147 @@ mod tests {
             "More text".to_string(),
         ];
 
-        let (in_block, is_start, is_end) = is_in_math_block(0, &lines);
+        let BlockPosition { in_block, is_start, is_end } = block_state_at(0, &lines).math;
         assert!(!in_block && !is_start && !is_end);
 
-        let (_in_block, is_start, is_end) = is_in_math_block(1, &lines);
+        let BlockPosition { is_start, is_end, .. } = block_state_at(1, &lines).math;
         assert!(is_start && !is_end);
 
-        let (in_block, is_start, is_end) = is_in_math_block(2, &lines);
+        let BlockPosition { in_block, is_start, is_end } = block_state_at(2, &lines).math;
         assert!(in_block && !is_start && !is_end);
 
-        let (_in_block, is_start, is_end) = is_in_math_block(3, &lines);
+        let BlockPosition { is_start, is_end, .. } = block_state_at(3, &lines).math;
         assert!(is_end && !is_start);
 
-        let (in_block, is_start, is_end) = is_in_math_block(4, &lines);
+        let BlockPosition { in_block, is_start, is_end } = block_state_at(4, &lines).math;
         assert!(!in_block && !is_start && !is_end);
+
+        // The closing line carries the whole formula
+        assert_eq!(block_state_at(3, &lines).math_source.as_deref(), Some("x^2 + y^2 = z^2"));
+        assert_eq!(block_state_at(2, &lines).math_source, None);
+    }
+
+    #[test]
+    fn test_scanner_matches_full_scan() {
+        let lines = vec![
+            "```".to_string(),
+            "$$".to_string(),
+            "```".to_string(),
+            "$$".to_string(),
+            "text".to_string(),
+            "```python".to_string(),
+        ];
+
+        let position = |in_block, is_start, is_end| BlockPosition { in_block, is_start, is_end };
+        let outside = BlockPosition::default();
+        let expected = [
+            (position(true, true, false), outside),
+            (position(true, false, false), position(true, true, false)),
+            (position(true, false, true), position(true, false, false)),
+            (outside, position(true, false, true)),
+            (outside, outside),
+            (position(true, true, false), outside),
+        ];
+
+        let mut scanner = BlockScanner::for_document(&lines);
+        for (i, line) in lines.iter().enumerate() {
+            let state = scanner.advance(line, lines.get(i + 1).map(String::as_str));
+            assert_eq!((state.code, state.math), expected[i], "line {}", i);
+            let full = block_state_at(i, &lines);
+            assert_eq!((full.code, full.math), expected[i], "line {}", i);
+        }
+    }
+
+    #[test]
+    fn test_table_detection() {
+        let lines = vec![
+            "| Name | Qty | Price |".to_string(),
+            "| :--- | :-: | ----: |".to_string(),
+            "| a | 1 | 2 |".to_string(),
+            "b | 3 | 4".to_string(),
+            "".to_string(),
+            "| not | a table |".to_string(),
+        ];
+
+        let header = block_state_at(0, &lines).table.unwrap();
+        assert_eq!(header.row, TableRow::Header);
+        assert_eq!(
+            header.alignments,
+            vec![ColumnAlignment::Left, ColumnAlignment::Center, ColumnAlignment::Right]
+        );
+        assert_eq!(block_state_at(1, &lines).table.unwrap().row, TableRow::Delimiter);
+        assert_eq!(block_state_at(2, &lines).table.unwrap().row, TableRow::Body);
+        assert_eq!(block_state_at(3, &lines).table.unwrap().row, TableRow::Body);
+        assert!(block_state_at(4, &lines).table.is_none());
+        assert!(block_state_at(5, &lines).table.is_none());
+    }
+
+    #[test]
+    fn test_table_requires_matching_delimiter() {
+        let lines = vec!["a | b".to_string(), "--- | --- | ---".to_string()];
+        assert!(block_state_at(0, &lines).table.is_none());
+
+        let fenced = vec!["```".to_string(), "a | b".to_string(), "--- | ---".to_string()];
+        assert!(block_state_at(1, &fenced).table.is_none());
+    }
+
+    #[test]
+    fn test_table_cell_ranges() {
+        let line = r"| a | b \| c |";
+        let cells: Vec<&str> = table_cell_ranges(line).into_iter().map(|r| line[r].trim()).collect();
+        assert_eq!(cells, vec!["a", r"b \| c"]);
+    }
+
+    #[test]
+    fn test_frontmatter_detection() {
+        let lines: Vec<String> = ["---", "title: x", "```", "---", "```", "---"].iter().map(|s| s.to_string()).collect();
+
+        let start = block_state_at(0, &lines).frontmatter.unwrap();
+        assert_eq!(start, (FrontmatterFormat::Yaml, BlockPosition { in_block: true, is_start: true, is_end: false }));
+
+        // Fences inside frontmatter don't open code blocks
+        let inner = block_state_at(2, &lines);
+        assert!(inner.frontmatter.is_some() && !inner.code.in_block);
+        assert!(block_state_at(3, &lines).frontmatter.unwrap().1.is_end);
+        assert!(block_state_at(4, &lines).code.is_start);
+
+        // Only the first line can open frontmatter
+        let later: Vec<String> = ["text", "+++", "a = 1", "+++"].iter().map(|s| s.to_string()).collect();
+        assert!((0..later.len()).all(|i| block_state_at(i, &later).frontmatter.is_none()));
+
+        let toml: Vec<String> = ["+++", "a = 1", "+++"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(block_state_at(1, &toml).frontmatter.unwrap().0, FrontmatterFormat::Toml);
+
+        // Without a closing delimiter the opener is an ordinary line
+        let unclosed: Vec<String> = ["---", "# Title", "text"].iter().map(|s| s.to_string()).collect();
+        assert!((0..unclosed.len()).all(|i| block_state_at(i, &unclosed).frontmatter.is_none()));
+        assert_eq!(block_state_at(1, &unclosed).heading_id.as_deref(), Some("title"));
+    }
+
+    #[test]
+    fn test_callout_detection() {
+        let lines: Vec<String> =
+            ["> [!WARNING]- Careful", "> hidden", "> > [!tip]", "> > nested", "> back", "> [!NOTE]", "", "> [!NOTE]+"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+
+        let header = block_state_at(0, &lines).quote.unwrap();
+        assert!(header.is_callout_start && !header.folded);
+        let warning = Callout { kind: "warning".to_string(), fold: CalloutFold::Collapsed, depth: 1 };
+        assert_eq!(header.callouts, vec![warning]);
+        assert!(block_state_at(1, &lines).quote.unwrap().folded);
+
+        let nested = block_state_at(3, &lines).quote.unwrap();
+        assert_eq!((nested.depth, nested.callouts.len(), nested.callouts[1].kind.as_str()), (2, 2, "tip"));
+
+        // Leaving the nested level closes its callout; a header mid-quote is text
+        assert_eq!(block_state_at(4, &lines).quote.unwrap().callouts.len(), 1);
+        assert!(!block_state_at(5, &lines).quote.unwrap().is_callout_start);
+        assert!(block_state_at(6, &lines).quote.is_none());
+        assert_eq!(block_state_at(7, &lines).quote.unwrap().callouts[0].fold, CalloutFold::Expanded);
+
+        assert_eq!(parse_quote(">>  > text"), Some((3, "text")));
+        assert_eq!(parse_callout_header("[!info] Title "), Some(("info", CalloutFold::None, "Title")));
     }
 }
@@ -0,0 +1,329 @@
+/**
+ * Document blocks
+ *
+ * A structured view of a note for output formats that lay text out
+ * themselves rather than rendering HTML, such as PDF export. Blocks follow
+ * the renderer line by line (a heading, list item or paragraph per line),
+ * except that code blocks, math blocks and tables are gathered into one
+ * block each. Inline markdown becomes runs of text with a style.
+ */
+
+use super::block_detection::{
+    parse_callout_header, parse_quote, table_cell_ranges, BlockScanner, ColumnAlignment, QuoteLine, TableRow,
+};
+use super::highlight::{CodeToken, Highlighter};
+use super::inline_rendering::{parse_inline, InlineKind, InlineNode};
+use super::quote_rendering::callout_title;
+use super::{HEADER_RE, HR_RE, LANG_RE, LIST_RE, TASK_RE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub strikethrough: bool,
+    pub link: bool,
+    pub math: bool,
+}
+
+/// Text sharing one style
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableBlockRow {
+    pub header: bool,
+    /// One entry per column
+    pub cells: Vec<Vec<TextRun>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading { level: u8, runs: Vec<TextRun>, slug: String },
+    Paragraph(Vec<TextRun>),
+    ListItem {
+        /// Leading spaces of the item
+        indent: usize,
+        /// `•` for unordered items, the number (`2.`) for ordered ones
+        marker: String,
+        /// Checkbox state of task items
+        task: Option<bool>,
+        runs: Vec<TextRun>,
+    },
+    Quote {
+        /// Number of `>` markers
+        depth: usize,
+        runs: Vec<TextRun>,
+    },
+    Code { lang: String, lines: Vec<Vec<CodeToken>> },
+    /// LaTeX source of a `$$` block
+    Math(String),
+    Table { alignments: Vec<ColumnAlignment>, rows: Vec<TableBlockRow> },
+    /// A line holding nothing but an image
+    Image { dest: String, alt: String },
+    Rule,
+    Blank,
+}
+
+fn collect_runs(nodes: &[InlineNode], source: &str, style: TextStyle, runs: &mut Vec<TextRun>) {
+    let mut push = |text: &str, style: TextStyle| match runs.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ if !text.is_empty() => runs.push(TextRun { text: text.to_string(), style }),
+        _ => {}
+    };
+
+    for node in nodes {
+        match &node.kind {
+            InlineKind::Text(text) => push(text, style),
+            InlineKind::Code(code) => push(code, TextStyle { code: true, ..style }),
+            InlineKind::Math => {
+                let latex = source[node.range.clone()].trim_matches('$').trim();
+                push(latex, TextStyle { math: true, ..style });
+            }
+            InlineKind::WikiLink(link) => push(&link.display_text(), TextStyle { link: true, ..style }),
+            InlineKind::Break { .. } => push(" ", style),
+            InlineKind::Html(_) => {}
+            InlineKind::Image { .. } => {
+                let mut alt = Vec::new();
+                collect_runs(&node.children, source, style, &mut alt);
+                let alt: String = alt.iter().map(|run| run.text.as_str()).collect();
+                push(&format!("[{}]", alt), TextStyle { italic: true, ..style });
+            }
+            kind => {
+                let style = match kind {
+                    InlineKind::Emphasis => TextStyle { italic: true, ..style },
+                    InlineKind::Strong => TextStyle { bold: true, ..style },
+                    InlineKind::Strikethrough => TextStyle { strikethrough: true, ..style },
+                    _ => TextStyle { link: true, ..style },
+                };
+                let mut children = Vec::new();
+                collect_runs(&node.children, source, style, &mut children);
+                for run in children {
+                    push(&run.text, run.style);
+                }
+            }
+        }
+    }
+}
+
+/// Styled runs of a line of inline markdown
+pub fn text_runs(source: &str) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    collect_runs(&parse_inline(source), source, TextStyle::default(), &mut runs);
+    runs
+}
+
+/// A line made of a single image, shown as a block of its own
+fn image_block(line: &str) -> Option<Block> {
+    let source = line.trim();
+    let nodes: Vec<InlineNode> = parse_inline(source)
+        .into_iter()
+        .filter(|node| !matches!(&node.kind, InlineKind::Text(text) if text.trim().is_empty()))
+        .collect();
+    match nodes.as_slice() {
+        [InlineNode { kind: InlineKind::Image { dest, .. }, children, .. }] => {
+            let mut alt = Vec::new();
+            collect_runs(children, source, TextStyle::default(), &mut alt);
+            Some(Block::Image {
+                dest: dest.clone(),
+                alt: alt.into_iter().map(|run| run.text).collect(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Block for a line outside code, math and tables
+fn line_block(line: &str, heading_id: Option<String>, quote: Option<QuoteLine>) -> Block {
+    if let Some(quote) = quote {
+        let text = parse_quote(line).map_or("", |(_, text)| text);
+        // A callout header shows its title in bold
+        let runs = match parse_callout_header(text).filter(|_| quote.is_callout_start) {
+            Some((kind, _, title)) => {
+                let mut runs = text_runs(&callout_title(kind, title));
+                runs.iter_mut().for_each(|run| run.style.bold = true);
+                runs
+            }
+            None => text_runs(text),
+        };
+        return Block::Quote { depth: quote.depth, runs };
+    }
+
+    if line.trim().is_empty() {
+        return Block::Blank;
+    }
+    if HR_RE.is_match(line) {
+        return Block::Rule;
+    }
+
+    if let Some(cap) = HEADER_RE.captures(line) {
+        return Block::Heading {
+            level: cap[1].len() as u8,
+            runs: text_runs(&cap[2]),
+            slug: heading_id.unwrap_or_default(),
+        };
+    }
+
+    if let Some(cap) = LIST_RE.captures(line) {
+        let text = cap.get(3).map_or("", |m| m.as_str());
+        let marker = if cap[2].starts_with(|c: char| c.is_ascii_digit()) { cap[2].to_string() } else { "•".to_string() };
+        let (task, text) = match TASK_RE.captures(text) {
+            Some(task) => (Some(&task[1] != " "), &text[task[0].len()..]),
+            None => (None, text),
+        };
+        return Block::ListItem { indent: cap[1].len(), marker, task, runs: text_runs(text) };
+    }
+
+    image_block(line).unwrap_or_else(|| Block::Paragraph(text_runs(line)))
+}
+
+/// The blocks of a note, in order; frontmatter is left out
+pub fn document_blocks(content: &str) -> Vec<Block> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut scanner = BlockScanner::for_document(&lines);
+    let mut blocks = Vec::new();
+    let mut code: Option<(Highlighter, String, Vec<Vec<CodeToken>>)> = None;
+    let mut math: Option<Vec<&str>> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = scanner.advance(line, lines.get(i + 1).copied());
+
+        if state.frontmatter.is_some() {
+            continue;
+        }
+
+        if state.code.is_start {
+            let lang = LANG_RE.captures(line.trim()).and_then(|cap| cap.get(1)).map_or("", |m| m.as_str());
+            code = Some((Highlighter::new(lang), lang.to_string(), Vec::new()));
+            continue;
+        }
+        if state.code.is_end {
+            if let Some((_, lang, lines)) = code.take() {
+                blocks.push(Block::Code { lang, lines });
+            }
+            continue;
+        }
+        if let Some((highlighter, _, lines)) = code.as_mut() {
+            lines.push(highlighter.tokens(line));
+            continue;
+        }
+
+        if state.math.is_start {
+            math = Some(Vec::new());
+            continue;
+        }
+        if state.math.is_end {
+            blocks.push(Block::Math(math.take().unwrap_or_default().join("\n")));
+            continue;
+        }
+        if let Some(latex) = math.as_mut() {
+            latex.push(line);
+            continue;
+        }
+
+        if let Some(table) = state.table {
+            if table.row == TableRow::Delimiter {
+                continue;
+            }
+            let mut ranges = table_cell_ranges(line).into_iter();
+            let cells = (0..table.alignments.len())
+                .map(|_| ranges.next().map(|range| text_runs(line[range].trim())).unwrap_or_default())
+                .collect();
+            let row = TableBlockRow { header: table.row == TableRow::Header, cells };
+
+            match blocks.last_mut() {
+                Some(Block::Table { rows, .. }) if !row.header => rows.push(row),
+                _ => blocks.push(Block::Table { alignments: table.alignments, rows: vec![row] }),
+            }
+            continue;
+        }
+
+        blocks.push(line_block(line, state.heading_id, state.quote));
+    }
+
+    // Blocks left open at the end of the note
+    if let Some((_, lang, lines)) = code {
+        blocks.push(Block::Code { lang, lines });
+    }
+    if let Some(latex) = math {
+        blocks.push(Block::Math(latex.join("\n")));
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str, style: TextStyle) -> TextRun {
+        TextRun { text: text.to_string(), style }
+    }
+
+    #[test]
+    fn test_document_blocks() {
+        let content = "---\ntitle: x\n---\n# Spec **v2**\n\n- [x] Done with `code`\n2. Second\n> Quote\n\
+                       ![Diagram](d.png)\n| A | B |\n|---|--:|\n| 1 |\n```sh\necho hi\n```\n$$\nx^2\n$$\n---\n";
+        let blocks = document_blocks(content);
+        let bold = TextStyle { bold: true, ..Default::default() };
+        let plain = TextStyle::default();
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading { level: 1, runs: vec![run("Spec ", plain), run("v2", bold)], slug: "spec-v2".to_string() },
+                Block::Blank,
+                Block::ListItem {
+                    indent: 0,
+                    marker: "•".to_string(),
+                    task: Some(true),
+                    runs: vec![run("Done with ", plain), run("code", TextStyle { code: true, ..plain })],
+                },
+                Block::ListItem { indent: 0, marker: "2.".to_string(), task: None, runs: vec![run("Second", plain)] },
+                Block::Quote { depth: 1, runs: vec![run("Quote", plain)] },
+                Block::Image { dest: "d.png".to_string(), alt: "Diagram".to_string() },
+                Block::Table {
+                    alignments: vec![ColumnAlignment::None, ColumnAlignment::Right],
+                    rows: vec![
+                        TableBlockRow { header: true, cells: vec![vec![run("A", plain)], vec![run("B", plain)]] },
+                        TableBlockRow { header: false, cells: vec![vec![run("1", plain)], vec![]] },
+                    ],
+                },
+                Block::Code {
+                    lang: "sh".to_string(),
+                    lines: vec![highlight_tokens("sh", "echo hi")],
+                },
+                Block::Math("x^2".to_string()),
+                Block::Rule,
+            ]
+        );
+    }
+
+    fn highlight_tokens(lang: &str, line: &str) -> Vec<CodeToken> {
+        Highlighter::new(lang).tokens(line)
+    }
+
+    #[test]
+    fn test_text_runs() {
+        let runs = text_runs("See [the *docs*](x.md), [[Note|alias]] and $a+b$ ~~old~~");
+        let link = TextStyle { link: true, ..Default::default() };
+
+        assert_eq!(
+            runs,
+            vec![
+                run("See ", TextStyle::default()),
+                run("the ", link),
+                run("docs", TextStyle { italic: true, ..link }),
+                run(", ", TextStyle::default()),
+                run("alias", link),
+                run(" and ", TextStyle::default()),
+                run("a+b", TextStyle { math: true, ..Default::default() }),
+                run(" ", TextStyle::default()),
+                run("old", TextStyle { strikethrough: true, ..Default::default() }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,329 @@
+/**
+ * Graphviz DOT graphs
+ *
+ * Parses `digraph` and `graph` statements: nodes with `label` and `shape`
+ * attributes, edge chains (`a -> b -> c`) with `label` and `style`,
+ * `{ a b }` groups as edge ends, `rankdir`, and `node [...]` defaults.
+ * Subgraphs and clusters are flattened; other attributes are ignored.
+ */
+
+use super::layout::{Direction, Edge, Graph, LineStyle, Shape};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Identifier, number or quoted string
+    Id(String),
+    Arrow,
+    Line,
+    Punct(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                tokens.push(Token::Arrow);
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.next();
+                tokens.push(Token::Line);
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n' | 'l' | 'r') => text.push('\n'),
+                            Some(c) => text.push(c),
+                            None => return Err("Unterminated string".to_string()),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Id(text));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut id = c.to_string();
+                while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_' || c == '.') {
+                    id.push(c);
+                }
+                tokens.push(Token::Id(id));
+            }
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' => tokens.push(Token::Punct(c)),
+            c => return Err(format!("Unexpected character `{}`", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn shape(name: &str) -> Shape {
+    match name {
+        "box" | "rect" | "rectangle" | "square" | "record" => Shape::Rect,
+        "circle" | "doublecircle" | "point" => Shape::Circle,
+        "diamond" => Shape::Diamond,
+        "hexagon" => Shape::Hexagon,
+        "plaintext" | "plain" | "none" => Shape::Plain,
+        _ => Shape::Ellipse,
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    graph: Graph,
+    directed: bool,
+    node_shape: Shape,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self.peek() == Some(token);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn id(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Id(id)) => {
+                let id = id.clone();
+                self.pos += 1;
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(&Token::Punct(c)) {
+            Ok(())
+        } else {
+            Err(format!("Expected `{}`", c))
+        }
+    }
+
+    /// `[key=value, ...]` lists, possibly several in a row
+    fn attributes(&mut self) -> Result<Vec<(String, String)>, String> {
+        let mut attributes = Vec::new();
+        while self.eat(&Token::Punct('[')) {
+            while !self.eat(&Token::Punct(']')) {
+                let key = self.id().ok_or("Expected an attribute name")?;
+                let value = if self.eat(&Token::Punct('=')) {
+                    self.id().ok_or_else(|| format!("Expected a value for `{}`", key))?
+                } else {
+                    String::new()
+                };
+                attributes.push((key, value));
+                if !self.eat(&Token::Punct(',')) {
+                    self.eat(&Token::Punct(';'));
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// A node id, with any `:port` dropped
+    fn node_id(&mut self) -> Option<String> {
+        let id = self.id()?;
+        while self.eat(&Token::Punct(':')) {
+            self.id();
+        }
+        Some(id)
+    }
+
+    /// One end of an edge: a node, or the nodes of a `{ ... }` group
+    fn edge_end(&mut self) -> Result<Vec<usize>, String> {
+        if matches!(self.peek(), Some(Token::Id(id)) if id == "subgraph") || self.peek() == Some(&Token::Punct('{')) {
+            return self.subgraph();
+        }
+        let id = self.node_id().ok_or("Expected a node")?;
+        Ok(vec![self.graph.node(&id, self.node_shape)])
+    }
+
+    /// `subgraph name { ... }` or `{ ... }`; returns the nodes inside
+    fn subgraph(&mut self) -> Result<Vec<usize>, String> {
+        if self.eat(&Token::Id("subgraph".to_string())) {
+            self.id();
+        }
+        let before = self.graph.nodes.len();
+        self.expect('{')?;
+        let mentioned = self.statements()?;
+        self.expect('}')?;
+        let mut nodes = mentioned;
+        nodes.extend(before..self.graph.nodes.len());
+        nodes.sort_unstable();
+        nodes.dedup();
+        Ok(nodes)
+    }
+
+    /// Statements up to a closing `}`; returns the nodes they mention
+    fn statements(&mut self) -> Result<Vec<usize>, String> {
+        let mut mentioned = Vec::new();
+        while self.peek().is_some() && self.peek() != Some(&Token::Punct('}')) {
+            mentioned.extend(self.statement()?);
+            self.eat(&Token::Punct(';'));
+        }
+        Ok(mentioned)
+    }
+
+    fn statement(&mut self) -> Result<Vec<usize>, String> {
+        match self.peek() {
+            Some(Token::Id(id)) if id == "graph" || id == "edge" || id == "node" => {
+                let kind = id.clone();
+                self.pos += 1;
+                for (key, value) in self.attributes()? {
+                    match (kind.as_str(), key.as_str()) {
+                        ("graph", "rankdir") => self.graph.direction = direction(&value),
+                        ("node", "shape") => self.node_shape = shape(&value),
+                        _ => {}
+                    }
+                }
+                return Ok(Vec::new());
+            }
+            // `rankdir=LR` and other graph attributes
+            Some(Token::Id(_)) if self.tokens.get(self.pos + 1) == Some(&Token::Punct('=')) => {
+                let key = self.id().unwrap_or_default();
+                self.pos += 1;
+                let value = self.id().ok_or_else(|| format!("Expected a value for `{}`", key))?;
+                if key == "rankdir" {
+                    self.graph.direction = direction(&value);
+                }
+                return Ok(Vec::new());
+            }
+            _ => {}
+        }
+
+        let mut ends = vec![self.edge_end()?];
+        while let Some(Token::Arrow | Token::Line) = self.peek() {
+            self.pos += 1;
+            ends.push(self.edge_end()?);
+        }
+        let attributes = self.attributes()?;
+        let value = |name: &str| attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+
+        if ends.len() == 1 {
+            for &node in &ends[0] {
+                if let Some(label) = value("label") {
+                    self.graph.nodes[node].label = label;
+                }
+                if let Some(name) = value("shape") {
+                    self.graph.nodes[node].shape = shape(&name);
+                }
+            }
+        } else {
+            let style = match value("style").as_deref() {
+                Some("dashed" | "dotted") => LineStyle::Dotted,
+                Some("bold") => LineStyle::Thick,
+                _ => LineStyle::Solid,
+            };
+            let arrow = self.directed && value("dir").as_deref() != Some("none");
+            for pair in ends.windows(2) {
+                for &from in &pair[0] {
+                    for &to in &pair[1] {
+                        self.graph.edges.push(Edge { from, to, label: value("label"), style, arrow });
+                    }
+                }
+            }
+        }
+        Ok(ends.concat())
+    }
+}
+
+fn direction(rankdir: &str) -> Direction {
+    match rankdir {
+        "LR" => Direction::LeftRight,
+        "RL" => Direction::RightLeft,
+        "BT" => Direction::BottomUp,
+        _ => Direction::TopDown,
+    }
+}
+
+/// Parse a `digraph` or `graph` into a graph
+pub fn parse(source: &str) -> Result<Graph, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+        graph: Graph::new(Direction::TopDown),
+        directed: true,
+        node_shape: Shape::Ellipse,
+    };
+
+    parser.eat(&Token::Id("strict".to_string()));
+    parser.directed = match parser.id().as_deref() {
+        Some("digraph") => true,
+        Some("graph") => false,
+        _ => return Err("Expected `digraph` or `graph`".to_string()),
+    };
+    if parser.peek() != Some(&Token::Punct('{')) {
+        parser.id();
+    }
+    parser.expect('{')?;
+    parser.statements()?;
+    parser.expect('}')?;
+    Ok(parser.graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dot() {
+        let graph = parse(
+            r#"digraph build {
+                rankdir=LR; // left to right
+                node [shape=box];
+                src [label="Sources\nfiles"];
+                src -> obj -> bin [label="compile"];
+                obj -> { lib test } [style=dashed];
+                bin [shape=ellipse]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(graph.direction, Direction::LeftRight);
+        let nodes: Vec<(&str, &str, Shape)> =
+            graph.nodes.iter().map(|node| (node.id.as_str(), node.label.as_str(), node.shape)).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                ("src", "Sources\nfiles", Shape::Rect),
+                ("obj", "obj", Shape::Rect),
+                ("bin", "bin", Shape::Ellipse),
+                ("lib", "lib", Shape::Rect),
+                ("test", "test", Shape::Rect),
+            ]
+        );
+        assert_eq!(graph.edges.len(), 4);
+        assert_eq!(graph.edges[1].label.as_deref(), Some("compile"));
+        assert_eq!((graph.edges[3].to, graph.edges[3].style), (4, LineStyle::Dotted));
+
+        let undirected = parse("graph { a -- b }").unwrap();
+        assert!(!undirected.edges[0].arrow);
+        assert_eq!(parse("digraph { a -> }").unwrap_err(), "Expected a node");
+    }
+}
@@ -0,0 +1,263 @@
+/**
+ * Mermaid flowcharts
+ *
+ * Parses `graph` / `flowchart` diagrams: the direction, node shapes
+ * (`[rect]`, `(round)`, `([stadium])`, `((circle))`, `{diamond}`,
+ * `{{hexagon}}`), chained links (`A --> B --> C`), `&` groups, and links
+ * that are dotted (`-.->`), thick (`==>`), headless (`---`) or labelled
+ * (`-->|text|`, `-- text -->`). Subgraphs are flattened; styling statements
+ * are ignored.
+ */
+
+use super::layout::{Direction, Edge, Graph, LineStyle, Shape};
+
+/// Statements that only affect styling or grouping
+const IGNORED: &[&str] = &["subgraph", "end", "classDef", "class", "style", "linkStyle", "click", "direction"];
+
+/// Opening and closing delimiters of node shapes; longer ones first
+const SHAPES: &[(&str, &str, Shape)] = &[
+    ("((", "))", Shape::Circle),
+    ("([", "])", Shape::Stadium),
+    ("[(", ")]", Shape::Round),
+    ("[[", "]]", Shape::Rect),
+    ("{{", "}}", Shape::Hexagon),
+    ("[", "]", Shape::Rect),
+    ("(", ")", Shape::Round),
+    ("{", "}", Shape::Diamond),
+    (">", "]", Shape::Rect),
+];
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Label text: quotes removed and `<br>` turned into line breaks
+fn clean_label(text: &str) -> String {
+    let text = text.trim();
+    let text = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text);
+    text.replace("<br/>", "\n").replace("<br />", "\n").replace("<br>", "\n")
+}
+
+/// Split a line into statements at `;` outside labels
+fn statements(line: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' | '{' if !quoted => depth += 1,
+            ']' | ')' | '}' if !quoted => depth -= 1,
+            ';' if !quoted && depth <= 0 => {
+                parts.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&line[start..]);
+    parts
+}
+
+struct Statement<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Statement<'a> {
+    fn skip_spaces(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// A node reference, defining its shape and label if they follow the id
+    fn node(&mut self, graph: &mut Graph) -> Option<usize> {
+        self.skip_spaces();
+        let end = self.rest.find(|c: char| !is_id_char(c)).unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let id = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        let index = graph.node(id, Shape::Rect);
+
+        // `>` only opens a shape directly after the id, never as part of a link
+        if let Some(&(open, close, shape)) = SHAPES.iter().find(|(open, _, _)| self.rest.starts_with(open)) {
+            let body = &self.rest[open.len()..];
+            if let Some(close_at) = body.find(close) {
+                graph.nodes[index].label = clean_label(&body[..close_at]);
+                graph.nodes[index].shape = shape;
+                self.rest = &body[close_at + close.len()..];
+            }
+        }
+        Some(index)
+    }
+
+    /// Nodes joined by `&`
+    fn node_group(&mut self, graph: &mut Graph) -> Option<Vec<usize>> {
+        let mut nodes = vec![self.node(graph)?];
+        loop {
+            self.skip_spaces();
+            match self.rest.strip_prefix('&') {
+                Some(rest) => {
+                    self.rest = rest;
+                    nodes.push(self.node(graph)?);
+                }
+                None => return Some(nodes),
+            }
+        }
+    }
+
+    /// A link between node groups: its style, whether it has an arrowhead and its label
+    fn link(&mut self) -> Option<(LineStyle, bool, Option<String>)> {
+        self.skip_spaces();
+        let length = self.rest.find(|c: char| !matches!(c, '-' | '=' | '.')).unwrap_or(self.rest.len());
+        let mut token = &self.rest[..length];
+        if token.len() < 2 {
+            return None;
+        }
+        self.rest = &self.rest[length..];
+
+        let mut label = None;
+        let mut arrow = false;
+        if let Some(rest) = self.rest.strip_prefix('>') {
+            arrow = true;
+            self.rest = rest;
+        } else if matches!(token, "--" | "==" | "-.") {
+            // `-- text -->`: the label runs up to the rest of the link
+            let closing = ["-->", "---", "==>", "===", ".->", ".-"]
+                .iter()
+                .filter_map(|closing| self.rest.find(closing).map(|at| (at, *closing)))
+                .min_by_key(|(at, _)| *at);
+            if let Some((at, closing)) = closing {
+                label = Some(clean_label(&self.rest[..at]));
+                let after = &self.rest[at..];
+                let length = after.find(|c: char| !matches!(c, '-' | '=' | '.')).unwrap_or(after.len());
+                token = &after[..length];
+                self.rest = &after[length..];
+                arrow = closing.ends_with('>');
+                if arrow {
+                    self.rest = &self.rest[1..];
+                }
+            }
+        }
+
+        self.skip_spaces();
+        if let Some(body) = self.rest.strip_prefix('|') {
+            if let Some(end) = body.find('|') {
+                label = Some(clean_label(&body[..end]));
+                self.rest = &body[end + 1..];
+            }
+        }
+
+        let style = if token.contains('=') {
+            LineStyle::Thick
+        } else if token.contains('.') {
+            LineStyle::Dotted
+        } else {
+            LineStyle::Solid
+        };
+        Some((style, arrow, label))
+    }
+}
+
+fn parse_direction(header: &str) -> Direction {
+    match header.split_whitespace().nth(1).unwrap_or("TD") {
+        "BT" => Direction::BottomUp,
+        "LR" => Direction::LeftRight,
+        "RL" => Direction::RightLeft,
+        _ => Direction::TopDown,
+    }
+}
+
+/// Parse a flowchart into a graph
+pub fn parse(source: &str) -> Result<Graph, String> {
+    let mut graph: Option<Graph> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        if line.trim().starts_with("%%") {
+            continue;
+        }
+        for text in statements(line) {
+            let text = text.trim();
+            let keyword = text.split_whitespace().next().unwrap_or("");
+            if text.is_empty() || IGNORED.contains(&keyword) {
+                continue;
+            }
+            // The header may share its line with statements: `graph LR; A --> B`
+            let Some(graph) = graph.as_mut() else {
+                graph = Some(Graph::new(parse_direction(text)));
+                continue;
+            };
+
+            let mut statement = Statement { rest: text };
+            let error = || format!("Line {}: expected a node at `{}`", number + 1, text);
+            let mut previous = statement.node_group(graph).ok_or_else(error)?;
+            loop {
+                statement.skip_spaces();
+                if statement.rest.is_empty() {
+                    break;
+                }
+                let (style, arrow, label) = statement
+                    .link()
+                    .ok_or_else(|| format!("Line {}: expected a link at `{}`", number + 1, statement.rest))?;
+                let next = statement.node_group(graph).ok_or_else(error)?;
+                for &from in &previous {
+                    for &to in &next {
+                        graph.edges.push(Edge { from, to, label: label.clone(), style, arrow });
+                    }
+                }
+                previous = next;
+            }
+        }
+    }
+    graph.ok_or_else(|| "Empty diagram".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flowchart() {
+        let graph = parse(
+            "flowchart LR\n  A[Start] --> B{Ok?}\n  B -->|yes| C((Done)) & D\n  B -. retry .-> A; D === E([End])\n  style A fill:#f9f",
+        )
+        .unwrap();
+
+        assert_eq!(graph.direction, Direction::LeftRight);
+        let nodes: Vec<(&str, &str, Shape)> =
+            graph.nodes.iter().map(|node| (node.id.as_str(), node.label.as_str(), node.shape)).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                ("A", "Start", Shape::Rect),
+                ("B", "Ok?", Shape::Diamond),
+                ("C", "Done", Shape::Circle),
+                ("D", "D", Shape::Rect),
+                ("E", "End", Shape::Stadium),
+            ]
+        );
+
+        let edges: Vec<(usize, usize, Option<&str>, LineStyle, bool)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.label.as_deref(), edge.style, edge.arrow))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (0, 1, None, LineStyle::Solid, true),
+                (1, 2, Some("yes"), LineStyle::Solid, true),
+                (1, 3, Some("yes"), LineStyle::Solid, true),
+                (1, 0, Some("retry"), LineStyle::Dotted, true),
+                (3, 4, None, LineStyle::Thick, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("graph TD\n  A --> ").unwrap_err(), "Line 2: expected a node at `A -->`");
+        assert_eq!(parse("graph RL; A --> B").unwrap().edges.len(), 1);
+    }
+}
@@ -0,0 +1,496 @@
+/**
+ * Layered graph layout
+ *
+ * Places the nodes of a directed graph in ranks, so edges run in one
+ * direction: cycles are broken by reversing back edges, each node goes one
+ * rank below its deepest predecessor, and nodes within a rank are reordered
+ * by the average position of their neighbours to reduce crossings. Edges are
+ * straight lines clipped to the node outlines.
+ */
+
+use super::{label_size, svg_open, svg_text, MARGIN};
+
+const NODE_PADDING: f32 = 12.0;
+const NODE_HEIGHT: f32 = 36.0;
+const NODE_GAP: f32 = 24.0;
+const RANK_GAP: f32 = 48.0;
+/// Barycenter passes (each one down and one up)
+const ORDER_PASSES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    TopDown,
+    BottomUp,
+    LeftRight,
+    RightLeft,
+}
+
+impl Direction {
+    fn is_vertical(self) -> bool {
+        matches!(self, Direction::TopDown | Direction::BottomUp)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Rect,
+    Round,
+    Stadium,
+    Circle,
+    Diamond,
+    Hexagon,
+    Ellipse,
+    /// Label only, without an outline
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineStyle {
+    Solid,
+    Dotted,
+    Thick,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub label: Option<String>,
+    pub style: LineStyle,
+    pub arrow: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    pub direction: Direction,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn new(direction: Direction) -> Self {
+        Self { direction, nodes: Vec::new(), edges: Vec::new() }
+    }
+
+    /// Index of the node with `id`, added with `shape` if it doesn't exist yet
+    pub fn node(&mut self, id: &str, shape: Shape) -> usize {
+        match self.nodes.iter().position(|node| node.id == id) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node { id: id.to_string(), label: id.to_string(), shape });
+                self.nodes.len() - 1
+            }
+        }
+    }
+}
+
+/// Edges as (from, to) with back edges reversed, so the graph has no cycles
+fn acyclic_edges(graph: &Graph) -> Vec<(usize, usize)> {
+    let count = graph.nodes.len();
+    let mut outgoing = vec![Vec::new(); count];
+    for edge in graph.edges.iter().filter(|edge| edge.from != edge.to) {
+        outgoing[edge.from].push(edge.to);
+    }
+
+    // Iterative DFS; an edge to a node still on the stack closes a cycle
+    let mut on_stack = vec![false; count];
+    let mut visited = vec![false; count];
+    let mut back_edges = Vec::new();
+    for root in 0..count {
+        if visited[root] {
+            continue;
+        }
+        let mut stack = vec![(root, 0)];
+        visited[root] = true;
+        on_stack[root] = true;
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            match outgoing[node].get(*next).copied() {
+                Some(target) => {
+                    *next += 1;
+                    if on_stack[target] {
+                        back_edges.push((node, target));
+                    } else if !visited[target] {
+                        visited[target] = true;
+                        on_stack[target] = true;
+                        stack.push((target, 0));
+                    }
+                }
+                None => {
+                    on_stack[node] = false;
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    graph
+        .edges
+        .iter()
+        .filter(|edge| edge.from != edge.to)
+        .map(|edge| {
+            if back_edges.contains(&(edge.from, edge.to)) {
+                (edge.to, edge.from)
+            } else {
+                (edge.from, edge.to)
+            }
+        })
+        .collect()
+}
+
+/// Rank of every node: one more than its deepest predecessor
+fn ranks(count: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut incoming = vec![0; count];
+    for &(_, to) in edges {
+        incoming[to] += 1;
+    }
+    let mut rank = vec![0; count];
+    let mut ready: Vec<usize> = (0..count).filter(|&node| incoming[node] == 0).rev().collect();
+    while let Some(node) = ready.pop() {
+        for &(from, to) in edges.iter().filter(|(from, _)| *from == node) {
+            rank[to] = rank[to].max(rank[from] + 1);
+            incoming[to] -= 1;
+            if incoming[to] == 0 {
+                ready.push(to);
+            }
+        }
+    }
+    rank
+}
+
+/// Nodes of each rank, ordered to reduce edge crossings
+fn order_ranks(rank: &[usize], edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let rank_count = rank.iter().max().map_or(0, |max| max + 1);
+    let mut layers = vec![Vec::new(); rank_count];
+    for (node, &r) in rank.iter().enumerate() {
+        layers[r].push(node);
+    }
+
+    let mut position = vec![0.0; rank.len()];
+    let update_positions = |layers: &[Vec<usize>], position: &mut [f32]| {
+        for layer in layers {
+            for (i, &node) in layer.iter().enumerate() {
+                position[node] = i as f32;
+            }
+        }
+    };
+    update_positions(&layers, &mut position);
+
+    for pass in 0..ORDER_PASSES * 2 {
+        let downward = pass % 2 == 0;
+        let sequence: Vec<usize> = if downward {
+            (1..rank_count).collect()
+        } else {
+            (0..rank_count.saturating_sub(1)).rev().collect()
+        };
+        for r in sequence {
+            let mut keyed: Vec<(f32, usize)> = layers[r]
+                .iter()
+                .map(|&node| {
+                    // Neighbours on the side the pass comes from
+                    let neighbours: Vec<f32> = edges
+                        .iter()
+                        .filter_map(|&(from, to)| match downward {
+                            true if to == node && rank[from] < r => Some(position[from]),
+                            false if from == node && rank[to] > r => Some(position[to]),
+                            _ => None,
+                        })
+                        .collect();
+                    let key = if neighbours.is_empty() {
+                        position[node]
+                    } else {
+                        neighbours.iter().sum::<f32>() / neighbours.len() as f32
+                    };
+                    (key, node)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            layers[r] = keyed.into_iter().map(|(_, node)| node).collect();
+            update_positions(&layers, &mut position);
+        }
+    }
+    layers
+}
+
+/// Width and height of a node's outline
+fn node_size(node: &Node) -> (f32, f32) {
+    let (text_width, text_height) = label_size(&node.label);
+    let width = text_width + NODE_PADDING * 2.0;
+    let height = (text_height + NODE_PADDING).max(NODE_HEIGHT);
+    match node.shape {
+        Shape::Circle => {
+            let size = width.max(height);
+            (size, size)
+        }
+        Shape::Diamond => (width + height, height * 1.5),
+        Shape::Ellipse => (width * 1.2, height),
+        Shape::Hexagon => (width + height / 2.0, height),
+        _ => (width.max(NODE_HEIGHT), height),
+    }
+}
+
+/// Distance from the center to the outline along (dx, dy), as a fraction of it
+fn clip_factor(shape: Shape, width: f32, height: f32, dx: f32, dy: f32) -> f32 {
+    let (rx, ry) = (width / 2.0, height / 2.0);
+    let (ax, ay) = (dx.abs().max(f32::EPSILON), dy.abs().max(f32::EPSILON));
+    match shape {
+        Shape::Circle | Shape::Ellipse => 1.0 / ((ax / rx).powi(2) + (ay / ry).powi(2)).sqrt(),
+        Shape::Diamond => 1.0 / (ax / rx + ay / ry),
+        _ => (rx / ax).min(ry / ay),
+    }
+}
+
+fn svg_shape(out: &mut String, shape: Shape, x: f32, y: f32, width: f32, height: f32) {
+    let (left, top) = (x - width / 2.0, y - height / 2.0);
+    let attrs = "class=\"diagram-shape\" fill=\"none\" stroke=\"currentColor\"";
+    match shape {
+        Shape::Rect => out.push_str(&format!(
+            "<rect {} x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"/>",
+            attrs, left, top, width, height
+        )),
+        Shape::Round | Shape::Stadium => {
+            let radius = if shape == Shape::Round { 6.0 } else { height / 2.0 };
+            out.push_str(&format!(
+                "<rect {} x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"{:.1}\"/>",
+                attrs, left, top, width, height, radius
+            ))
+        }
+        Shape::Circle | Shape::Ellipse => out.push_str(&format!(
+            "<ellipse {} cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\"/>",
+            attrs,
+            x,
+            y,
+            width / 2.0,
+            height / 2.0
+        )),
+        Shape::Diamond => out.push_str(&format!(
+            "<polygon {} points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>",
+            attrs,
+            x,
+            top,
+            left + width,
+            y,
+            x,
+            top + height,
+            left,
+            y
+        )),
+        Shape::Hexagon => {
+            let inset = height / 4.0;
+            out.push_str(&format!(
+                "<polygon {} points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>",
+                attrs,
+                left + inset,
+                top,
+                left + width - inset,
+                top,
+                left + width,
+                y,
+                left + width - inset,
+                top + height,
+                left + inset,
+                top + height,
+                left,
+                y
+            ))
+        }
+        Shape::Plain => {}
+    }
+}
+
+fn edge_attrs(edge: &Edge, id: &str) -> String {
+    let mut attrs = String::from("class=\"diagram-edge\" fill=\"none\" stroke=\"currentColor\"");
+    match edge.style {
+        LineStyle::Solid => {}
+        LineStyle::Dotted => attrs.push_str(" stroke-dasharray=\"4 3\""),
+        LineStyle::Thick => attrs.push_str(" stroke-width=\"2.5\""),
+    }
+    if edge.arrow {
+        attrs.push_str(&format!(" marker-end=\"url(#{}-arrow)\"", id));
+    }
+    attrs
+}
+
+/// Label of an edge, on a box that hides the line behind it
+fn svg_edge_label(out: &mut String, label: &str, x: f32, y: f32) {
+    let (width, height) = label_size(label);
+    out.push_str(&format!(
+        "<rect class=\"diagram-label-bg\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"Canvas\"/>",
+        x - width / 2.0 - 2.0,
+        y - height / 2.0,
+        width + 4.0,
+        height
+    ));
+    svg_text(out, label, x, y, "diagram-edge-label");
+}
+
+/// Lay out a graph and draw it as SVG
+pub fn render_graph(graph: &Graph, id: &str) -> String {
+    let vertical = graph.direction.is_vertical();
+    let sizes: Vec<(f32, f32)> = graph.nodes.iter().map(node_size).collect();
+    // Extent of a node along the rank axis, and across it
+    let along = |node: usize| if vertical { sizes[node].1 } else { sizes[node].0 };
+    let across = |node: usize| if vertical { sizes[node].0 } else { sizes[node].1 };
+
+    let edges = acyclic_edges(graph);
+    let rank = ranks(graph.nodes.len(), &edges);
+    let layers = order_ranks(&rank, &edges);
+
+    // Edge labels need room between ranks: their height, or in horizontal
+    // layouts their width
+    let label_room = graph
+        .edges
+        .iter()
+        .filter_map(|edge| edge.label.as_deref())
+        .map(|label| {
+            let (width, height) = label_size(label);
+            if vertical { height } else { width }
+        })
+        .fold(0.0, f32::max);
+    let rank_gap = RANK_GAP + label_room;
+
+    let layer_depth: Vec<f32> = layers
+        .iter()
+        .map(|layer| layer.iter().map(|&node| along(node)).fold(0.0, f32::max))
+        .collect();
+    let layer_breadth: Vec<f32> = layers
+        .iter()
+        .map(|layer| {
+            layer.iter().map(|&node| across(node)).sum::<f32>() + NODE_GAP * layer.len().saturating_sub(1) as f32
+        })
+        .collect();
+    let breadth = layer_breadth.iter().copied().fold(0.0, f32::max);
+    let depth = layer_depth.iter().sum::<f32>() + rank_gap * layers.len().saturating_sub(1) as f32;
+
+    // Centers in layout coordinates: main along the ranks, cross within them
+    let mut centers = vec![(0.0, 0.0); graph.nodes.len()];
+    let mut main = 0.0;
+    for (r, layer) in layers.iter().enumerate() {
+        let mut cross = (breadth - layer_breadth[r]) / 2.0;
+        for &node in layer {
+            centers[node] = (main + layer_depth[r] / 2.0, cross + across(node) / 2.0);
+            cross += across(node) + NODE_GAP;
+        }
+        main += layer_depth[r] + rank_gap;
+    }
+
+    let point = |(main, cross): (f32, f32)| {
+        let main = match graph.direction {
+            Direction::BottomUp | Direction::RightLeft => depth - main,
+            _ => main,
+        };
+        if vertical {
+            (MARGIN + cross, MARGIN + main)
+        } else {
+            (MARGIN + main, MARGIN + cross)
+        }
+    };
+    let positions: Vec<(f32, f32)> = centers.into_iter().map(point).collect();
+
+    let (width, height) = if vertical { (breadth, depth) } else { (depth, breadth) };
+    // Self loops stick out to the right of their node
+    let loop_room = if graph.edges.iter().any(|edge| edge.from == edge.to) { 24.0 } else { 0.0 };
+    let mut svg = svg_open(id, width + MARGIN * 2.0 + loop_room, height + MARGIN * 2.0);
+
+    for edge in &graph.edges {
+        let (x1, y1) = positions[edge.from];
+        let (w1, h1) = sizes[edge.from];
+        let attrs = edge_attrs(edge, id);
+
+        if edge.from == edge.to {
+            let (right, top, bottom) = (x1 + w1 / 2.0, y1 - h1 / 4.0, y1 + h1 / 4.0);
+            svg.push_str(&format!(
+                "<path {} d=\"M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>",
+                attrs,
+                right,
+                top,
+                right + 24.0,
+                top - 8.0,
+                right + 24.0,
+                bottom + 8.0,
+                right,
+                bottom
+            ));
+            if let Some(label) = &edge.label {
+                svg_edge_label(&mut svg, label, right + 12.0, y1);
+            }
+            continue;
+        }
+
+        let (x2, y2) = positions[edge.to];
+        let (w2, h2) = sizes[edge.to];
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let start = clip_factor(graph.nodes[edge.from].shape, w1, h1, dx, dy);
+        let end = 1.0 - clip_factor(graph.nodes[edge.to].shape, w2, h2, dx, dy);
+        svg.push_str(&format!(
+            "<line {} x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>",
+            attrs,
+            x1 + dx * start,
+            y1 + dy * start,
+            x1 + dx * end,
+            y1 + dy * end
+        ));
+        if let Some(label) = &edge.label {
+            let middle = (start + end) / 2.0;
+            svg_edge_label(&mut svg, label, x1 + dx * middle, y1 + dy * middle);
+        }
+    }
+
+    for ((node, &(x, y)), &(width, height)) in graph.nodes.iter().zip(&positions).zip(&sizes) {
+        svg.push_str("<g class=\"diagram-node\">");
+        svg_shape(&mut svg, node.shape, x, y, width, height);
+        svg_text(&mut svg, &node.label, x, y, "diagram-node-label");
+        svg.push_str("</g>");
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(ids: &[&str], back_to_start: bool) -> Graph {
+        let mut graph = Graph::new(Direction::TopDown);
+        for pair in ids.windows(2) {
+            let from = graph.node(pair[0], Shape::Rect);
+            let to = graph.node(pair[1], Shape::Rect);
+            graph.edges.push(Edge { from, to, label: None, style: LineStyle::Solid, arrow: true });
+        }
+        if back_to_start {
+            graph.edges.push(Edge { from: ids.len() - 1, to: 0, label: None, style: LineStyle::Solid, arrow: true });
+        }
+        graph
+    }
+
+    #[test]
+    fn test_ranks_break_cycles() {
+        let graph = chain(&["a", "b", "c"], true);
+        let edges = acyclic_edges(&graph);
+        assert_eq!(edges, vec![(0, 1), (1, 2), (0, 2)]);
+        assert_eq!(ranks(3, &edges), vec![0, 1, 2]);
+
+        // A node joins the rank below its deepest predecessor
+        let mut graph = chain(&["a", "b", "c"], false);
+        let d = graph.node("d", Shape::Rect);
+        graph.edges.push(Edge { from: 0, to: d, label: None, style: LineStyle::Solid, arrow: true });
+        graph.edges.push(Edge { from: 2, to: d, label: None, style: LineStyle::Solid, arrow: true });
+        assert_eq!(ranks(4, &acyclic_edges(&graph)), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_render_graph() {
+        let svg = render_graph(&chain(&["a", "b"], false), "g");
+        assert!(svg.contains("marker-end=\"url(#g-arrow)\""));
+        // b is drawn below a, and the edge runs between their outlines
+        assert!(svg.contains("<rect class=\"diagram-shape\" fill=\"none\" stroke=\"currentColor\" x=\"8.0\" y=\"8.0\""));
+        assert!(svg.contains("x1=\"26.0\" y1=\"44.0\" x2=\"26.0\" y2=\"92.0\""));
+    }
+}
@@ -0,0 +1,170 @@
+/**
+ * Diagram blocks
+ *
+ * Fenced blocks tagged `mermaid`, `dot` or `graphviz` are drawn as inline
+ * SVG instead of code. A subset of each language is supported: Mermaid
+ * flowcharts (`graph` / `flowchart`) and sequence diagrams, and Graphviz
+ * `digraph` / `graph` statements. Flowcharts and Graphviz graphs share one
+ * layered layout. Results are cached by a hash of the source, since the
+ * closing fence of a block re-renders whenever any of its lines change.
+ */
+
+mod dot;
+mod flowchart;
+mod layout;
+mod sequence;
+
+use html_escape::encode_text;
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// Font size of diagram labels, in pixels
+const FONT_SIZE: f32 = 14.0;
+/// Height of one line of label text
+const LINE_HEIGHT: f32 = 18.0;
+/// Space around the drawing
+const MARGIN: f32 = 8.0;
+
+/// Number of rendered diagrams kept before the cache is cleared
+const CACHE_LIMIT: usize = 256;
+
+/// Rendered SVG, or the error message, by hash of the diagram source
+static CACHE: Lazy<Mutex<HashMap<u64, Result<String, String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Language of a diagram block, from the fence's info string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagramKind {
+    Mermaid,
+    Dot,
+}
+
+impl DiagramKind {
+    pub fn from_lang(lang: &str) -> Option<Self> {
+        match lang.to_lowercase().as_str() {
+            "mermaid" => Some(Self::Mermaid),
+            "dot" | "graphviz" => Some(Self::Dot),
+            _ => None,
+        }
+    }
+}
+
+/// Approximate width of a line of label text; exact metrics are not
+/// available without a font, so shapes get some slack instead
+fn text_width(text: &str) -> f32 {
+    text.chars().count() as f32 * FONT_SIZE * 0.6
+}
+
+/// Width and height of a possibly multi-line label
+fn label_size(label: &str) -> (f32, f32) {
+    let width = label.lines().map(text_width).fold(0.0, f32::max);
+    (width, label.lines().count().max(1) as f32 * LINE_HEIGHT)
+}
+
+/// `<text>` centered on (x, y), one `<tspan>` per line
+fn svg_text(out: &mut String, label: &str, x: f32, y: f32, class: &str) {
+    let lines: Vec<&str> = if label.is_empty() { vec![""] } else { label.lines().collect() };
+    let top = y - (lines.len() - 1) as f32 * LINE_HEIGHT / 2.0;
+    out.push_str(&format!(
+        "<text class=\"{}\" x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\" \
+         fill=\"currentColor\" font-size=\"{}\">",
+        class, x, top, FONT_SIZE
+    ));
+    for (i, line) in lines.iter().enumerate() {
+        if i == 0 {
+            out.push_str(&format!("<tspan x=\"{:.1}\">{}</tspan>", x, encode_text(line)));
+        } else {
+            out.push_str(&format!("<tspan x=\"{:.1}\" dy=\"{}\">{}</tspan>", x, LINE_HEIGHT, encode_text(line)));
+        }
+    }
+    out.push_str("</text>");
+}
+
+/// Opening `<svg>` tag and the arrowhead markers, whose ids are prefixed
+/// with `id` so several diagrams can share a page
+fn svg_open(id: &str, width: f32, height: f32) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"diagram-svg\" role=\"img\" \
+         width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.0} {h:.0}\" font-family=\"sans-serif\">\
+         <defs><marker id=\"{id}-arrow\" viewBox=\"0 0 10 10\" refX=\"9\" refY=\"5\" markerWidth=\"8\" \
+         markerHeight=\"8\" orient=\"auto-start-reverse\"><path class=\"diagram-arrow\" d=\"M0,0 L10,5 L0,10 z\" \
+         fill=\"currentColor\"/></marker>\
+         <marker id=\"{id}-cross\" viewBox=\"0 0 10 10\" refX=\"5\" refY=\"5\" markerWidth=\"8\" markerHeight=\"8\">\
+         <path class=\"diagram-arrow\" d=\"M1,1 L9,9 M9,1 L1,9\" stroke=\"currentColor\" stroke-width=\"1.5\"/>\
+         </marker></defs>",
+        w = width,
+        h = height,
+        id = id
+    )
+}
+
+fn render_mermaid(source: &str, id: &str) -> Result<String, String> {
+    let header = source
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("%%"))
+        .unwrap_or("");
+    let keyword = header.split_whitespace().next().unwrap_or("");
+
+    match keyword {
+        "graph" | "flowchart" => flowchart::parse(source).map(|graph| layout::render_graph(&graph, id)),
+        "sequenceDiagram" => sequence::render(source, id),
+        "" => Err("Empty diagram".to_string()),
+        _ => Err(format!("Unsupported diagram type: {}", keyword)),
+    }
+}
+
+/// Render a diagram block to SVG
+pub fn render_diagram(kind: DiagramKind, source: &str) -> Result<String, String> {
+    let mut hasher = DefaultHasher::new();
+    (kind, source).hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some(cached) = CACHE.lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+        return cached;
+    }
+
+    let id = format!("diagram-{:016x}", key);
+    let result = match kind {
+        DiagramKind::Mermaid => render_mermaid(source, &id),
+        DiagramKind::Dot => dot::parse(source).map(|graph| layout::render_graph(&graph, &id)),
+    };
+
+    if let Ok(mut cache) = CACHE.lock() {
+        if cache.len() >= CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key, result.clone());
+    }
+    result
+}
+
+/// HTML for a diagram block: the SVG, or the reason it could not be drawn
+pub fn diagram_html(kind: DiagramKind, source: &str) -> String {
+    match render_diagram(kind, source) {
+        Ok(svg) => format!("<div class=\"diagram\">{}</div>", svg),
+        Err(error) => format!("<div class=\"diagram diagram-error\">{}</div>", encode_text(&error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_diagram() {
+        let svg = render_diagram(DiagramKind::Mermaid, "graph LR\n  A --> B").unwrap();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        // Cached renders are identical, including marker ids
+        assert_eq!(render_diagram(DiagramKind::Mermaid, "graph LR\n  A --> B").unwrap(), svg);
+
+        assert_eq!(DiagramKind::from_lang("Graphviz"), Some(DiagramKind::Dot));
+        assert_eq!(DiagramKind::from_lang("rust"), None);
+        assert_eq!(
+            diagram_html(DiagramKind::Mermaid, "pie\n  \"A\": 1"),
+            "<div class=\"diagram diagram-error\">Unsupported diagram type: pie</div>"
+        );
+    }
+}
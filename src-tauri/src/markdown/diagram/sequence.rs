@@ -0,0 +1,422 @@
+/**
+ * Mermaid sequence diagrams
+ *
+ * Parses `sequenceDiagram` blocks: `participant` / `actor` declarations
+ * (with `as` aliases), messages (`->>`, `-->>`, `->`, `-->`, `-x`, `--x`,
+ * `-)`), notes (`Note left of`, `right of`, `over A,B`), `autonumber`, and
+ * `loop` / `alt` / `opt` / `par` / `critical` / `break` frames with their
+ * `else` / `and` sections. Participants are columns in order of appearance
+ * and every message or note takes a row.
+ */
+
+use super::{label_size, svg_open, svg_text, text_width, LINE_HEIGHT, MARGIN};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static PARTICIPANT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(participant|actor)\s+(.+?)(?:\s+as\s+(.+))?$").unwrap());
+static MESSAGE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(.+?)\s*(-->>|->>|--x|-x|--\)|-\)|-->|->)\s*[+-]?\s*(.+?)\s*(?::\s*(.*))?$").unwrap()
+});
+static NOTE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^note\s+(left of|right of|over)\s+([^,:]+?)(?:\s*,\s*([^:]+?))?\s*:\s*(.*)$").unwrap()
+});
+
+const BOX_HEIGHT: f32 = 36.0;
+const MIN_BOX_WIDTH: f32 = 80.0;
+const COLUMN_GAP: f32 = 40.0;
+/// Space below each row
+const ROW_GAP: f32 = 16.0;
+/// Height of the loop drawn for a message to oneself
+const SELF_LOOP: f32 = 20.0;
+/// Height of a frame's title row, and of each `else` row
+const FRAME_ROW: f32 = 24.0;
+
+const FRAME_KINDS: &[&str] = &["loop", "alt", "opt", "par", "critical", "break", "rect"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Head {
+    Arrow,
+    Cross,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Message { from: usize, to: usize, label: String, dashed: bool, head: Head },
+    /// A note spanning participants `first..=last`, or beside one of them
+    Note { first: usize, last: usize, side: Option<bool>, text: String },
+    FrameStart { kind: String, label: String },
+    /// `else` / `and` section of the enclosing frame
+    FrameSection { label: String },
+    FrameEnd,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Participant {
+    id: String,
+    label: String,
+    actor: bool,
+}
+
+#[derive(Debug, Default)]
+struct Diagram {
+    participants: Vec<Participant>,
+    events: Vec<Event>,
+}
+
+impl Diagram {
+    fn participant(&mut self, id: &str) -> usize {
+        match self.participants.iter().position(|p| p.id == id) {
+            Some(index) => index,
+            None => {
+                self.participants.push(Participant { id: id.to_string(), label: id.to_string(), actor: false });
+                self.participants.len() - 1
+            }
+        }
+    }
+}
+
+fn clean_text(text: &str) -> String {
+    text.trim().replace("<br/>", "\n").replace("<br />", "\n").replace("<br>", "\n")
+}
+
+fn parse(source: &str) -> Result<Diagram, String> {
+    let mut diagram = Diagram::default();
+    let mut autonumber = false;
+    let mut depth = 0;
+    let mut header_seen = false;
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("%%") {
+            continue;
+        }
+        if !header_seen {
+            header_seen = true;
+            continue;
+        }
+
+        let keyword = line.split_whitespace().next().unwrap_or("");
+        let rest = line[keyword.len()..].trim();
+        match keyword {
+            "autonumber" => autonumber = true,
+            "activate" | "deactivate" | "title" => {}
+            "end" => {
+                if depth == 0 {
+                    return Err(format!("Line {}: `end` without a block", number + 1));
+                }
+                depth -= 1;
+                diagram.events.push(Event::FrameEnd);
+            }
+            "else" | "and" | "option" => {
+                if depth == 0 {
+                    return Err(format!("Line {}: `{}` outside a block", number + 1, keyword));
+                }
+                diagram.events.push(Event::FrameSection { label: clean_text(rest) });
+            }
+            kind if FRAME_KINDS.contains(&kind) => {
+                depth += 1;
+                diagram.events.push(Event::FrameStart { kind: kind.to_string(), label: clean_text(rest) });
+            }
+            _ => {
+                if let Some(caps) = PARTICIPANT_RE.captures(line) {
+                    let index = diagram.participant(&caps[2]);
+                    let participant = &mut diagram.participants[index];
+                    participant.actor = &caps[1] == "actor";
+                    if let Some(alias) = caps.get(3) {
+                        participant.label = clean_text(alias.as_str());
+                    }
+                } else if let Some(caps) = NOTE_RE.captures(line) {
+                    let first = diagram.participant(caps[2].trim());
+                    let last = caps.get(3).map_or(first, |other| diagram.participant(other.as_str().trim()));
+                    let side = match caps[1].to_lowercase().as_str() {
+                        "left of" => Some(false),
+                        "right of" => Some(true),
+                        _ => None,
+                    };
+                    let (first, last) = (first.min(last), first.max(last));
+                    diagram.events.push(Event::Note { first, last, side, text: clean_text(&caps[4]) });
+                } else if let Some(caps) = MESSAGE_RE.captures(line) {
+                    let from = diagram.participant(caps[1].trim());
+                    let to = diagram.participant(caps[3].trim());
+                    let arrow = &caps[2];
+                    let head = if arrow.ends_with('x') {
+                        Head::Cross
+                    } else if arrow.ends_with(">>") || arrow.ends_with(')') {
+                        Head::Arrow
+                    } else {
+                        Head::None
+                    };
+                    let mut label = caps.get(4).map(|m| clean_text(m.as_str())).unwrap_or_default();
+                    if autonumber {
+                        let count = diagram.events.iter().filter(|e| matches!(e, Event::Message { .. })).count();
+                        label = format!("{}. {}", count + 1, label);
+                    }
+                    diagram.events.push(Event::Message { from, to, label, dashed: arrow.starts_with("--"), head });
+                } else {
+                    return Err(format!("Line {}: unrecognized statement `{}`", number + 1, line));
+                }
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err("Missing `end` for a block".to_string());
+    }
+    if diagram.participants.is_empty() {
+        return Err("Empty diagram".to_string());
+    }
+    Ok(diagram)
+}
+
+/// Column centers, widened so labels fit between the columns they join,
+/// and the total width
+fn columns(diagram: &Diagram) -> (Vec<f32>, Vec<f32>, f32) {
+    let widths: Vec<f32> = diagram
+        .participants
+        .iter()
+        .map(|p| (label_size(&p.label).0 + 24.0).max(MIN_BOX_WIDTH))
+        .collect();
+    let count = widths.len();
+    let mut gaps: Vec<f32> = widths.windows(2).map(|pair| (pair[0] + pair[1]) / 2.0 + COLUMN_GAP).collect();
+    let (mut left_room, mut right_room) = (0.0_f32, 0.0_f32);
+
+    // Room needed to the right (true) or left (false) of a column
+    let mut make_room = |gaps: &mut Vec<f32>, column: usize, right: bool, need: f32| {
+        if right && column + 1 < count {
+            gaps[column] = gaps[column].max(need + widths[column + 1] / 2.0);
+        } else if right {
+            right_room = right_room.max(need - widths[column] / 2.0);
+        } else if column > 0 {
+            gaps[column - 1] = gaps[column - 1].max(need + widths[column - 1] / 2.0);
+        } else {
+            left_room = left_room.max(need - widths[column] / 2.0);
+        }
+    };
+
+    for event in &diagram.events {
+        match event {
+            Event::Message { from, to, label, .. } if from == to => {
+                make_room(&mut gaps, *from, true, label_size(label).0 + 40.0);
+            }
+            Event::Message { from, to, label, .. } => {
+                let (low, high) = ((*from).min(*to), (*from).max(*to));
+                let need = label_size(label).0 + 24.0;
+                let current: f32 = gaps[low..high].iter().sum();
+                if current < need {
+                    gaps[high - 1] += need - current;
+                }
+            }
+            Event::Note { first, side: Some(right), text, .. } => {
+                make_room(&mut gaps, *first, *right, label_size(text).0 + 30.0);
+            }
+            _ => {}
+        }
+    }
+
+    let mut centers = Vec::with_capacity(count);
+    let mut x = MARGIN + left_room.max(0.0) + widths[0] / 2.0;
+    for i in 0..count {
+        centers.push(x);
+        if i < gaps.len() {
+            x += gaps[i];
+        }
+    }
+    let width = x + widths[count - 1] / 2.0 + right_room.max(0.0) + MARGIN;
+    (centers, widths, width)
+}
+
+fn rect(out: &mut String, class: &str, x: f32, y: f32, width: f32, height: f32, radius: f32) {
+    out.push_str(&format!(
+        "<rect class=\"{}\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"{:.1}\" \
+         fill=\"none\" stroke=\"currentColor\"/>",
+        class, x, y, width, height, radius
+    ));
+}
+
+/// A frame being drawn: its kind, title, top and `else` separators
+struct Frame {
+    kind: String,
+    label: String,
+    top: f32,
+    sections: Vec<(f32, String)>,
+}
+
+/// Parse and draw a sequence diagram
+pub fn render(source: &str, id: &str) -> Result<String, String> {
+    let diagram = parse(source)?;
+    let (centers, widths, width) = columns(&diagram);
+    let left = centers[0] - widths[0] / 2.0;
+    let right = centers[centers.len() - 1] + widths[widths.len() - 1] / 2.0;
+
+    let mut frames = String::new();
+    let mut body = String::new();
+    let mut open: Vec<Frame> = Vec::new();
+    let mut y = MARGIN + BOX_HEIGHT + ROW_GAP;
+
+    for event in &diagram.events {
+        match event {
+            Event::Message { from, to, label, dashed, head } => {
+                let mut attrs = String::from("class=\"diagram-edge\" fill=\"none\" stroke=\"currentColor\"");
+                if *dashed {
+                    attrs.push_str(" stroke-dasharray=\"4 3\"");
+                }
+                match head {
+                    Head::Arrow => attrs.push_str(&format!(" marker-end=\"url(#{}-arrow)\"", id)),
+                    Head::Cross => attrs.push_str(&format!(" marker-end=\"url(#{}-cross)\"", id)),
+                    Head::None => {}
+                }
+
+                let (label_width, label_height) = label_size(label);
+                let (x1, x2) = (centers[*from], centers[*to]);
+                if from == to {
+                    let x = x1 + 20.0 + label_width / 2.0;
+                    svg_text(&mut body, label, x, y + label_height / 2.0, "diagram-edge-label");
+                    y += label_height + 4.0;
+                    body.push_str(&format!(
+                        "<path {} d=\"M{:.1},{:.1} h30 v{:.1} h-30\"/>",
+                        attrs, x1, y, SELF_LOOP
+                    ));
+                    y += SELF_LOOP;
+                } else {
+                    svg_text(&mut body, label, (x1 + x2) / 2.0, y + label_height / 2.0, "diagram-edge-label");
+                    y += label_height + 4.0;
+                    body.push_str(&format!(
+                        "<line {} x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>",
+                        attrs, x1, y, x2, y
+                    ));
+                }
+                y += ROW_GAP;
+            }
+            Event::Note { first, last, side, text } => {
+                let (text_width, text_height) = label_size(text);
+                let note_width = text_width + 20.0;
+                let note_left = match side {
+                    Some(true) => centers[*first] + 10.0,
+                    Some(false) => centers[*first] - 10.0 - note_width,
+                    None => {
+                        let middle = (centers[*first] + centers[*last]) / 2.0;
+                        let span = centers[*last] - centers[*first] + 40.0;
+                        middle - span.max(note_width) / 2.0
+                    }
+                };
+                let note_width = if side.is_none() {
+                    (centers[*last] - centers[*first] + 40.0).max(note_width)
+                } else {
+                    note_width
+                };
+                let note_height = text_height + 12.0;
+                rect(&mut body, "diagram-note", note_left, y, note_width, note_height, 0.0);
+                svg_text(&mut body, text, note_left + note_width / 2.0, y + note_height / 2.0, "diagram-note-label");
+                y += note_height + ROW_GAP;
+            }
+            Event::FrameStart { kind, label } => {
+                open.push(Frame { kind: kind.clone(), label: label.clone(), top: y, sections: Vec::new() });
+                y += FRAME_ROW;
+            }
+            Event::FrameSection { label } => {
+                if let Some(frame) = open.last_mut() {
+                    frame.sections.push((y, label.clone()));
+                }
+                y += FRAME_ROW;
+            }
+            Event::FrameEnd => {
+                let Some(frame) = open.pop() else { continue };
+                let inset = open.len() as f32 * 6.0;
+                let (frame_left, frame_right) = (left - 8.0 + inset, right + 8.0 - inset);
+                rect(&mut frames, "diagram-frame", frame_left, frame.top, frame_right - frame_left, y - frame.top, 0.0);
+
+                let tag_width = text_width(&frame.kind) + 12.0;
+                rect(&mut frames, "diagram-frame-tag", frame_left, frame.top, tag_width, 20.0, 0.0);
+                let x = frame_left + tag_width / 2.0;
+                svg_text(&mut frames, &frame.kind, x, frame.top + 10.0, "diagram-frame-label");
+                if !frame.label.is_empty() {
+                    let title = format!("[{}]", frame.label);
+                    let x = frame_left + tag_width + 8.0 + text_width(&title) / 2.0;
+                    svg_text(&mut frames, &title, x, frame.top + 10.0, "diagram-frame-label");
+                }
+                for (section_y, label) in &frame.sections {
+                    frames.push_str(&format!(
+                        "<line class=\"diagram-frame\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" \
+                         stroke=\"currentColor\" stroke-dasharray=\"4 3\"/>",
+                        frame_left, section_y, frame_right, section_y
+                    ));
+                    if !label.is_empty() {
+                        let title = format!("[{}]", label);
+                        let (x, y) = ((frame_left + frame_right) / 2.0, section_y + LINE_HEIGHT / 2.0 + 2.0);
+                        svg_text(&mut frames, &title, x, y, "diagram-frame-label");
+                    }
+                }
+                y += 8.0;
+            }
+        }
+    }
+
+    let height = y + MARGIN;
+    let mut svg = svg_open(id, width, height);
+    svg.push_str(&frames);
+    for ((participant, &x), &box_width) in diagram.participants.iter().zip(&centers).zip(&widths) {
+        svg.push_str(&format!(
+            "<line class=\"diagram-lifeline\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" \
+             stroke=\"currentColor\" stroke-dasharray=\"4 3\"/>",
+            x,
+            MARGIN + BOX_HEIGHT,
+            x,
+            y
+        ));
+        let (class, radius) = if participant.actor {
+            ("diagram-shape diagram-actor", BOX_HEIGHT / 2.0)
+        } else {
+            ("diagram-shape", 4.0)
+        };
+        svg.push_str("<g class=\"diagram-node\">");
+        rect(&mut svg, class, x - box_width / 2.0, MARGIN, box_width, BOX_HEIGHT, radius);
+        svg_text(&mut svg, &participant.label, x, MARGIN + BOX_HEIGHT / 2.0, "diagram-node-label");
+        svg.push_str("</g>");
+    }
+    svg.push_str(&body);
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sequence() {
+        let diagram = parse(
+            "sequenceDiagram\n  autonumber\n  actor U as User\n  U->>+API: GET /notes\n  loop Every page\n    API-->>DB: query\n  end\n  Note over U,API: cached\n  API--xU: 404",
+        )
+        .unwrap();
+
+        let labels: Vec<&str> = diagram.participants.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["User", "API", "DB"]);
+        assert!(diagram.participants[0].actor);
+        assert_eq!(
+            diagram.events,
+            vec![
+                Event::Message { from: 0, to: 1, label: "1. GET /notes".to_string(), dashed: false, head: Head::Arrow },
+                Event::FrameStart { kind: "loop".to_string(), label: "Every page".to_string() },
+                Event::Message { from: 1, to: 2, label: "2. query".to_string(), dashed: true, head: Head::Arrow },
+                Event::FrameEnd,
+                Event::Note { first: 0, last: 1, side: None, text: "cached".to_string() },
+                Event::Message { from: 1, to: 0, label: "3. 404".to_string(), dashed: true, head: Head::Cross },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_sequence() {
+        let svg = render("sequenceDiagram\n  A->>B: hi\n  B->>B: think", "s").unwrap();
+        assert_eq!(svg.matches("class=\"diagram-lifeline\"").count(), 2);
+        assert!(svg.contains("marker-end=\"url(#s-arrow)\""));
+
+        assert_eq!(render("sequenceDiagram\n  loop\n  A->>B: hi", "s").unwrap_err(), "Missing `end` for a block");
+        assert_eq!(
+            render("sequenceDiagram\n  A => B", "s").unwrap_err(),
+            "Line 2: unrecognized statement `A => B`"
+        );
+    }
+}
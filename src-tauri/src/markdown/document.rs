@@ -0,0 +1,575 @@
+/**
+ * Stateful document sessions for incremental rendering
+ *
+ * A document is opened once with its full content. Afterwards the frontend
+ * only sends line edits, and the session re-renders the lines whose content
+ * or block context changed, returning just the lines whose HTML differs.
+ */
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::block_detection::{LineBlockState, StructureScanner};
+use super::outline::SlugGenerator;
+use super::{render_line, LineRenderResult, RenderContext};
+
+/// Lines between saved scanner states: a rescan starts at most this far
+/// above an edit, and runs at most this far past the lines it changed
+const CHECKPOINT_INTERVAL: usize = 32;
+
+/// Replace `delete_count` lines starting at `start` with `insert`
+///
+/// Covers insertions (`delete_count == 0`), deletions (`insert` empty)
+/// and in-place replacements.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineEdit {
+    pub start: usize,
+    #[serde(default)]
+    pub delete_count: usize,
+    #[serde(default)]
+    pub insert: Vec<String>,
+}
+
+/// A re-rendered line whose HTML differs from the previous render
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedLine {
+    pub line_index: usize,
+    pub result: LineRenderResult,
+}
+
+/// Result of applying edits to a document session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentUpdate {
+    pub line_count: usize,
+    pub changed: Vec<ChangedLine>,
+}
+
+/// How much work a line needs after a batch of edits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineStatus {
+    Clean,
+    /// Neighbours or cursor changed: re-render and report if the HTML differs
+    Recheck,
+    /// Newly inserted content: always re-render and report
+    New,
+}
+
+/// Rendered state of one open document
+pub struct DocumentSession {
+    lines: Vec<String>,
+    /// Scanner state before some of the lines, about one in every
+    /// `CHECKPOINT_INTERVAL`; a state moves with its line on edits above it
+    checkpoints: Vec<Option<Box<StructureScanner>>>,
+    /// Slug of each heading line before it is made unique in the document
+    heading_slugs: Vec<Option<String>>,
+    block_states: Vec<LineBlockState>,
+    rendered: Vec<LineRenderResult>,
+    editing_line: Option<usize>,
+    /// Revision of the render context the cached lines were rendered with
+    context_revision: u64,
+}
+
+impl DocumentSession {
+    pub fn new(lines: Vec<String>, editing_line: Option<usize>, context: &RenderContext) -> Self {
+        let mut scanner = StructureScanner::for_document(&lines);
+        let mut checkpoints = Vec::with_capacity(lines.len());
+        let mut heading_slugs = Vec::with_capacity(lines.len());
+        let mut block_states = Vec::with_capacity(lines.len());
+
+        for (i, line) in lines.iter().enumerate() {
+            checkpoints.push((i % CHECKPOINT_INTERVAL == 0).then(|| Box::new(scanner.clone())));
+            let mut block_state = scanner.advance(line, lines.get(i + 1).map(String::as_str));
+            heading_slugs.push(block_state.heading_id.take());
+            block_states.push(block_state);
+        }
+
+        let mut session = Self {
+            lines,
+            checkpoints,
+            heading_slugs,
+            block_states,
+            rendered: Vec::new(),
+            editing_line,
+            context_revision: context.revision,
+        };
+        session.assign_heading_ids();
+
+        // Block state is known for every line, so rendering is independent per line
+        session.rendered = session
+            .lines
+            .par_iter()
+            .zip(session.block_states.par_iter())
+            .enumerate()
+            .map(|(i, (line, state))| render_line(line, state, editing_line == Some(i), context))
+            .collect();
+        session
+    }
+
+    pub fn rendered(&self) -> &[LineRenderResult] {
+        &self.rendered
+    }
+
+    /// Make heading slugs unique in document order, returning the lines
+    /// whose anchor changed
+    ///
+    /// Anchors depend on every heading above, so they are kept out of the
+    /// scanner state; otherwise renaming a heading would rescan the rest of
+    /// the document.
+    fn assign_heading_ids(&mut self) -> Vec<usize> {
+        let mut slugs = SlugGenerator::default();
+        let mut changed = Vec::new();
+        for (i, base) in self.heading_slugs.iter().enumerate() {
+            let id = base.clone().map(|base| slugs.unique(base));
+            if self.block_states[i].heading_id != id {
+                self.block_states[i].heading_id = id;
+                changed.push(i);
+            }
+        }
+        changed
+    }
+
+    /// Apply edits in order, then re-render every line affected by them
+    pub fn apply_edits(
+        &mut self,
+        edits: &[LineEdit],
+        editing_line: Option<usize>,
+        context: &RenderContext,
+    ) -> Result<DocumentUpdate, String> {
+        // Validate every edit up front so a bad batch leaves the session untouched
+        let mut line_count = self.lines.len();
+        for edit in edits {
+            match edit.start.checked_add(edit.delete_count) {
+                Some(end) if end <= line_count => {
+                    line_count = line_count - edit.delete_count + edit.insert.len();
+                }
+                _ => {
+                    return Err(format!(
+                        "Edit range {}..{} is out of bounds for {} lines",
+                        edit.start,
+                        edit.start.saturating_add(edit.delete_count),
+                        line_count
+                    ));
+                }
+            }
+        }
+
+        let mut status = vec![LineStatus::Clean; self.lines.len()];
+
+        for edit in edits {
+            let end = edit.start + edit.delete_count;
+            let inserted = edit.insert.len();
+
+            self.lines.splice(edit.start..end, edit.insert.iter().cloned());
+            self.checkpoints.splice(edit.start..end, std::iter::repeat_n(None, inserted));
+            self.heading_slugs.splice(edit.start..end, std::iter::repeat_n(None, inserted));
+            self.block_states
+                .splice(edit.start..end, std::iter::repeat_n(LineBlockState::default(), inserted));
+            self.rendered.splice(
+                edit.start..end,
+                std::iter::repeat_n(
+                    LineRenderResult {
+                        html: String::new(),
+                        is_code_block_boundary: false,
+                    },
+                    inserted,
+                ),
+            );
+            status.splice(edit.start..end, std::iter::repeat_n(LineStatus::New, inserted));
+
+            // The line before the edit sees a new next line (table headers depend
+            // on it), and the line after it may see a new block state
+            for index in [edit.start.checked_sub(1), Some(edit.start + inserted)].into_iter().flatten() {
+                if let Some(line_status) = status.get_mut(index) {
+                    if *line_status == LineStatus::Clean {
+                        *line_status = LineStatus::Recheck;
+                    }
+                }
+            }
+        }
+
+        // Adding or removing a closing delimiter decides whether the first line opens frontmatter
+        let initial_state = StructureScanner::for_document(&self.lines);
+        if self.checkpoints.first().is_some_and(|state| state.as_deref() != Some(&initial_state))
+            && status[0] == LineStatus::Clean
+        {
+            status[0] = LineStatus::Recheck;
+        }
+
+        // Moving the cursor toggles editing mode on the old and new lines
+        if editing_line != self.editing_line {
+            for index in [self.editing_line, editing_line].into_iter().flatten() {
+                if let Some(line_status) = status.get_mut(index) {
+                    if *line_status == LineStatus::Clean {
+                        *line_status = LineStatus::Recheck;
+                    }
+                }
+            }
+            self.editing_line = editing_line;
+        }
+
+        // New settings or note names can change the HTML of any line
+        if context.revision != self.context_revision {
+            for line_status in status.iter_mut().filter(|s| **s == LineStatus::Clean) {
+                *line_status = LineStatus::Recheck;
+            }
+            self.context_revision = context.revision;
+        }
+
+        if let Some(first) = status.iter().position(|&s| s != LineStatus::Clean) {
+            self.rescan(first, &mut status, initial_state);
+        }
+
+        let mut changed = Vec::new();
+        for (i, &line_status) in status.iter().enumerate() {
+            if line_status == LineStatus::Clean {
+                continue;
+            }
+            let result = render_line(&self.lines[i], &self.block_states[i], self.editing_line == Some(i), context);
+            if line_status == LineStatus::New || result != self.rendered[i] {
+                self.rendered[i] = result.clone();
+                changed.push(ChangedLine { line_index: i, result });
+            }
+        }
+
+        Ok(DocumentUpdate {
+            line_count: self.lines.len(),
+            changed,
+        })
+    }
+
+    /// Recompute block states from the line `first` on, marking the lines
+    /// whose state or anchor changed for a re-render
+    fn rescan(&mut self, first: usize, status: &mut [LineStatus], initial_state: StructureScanner) {
+        let last_touched = status.iter().rposition(|&s| s != LineStatus::Clean).unwrap_or(first);
+
+        // Lines above `first` are unchanged, so a state saved there still holds
+        let (start, mut scanner) = match (1..=first).rev().find(|&i| self.checkpoints[i].is_some()) {
+            Some(i) => (i, self.checkpoints[i].as_deref().cloned().unwrap_or_default()),
+            None => (0, initial_state),
+        };
+
+        for (i, line_status) in status.iter_mut().enumerate().skip(start) {
+            let saved = self.checkpoints[i].take();
+
+            // Past the last edit, reaching a saved state unchanged means nothing below can differ
+            if i > last_touched && *line_status == LineStatus::Clean && saved.as_deref() == Some(&scanner) {
+                self.checkpoints[i] = saved;
+                break;
+            }
+
+            if i % CHECKPOINT_INTERVAL == 0 {
+                self.checkpoints[i] = Some(Box::new(scanner.clone()));
+            }
+            let mut block_state = scanner.advance(&self.lines[i], self.lines.get(i + 1).map(String::as_str));
+            self.heading_slugs[i] = block_state.heading_id.take();
+            // Anchors are settled below, once every heading is known
+            block_state.heading_id = self.block_states[i].heading_id.clone();
+
+            if block_state != self.block_states[i] {
+                self.block_states[i] = block_state;
+                if *line_status == LineStatus::Clean {
+                    *line_status = LineStatus::Recheck;
+                }
+            }
+        }
+
+        for i in self.assign_heading_ids() {
+            if status[i] == LineStatus::Clean {
+                status[i] = LineStatus::Recheck;
+            }
+        }
+    }
+}
+
+/// Open document sessions keyed by a frontend-chosen document id
+#[derive(Default)]
+pub struct DocumentStore {
+    sessions: HashMap<String, DocumentSession>,
+}
+
+impl DocumentStore {
+    pub fn open(
+        &mut self,
+        doc_id: String,
+        lines: Vec<String>,
+        editing_line: Option<usize>,
+        context: &RenderContext,
+    ) -> Vec<LineRenderResult> {
+        let session = DocumentSession::new(lines, editing_line, context);
+        let rendered = session.rendered().to_vec();
+        self.sessions.insert(doc_id, session);
+        rendered
+    }
+
+    pub fn update(
+        &mut self,
+        doc_id: &str,
+        edits: &[LineEdit],
+        editing_line: Option<usize>,
+        context: &RenderContext,
+    ) -> Result<DocumentUpdate, String> {
+        self.sessions
+            .get_mut(doc_id)
+            .ok_or_else(|| format!("Document '{}' is not open", doc_id))?
+            .apply_edits(edits, editing_line, context)
+    }
+
+    pub fn close(&mut self, doc_id: &str) {
+        self.sessions.remove(doc_id);
+    }
+}
+
+// Global state wrapped in Arc<Mutex<>> for thread-safe access
+pub type DocumentStoreHandle = Arc<Mutex<DocumentStore>>;
+
+pub fn create_document_store() -> DocumentStoreHandle {
+    Arc::new(Mutex::new(DocumentStore::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{render_markdown_line, RenderRequest};
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    /// Render every line the old way, for comparison
+    fn full_render(all_lines: &[String], editing_line: Option<usize>) -> Vec<LineRenderResult> {
+        all_lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                render_markdown_line(RenderRequest {
+                    line: line.clone(),
+                    line_index: i,
+                    all_lines: all_lines.to_vec(),
+                    is_editing: editing_line == Some(i),
+                }, &RenderContext::default())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_open_matches_full_render() {
+        let context = RenderContext::default();
+        let doc = lines("# Title\n```rust\nlet x = 1;\n```\n$$\nx^2\n$$\n**done**");
+        let session = DocumentSession::new(doc.clone(), Some(2), &context);
+        assert_eq!(session.rendered(), full_render(&doc, Some(2)).as_slice());
+    }
+
+    #[test]
+    fn test_edit_only_returns_changed_lines() {
+        let context = RenderContext::default();
+        let doc = lines("# Title\nfirst\nsecond\nthird");
+        let mut session = DocumentSession::new(doc, None, &context);
+
+        let update = session
+            .apply_edits(
+                &[LineEdit { start: 2, delete_count: 1, insert: vec!["**second**".to_string()] }],
+                None,
+                &context,
+            )
+            .unwrap();
+
+        assert_eq!(update.line_count, 4);
+        assert_eq!(update.changed.len(), 1);
+        assert_eq!(update.changed[0].line_index, 2);
+        assert!(update.changed[0].result.html.contains("<strong>"));
+    }
+
+    #[test]
+    fn test_opening_fence_rerenders_following_lines() {
+        let context = RenderContext::default();
+        let doc = lines("intro\nfn main() {}\nlet x = 1;\n```");
+        let mut session = DocumentSession::new(doc, None, &context);
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 1, delete_count: 0, insert: vec!["```rust".to_string()] }], None, &context)
+            .unwrap();
+
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![1, 2, 3, 4]);
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+
+    #[test]
+    fn test_block_comment_rehighlights_following_lines() {
+        let context = RenderContext::default();
+        let doc = lines("```rust
+let a = 1;
+let b = 2;
+```
+after");
+        let mut session = DocumentSession::new(doc, None, &context);
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 1, delete_count: 1, insert: vec!["/* let a = 1;".to_string()] }], None, &context)
+            .unwrap();
+
+        // The comment runs on into the next line of the block, and no further
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![1, 2]);
+        assert_eq!(update.changed[1].result.html, "<code class=\"code-block-line\"><span class=\"tok-comment\">let b = 2;</span></code>");
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+
+    #[test]
+    fn test_math_edit_rerenders_closing_line() {
+        let context = RenderContext::default();
+        let doc = lines("$$\nx^2\n$$\nafter");
+        let mut session = DocumentSession::new(doc, None, &context);
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 1, delete_count: 1, insert: vec!["y^3".to_string()] }], Some(1), &context)
+            .unwrap();
+
+        // The closing `$$` line shows the formula of the whole block
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![1, 2]);
+        assert!(update.changed[1].result.html.contains("<msup><mi>y</mi><mn>3</mn></msup>"));
+        assert_eq!(session.rendered(), full_render(&session.lines, Some(1)).as_slice());
+    }
+
+    #[test]
+    fn test_deleting_fence_and_moving_cursor() {
+        let context = RenderContext::default();
+        let doc = lines("```\ncode\n```\nafter\n```\nmore");
+        let mut session = DocumentSession::new(doc, Some(3), &context);
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 0, delete_count: 1, insert: vec![] }], Some(0), &context)
+            .unwrap();
+
+        assert_eq!(update.line_count, 5);
+        assert_eq!(session.rendered(), full_render(&session.lines, Some(0)).as_slice());
+    }
+
+    #[test]
+    fn test_context_change_rerenders_cached_lines() {
+        let mut context = RenderContext::default();
+        let mut session = DocumentSession::new(lines("plain\n<u>under</u>"), None, &context);
+        assert!(session.rendered()[1].html.contains("<u>"));
+
+        context.sanitize.allowed_tags.retain(|tag| tag != "u");
+        context.revision += 1;
+        let update = session.apply_edits(&[], None, &context).unwrap();
+
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![1]);
+        assert!(!session.rendered()[1].html.contains("<u>"));
+    }
+
+    #[test]
+    fn test_closing_delimiter_opens_frontmatter() {
+        let context = RenderContext::default();
+        let doc = lines("---\ntitle: x\ntext");
+        let mut session = DocumentSession::new(doc.clone(), None, &context);
+        assert_eq!(session.rendered(), full_render(&doc, None).as_slice());
+        assert!(session.rendered()[1].html.contains("title: x"));
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 2, delete_count: 0, insert: vec!["---".to_string()] }], None, &context)
+            .unwrap();
+
+        // The line after the frontmatter renders the same either way
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+
+    #[test]
+    fn test_heading_far_above_renumbers_anchors_below() {
+        let context = RenderContext::default();
+        let mut text = "# Setup\n".to_string();
+        for i in 0..200 {
+            text.push_str(&format!("line {}\n", i));
+        }
+        text.push_str("# Setup");
+        let mut session = DocumentSession::new(lines(&text), None, &context);
+        assert!(session.rendered()[201].html.contains("id=\"setup-1\""));
+        // Only every `CHECKPOINT_INTERVAL`th line keeps a scanner state
+        assert_eq!(session.checkpoints.iter().filter(|state| state.is_some()).count(), 7);
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 100, delete_count: 0, insert: vec!["## Setup".to_string()] }], None, &context)
+            .unwrap();
+
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![100, 202]);
+        assert!(session.rendered()[202].html.contains("id=\"setup-2\""));
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+
+    #[test]
+    fn test_out_of_bounds_edit() {
+        let context = RenderContext::default();
+        let mut session = DocumentSession::new(lines("a\nb"), None, &context);
+        let result = session.apply_edits(&[LineEdit { start: 1, delete_count: 5, insert: vec![] }], None, &context);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_delimiter_row_turns_previous_line_into_header() {
+        let context = RenderContext::default();
+        let doc = lines("| a | b |\n| 1 | 2 |");
+        let mut session = DocumentSession::new(doc, None, &context);
+
+        let update = session
+            .apply_edits(&[LineEdit { start: 1, delete_count: 0, insert: vec!["|---|---|".to_string()] }], None, &context)
+            .unwrap();
+
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+
+    #[test]
+    fn test_separate_deletions_update_block_state() {
+        let context = RenderContext::default();
+        let doc = lines("a\nb\nc\nd\n```\ne\nf");
+        let mut session = DocumentSession::new(doc, None, &context);
+
+        // Two deletions far apart; the second removes a fence
+        session
+            .apply_edits(
+                &[
+                    LineEdit { start: 0, delete_count: 1, insert: vec![] },
+                    LineEdit { start: 3, delete_count: 1, insert: vec![] },
+                ],
+                None,
+                &context,
+            )
+            .unwrap();
+
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+
+    #[test]
+    fn test_heading_ids_follow_earlier_headings() {
+        let context = RenderContext::default();
+        let doc = lines("# Setup
+text
+## Setup
+```
+# Setup
+```");
+        let mut session = DocumentSession::new(doc, None, &context);
+        assert!(session.rendered()[0].html.contains("id=\"setup\""));
+        assert!(session.rendered()[2].html.contains("id=\"setup-1\""));
+        assert!(!session.rendered()[4].html.contains("id="));
+
+        // Renaming the first heading frees its anchor for the second
+        let update = session
+            .apply_edits(&[LineEdit { start: 0, delete_count: 1, insert: vec!["# Intro".to_string()] }], None, &context)
+            .unwrap();
+
+        let indices: Vec<usize> = update.changed.iter().map(|c| c.line_index).collect();
+        assert_eq!(indices, vec![0, 2]);
+        assert!(session.rendered()[2].html.contains("id=\"setup\""));
+        assert_eq!(session.rendered(), full_render(&session.lines, None).as_slice());
+    }
+}
@@ -0,0 +1,306 @@
+/**
+ * Frontmatter properties
+ *
+ * A YAML (`---`) or TOML (`+++`) block at the very top of a note holds its
+ * properties. In preview the block renders as a collapsible list of
+ * key/value rows; the parsing and write-back helpers here back the
+ * properties commands and keep the note body untouched.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+use super::block_detection::{BlockPosition, FrontmatterFormat};
+use super::{escape_html, LineRenderResult};
+
+// Top-level `key: value` (YAML) and `key = value` (TOML) lines
+static YAML_PROPERTY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([^\s:#-][^:#]*?)\s*:(?:\s+(.*))?$").unwrap());
+static TOML_PROPERTY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^([A-Za-z0-9_."'-]+)\s*=\s*(.*)$"#).unwrap());
+
+/// Render a line of the frontmatter block
+pub fn render_frontmatter_line(
+    line: &str,
+    format: FrontmatterFormat,
+    position: BlockPosition,
+    is_editing: bool,
+) -> LineRenderResult {
+    let is_boundary = position.is_start || position.is_end;
+
+    let html = if is_editing {
+        format!("<span class=\"frontmatter-line-editing\">{}</span>", escape_html(line))
+    } else if position.is_start {
+        format!(
+            "<span class=\"frontmatter-start\" data-format=\"{}\">\
+            <span class=\"frontmatter-toggle\">Properties</span></span>",
+            format.name()
+        )
+    } else if position.is_end {
+        "<span class=\"frontmatter-end\"></span>".to_string()
+    } else {
+        render_property_row(line, format)
+    };
+
+    LineRenderResult {
+        html,
+        is_code_block_boundary: is_boundary,
+    }
+}
+
+fn render_property_row(line: &str, format: FrontmatterFormat) -> String {
+    let property_re = match format {
+        FrontmatterFormat::Yaml => &YAML_PROPERTY_RE,
+        FrontmatterFormat::Toml => &TOML_PROPERTY_RE,
+    };
+
+    match property_re.captures(line) {
+        Some(cap) => format!(
+            "<span class=\"frontmatter-property\"><span class=\"property-key\">{}</span>\
+            <span class=\"property-value\">{}</span></span>",
+            escape_html(&cap[1]),
+            escape_html(cap.get(2).map_or("", |m| m.as_str()))
+        ),
+        // Continuation lines (list items, nested values) belong to the key above
+        None => format!(
+            "<span class=\"frontmatter-property frontmatter-continuation\">\
+            <span class=\"property-value\">{}</span></span>",
+            escape_html(line.trim())
+        ),
+    }
+}
+
+/// Location of a closed frontmatter block in a note
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontmatterBlock {
+    pub format: FrontmatterFormat,
+    /// Text between the delimiter lines
+    pub content: Range<usize>,
+    /// Start of the body, right after the closing delimiter's line ending
+    pub body_start: usize,
+}
+
+/// Find the frontmatter block at the top of a note
+///
+/// Unlike the renderer, this requires the closing delimiter: an unclosed
+/// block has no properties to read or write.
+pub fn find_frontmatter(content: &str) -> Option<FrontmatterBlock> {
+    let mut lines = content.split_inclusive('\n');
+    let first = lines.next()?;
+    let format = FrontmatterFormat::from_opening_line(first.trim_end_matches(['\n', '\r']))?;
+
+    let mut offset = first.len();
+    for line in lines {
+        if format.is_closing_line(line.trim_end_matches(['\n', '\r'])) {
+            return Some(FrontmatterBlock {
+                format,
+                content: first.len()..offset,
+                body_start: offset + line.len(),
+            });
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Frontmatter of a note as JSON, with the common properties normalized
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteProperties {
+    /// `yaml`, `toml`, or absent when the note has no frontmatter
+    pub format: Option<String>,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+    /// Every property as written (TOML dates become ISO strings)
+    pub properties: Map<String, Value>,
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => {
+            Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
+/// String list property that may be written as a list or a single string
+fn string_list(properties: &Map<String, Value>, keys: &[&str], split_words: bool) -> Vec<String> {
+    let mut items = Vec::new();
+
+    for key in keys {
+        match properties.get(*key) {
+            Some(Value::Array(values)) => {
+                items.extend(values.iter().filter_map(|v| v.as_str()).map(str::to_string));
+            }
+            Some(Value::String(s)) if split_words => {
+                items.extend(s.split([',', ' ']).map(str::to_string));
+            }
+            Some(Value::String(s)) => items.extend(s.split(',').map(str::to_string)),
+            _ => {}
+        }
+    }
+
+    items
+        .into_iter()
+        .map(|item| item.trim().trim_start_matches('#').to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parse a note's frontmatter into JSON properties
+pub fn parse_properties(content: &str) -> Result<NoteProperties, String> {
+    let Some(block) = find_frontmatter(content) else {
+        return Ok(NoteProperties {
+            format: None,
+            title: None,
+            tags: Vec::new(),
+            aliases: Vec::new(),
+            properties: Map::new(),
+        });
+    };
+
+    let raw = &content[block.content.clone()];
+    let value = match block.format {
+        FrontmatterFormat::Yaml => serde_yaml::from_str::<Value>(raw)
+            .map_err(|e| format!("Failed to parse YAML frontmatter: {}", e))?,
+        FrontmatterFormat::Toml => toml::from_str::<toml::Table>(raw)
+            .map(|table| toml_to_json(toml::Value::Table(table)))
+            .map_err(|e| format!("Failed to parse TOML frontmatter: {}", e))?,
+    };
+
+    let properties = match value {
+        Value::Object(map) => map,
+        Value::Null => Map::new(),
+        _ => return Err("Frontmatter must be a set of key/value properties".to_string()),
+    };
+
+    Ok(NoteProperties {
+        format: Some(block.format.name().to_string()),
+        title: properties.get("title").and_then(Value::as_str).map(str::to_string),
+        tags: string_list(&properties, &["tags", "tag"], true),
+        aliases: string_list(&properties, &["aliases", "alias"], false),
+        properties,
+    })
+}
+
+/// Replace a note's frontmatter with new properties
+///
+/// Everything after the frontmatter block is kept byte-for-byte. Notes
+/// without frontmatter get a YAML block; an empty property set removes
+/// the block.
+pub fn replace_properties(content: &str, properties: &Map<String, Value>) -> Result<String, String> {
+    let block = find_frontmatter(content);
+    let format = block.as_ref().map_or(FrontmatterFormat::Yaml, |b| b.format);
+    let body = &content[block.as_ref().map_or(0, |b| b.body_start)..];
+
+    if properties.is_empty() {
+        return Ok(body.to_string());
+    }
+
+    let serialized = match format {
+        FrontmatterFormat::Yaml => serde_yaml::to_string(properties)
+            .map_err(|e| format!("Failed to write YAML frontmatter: {}", e))?,
+        FrontmatterFormat::Toml => toml::to_string(properties)
+            .map_err(|e| format!("Failed to write TOML frontmatter: {}", e))?,
+    };
+
+    // Match the note's line endings
+    let line_ending = if content.split('\n').next().is_some_and(|line| line.ends_with('\r')) {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let serialized = serialized.trim_end().replace('\n', line_ending);
+
+    // The closing delimiter is kept as written (YAML allows `...`)
+    let closing = block
+        .as_ref()
+        .and_then(|b| content[b.content.end..b.body_start].lines().next())
+        .unwrap_or(format.delimiter());
+    let separator = if block.is_some() || body.is_empty() { "" } else { line_ending };
+
+    Ok(format!(
+        "{delimiter}{eol}{serialized}{eol}{closing}{eol}{separator}{body}",
+        delimiter = format.delimiter(),
+        eol = line_ending,
+        serialized = serialized,
+        closing = closing,
+        separator = separator,
+        body = body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_frontmatter() {
+        let content = "---\r\ntitle: x\r\n---\r\nBody";
+        let block = find_frontmatter(content).unwrap();
+        assert_eq!(&content[block.content.clone()], "title: x\r\n");
+        assert_eq!(&content[block.body_start..], "Body");
+
+        assert!(find_frontmatter("---\ntitle: unclosed\n").is_none());
+        assert!(find_frontmatter("text\n---\na: 1\n---\n").is_none());
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let yaml = "---\ntitle: Plan\ntags: [project/alpha, '#urgent']\naliases: Road map\ncreated: 2024-01-05\nextra:\n  nested: true\n---\n# Body";
+        let parsed = parse_properties(yaml).unwrap();
+        assert_eq!(parsed.format.as_deref(), Some("yaml"));
+        assert_eq!(parsed.title.as_deref(), Some("Plan"));
+        assert_eq!(parsed.tags, vec!["project/alpha", "urgent"]);
+        assert_eq!(parsed.aliases, vec!["Road map"]);
+        assert_eq!(parsed.properties["created"], Value::from("2024-01-05"));
+        assert_eq!(parsed.properties["extra"]["nested"], Value::Bool(true));
+
+        let toml = "+++\ntitle = \"T\"\ntags = \"a b\"\ndate = 2024-01-05T10:00:00Z\n+++\n";
+        let parsed = parse_properties(toml).unwrap();
+        assert_eq!(parsed.tags, vec!["a", "b"]);
+        assert_eq!(parsed.properties["date"], Value::from("2024-01-05T10:00:00Z"));
+
+        assert!(parse_properties("---\n- just a list\n---\n").is_err());
+        assert!(parse_properties("no frontmatter").unwrap().format.is_none());
+    }
+
+    #[test]
+    fn test_replace_preserves_body() {
+        let body = "\n# Heading\r\n\ttrailing  \r\n";
+        let content = format!("---\r\ntitle: Old\r\ntags: [a]\r\n...\r\n{}", body);
+
+        let mut properties = parse_properties(&content).unwrap().properties;
+        properties.insert("title".to_string(), Value::from("New"));
+        let updated = replace_properties(&content, &properties).unwrap();
+
+        assert_eq!(updated, format!("---\r\ntitle: New\r\ntags:\r\n- a\r\n...\r\n{}", body));
+        assert!(updated.ends_with(body));
+
+        // New block on a note without one, and removal when empty
+        let mut only_title = Map::new();
+        only_title.insert("title".to_string(), Value::from("T"));
+        assert_eq!(replace_properties("Body", &only_title).unwrap(), "---\ntitle: T\n---\n\nBody");
+        assert_eq!(replace_properties(&content, &Map::new()).unwrap(), body);
+    }
+
+    #[test]
+    fn test_render_rows() {
+        let position = BlockPosition { in_block: true, is_start: false, is_end: false };
+        let row = render_frontmatter_line("title: <b>x</b>", FrontmatterFormat::Yaml, position, false);
+        assert!(row.html.contains("<span class=\"property-key\">title</span>"));
+        assert!(row.html.contains("&lt;b&gt;x&lt;/b&gt;"));
+
+        let item = render_frontmatter_line("  - alpha", FrontmatterFormat::Yaml, position, false);
+        assert!(item.html.contains("frontmatter-continuation"));
+    }
+}
@@ -0,0 +1,625 @@
+/**
+ * Code block syntax highlighting
+ *
+ * A small tokenizer driven by bundled per-language grammars: keywords,
+ * types, constants, comments, strings and numbers, plus identifiers called
+ * like functions. Lines are highlighted one at a time; block comments and
+ * multi-line strings carry over to the next line through the highlighter's
+ * state. Lines come out as HTML, with tokens in `<span class="tok-...">`
+ * styled by CSS, or as token lists for other output formats.
+ */
+
+use super::escape_html;
+
+/// Token classes, used as `tok-<name>` CSS classes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Type,
+    Constant,
+    String,
+    Comment,
+    Number,
+    Function,
+}
+
+impl TokenKind {
+    pub const ALL: [TokenKind; 7] = [
+        TokenKind::Keyword,
+        TokenKind::Type,
+        TokenKind::Constant,
+        TokenKind::String,
+        TokenKind::Comment,
+        TokenKind::Number,
+        TokenKind::Function,
+    ];
+
+    pub fn class_name(self) -> &'static str {
+        match self {
+            TokenKind::Keyword => "tok-keyword",
+            TokenKind::Type => "tok-type",
+            TokenKind::Constant => "tok-constant",
+            TokenKind::String => "tok-string",
+            TokenKind::Comment => "tok-comment",
+            TokenKind::Number => "tok-number",
+            TokenKind::Function => "tok-function",
+        }
+    }
+
+    /// Theme variable holding the token's color
+    pub fn theme_variable(self) -> &'static str {
+        match self {
+            TokenKind::Keyword => "token-keyword",
+            TokenKind::Type => "token-type",
+            TokenKind::Constant => "token-constant",
+            TokenKind::String => "token-string",
+            TokenKind::Comment => "token-comment",
+            TokenKind::Number => "token-number",
+            TokenKind::Function => "token-function",
+        }
+    }
+}
+
+/// What the tokenizer is in the middle of at the end of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LexState {
+    #[default]
+    Normal,
+    BlockComment,
+    /// Inside a string closed by the given delimiter
+    String(&'static str),
+}
+
+struct Grammar {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    constants: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// String delimiters, longest first
+    strings: &'static [&'static str],
+    /// Delimiters of strings that may span several lines
+    multiline_strings: &'static [&'static str],
+    /// `'` starts a character literal, not a string (`'a'`, but `'a` is a lifetime)
+    char_literals: bool,
+    /// Capitalized identifiers are types
+    capitalized_types: bool,
+    case_insensitive: bool,
+}
+
+static GRAMMARS: &[Grammar] = &[
+    Grammar {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+            "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+            "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+            "f32", "f64", "bool", "char", "str",
+        ],
+        constants: &["true", "false", "None", "Some", "Ok", "Err"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\""],
+        multiline_strings: &["\""],
+        char_literals: true,
+        capitalized_types: true,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["javascript", "js", "jsx", "typescript", "ts", "tsx", "mjs"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+            "default", "delete", "do", "else", "export", "extends", "finally", "for", "from",
+            "function", "if", "import", "in", "instanceof", "interface", "let", "new", "of", "return",
+            "static", "switch", "this", "throw", "try", "type", "typeof", "var", "void", "while",
+            "yield", "implements", "enum", "readonly", "as",
+        ],
+        types: &["string", "number", "boolean", "any", "unknown", "never", "object", "symbol", "bigint"],
+        constants: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["`", "\"", "'"],
+        multiline_strings: &["`"],
+        char_literals: false,
+        capitalized_types: true,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+            "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+            "yield", "self",
+        ],
+        types: &["int", "float", "str", "bool", "list", "dict", "set", "tuple", "bytes"],
+        constants: &["True", "False", "None"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"\"\"", "'''", "\"", "'"],
+        multiline_strings: &["\"\"\"", "'''"],
+        char_literals: false,
+        capitalized_types: true,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["go", "golang"],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough",
+            "for", "func", "go", "goto", "if", "import", "interface", "map", "package", "range",
+            "return", "select", "struct", "switch", "type", "var",
+        ],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32", "int64",
+            "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64", "uintptr", "any",
+        ],
+        constants: &["true", "false", "nil", "iota"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["`", "\"", "'"],
+        multiline_strings: &["`"],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["java", "kotlin", "kt", "csharp", "cs"],
+        keywords: &[
+            "abstract", "break", "case", "catch", "class", "continue", "default", "do", "else",
+            "enum", "extends", "final", "finally", "for", "if", "implements", "import", "instanceof",
+            "interface", "new", "package", "private", "protected", "public", "return", "static",
+            "super", "switch", "this", "throw", "throws", "try", "var", "val", "fun", "void", "while",
+            "namespace", "using",
+        ],
+        types: &["int", "long", "short", "byte", "float", "double", "boolean", "char", "string"],
+        constants: &["true", "false", "null"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"\"\"", "\"", "'"],
+        multiline_strings: &["\"\"\""],
+        char_literals: false,
+        capitalized_types: true,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["c", "h", "cpp", "c++", "cc", "hpp"],
+        keywords: &[
+            "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern",
+            "for", "goto", "if", "inline", "return", "sizeof", "static", "struct", "switch", "typedef",
+            "union", "volatile", "while", "class", "namespace", "template", "typename", "public",
+            "private", "protected", "virtual", "override", "new", "delete", "using", "try", "catch",
+            "throw", "constexpr", "#include", "#define", "#ifdef", "#ifndef", "#endif", "#if",
+            "#else", "#pragma",
+        ],
+        types: &[
+            "int", "long", "short", "char", "float", "double", "void", "unsigned", "signed", "bool",
+            "size_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t", "int32_t", "int64_t",
+        ],
+        constants: &["true", "false", "NULL", "nullptr"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+        multiline_strings: &[],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["json", "jsonc"],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "null"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\""],
+        multiline_strings: &[],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["bash", "sh", "shell", "zsh", "console"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+            "in", "function", "return", "local", "export", "source", "echo", "exit", "set", "unset",
+        ],
+        types: &[],
+        constants: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"", "'"],
+        multiline_strings: &["\"", "'"],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["sql", "postgres", "mysql", "sqlite"],
+        keywords: &[
+            "select", "from", "where", "and", "or", "not", "insert", "into", "values", "update", "set",
+            "delete", "create", "table", "drop", "alter", "join", "left", "right", "inner", "outer",
+            "on", "group", "by", "order", "having", "limit", "offset", "as", "distinct", "union",
+            "primary", "key", "foreign", "references", "index", "is", "in", "like", "between",
+            "case", "when", "then", "else", "end", "with", "asc", "desc",
+        ],
+        types: &["int", "integer", "text", "varchar", "boolean", "date", "timestamp", "real", "blob"],
+        constants: &["null", "true", "false"],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["'", "\""],
+        multiline_strings: &[],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: true,
+    },
+    Grammar {
+        names: &["yaml", "yml", "toml"],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "null", "yes", "no", "on", "off"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"\"\"", "\"", "'"],
+        multiline_strings: &["\"\"\""],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: false,
+    },
+    Grammar {
+        names: &["css", "scss", "less"],
+        keywords: &["@media", "@import", "@keyframes", "@supports", "!important"],
+        types: &[],
+        constants: &["inherit", "initial", "unset", "none", "auto"],
+        line_comments: &[],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+        multiline_strings: &[],
+        char_literals: false,
+        capitalized_types: false,
+        case_insensitive: false,
+    },
+];
+
+fn find_grammar(lang: &str) -> Option<&'static Grammar> {
+    let lang = lang.to_lowercase();
+    GRAMMARS.iter().find(|g| g.names.contains(&lang.as_str()))
+}
+
+/// Highlights the lines of one code block in order
+#[derive(Clone, Copy)]
+pub struct Highlighter {
+    grammar: Option<&'static Grammar>,
+    state: LexState,
+}
+
+impl std::fmt::Debug for Highlighter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Highlighter")
+            .field("language", &self.grammar.map(|g| g.names[0]))
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl PartialEq for Highlighter {
+    fn eq(&self, other: &Self) -> bool {
+        let same_grammar = match (self.grammar, other.grammar) {
+            (Some(a), Some(b)) => std::ptr::eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        same_grammar && self.state == other.state
+    }
+}
+
+impl Eq for Highlighter {}
+
+impl Highlighter {
+    /// Highlighter for a fence language; unknown languages are only escaped
+    pub fn new(lang: &str) -> Self {
+        Self { grammar: find_grammar(lang), state: LexState::Normal }
+    }
+
+    /// Split the next line of the block into tokens
+    pub fn tokens(&mut self, line: &str) -> Vec<CodeToken> {
+        let mut tokens = Vec::new();
+        match self.grammar {
+            Some(grammar) => self.state = tokenize_line(grammar, line, self.state, &mut tokens),
+            None => push_plain(&mut tokens, line),
+        }
+        tokens
+    }
+
+    /// Move past the next line of the block without keeping its tokens
+    pub fn skip_line(&mut self, line: &str) {
+        if let Some(grammar) = self.grammar {
+            self.state = tokenize_line(grammar, line, self.state, &mut Vec::new());
+        }
+    }
+
+    /// Highlight the next line of the block as HTML
+    pub fn highlight_line(&mut self, line: &str) -> String {
+        self.tokens(line)
+            .iter()
+            .map(|token| match token.kind {
+                Some(kind) => format!("<span class=\"{}\">{}</span>", kind.class_name(), escape_html(&token.text)),
+                None => escape_html(&token.text),
+            })
+            .collect()
+    }
+}
+
+/// A piece of a code line; `kind` is `None` for text that isn't highlighted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeToken {
+    pub kind: Option<TokenKind>,
+    pub text: String,
+}
+
+fn push_token(tokens: &mut Vec<CodeToken>, kind: TokenKind, text: &str) {
+    if !text.is_empty() {
+        tokens.push(CodeToken { kind: Some(kind), text: text.to_string() });
+    }
+}
+
+fn push_plain(tokens: &mut Vec<CodeToken>, text: &str) {
+    match tokens.last_mut() {
+        Some(CodeToken { kind: None, text: previous }) => previous.push_str(text),
+        _ if !text.is_empty() => tokens.push(CodeToken { kind: None, text: text.to_string() }),
+        _ => {}
+    }
+}
+
+/// Byte offset just past the closing `delim` at or after `from`, honouring
+/// backslash escapes
+fn find_string_end(line: &str, from: usize, delim: &str) -> Option<usize> {
+    let mut i = from;
+    while i < line.len() {
+        let rest = &line[i..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            i += 1 + escaped.chars().next().map_or(0, char::len_utf8);
+        } else if rest.starts_with(delim) {
+            return Some(i + delim.len());
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// Length of a character literal starting at `line[i]` (`'a'`, `'\n'`), if it is one
+fn char_literal_len(line: &str, i: usize) -> Option<usize> {
+    let mut chars = line[i + 1..].char_indices();
+    let (_, c) = chars.next()?;
+    if c == '\\' {
+        return find_string_end(line, i + 1, "'").map(|end| end - i);
+    }
+    match chars.next()? {
+        (offset, '\'') => Some(offset + 2),
+        _ => None,
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Classify an identifier using the grammar's word lists
+fn classify_word(grammar: &Grammar, word: &str, called: bool) -> Option<TokenKind> {
+    let contains = |list: &[&str]| {
+        if grammar.case_insensitive {
+            list.iter().any(|w| w.eq_ignore_ascii_case(word))
+        } else {
+            list.contains(&word)
+        }
+    };
+
+    if contains(grammar.keywords) {
+        Some(TokenKind::Keyword)
+    } else if contains(grammar.constants) {
+        Some(TokenKind::Constant)
+    } else if contains(grammar.types) {
+        Some(TokenKind::Type)
+    } else if called {
+        Some(TokenKind::Function)
+    } else if grammar.capitalized_types && word.starts_with(|c: char| c.is_uppercase()) {
+        Some(TokenKind::Type)
+    } else {
+        None
+    }
+}
+
+/// Tokenize one line, appending its tokens to `out`, and return the state
+/// the next line starts in
+fn tokenize_line(grammar: &Grammar, line: &str, mut state: LexState, out: &mut Vec<CodeToken>) -> LexState {
+    let mut i = 0;
+
+    while i < line.len() {
+        // Finish whatever the previous line left open
+        match state {
+            LexState::BlockComment => {
+                let close = grammar.block_comment.map_or("*/", |(_, close)| close);
+                match line[i..].find(close) {
+                    Some(offset) => {
+                        let end = i + offset + close.len();
+                        push_token(out, TokenKind::Comment, &line[i..end]);
+                        i = end;
+                        state = LexState::Normal;
+                    }
+                    None => {
+                        push_token(out, TokenKind::Comment, &line[i..]);
+                        return state;
+                    }
+                }
+                continue;
+            }
+            LexState::String(delim) => {
+                match find_string_end(line, i, delim) {
+                    Some(end) => {
+                        push_token(out, TokenKind::String, &line[i..end]);
+                        i = end;
+                        state = LexState::Normal;
+                    }
+                    None => {
+                        push_token(out, TokenKind::String, &line[i..]);
+                        return state;
+                    }
+                }
+                continue;
+            }
+            LexState::Normal => {}
+        }
+
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap_or(' ');
+
+        if grammar.line_comments.iter().any(|prefix| rest.starts_with(prefix)) {
+            push_token(out, TokenKind::Comment, rest);
+            return state;
+        }
+
+        if let Some((open, close)) = grammar.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+            match line[i + open.len()..].find(close) {
+                Some(offset) => {
+                    let end = i + open.len() + offset + close.len();
+                    push_token(out, TokenKind::Comment, &line[i..end]);
+                    i = end;
+                }
+                None => {
+                    push_token(out, TokenKind::Comment, rest);
+                    return LexState::BlockComment;
+                }
+            }
+            continue;
+        }
+
+        if grammar.char_literals && c == '\'' {
+            // Without a closing quote it is a lifetime or label
+            let len = char_literal_len(line, i).unwrap_or(1);
+            if len > 1 {
+                push_token(out, TokenKind::String, &line[i..i + len]);
+            } else {
+                push_plain(out, "'");
+            }
+            i += len;
+            continue;
+        }
+
+        if let Some(delim) = grammar.strings.iter().find(|d| rest.starts_with(**d)) {
+            match find_string_end(line, i + delim.len(), delim) {
+                Some(end) => {
+                    push_token(out, TokenKind::String, &line[i..end]);
+                    i = end;
+                }
+                None => {
+                    push_token(out, TokenKind::String, rest);
+                    if grammar.multiline_strings.contains(delim) {
+                        return LexState::String(delim);
+                    }
+                    return state;
+                }
+            }
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            push_token(out, TokenKind::Number, &rest[..len]);
+            i += len;
+            continue;
+        }
+
+        if is_ident_start(c) {
+            let len = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let called = rest[len..].trim_start().starts_with('(');
+
+            match classify_word(grammar, word, called) {
+                Some(kind) => push_token(out, kind, word),
+                None => push_plain(out, word),
+            }
+            i += len;
+            continue;
+        }
+
+        // Directives and at-rules such as `#include` and `@media`
+        if matches!(c, '#' | '@' | '!') {
+            let len = 1 + rest[1..].find(|c: char| !is_ident_char(c)).unwrap_or(rest.len() - 1);
+            if len > 1 && classify_word(grammar, &rest[..len], false) == Some(TokenKind::Keyword) {
+                push_token(out, TokenKind::Keyword, &rest[..len]);
+                i += len;
+                continue;
+            }
+        }
+
+        push_plain(out, &rest[..c.len_utf8()]);
+        i += c.len_utf8();
+    }
+
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight_code(lang: &str, code: &str) -> Vec<String> {
+        let mut highlighter = Highlighter::new(lang);
+        code.lines().map(|line| highlighter.highlight_line(line)).collect()
+    }
+
+    #[test]
+    fn test_highlight_rust() {
+        let lines = highlight_code(
+            "rust",
+            "fn parse<'a>(s: &'a str) -> Option<u8> {\n    /* multi\n       line */ let c = 'x'; // done\n    s.len() > 0x1F\n}",
+        );
+
+        assert_eq!(
+            lines[0],
+            "<span class=\"tok-keyword\">fn</span> parse&lt;'a&gt;(s: &amp;'a \
+             <span class=\"tok-type\">str</span>) -&gt; <span class=\"tok-type\">Option</span>&lt;\
+             <span class=\"tok-type\">u8</span>&gt; {"
+        );
+        assert_eq!(lines[1], "    <span class=\"tok-comment\">/* multi</span>");
+        assert_eq!(
+            lines[2],
+            "<span class=\"tok-comment\">       line */</span> <span class=\"tok-keyword\">let</span> c = \
+             <span class=\"tok-string\">'x'</span>; <span class=\"tok-comment\">// done</span>"
+        );
+        assert!(lines[3].contains("<span class=\"tok-function\">len</span>()"));
+        assert!(lines[3].contains("<span class=\"tok-number\">0x1F</span>"));
+    }
+
+    #[test]
+    fn test_highlight_state_crosses_lines() {
+        let mut highlighter = Highlighter::new("python");
+        highlighter.highlight_line("doc = \"\"\"Start");
+        assert_eq!(highlighter.state, LexState::String("\"\"\""));
+        assert_eq!(
+            highlighter.highlight_line("end\"\"\" if True else None"),
+            "<span class=\"tok-string\">end\"\"\"</span> <span class=\"tok-keyword\">if</span> \
+             <span class=\"tok-constant\">True</span> <span class=\"tok-keyword\">else</span> \
+             <span class=\"tok-constant\">None</span>"
+        );
+        assert_eq!(highlighter.state, LexState::Normal);
+
+        // Keywords are case-insensitive in SQL; unknown languages are just escaped
+        assert_eq!(
+            highlight_code("sql", "SELECT 1")[0],
+            "<span class=\"tok-keyword\">SELECT</span> <span class=\"tok-number\">1</span>"
+        );
+        assert_eq!(highlight_code("brainfuck", "<+>")[0], "&lt;+&gt;");
+    }
+}
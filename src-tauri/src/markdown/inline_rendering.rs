@@ -3,123 +3,562 @@
  *
  * This module handles rendering of inline markdown elements such as
  * bold, italic, code, links, etc.
+ *
+ * Lines are parsed once with pulldown-cmark into a small inline AST whose
+ * nodes keep their byte range in the source line. The preview renderer
+ * walks the AST, while the editing renderer uses the ranges to re-emit the
+ * original markers around each element.
  */
 
-use regex::Regex;
 use once_cell::sync::Lazy;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
+use std::ops::Range;
+
+use super::math;
+use super::wiki_links::{WikiLink, WIKI_LINK_RE};
+use super::RenderContext;
 
-// Pre-compiled regex patterns for better performance
-static BOLD_ITALIC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*\*(.+?)\*\*\*").unwrap());
-static BOLD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*(.+?)\*\*").unwrap());
-static BOLD_UNDERSCORE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"__(.+?)__").unwrap());
-static ITALIC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*(.+?)\*").unwrap());
-static ITALIC_UNDERSCORE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"_(.+?)_").unwrap());
-static STRIKE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"~~(.+?)~~").unwrap());
-static CODE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`]+)`").unwrap());
-static IMAGE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[([^\]]*)\]\(([^\)]+)\)").unwrap());
-static LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\(([^\)]+)\)").unwrap());
-
-/// Render inline markdown (bold, italic, code, links, images, etc.)
+// Inline math spans: `$...$`, or `$$...$$` on a single line for display math.
+// As in Pandoc, inline math can't start or end with a space, so prices like
+// `$5 and $10` stay text; a digit after the closing `$` is checked separately.
+static MATH_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$\$[^$\n]+?\$\$|\$(?:[^\s$]|[^\s$][^$\n]*?[^\s$])\$").unwrap());
+static BACKTICKS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`+").unwrap());
+
+/// Prefix that forces the parser to treat the line as paragraph text
 ///
-/// Note: LaTeX rendering is still handled on the frontend via KaTeX
-pub fn render_inline_markdown(text: &str) -> String {
-    let mut result = text.to_string();
-
-    // Bold + Italic (must come before individual bold/italic)
-    result = BOLD_ITALIC_RE
-        .replace_all(&result, "<strong><em>$1</em></strong>")
-        .to_string();
-
-    // Bold
-    result = BOLD_RE
-        .replace_all(&result, "<strong>$1</strong>")
-        .to_string();
-    result = BOLD_UNDERSCORE_RE
-        .replace_all(&result, "<strong>$1</strong>")
-        .to_string();
-
-    // Italic
-    result = ITALIC_RE.replace_all(&result, "<em>$1</em>").to_string();
-    result = ITALIC_UNDERSCORE_RE
-        .replace_all(&result, "<em>$1</em>")
-        .to_string();
-
-    // Strikethrough
-    result = STRIKE_RE.replace_all(&result, "<del>$1</del>").to_string();
-
-    // Inline code
-    result = CODE_RE.replace_all(&result, "<code>$1</code>").to_string();
-
-    // Images (must come before links to avoid conflict)
-    result = IMAGE_RE
-        .replace_all(&result, "<img src=\"$2\" alt=\"$1\" class=\"markdown-image\" />")
-        .to_string();
-
-    // Links
-    result = LINK_RE
-        .replace_all(&result, "<a href=\"$2\">$1</a>")
-        .to_string();
+/// A non-breaking space cannot start any block construct (headings, lists,
+/// indented code, HTML blocks...), but counts as whitespace for the
+/// emphasis flanking rules, so inline parsing is unaffected.
+const PARAGRAPH_GUARD: &str = "\u{a0}";
 
-    result
+/// Character used to hide math and wiki-link content from the markdown parser
+const MATH_MASK: char = 'a';
+
+/// Spans recognized before markdown parsing
+#[derive(Debug, Clone)]
+enum SpecialSpan {
+    Math(Range<usize>),
+    WikiLink(Range<usize>, WikiLink),
 }
 
-/// Render inline markdown with markers visible (for editing mode)
-pub fn render_inline_markdown_with_markers(text: &str) -> String {
-    let mut result = text.to_string();
-
-    // Bold + Italic (must come before individual bold/italic)
-    result = BOLD_ITALIC_RE
-        .replace_all(&result, "<strong><em>***$1***</em></strong>")
-        .to_string();
-
-    // Bold
-    result = BOLD_RE
-        .replace_all(&result, "<strong>**$1**</strong>")
-        .to_string();
-    result = BOLD_UNDERSCORE_RE
-        .replace_all(&result, "<strong>__$1__</strong>")
-        .to_string();
-
-    // Italic
-    result = ITALIC_RE
-        .replace_all(&result, "<em>*$1*</em>")
-        .to_string();
-    result = ITALIC_UNDERSCORE_RE
-        .replace_all(&result, "<em>_$1_</em>")
-        .to_string();
-
-    // Strikethrough
-    result = STRIKE_RE
-        .replace_all(&result, "<del>~~$1~~</del>")
-        .to_string();
-
-    // Inline code
-    result = CODE_RE
-        .replace_all(&result, "<code>`$1`</code>")
-        .to_string();
-
-    // Images (must come before links to avoid conflict)
-    // In editing mode, show syntax but still render the image inline
-    result = IMAGE_RE
-        .replace_all(&result, "<span class=\"image-inline\"><img src=\"$2\" alt=\"$1\" class=\"markdown-image-editing\" /><span class=\"image-syntax\">![$1]($2)</span></span>")
-        .to_string();
-
-    // Links
-    result = LINK_RE
-        .replace_all(&result, "<a href=\"$2\">[$1]($2)</a>")
-        .to_string();
+impl SpecialSpan {
+    fn range(&self) -> &Range<usize> {
+        match self {
+            SpecialSpan::Math(range) | SpecialSpan::WikiLink(range, _) => range,
+        }
+    }
+}
+
+/// Kind of an inline AST node
+#[derive(Debug, Clone, PartialEq)]
+pub enum InlineKind {
+    Text(String),
+    Math,
+    WikiLink(WikiLink),
+    Code(String),
+    Html(String),
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Link { dest: String, title: String },
+    Image { dest: String, title: String },
+    Break { hard: bool },
+}
+
+/// Inline element with its byte range in the source line
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineNode {
+    pub kind: InlineKind,
+    pub range: Range<usize>,
+    pub children: Vec<InlineNode>,
+}
+
+impl InlineNode {
+    fn leaf(kind: InlineKind, range: Range<usize>) -> Self {
+        Self { kind, range, children: Vec::new() }
+    }
+}
+
+/// Parse a single line of markdown into inline nodes
+pub fn parse_inline(source: &str) -> Vec<InlineNode> {
+    let spans = special_spans(source);
+    let masked = mask_spans(source, &spans);
+    let guarded = format!("{}{}", PARAGRAPH_GUARD, masked);
+    let offset = PARAGRAPH_GUARD.len();
+
+    // Top-level nodes, and the inline elements currently open around the parser position
+    let mut nodes: Vec<InlineNode> = Vec::new();
+    let mut open: Vec<InlineNode> = Vec::new();
+
+    for (event, range) in Parser::new_ext(&guarded, Options::ENABLE_STRIKETHROUGH).into_offset_iter() {
+        let range = range.start.saturating_sub(offset).min(source.len())
+            ..range.end.saturating_sub(offset).min(source.len());
+
+        let node = match event {
+            Event::Start(tag) => {
+                if let Some(kind) = inline_kind(tag) {
+                    open.push(InlineNode::leaf(kind, range));
+                }
+                continue;
+            }
+            Event::End(tag) => match (inline_kind(tag), open.pop()) {
+                (Some(_), Some(node)) => node,
+                (_, unclosed) => {
+                    open.extend(unclosed);
+                    continue;
+                }
+            },
+            Event::Text(text) => {
+                let text = text.strip_prefix(PARAGRAPH_GUARD).unwrap_or(&text);
+                InlineNode::leaf(InlineKind::Text(unmask(source, &masked, &range, text)), range)
+            }
+            Event::Code(code) => {
+                InlineNode::leaf(InlineKind::Code(unmask(source, &masked, &range, &code)), range)
+            }
+            Event::Html(html) => InlineNode::leaf(InlineKind::Html(html.to_string()), range),
+            Event::SoftBreak => InlineNode::leaf(InlineKind::Break { hard: false }, range),
+            Event::HardBreak => InlineNode::leaf(InlineKind::Break { hard: true }, range),
+            _ => {
+                // Block-level leftovers (rules, task markers...) are kept as plain text
+                let text = source[range.clone()].to_string();
+                InlineNode::leaf(InlineKind::Text(text), range)
+            }
+        };
+
+        match open.last_mut() {
+            Some(parent) => push_child(&mut parent.children, node),
+            None => push_child(&mut nodes, node),
+        }
+    }
+
+    // Close anything the parser left open
+    while let Some(node) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => push_child(&mut parent.children, node),
+            None => push_child(&mut nodes, node),
+        }
+    }
+
+    if !spans.is_empty() {
+        nodes = split_spans(nodes, source, &spans);
+    }
+    nodes
+}
+
+/// Map a span-level tag to its node kind (block-level tags return None)
+fn inline_kind(tag: Tag) -> Option<InlineKind> {
+    match tag {
+        Tag::Emphasis => Some(InlineKind::Emphasis),
+        Tag::Strong => Some(InlineKind::Strong),
+        Tag::Strikethrough => Some(InlineKind::Strikethrough),
+        Tag::Link(_, dest, title) => Some(InlineKind::Link {
+            dest: dest.to_string(),
+            title: title.to_string(),
+        }),
+        Tag::Image(_, dest, title) => Some(InlineKind::Image {
+            dest: dest.to_string(),
+            title: title.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Append a node, merging adjacent text so it is not split at every special character
+fn push_child(siblings: &mut Vec<InlineNode>, node: InlineNode) {
+    if let InlineKind::Text(text) = &node.kind {
+        if let Some(InlineNode { kind: InlineKind::Text(previous), range, .. }) = siblings.last_mut() {
+            previous.push_str(text);
+            range.end = range.end.max(node.range.end);
+            return;
+        }
+    }
+    siblings.push(node);
+}
+
+/// Byte ranges of the code spans in a line: a run of backticks up to the
+/// next run of the same length
+fn code_span_ranges(source: &str) -> Vec<Range<usize>> {
+    let runs: Vec<Range<usize>> = BACKTICKS_RE
+        .find_iter(source)
+        .map(|m| m.range())
+        // An escaped backtick is literal, so the run starts after it
+        .map(|run| if source[..run.start].ends_with('\\') { run.start + 1..run.end } else { run })
+        .filter(|run| !run.is_empty())
+        .collect();
+
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < runs.len() {
+        let open = &runs[i];
+        match runs[i + 1..].iter().position(|close| close.len() == open.len()) {
+            Some(offset) => {
+                ranges.push(open.start..runs[i + 1 + offset].end);
+                i += offset + 2;
+            }
+            None => i += 1,
+        }
+    }
+    ranges
+}
+
+/// Inline math spans outside code spans
+fn math_ranges(source: &str) -> Vec<Range<usize>> {
+    let code_spans = code_span_ranges(source);
+    let mut ranges = Vec::new();
+    let mut start = 0;
+
+    while let Some(m) = MATH_RE.find_at(source, start) {
+        let in_code = code_spans.iter().any(|code| code.start < m.end() && m.start() < code.end);
+        let before_digit = source[m.end()..].starts_with(|c: char| c.is_ascii_digit());
+        if in_code || before_digit {
+            // The opening `$` may still pair with a later one
+            start = m.start() + 1;
+        } else {
+            ranges.push(m.range());
+            start = m.end();
+        }
+    }
+    ranges
+}
+
+/// Find math and wiki-link spans, dropping any that overlap an earlier one
+fn special_spans(source: &str) -> Vec<SpecialSpan> {
+    let mut spans: Vec<SpecialSpan> = math_ranges(source)
+        .into_iter()
+        .map(SpecialSpan::Math)
+        .chain(WIKI_LINK_RE.captures_iter(source).filter_map(|cap| {
+            let link = WikiLink::parse(&cap[1])?;
+            Some(SpecialSpan::WikiLink(cap.get(0)?.range(), link))
+        }))
+        .collect();
+    spans.sort_by_key(|span| span.range().start);
+
+    let mut end = 0;
+    spans.retain(|span| {
+        let keep = span.range().start >= end;
+        if keep {
+            end = span.range().end;
+        }
+        keep
+    });
+    spans
+}
+
+/// Replace span content with inert characters of the same byte length
+fn mask_spans(source: &str, spans: &[SpecialSpan]) -> String {
+    let mut masked = source.to_string();
+    for span in spans {
+        let (start, end) = match span {
+            SpecialSpan::Math(range) => {
+                // Keep the $ delimiters, hide everything between them
+                let inner = source[range.clone()].trim_matches('$');
+                let start = range.start + source[range.clone()].find(inner).unwrap_or(0);
+                (start, start + inner.len())
+            }
+            // Hide the brackets too, so they can't pair up with other link syntax
+            SpecialSpan::WikiLink(range, _) => (range.start, range.end),
+        };
+        let replacement: String = source[start..end]
+            .chars()
+            .flat_map(|c| std::iter::repeat_n(MATH_MASK, c.len_utf8()))
+            .collect();
+        masked.replace_range(start..end, &replacement);
+    }
+    masked
+}
+
+/// Recover the original text of a parsed event from the masked source
+fn unmask(source: &str, masked: &str, range: &Range<usize>, text: &str) -> String {
+    if source == masked {
+        return text.to_string();
+    }
+    masked
+        .get(range.clone())
+        .and_then(|slice| slice.find(text))
+        .and_then(|position| source.get(range.start + position..range.start + position + text.len()))
+        .unwrap_or(text)
+        .to_string()
+}
+
+/// Split text nodes around the math and wiki-link spans they contain
+fn split_spans(nodes: Vec<InlineNode>, source: &str, spans: &[SpecialSpan]) -> Vec<InlineNode> {
+    let mut result = Vec::with_capacity(nodes.len());
+
+    for mut node in nodes {
+        let is_plain_text = matches!(&node.kind, InlineKind::Text(text) if source.get(node.range.clone()) == Some(text.as_str()));
+
+        if !is_plain_text {
+            node.children = split_spans(node.children, source, spans);
+            result.push(node);
+            continue;
+        }
+
+        let mut cursor = node.range.start;
+        for span in spans.iter().filter(|s| s.range().start >= node.range.start && s.range().end <= node.range.end) {
+            let range = span.range().clone();
+            if range.start > cursor {
+                result.push(InlineNode::leaf(InlineKind::Text(source[cursor..range.start].to_string()), cursor..range.start));
+            }
+            let kind = match span {
+                SpecialSpan::Math(_) => InlineKind::Math,
+                SpecialSpan::WikiLink(_, link) => InlineKind::WikiLink(link.clone()),
+            };
+            cursor = range.end;
+            result.push(InlineNode::leaf(kind, range));
+        }
+        if cursor < node.range.end {
+            result.push(InlineNode::leaf(InlineKind::Text(source[cursor..node.range.end].to_string()), cursor..node.range.end));
+        }
+    }
 
     result
 }
 
+fn escape_text(text: &str) -> String {
+    html_escape::encode_text(text).to_string()
+}
+
+fn escape_attr(text: &str) -> String {
+    html_escape::encode_double_quoted_attribute(text).to_string()
+}
+
+/// Plain text content of a node list (used for image alt text)
+fn plain_text(nodes: &[InlineNode], source: &str) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match &node.kind {
+            InlineKind::Text(t) | InlineKind::Code(t) => text.push_str(t),
+            InlineKind::Math => text.push_str(&source[node.range.clone()]),
+            InlineKind::WikiLink(link) => text.push_str(&link.display_text()),
+            InlineKind::Break { .. } => text.push(' '),
+            _ => text.push_str(&plain_text(&node.children, source)),
+        }
+    }
+    text
+}
+
+/// Render nodes for preview mode (markers hidden)
+fn render_preview(nodes: &[InlineNode], source: &str, context: &RenderContext, out: &mut String) {
+    for node in nodes {
+        match &node.kind {
+            InlineKind::Text(text) => out.push_str(&escape_text(text)),
+            InlineKind::Math => out.push_str(&math_html(&source[node.range.clone()])),
+            InlineKind::WikiLink(link) => {
+                out.push_str(&wiki_link_open_tag(link, context));
+                out.push_str(&escape_text(&link.display_text()));
+                out.push_str("</a>");
+            }
+            InlineKind::Code(code) => {
+                out.push_str("<code>");
+                out.push_str(&escape_text(code));
+                out.push_str("</code>");
+            }
+            InlineKind::Html(html) => match context.sanitize.sanitize_tag(html) {
+                Some(tag) => out.push_str(&tag),
+                None => out.push_str(&escape_text(html)),
+            },
+            InlineKind::Emphasis | InlineKind::Strong | InlineKind::Strikethrough => {
+                let tag = wrapper_tag(&node.kind);
+                out.push_str(&format!("<{}>", tag));
+                render_preview(&node.children, source, context, out);
+                out.push_str(&format!("</{}>", tag));
+            }
+            InlineKind::Link { dest, title } => {
+                out.push_str(&format!("<a{}{}>", href_attr(dest, context), title_attr(title)));
+                render_preview(&node.children, source, context, out);
+                out.push_str("</a>");
+            }
+            InlineKind::Image { dest, title } => {
+                out.push_str(&format!(
+                    "<img{} alt=\"{}\"{} class=\"markdown-image\" />",
+                    src_attr(dest, context),
+                    escape_attr(&plain_text(&node.children, source)),
+                    title_attr(title)
+                ));
+            }
+            InlineKind::Break { hard: true } => out.push_str("<br>"),
+            InlineKind::Break { hard: false } => out.push(' '),
+        }
+    }
+}
+
+/// Render nodes for editing mode, re-emitting the source markers around each element
+///
+/// Every byte of `source[range]` is written exactly once: bytes not covered by a
+/// child node (delimiters, link destinations, escapes) are copied verbatim.
+fn render_with_markers(
+    nodes: &[InlineNode],
+    source: &str,
+    range: Range<usize>,
+    context: &RenderContext,
+    out: &mut String,
+) {
+    let mut cursor = range.start;
+
+    for node in nodes {
+        if node.range.start > cursor {
+            out.push_str(&escape_text(&source[cursor..node.range.start]));
+        }
+        cursor = cursor.max(node.range.start);
+        let node_range = cursor..node.range.end.max(cursor);
+
+        match &node.kind {
+            InlineKind::Text(_) | InlineKind::Math | InlineKind::Html(_) | InlineKind::Break { .. } => {
+                out.push_str(&escape_text(&source[node_range.clone()]));
+            }
+            InlineKind::Code(_) => {
+                out.push_str("<code>");
+                out.push_str(&escape_text(&source[node_range.clone()]));
+                out.push_str("</code>");
+            }
+            InlineKind::WikiLink(link) => {
+                out.push_str(&wiki_link_open_tag(link, context));
+                out.push_str(&escape_text(&source[node_range.clone()]));
+                out.push_str("</a>");
+            }
+            InlineKind::Emphasis | InlineKind::Strong | InlineKind::Strikethrough => {
+                let tag = wrapper_tag(&node.kind);
+                out.push_str(&format!("<{}>", tag));
+                render_with_markers(&node.children, source, node_range.clone(), context, out);
+                out.push_str(&format!("</{}>", tag));
+            }
+            InlineKind::Link { dest, title } => {
+                out.push_str(&format!("<a{}{}>", href_attr(dest, context), title_attr(title)));
+                render_with_markers(&node.children, source, node_range.clone(), context, out);
+                out.push_str("</a>");
+            }
+            InlineKind::Image { dest, title } => {
+                // In editing mode, show syntax but still render the image inline
+                out.push_str(&format!(
+                    "<span class=\"image-inline\"><img{} alt=\"{}\"{} class=\"markdown-image-editing\" />\
+                    <span class=\"image-syntax\">{}</span></span>",
+                    src_attr(dest, context),
+                    escape_attr(&plain_text(&node.children, source)),
+                    title_attr(title),
+                    escape_text(&source[node_range.clone()])
+                ));
+            }
+        }
+
+        cursor = node_range.end;
+    }
+
+    if range.end > cursor {
+        out.push_str(&escape_text(&source[cursor..range.end]));
+    }
+}
+
+fn wrapper_tag(kind: &InlineKind) -> &'static str {
+    match kind {
+        InlineKind::Strong => "strong",
+        InlineKind::Strikethrough => "del",
+        _ => "em",
+    }
+}
+
+/// `href` attribute for a link, omitted when the URL scheme is not allowed
+fn href_attr(dest: &str, context: &RenderContext) -> String {
+    if context.sanitize.is_safe_url(dest, false) {
+        format!(" href=\"{}\"", escape_attr(dest))
+    } else {
+        String::new()
+    }
+}
+
+/// MathML for an inline math span; `$$...$$` within a line is display math
+fn math_html(span: &str) -> String {
+    match span.strip_prefix("$$").and_then(|s| s.strip_suffix("$$")) {
+        Some(latex) => math::math_html(latex, true),
+        None => math::math_html(span.trim_matches('$'), false),
+    }
+}
+
+/// `src` attribute for an image, omitted when the URL scheme is not allowed
+fn src_attr(dest: &str, context: &RenderContext) -> String {
+    if let Some(src) = context.image_sources.get(dest) {
+        return format!(" src=\"{}\"", escape_attr(src));
+    }
+    if context.sanitize.is_safe_url(dest, true) {
+        format!(" src=\"{}\"", escape_attr(dest))
+    } else {
+        String::new()
+    }
+}
+
+/// Opening `<a>` tag for a wiki-link, marked resolved or unresolved
+///
+/// Resolved links carry the workspace-relative path in `href` and the
+/// absolute path in `data-path` so the frontend can open the note.
+fn wiki_link_open_tag(link: &WikiLink, context: &RenderContext) -> String {
+    let heading_attr = link
+        .heading
+        .as_ref()
+        .map(|heading| format!(" data-heading=\"{}\"", escape_attr(heading)))
+        .unwrap_or_default();
+
+    // [[#Heading]] links within the current note
+    if link.target.is_empty() {
+        return format!("<a class=\"wiki-link\"{}>", heading_attr);
+    }
+
+    match context.notes.resolve(&link.target) {
+        Some(relative_path) => {
+            let path_attr = context
+                .notes
+                .absolute_path(relative_path)
+                .map(|path| format!(" data-path=\"{}\"", escape_attr(&path.to_string_lossy())))
+                .unwrap_or_default();
+            format!(
+                "<a class=\"wiki-link\"{}{}{}>",
+                href_attr(relative_path, context),
+                path_attr,
+                heading_attr
+            )
+        }
+        None => format!(
+            "<a class=\"wiki-link unresolved\" data-target=\"{}\"{}>",
+            escape_attr(&link.target),
+            heading_attr
+        ),
+    }
+}
+
+fn title_attr(title: &str) -> String {
+    if title.is_empty() {
+        String::new()
+    } else {
+        format!(" title=\"{}\"", escape_attr(title))
+    }
+}
+
+/// Render inline markdown (bold, italic, code, links, images, math, etc.)
+pub fn render_inline_markdown(text: &str, context: &RenderContext) -> String {
+    let nodes = parse_inline(text);
+    let mut html = String::with_capacity(text.len() + 16);
+    render_preview(&nodes, text, context, &mut html);
+    html
+}
+
+/// Render inline markdown with markers visible (for editing mode)
+pub fn render_inline_markdown_with_markers(text: &str, context: &RenderContext) -> String {
+    let nodes = parse_inline(text);
+    let mut html = String::with_capacity(text.len() + 16);
+    render_with_markers(&nodes, text, 0..text.len(), context, &mut html);
+    html
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::markdown::NoteIndex;
 
     #[test]
     fn test_inline_markdown() {
+        let context = RenderContext::default();
         let text = "This is **bold** and *italic* and `code`";
-        let result = render_inline_markdown(text);
+        let result = render_inline_markdown(text, &context);
         assert!(result.contains("<strong>bold</strong>"));
         assert!(result.contains("<em>italic</em>"));
         assert!(result.contains("<code>code</code>"));
@@ -127,30 +566,146 @@ mod tests {
 
     #[test]
     fn test_inline_markdown_with_markers() {
+        let context = RenderContext::default();
         let text = "This is **bold** and *italic*";
-        let result = render_inline_markdown_with_markers(text);
+        let result = render_inline_markdown_with_markers(text, &context);
         assert!(result.contains("<strong>**bold**</strong>"));
         assert!(result.contains("<em>*italic*</em>"));
     }
 
     #[test]
     fn test_bold_italic_combination() {
+        let context = RenderContext::default();
+        // CommonMark nests strong inside emphasis for ***text***
         let text = "This is ***bold and italic***";
-        let result = render_inline_markdown(text);
-        assert!(result.contains("<strong><em>bold and italic</em></strong>"));
+        let result = render_inline_markdown(text, &context);
+        assert!(result.contains("<em><strong>bold and italic</strong></em>"));
     }
 
     #[test]
     fn test_links() {
+        let context = RenderContext::default();
         let text = "Check out [this link](https://example.com)";
-        let result = render_inline_markdown(text);
+        let result = render_inline_markdown(text, &context);
         assert!(result.contains("<a href=\"https://example.com\">this link</a>"));
     }
 
     #[test]
     fn test_strikethrough() {
+        let context = RenderContext::default();
         let text = "This is ~~strikethrough~~";
-        let result = render_inline_markdown(text);
+        let result = render_inline_markdown(text, &context);
         assert!(result.contains("<del>strikethrough</del>"));
     }
+
+    #[test]
+    fn test_commonmark_edge_cases() {
+        let context = RenderContext::default();
+        // Intraword underscores are not emphasis
+        assert_eq!(render_inline_markdown("call snake_case_name now", &context), "call snake_case_name now");
+        // No emphasis inside code spans
+        assert_eq!(render_inline_markdown("`*not em*`", &context), "<code>*not em*</code>");
+        // Backslash escapes
+        assert_eq!(render_inline_markdown(r"\*literal\*", &context), "*literal*");
+        // Nesting
+        assert_eq!(
+            render_inline_markdown("**bold *and em* [link](u)**", &context),
+            "<strong>bold <em>and em</em> <a href=\"u\">link</a></strong>"
+        );
+        // Block syntax is not interpreted inside a line
+        assert_eq!(render_inline_markdown("1) not a list", &context), "1) not a list");
+    }
+
+    #[test]
+    fn test_markers_preserve_source() {
+        let context = RenderContext::default();
+        let text = r"a **b** \*c\* `d` [e](f) ![g](h.png) ~~i~~ <j> & snake_case";
+        let html = render_inline_markdown_with_markers(text, &context);
+        let stripped = Regex::new(r"<[^>]*>")
+            .unwrap()
+            .replace_all(&html, "");
+        let decoded = html_escape::decode_html_entities(&stripped);
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn test_math_is_not_parsed_as_markdown() {
+        let context = RenderContext::default();
+        let html = render_inline_markdown("$a*b*c$ and *x*", &context);
+        assert!(html.starts_with("<math") && html.contains("<mi>a</mi><mo>∗</mo><mi>b</mi>"));
+        assert!(html.ends_with("</math> and <em>x</em>"));
+        let html = render_inline_markdown("**sum $x_1 + x_2$**", &context);
+        assert!(html.starts_with("<strong>sum <math") && html.ends_with("</math></strong>"));
+
+        let nodes = parse_inline("see $x$");
+        assert_eq!(nodes[1].kind, InlineKind::Math);
+        assert_eq!(nodes[1].range, 4..7);
+
+        // Masked multi-byte characters must not break offset mapping
+        let html = render_inline_markdown("$é$ a `a`", &context);
+        assert!(html.contains("<mi>é</mi>") && html.ends_with("</math> a <code>a</code>"));
+
+        // Dollars in code spans and prices are not math
+        let html = render_inline_markdown("`echo $HOME $PATH` and `$x` then $y$", &context);
+        assert!(html.starts_with("<code>echo $HOME $PATH</code> and <code>$x</code> then <math"), "{}", html);
+        let html = render_inline_markdown("costs $5 and $10, or $ 3 $ and $4$5", &context);
+        assert_eq!(html, "costs $5 and $10, or $ 3 $ and $4$5");
+    }
+
+    #[test]
+    fn test_xss_vectors_are_neutralized() {
+        let context = RenderContext::default();
+        let handler_re = Regex::new(r"<[^>]*\son\w+\s*=").unwrap();
+        let script_url_re = Regex::new(r#"<[^>]*\s(href|src)\s*=\s*"?\s*javascript"#).unwrap();
+        let vectors = [
+            "<script>alert(1)</script>",
+            "<img src=x onerror=alert(1)>",
+            "[x](javascript:alert(1))",
+            "[x](JAVASCRIPT:alert(1))",
+            "![x](javascript:alert(1))",
+            "<a href=\"javascript:alert(1)\">x</a>",
+            "<svg onload=alert(1)>",
+            "<iframe src=\"data:text/html,<script>alert(1)</script>\">",
+            "<style>body{display:none}</style>",
+        ];
+
+        for vector in vectors {
+            for html in [
+                render_inline_markdown(vector, &context),
+                render_inline_markdown_with_markers(vector, &context),
+            ] {
+                let lower = html.to_lowercase();
+                assert!(!lower.contains("<script"), "{} -> {}", vector, html);
+                assert!(!lower.contains("<svg"), "{} -> {}", vector, html);
+                assert!(!lower.contains("<iframe"), "{} -> {}", vector, html);
+                assert!(!lower.contains("<style"), "{} -> {}", vector, html);
+                assert!(!handler_re.is_match(&lower), "{} -> {}", vector, html);
+                assert!(!script_url_re.is_match(&lower), "{} -> {}", vector, html);
+            }
+        }
+
+        assert_eq!(render_inline_markdown("<b>ok</b>", &context), "<b>ok</b>");
+    }
+
+    #[test]
+    fn test_wiki_links() {
+        let mut notes = NoteIndex::new(std::path::Path::new("/vault"));
+        notes.insert("projects/Road Map.md");
+        let context = RenderContext { notes: std::sync::Arc::new(notes), ..Default::default() };
+
+        let result = render_inline_markdown("See [[road map#Q3|the plan]] and [[Missing]]", &context);
+        assert!(result.contains("<a class=\"wiki-link\" href=\"projects/Road Map.md\""));
+        assert!(result.contains("data-heading=\"Q3\">the plan</a>"));
+        assert!(result.contains("<a class=\"wiki-link unresolved\" data-target=\"Missing\">Missing</a>"));
+
+        // Editing mode keeps the brackets, code spans keep the literal text
+        let editing = render_inline_markdown_with_markers("**[[Road Map]]**", &context);
+        assert!(editing.contains("<strong>**<a class=\"wiki-link\""));
+        assert!(editing.contains(">[[Road Map]]</a>**</strong>"));
+        assert_eq!(render_inline_markdown("`[[Road Map]]`", &context), "<code>[[Road Map]]</code>");
+
+        // Brackets masked as text can't combine with link syntax
+        let mixed = render_inline_markdown("[[Road Map]](javascript:alert(1))", &context);
+        assert!(!mixed.contains("javascript:alert(1)\""));
+    }
 }
@@ -0,0 +1,148 @@
+/**
+ * Link extraction
+ *
+ * Finds the outgoing links of a note (markdown links and wiki-links) using
+ * the same inline parser as the renderer, so anything inside code spans or
+ * code/math blocks is ignored exactly as it is on screen.
+ */
+
+use super::inline_rendering::{parse_inline, InlineKind, InlineNode};
+use super::literal_line_mask;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// `[text](destination)`
+    Markdown,
+    /// `![alt](source)`
+    Image,
+    /// `[[Target]]`
+    Wiki,
+}
+
+/// An outgoing link found in a note
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteLink {
+    pub kind: LinkKind,
+    /// Raw destination (markdown) or note name (wiki), without any `#heading`
+    pub target: String,
+    /// Zero-based line index
+    pub line: usize,
+    /// Byte range of the whole link syntax within the line
+    pub range: Range<usize>,
+    /// Byte range of the destination (markdown) or target name (wiki) as
+    /// written, when it can be edited in place
+    pub destination: Option<Range<usize>>,
+    pub line_text: String,
+}
+
+impl NoteLink {
+    /// Source text of the link
+    pub fn text(&self) -> &str {
+        &self.line_text[self.range.clone()]
+    }
+}
+
+/// Collect every link in a note's content
+pub fn extract_links(content: &str) -> Vec<NoteLink> {
+    let lines: Vec<&str> = content.lines().collect();
+    let literal = literal_line_mask(&lines);
+    let mut links = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        // Cheap pre-check: most lines have no link syntax at all
+        if literal[i] || !line.contains('[') {
+            continue;
+        }
+        collect_links(&parse_inline(line), i, line, &mut links);
+    }
+
+    links
+}
+
+fn collect_links(nodes: &[InlineNode], line_index: usize, line: &str, links: &mut Vec<NoteLink>) {
+    for node in nodes {
+        let target = match &node.kind {
+            InlineKind::Link { dest, .. } => Some((LinkKind::Markdown, dest.split('#').next().unwrap_or(""))),
+            InlineKind::Image { dest, .. } => Some((LinkKind::Image, dest.split('#').next().unwrap_or(""))),
+            InlineKind::WikiLink(link) => Some((LinkKind::Wiki, link.target.as_str())),
+            _ => None,
+        };
+
+        match target {
+            Some((kind, target)) if !target.is_empty() => links.push(NoteLink {
+                kind,
+                target: target.to_string(),
+                line: line_index,
+                range: node.range.clone(),
+                destination: destination_range(node, line),
+                line_text: line.to_string(),
+            }),
+            Some(_) => {}
+            None => collect_links(&node.children, line_index, line, links),
+        }
+    }
+}
+
+/// Locate the destination of a link node in the line it was parsed from
+fn destination_range(node: &InlineNode, line: &str) -> Option<Range<usize>> {
+    match &node.kind {
+        InlineKind::Link { dest, .. } | InlineKind::Image { dest, .. } => {
+            // The destination follows the `](` after the link text
+            let text_end = node.children.last().map_or(node.range.start + 1, |child| child.range.end);
+            let after_text = line.get(text_end..node.range.end)?;
+            let mut start = text_end + after_text.find("](")? + 2;
+
+            start += line[start..].len() - line[start..].trim_start().len();
+            if line[start..].starts_with('<') {
+                start += 1;
+            }
+
+            // Destinations with backslash escapes or entities aren't edited in place
+            line[start..node.range.end].starts_with(dest.as_str()).then(|| start..start + dest.len())
+        }
+        InlineKind::WikiLink(link) => {
+            let inner_start = node.range.start + 2;
+            let inner = line.get(inner_start..node.range.end.checked_sub(2)?)?;
+            let offset = inner.find(link.target.as_str())?;
+            Some(inner_start + offset..inner_start + offset + link.target.len())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let content = "See [[Plan#Q3|plan]] and [notes](../notes/Setup.md#install).\n\
+                       `[[not a link]]`\n\
+                       ```\n\
+                       [[also not]]\n\
+                       ```\n\
+                       - **[[Inbox]]** and [top](#local) ![*img*](<a b.png>)";
+        let links = extract_links(content);
+
+        let found: Vec<(LinkKind, &str, usize, &str)> = links
+            .iter()
+            .map(|l| (l.kind, l.target.as_str(), l.line, l.text()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (LinkKind::Wiki, "Plan", 0, "[[Plan#Q3|plan]]"),
+                (LinkKind::Markdown, "../notes/Setup.md", 0, "[notes](../notes/Setup.md#install)"),
+                (LinkKind::Wiki, "Inbox", 5, "[[Inbox]]"),
+                (LinkKind::Image, "a b.png", 5, "![*img*](<a b.png>)"),
+            ]
+        );
+
+        let destinations: Vec<&str> = links
+            .iter()
+            .map(|l| &l.line_text[l.destination.clone().unwrap()])
+            .collect();
+        assert_eq!(destinations, vec!["Plan", "../notes/Setup.md#install", "Inbox", "a b.png"]);
+    }
+}
@@ -0,0 +1,943 @@
+/**
+ * LaTeX to MathML conversion
+ *
+ * Converts the LaTeX math subset used in notes (fractions, roots, scripts,
+ * greek letters and symbols, function names, accents, braces, boxes, colors,
+ * fonts, `\left` and `\right` fences and matrix-like environments) into
+ * presentation MathML, which browsers render natively. Conversion is lenient:
+ * commands it does not know are shown as `<merror>` and the rest of the
+ * formula still renders.
+ * Problems are also reported as `MathError`s naming the offending token.
+ */
+
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use std::fmt;
+use std::ops::Range;
+
+const MATHML_NS: &str = "http://www.w3.org/1998/Math/MathML";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// `\name`, or `\` followed by a single non-letter (`\,`, `\{`)
+    Command(String),
+    Letter(char),
+    Number(String),
+    Symbol(char),
+    Space,
+    Open,
+    Close,
+    Sub,
+    Sup,
+    /// `&` column separator
+    Align,
+    /// `\\` row separator
+    NewRow,
+}
+
+/// Split a formula into tokens, each with the byte range of its source
+fn tokenize(latex: &str) -> (Vec<Token>, Vec<Range<usize>>) {
+    let mut tokens = Vec::new();
+    let mut spans = Vec::new();
+    let mut chars = latex.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some((_, '\\')) => Token::NewRow,
+                Some((_, c)) if c.is_ascii_alphabetic() => {
+                    let mut name = c.to_string();
+                    while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_alphabetic()) {
+                        name.push(c);
+                    }
+                    Token::Command(name)
+                }
+                Some((_, c)) => Token::Command(c.to_string()),
+                None => Token::Symbol('\\'),
+            },
+            '%' => {
+                // Comment to the end of the line
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                continue;
+            }
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '_' => Token::Sub,
+            '^' => Token::Sup,
+            '&' => Token::Align,
+            c if c.is_whitespace() => Token::Space,
+            c if c.is_ascii_digit() || c == '.' && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit()) => {
+                let mut number = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() => Token::Letter(c),
+            c => Token::Symbol(c),
+        };
+        let end = chars.peek().map_or(latex.len(), |&(end, _)| end);
+        tokens.push(token);
+        spans.push(start..end);
+    }
+
+    (tokens, spans)
+}
+
+fn greek_letter(name: &str) -> Option<char> {
+    let c = match name {
+        "alpha" => 'α', "beta" => 'β', "gamma" => 'γ', "delta" => 'δ', "epsilon" => 'ϵ',
+        "varepsilon" => 'ε', "zeta" => 'ζ', "eta" => 'η', "theta" => 'θ', "vartheta" => 'ϑ',
+        "iota" => 'ι', "kappa" => 'κ', "lambda" => 'λ', "mu" => 'μ', "nu" => 'ν', "xi" => 'ξ',
+        "pi" => 'π', "varpi" => 'ϖ', "rho" => 'ρ', "varrho" => 'ϱ', "sigma" => 'σ',
+        "varsigma" => 'ς', "tau" => 'τ', "upsilon" => 'υ', "phi" => 'ϕ', "varphi" => 'φ',
+        "chi" => 'χ', "psi" => 'ψ', "omega" => 'ω', "Gamma" => 'Γ', "Delta" => 'Δ',
+        "Theta" => 'Θ', "Lambda" => 'Λ', "Xi" => 'Ξ', "Pi" => 'Π', "Sigma" => 'Σ',
+        "Upsilon" => 'Υ', "Phi" => 'Φ', "Psi" => 'Ψ', "Omega" => 'Ω',
+        // Letter-like symbols are identifiers too
+        "infty" => '∞', "partial" => '∂', "nabla" => '∇', "emptyset" => '∅', "varnothing" => '∅',
+        "hbar" => 'ℏ', "ell" => 'ℓ', "Re" => 'ℜ', "Im" => 'ℑ', "aleph" => 'ℵ', "wp" => '℘',
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn operator_symbol(name: &str) -> Option<&'static str> {
+    let symbol = match name {
+        "times" => "×", "cdot" => "⋅", "pm" => "±", "mp" => "∓", "div" => "÷", "ast" => "∗",
+        "star" => "⋆", "circ" => "∘", "bullet" => "∙", "oplus" => "⊕", "otimes" => "⊗",
+        "leq" | "le" => "≤", "geq" | "ge" => "≥", "neq" | "ne" => "≠", "approx" => "≈",
+        "equiv" => "≡", "sim" => "∼", "simeq" => "≃", "cong" => "≅", "propto" => "∝",
+        "ll" => "≪", "gg" => "≫", "to" | "rightarrow" => "→", "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔", "Rightarrow" | "implies" => "⇒", "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔", "mapsto" => "↦", "uparrow" => "↑", "downarrow" => "↓",
+        "in" => "∈", "notin" => "∉", "ni" => "∋", "subset" => "⊂", "subseteq" => "⊆",
+        "supset" => "⊃", "supseteq" => "⊇", "cup" => "∪", "cap" => "∩", "setminus" => "∖",
+        "forall" => "∀", "exists" => "∃", "nexists" => "∄", "neg" | "lnot" => "¬",
+        "land" | "wedge" => "∧", "lor" | "vee" => "∨", "cdots" => "⋯", "ldots" | "dots" => "…",
+        "vdots" => "⋮", "ddots" => "⋱", "langle" => "⟨", "rangle" => "⟩", "lfloor" => "⌊",
+        "rfloor" => "⌋", "lceil" => "⌈", "rceil" => "⌉", "|" | "Vert" => "‖", "vert" | "mid" => "|",
+        "{" | "lbrace" => "{", "}" | "rbrace" => "}", "parallel" => "∥", "perp" => "⊥",
+        "angle" => "∠", "prime" => "′", "colon" => ":", "triangle" => "△", "therefore" => "∴",
+        "because" => "∵", "#" => "#", "$" => "$", "%" => "%", "&" => "&amp;", "_" => "_",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+/// Large operators; the bool says whether limits go above and below in display mode
+fn big_operator(name: &str) -> Option<(&'static str, bool)> {
+    let op = match name {
+        "sum" => ("∑", true), "prod" => ("∏", true), "coprod" => ("∐", true),
+        "bigcup" => ("⋃", true), "bigcap" => ("⋂", true), "bigoplus" => ("⨁", true),
+        "int" => ("∫", false), "iint" => ("∬", false), "iiint" => ("∭", false), "oint" => ("∮", false),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Function names set upright; the bool says whether limits go underneath in display mode
+fn function_name(name: &str) -> Option<bool> {
+    match name {
+        "lim" | "max" | "min" | "sup" | "inf" | "limsup" | "liminf" | "det" | "gcd" | "Pr" => Some(true),
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "log" | "ln" | "lg" | "exp" | "deg" | "dim" | "ker" | "arg" | "hom" => Some(false),
+        _ => None,
+    }
+}
+
+/// Accent commands: the mark and whether it goes below
+fn accent(name: &str) -> Option<(&'static str, bool)> {
+    let accent = match name {
+        "hat" | "widehat" => ("^", false), "bar" | "overline" => ("‾", false), "vec" => ("→", false),
+        "dot" => ("˙", false), "ddot" => ("¨", false), "tilde" | "widetilde" => ("~", false),
+        "overrightarrow" => ("→", false), "underline" => ("_", true),
+        _ => return None,
+    };
+    Some(accent)
+}
+
+fn space_width(name: &str) -> Option<&'static str> {
+    let width = match name {
+        "," | "thinspace" => "0.1667em", ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em", " " => "0.25em", "quad" => "1em", "qquad" => "2em",
+        "!" => "-0.1667em",
+        _ => return None,
+    };
+    Some(width)
+}
+
+/// Font commands and the variant they select
+fn font_variant(name: &str) -> Option<Variant> {
+    let variant = match name {
+        "mathrm" | "rm" | "mathup" => Variant::Normal,
+        "mathbf" | "bf" | "boldsymbol" | "bm" => Variant::Bold,
+        "mathit" | "it" => Variant::Italic,
+        "mathbb" => Variant::DoubleStruck,
+        "mathcal" | "mathscr" => Variant::Script,
+        "mathfrak" => Variant::Fraktur,
+        "mathsf" => Variant::SansSerif,
+        "mathtt" => Variant::Monospace,
+        _ => return None,
+    };
+    Some(variant)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Normal,
+    Bold,
+    Italic,
+    DoubleStruck,
+    Script,
+    Fraktur,
+    SansSerif,
+    Monospace,
+}
+
+impl Variant {
+    /// The Mathematical Alphanumeric Symbols character for `c` in this
+    /// variant; MathML Core has no other way to select these fonts
+    fn styled(self, c: char) -> Option<char> {
+        let (upper, lower, digits, exceptions): (u32, u32, Option<u32>, &[(char, char)]) = match self {
+            Variant::Normal => return None,
+            Variant::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE), &[]),
+            Variant::Italic => (0x1D434, 0x1D44E, None, &[('h', 'ℎ')]),
+            Variant::DoubleStruck => (
+                0x1D538,
+                0x1D552,
+                Some(0x1D7D8),
+                &[('C', 'ℂ'), ('H', 'ℍ'), ('N', 'ℕ'), ('P', 'ℙ'), ('Q', 'ℚ'), ('R', 'ℝ'), ('Z', 'ℤ')],
+            ),
+            Variant::Script => (
+                0x1D49C,
+                0x1D4B6,
+                None,
+                &[
+                    ('B', 'ℬ'), ('E', 'ℰ'), ('F', 'ℱ'), ('H', 'ℋ'), ('I', 'ℐ'), ('L', 'ℒ'), ('M', 'ℳ'),
+                    ('R', 'ℛ'), ('e', 'ℯ'), ('g', 'ℊ'), ('o', 'ℴ'),
+                ],
+            ),
+            Variant::Fraktur => (
+                0x1D504,
+                0x1D51E,
+                None,
+                &[('C', 'ℭ'), ('H', 'ℌ'), ('I', 'ℑ'), ('R', 'ℜ'), ('Z', 'ℨ')],
+            ),
+            Variant::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2), &[]),
+            Variant::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6), &[]),
+        };
+
+        if let Some(&(_, styled)) = exceptions.iter().find(|(plain, _)| *plain == c) {
+            return Some(styled);
+        }
+        let code = match c {
+            'A'..='Z' => upper + (c as u32 - 'A' as u32),
+            'a'..='z' => lower + (c as u32 - 'a' as u32),
+            '0'..='9' => digits? + (c as u32 - '0' as u32),
+            _ => return None,
+        };
+        char::from_u32(code)
+    }
+}
+
+/// Groups, arguments and environments nested deeper than this are not
+/// converted, so that a hostile formula can't exhaust the stack
+const MAX_DEPTH: usize = 100;
+
+/// Environments laid out as tables; others still render, but are reported
+const ENVIRONMENTS: &[&str] = &[
+    "matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix", "cases",
+    "aligned", "align", "split", "alignat", "gathered", "gather", "array", "equation",
+];
+
+/// A converted piece of a formula
+struct Atom {
+    mathml: String,
+    /// Scripts go above and below rather than to the side (`\sum` in display mode)
+    limits: bool,
+}
+
+impl Atom {
+    fn new(mathml: String) -> Self {
+        Self { mathml, limits: false }
+    }
+}
+
+fn mo(text: &str) -> String {
+    format!("<mo>{}</mo>", text)
+}
+
+fn fence(text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", text)
+    }
+}
+
+/// Wrap several nodes into one, so they can be a script base or argument
+fn mrow(content: String) -> String {
+    format!("<mrow>{}</mrow>", content)
+}
+
+/// A problem in a formula, pointing at the token that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MathError {
+    pub message: String,
+    /// Source text of the offending token
+    pub token: String,
+    /// Character offset of the token in the formula
+    pub offset: usize,
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} `{}` at character {}", self.message, self.token, self.offset)
+    }
+}
+
+struct Parser<'a> {
+    latex: &'a str,
+    tokens: Vec<Token>,
+    spans: Vec<Range<usize>>,
+    pos: usize,
+    display: bool,
+    variant: Option<Variant>,
+    errors: Vec<MathError>,
+    /// Atoms being parsed that contain the current one
+    depth: usize,
+    /// Set once `MAX_DEPTH` is hit; the rest of the formula is dropped
+    too_deep: bool,
+}
+
+impl Parser<'_> {
+    /// Record a problem with the token at `index`; `token` overrides its source text
+    fn error(&mut self, index: usize, message: &str, token: Option<String>) {
+        // Unclosed groups above a too deep one would each be reported
+        if self.too_deep {
+            return;
+        }
+        let span = self.spans.get(index).cloned().unwrap_or(self.latex.len()..self.latex.len());
+        self.errors.push(MathError {
+            message: message.to_string(),
+            token: token.unwrap_or_else(|| self.latex[span.clone()].to_string()),
+            offset: self.latex[..span.start].chars().count(),
+        });
+    }
+
+    /// Consume the `}` closing the group opened at `open`
+    fn close_group(&mut self, open: usize) {
+        if self.peek() == Some(&Token::Close) {
+            self.pos += 1;
+        } else {
+            self.error(open, "Missing closing brace for", None);
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(&Token::Space) {
+            self.pos += 1;
+        }
+    }
+
+    /// Nodes up to the end of the current group, cell or `\right`
+    fn parse_row(&mut self) -> String {
+        let mut out = String::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None | Some(Token::Close | Token::Align | Token::NewRow) => break,
+                Some(Token::Command(name)) if name == "right" || name == "end" => break,
+                _ => {}
+            }
+            match self.parse_scripted() {
+                Some(atom) => out.push_str(&atom.mathml),
+                None => break,
+            }
+        }
+        out
+    }
+
+    /// A `{...}` group or a single atom, as one node; `owner` is the index
+    /// of the command or script token the argument belongs to
+    fn parse_argument(&mut self, owner: usize) -> String {
+        self.skip_spaces();
+        match self.peek() {
+            Some(Token::Open) => {
+                let open = self.pos;
+                self.pos += 1;
+                let content = self.parse_row();
+                self.close_group(open);
+                mrow(content)
+            }
+            None | Some(Token::Close | Token::Align | Token::NewRow) => {
+                self.error(owner, "Missing argument for", None);
+                mrow(String::new())
+            }
+            // Like TeX, a digit run only gives its first digit: `\frac12`, `x^12`
+            Some(Token::Number(n)) if n.len() > 1 => {
+                let (digit, rest) = n.split_at(1);
+                let digit = digit.to_string();
+                self.tokens[self.pos] = Token::Number(rest.to_string());
+                self.spans[self.pos].start += 1;
+                self.number(digit)
+            }
+            _ => self.parse_atom().map(|atom| atom.mathml).unwrap_or_else(|| mrow(String::new())),
+        }
+    }
+
+    /// Raw text of a `{...}` argument, for `\text` and `\begin`
+    fn parse_text_argument(&mut self, owner: usize) -> String {
+        self.skip_spaces();
+        if self.peek() != Some(&Token::Open) {
+            self.error(owner, "Missing argument for", None);
+            return String::new();
+        }
+        let open = self.pos;
+        self.pos += 1;
+
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            let Some(token) = self.next() else {
+                self.error(open, "Missing closing brace for", None);
+                break;
+            };
+            match token {
+                Token::Open => depth += 1,
+                Token::Close if depth == 0 => break,
+                Token::Close => depth -= 1,
+                Token::Letter(c) | Token::Symbol(c) => text.push(c),
+                Token::Number(n) => text.push_str(&n),
+                Token::Command(name) => text.push_str(&name),
+                Token::Space => text.push(' '),
+                Token::Sub => text.push('_'),
+                Token::Sup => text.push('^'),
+                Token::Align => text.push('&'),
+                Token::NewRow => text.push('\n'),
+            }
+        }
+        text
+    }
+
+    /// `[...]` optional argument, such as the index of `\sqrt[3]{x}`
+    fn parse_optional_argument(&mut self) -> Option<String> {
+        self.skip_spaces();
+        if self.peek() != Some(&Token::Symbol('[')) {
+            return None;
+        }
+        self.pos += 1;
+
+        let mut content = String::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None => break,
+                Some(Token::Symbol(']')) => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => match self.parse_scripted() {
+                    Some(atom) => content.push_str(&atom.mathml),
+                    None => break,
+                },
+            }
+        }
+        Some(mrow(content))
+    }
+
+    /// An atom followed by any `_` and `^` scripts
+    fn parse_scripted(&mut self) -> Option<Atom> {
+        let base = self.parse_atom()?;
+        let mut sub = None;
+        let mut sup = None;
+        let mut primes = String::new();
+
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.pos += 1;
+                    sub = Some(self.parse_argument(self.pos - 1));
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.pos += 1;
+                    sup = Some(self.parse_argument(self.pos - 1));
+                }
+                Some(Token::Sub) => {
+                    self.error(self.pos, "Double subscript", None);
+                    break;
+                }
+                Some(Token::Sup) => {
+                    self.error(self.pos, "Double superscript", None);
+                    break;
+                }
+                // Primes are superscripts too: f'(x), f'^2
+                Some(Token::Symbol('\'')) if sup.is_none() => {
+                    while self.peek() == Some(&Token::Symbol('\'')) {
+                        self.pos += 1;
+                        primes.push('′');
+                    }
+                }
+                _ => break,
+            }
+        }
+        if !primes.is_empty() {
+            sup = Some(match sup {
+                Some(sup) => mrow(format!("{}{}", mo(&primes), sup)),
+                None => mo(&primes),
+            });
+        }
+
+        let (under, over, both) = if base.limits {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+        let mathml = match (sub, sup) {
+            (None, None) => return Some(base),
+            (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, base.mathml, sub),
+            (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, base.mathml, sup),
+            (Some(sub), Some(sup)) => format!("<{0}>{1}{2}{3}</{0}>", both, base.mathml, sub, sup),
+        };
+        Some(Atom::new(mathml))
+    }
+
+    fn identifier(&self, c: char) -> String {
+        let text = encode_text(&c.to_string()).to_string();
+        match self.variant {
+            Some(Variant::Normal) => format!("<mi mathvariant=\"normal\">{}</mi>", text),
+            Some(variant) => format!("<mi>{}</mi>", variant.styled(c).map_or(text, String::from)),
+            None => format!("<mi>{}</mi>", text),
+        }
+    }
+
+    fn number(&self, n: String) -> String {
+        let digits: String = match self.variant {
+            Some(variant) => n.chars().map(|c| variant.styled(c).unwrap_or(c)).collect(),
+            None => n,
+        };
+        format!("<mn>{}</mn>", digits)
+    }
+
+    fn parse_atom(&mut self) -> Option<Atom> {
+        self.skip_spaces();
+        if self.depth >= MAX_DEPTH && self.peek().is_some() {
+            self.error(self.pos, "Too deeply nested", None);
+            self.too_deep = true;
+            self.pos = self.tokens.len();
+            return Some(Atom::new("<merror><mtext>…</mtext></merror>".to_string()));
+        }
+
+        self.depth += 1;
+        let atom = self.parse_nested_atom();
+        self.depth -= 1;
+        atom
+    }
+
+    /// `parse_atom` without the nesting check
+    fn parse_nested_atom(&mut self) -> Option<Atom> {
+        let index = self.pos;
+        let token = self.next()?;
+
+        let mathml = match token {
+            Token::Letter(c) => self.identifier(c),
+            Token::Number(n) => self.number(n),
+            Token::Open => {
+                let content = self.parse_row();
+                self.close_group(index);
+                mrow(content)
+            }
+            Token::Symbol(c) => match c {
+                '-' => mo("−"),
+                '*' => mo("∗"),
+                '<' => mo("&lt;"),
+                '>' => mo("&gt;"),
+                '\'' => mo("′"),
+                c => mo(&encode_text(&c.to_string())),
+            },
+            // Stray separators are shown as they are
+            Token::Close => mo("}"),
+            Token::Align => mo("&amp;"),
+            Token::Sub => mo("_"),
+            Token::Sup => mo("^"),
+            Token::NewRow | Token::Space => String::new(),
+            Token::Command(name) => return Some(self.parse_command(&name, index)),
+        };
+        Some(Atom::new(mathml))
+    }
+
+    /// A command and its arguments; `index` is the command's token
+    fn parse_command(&mut self, name: &str, index: usize) -> Atom {
+        if let Some(c) = greek_letter(name) {
+            // Capital greek letters are upright
+            return Atom::new(if c.is_uppercase() {
+                format!("<mi mathvariant=\"normal\">{}</mi>", c)
+            } else {
+                self.identifier(c)
+            });
+        }
+        if let Some(symbol) = operator_symbol(name) {
+            return Atom::new(mo(symbol));
+        }
+        if let Some((symbol, limits)) = big_operator(name) {
+            return Atom {
+                mathml: format!("<mo largeop=\"true\" movablelimits=\"true\">{}</mo>", symbol),
+                limits: limits && self.display,
+            };
+        }
+        if let Some(limits) = function_name(name) {
+            return Atom { mathml: format!("<mi>{}</mi>", name), limits: limits && self.display };
+        }
+        if let Some(width) = space_width(name) {
+            return Atom::new(format!("<mspace width=\"{}\"/>", width));
+        }
+        if let Some((mark, below)) = accent(name) {
+            let base = self.parse_argument(index);
+            let tag = if below { "munder" } else { "mover" };
+            let attr = if below { "accentunder" } else { "accent" };
+            return Atom::new(format!("<{0} {1}=\"true\">{2}<mo stretchy=\"true\">{3}</mo></{0}>", tag, attr, base, mark));
+        }
+        if let Some(variant) = font_variant(name) {
+            let outer = self.variant.replace(variant);
+            let content = self.parse_argument(index);
+            self.variant = outer;
+            return Atom::new(content);
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.parse_argument(index);
+                let denominator = self.parse_argument(index);
+                Atom::new(format!("<mfrac>{}{}</mfrac>", numerator, denominator))
+            }
+            "binom" => {
+                let n = self.parse_argument(index);
+                let k = self.parse_argument(index);
+                Atom::new(mrow(format!(
+                    "{}<mfrac linethickness=\"0\">{}{}</mfrac>{}",
+                    fence("("),
+                    n,
+                    k,
+                    fence(")")
+                )))
+            }
+            "sqrt" => match self.parse_optional_argument() {
+                Some(degree) => {
+                    let radicand = self.parse_argument(index);
+                    Atom::new(format!("<mroot>{}{}</mroot>", radicand, degree))
+                }
+                None => Atom::new(format!("<msqrt>{}</msqrt>", self.parse_argument(index))),
+            },
+            "text" | "textrm" | "textit" | "textbf" | "mbox" => {
+                let text = self.parse_text_argument(index);
+                Atom::new(format!("<mtext>{}</mtext>", encode_text(&text)))
+            }
+            "operatorname" => {
+                let text = self.parse_text_argument(index);
+                Atom::new(format!("<mi>{}</mi>", encode_text(&text)))
+            }
+            "boxed" | "fbox" => {
+                let content = self.parse_argument(index);
+                Atom::new(format!("<mrow style=\"border: 1px solid; padding: 0.2em\">{}</mrow>", content))
+            }
+            // `\color` applies to the rest of the group, `\textcolor` to its argument
+            "color" | "textcolor" => {
+                let color = self.parse_text_argument(index);
+                if !color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#') {
+                    self.error(index, "Invalid color for", None);
+                }
+                let content = if name == "color" { self.parse_row() } else { self.parse_argument(index) };
+                Atom::new(format!(
+                    "<mstyle mathcolor=\"{}\">{}</mstyle>",
+                    encode_double_quoted_attribute(color.trim()),
+                    content
+                ))
+            }
+            // The label is a script set under or over the brace
+            "underbrace" | "overbrace" => {
+                let base = self.parse_argument(index);
+                let (tag, attr, brace) = if name == "underbrace" {
+                    ("munder", "accentunder", "⏟")
+                } else {
+                    ("mover", "accent", "⏞")
+                };
+                Atom {
+                    mathml: format!("<{0} {1}=\"true\">{2}<mo stretchy=\"true\">{3}</mo></{0}>", tag, attr, base, brace),
+                    limits: true,
+                }
+            }
+            "left" => self.parse_fenced(index),
+            "begin" => self.parse_environment(index),
+            // Size hints for delimiters; the delimiter itself follows
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" => Atom::new(String::new()),
+            "displaystyle" | "textstyle" | "limits" | "nolimits" => Atom::new(String::new()),
+            _ => {
+                self.error(index, "Unknown command", None);
+                Atom::new(format!("<merror><mtext>\\{}</mtext></merror>", encode_text(name)))
+            }
+        }
+    }
+
+    /// Delimiter after `\left`, `\right` or a matrix environment
+    fn parse_delimiter(&mut self) -> String {
+        self.skip_spaces();
+        match self.next() {
+            Some(Token::Symbol('.')) | None => String::new(),
+            Some(Token::Symbol('<')) => "⟨".to_string(),
+            Some(Token::Symbol('>')) => "⟩".to_string(),
+            Some(Token::Symbol(c)) => encode_text(&c.to_string()).to_string(),
+            Some(Token::Command(name)) => operator_symbol(&name).unwrap_or_default().to_string(),
+            Some(_) => String::new(),
+        }
+    }
+
+    /// `\left( ... \right)`
+    fn parse_fenced(&mut self, left: usize) -> Atom {
+        let open = self.parse_delimiter();
+        let content = self.parse_row();
+        let close = if matches!(self.peek(), Some(Token::Command(name)) if name == "right") {
+            self.pos += 1;
+            self.parse_delimiter()
+        } else {
+            self.error(left, "Missing \\right for", None);
+            String::new()
+        };
+        Atom::new(mrow(format!("{}{}{}", fence(&open), content, fence(&close))))
+    }
+
+    /// `\begin{env} ... \end{env}` for matrices, cases and aligned equations
+    fn parse_environment(&mut self, begin: usize) -> Atom {
+        let name = self.parse_text_argument(begin);
+        let env = name.trim_end_matches('*');
+        if !ENVIRONMENTS.contains(&env) {
+            self.error(begin, "Unknown environment", Some(format!("\\begin{{{}}}", name)));
+        }
+        // Column spec of `array`, which only matters for borders
+        if env == "array" {
+            self.parse_text_argument(begin);
+        }
+
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        loop {
+            cells.push(format!("<mtd>{}</mtd>", self.parse_row()));
+            let index = self.pos;
+            match self.next() {
+                Some(Token::Align) => {}
+                Some(Token::NewRow) => rows.push(std::mem::take(&mut cells)),
+                Some(Token::Command(end)) if end == "end" => {
+                    let end = self.parse_text_argument(index);
+                    if end != name {
+                        self.error(index, "Mismatched", Some(format!("\\end{{{}}}", end)));
+                    }
+                    break;
+                }
+                // `\right` or a closing brace before `\end`; leave it to the caller
+                Some(_) => {
+                    self.pos -= 1;
+                    self.error(begin, "Missing \\end for", Some(format!("\\begin{{{}}}", name)));
+                    break;
+                }
+                None => {
+                    self.error(begin, "Missing \\end for", Some(format!("\\begin{{{}}}", name)));
+                    break;
+                }
+            }
+        }
+        // A trailing `\\` leaves an empty last row
+        if !(cells.len() == 1 && cells[0] == "<mtd></mtd>") {
+            rows.push(cells);
+        }
+
+        let column_align = match env {
+            "aligned" | "align" | "split" | "alignat" => " columnalign=\"right left\"",
+            "cases" => " columnalign=\"left left\"",
+            _ => "",
+        };
+        let table = format!(
+            "<mtable{}>{}</mtable>",
+            column_align,
+            rows.iter()
+                .map(|cells| format!("<mtr>{}</mtr>", cells.concat()))
+                .collect::<String>()
+        );
+
+        let (open, close) = match env {
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "Bmatrix" => ("{", "}"),
+            "vmatrix" => ("|", "|"),
+            "Vmatrix" => ("‖", "‖"),
+            "cases" => ("{", ""),
+            _ => ("", ""),
+        };
+        Atom::new(mrow(format!("{}{}{}", fence(open), table, fence(close))))
+    }
+}
+
+/// Convert a LaTeX formula to a `<math>` element, with any problems found
+///
+/// `display` selects block layout, used for `$$` formulas. The LaTeX source
+/// is kept as an annotation, so copying the formula gives back the source.
+pub fn latex_to_mathml(latex: &str, display: bool) -> (String, Vec<MathError>) {
+    let (tokens, spans) = tokenize(latex);
+    let mut parser = Parser {
+        latex,
+        tokens,
+        spans,
+        pos: 0,
+        display,
+        variant: None,
+        errors: Vec::new(),
+        depth: 0,
+        too_deep: false,
+    };
+    let mut content = String::new();
+
+    while parser.pos < parser.tokens.len() {
+        content.push_str(&parser.parse_row());
+        // Separators outside an environment, or a stray `}`: report, skip and carry on
+        match parser.peek() {
+            Some(Token::Close) => parser.error(parser.pos, "Unmatched closing brace", None),
+            Some(Token::Align | Token::Command(_)) => parser.error(parser.pos, "Unexpected", None),
+            _ => {}
+        }
+        parser.pos += 1;
+    }
+
+    let mathml = format!(
+        "<math xmlns=\"{}\" display=\"{}\"><semantics><mrow>{}</mrow>\
+         <annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        MATHML_NS,
+        if display { "block" } else { "inline" },
+        content,
+        encode_text(latex.trim())
+    );
+    (mathml, parser.errors)
+}
+
+/// HTML for a formula; problems are flagged with a `math-error` wrapper
+/// whose tooltip lists them
+pub fn math_html(latex: &str, display: bool) -> String {
+    let (mathml, errors) = latex_to_mathml(latex, display);
+    if errors.is_empty() {
+        return mathml;
+    }
+    let messages: Vec<String> = errors.iter().map(MathError::to_string).collect();
+    format!(
+        "<span class=\"math-error\" title=\"{}\">{}</span>",
+        encode_double_quoted_attribute(&messages.join("\n")),
+        mathml
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The formula's MathML without the `<math>` wrapper and annotation
+    fn body(latex: &str, display: bool) -> String {
+        let (mathml, _) = latex_to_mathml(latex, display);
+        let start = mathml.find("<semantics><mrow>").unwrap() + "<semantics><mrow>".len();
+        let end = mathml.find("</mrow><annotation").unwrap();
+        mathml[start..end].to_string()
+    }
+
+    #[test]
+    fn test_latex_to_mathml() {
+        assert_eq!(
+            body(r"\frac{a+1}{\sqrt[3]{x}}", false),
+            "<mfrac><mrow><mi>a</mi><mo>+</mo><mn>1</mn></mrow>\
+             <mrow><mroot><mrow><mi>x</mi></mrow><mrow><mn>3</mn></mrow></mroot></mrow></mfrac>"
+        );
+        assert_eq!(
+            body(r"x_i^2 - \alpha", false),
+            "<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup><mo>−</mo><mi>α</mi>"
+        );
+
+        // Limits go above and below only in display mode
+        assert!(body(r"\sum_{i=0}^n i", true).starts_with("<munderover><mo largeop"));
+        assert!(body(r"\sum_{i=0}^n i", false).starts_with("<msubsup><mo largeop"));
+
+        // Digit runs give a single digit as an argument
+        assert_eq!(body(r"\frac12", false), "<mfrac><mn>1</mn><mn>2</mn></mfrac>");
+        assert_eq!(body(r"x^12", false), "<msup><mi>x</mi><mn>1</mn></msup><mn>2</mn>");
+
+        assert_eq!(
+            body(r"\underbrace{a}_n", false),
+            "<munder><munder accentunder=\"true\"><mrow><mi>a</mi></mrow><mo stretchy=\"true\">⏟</mo></munder>\
+             <mi>n</mi></munder>"
+        );
+        assert_eq!(
+            body(r"{\color{red} x} y", false),
+            "<mrow><mstyle mathcolor=\"red\"><mi>x</mi></mstyle></mrow><mi>y</mi>"
+        );
+
+        assert_eq!(body(r"\mathbb{R}^n", false), "<msup><mrow><mi>ℝ</mi></mrow><mi>n</mi></msup>");
+        assert_eq!(body(r"\text{if } x", false), "<mtext>if </mtext><mi>x</mi>");
+        assert_eq!(
+            body(r"\left( x \right.", false),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mi>x</mi></mrow>"
+        );
+
+        // Unknown commands are flagged without losing the rest
+        assert_eq!(body(r"\foo + 1", false), "<merror><mtext>\\foo</mtext></merror><mo>+</mo><mn>1</mn>");
+        assert!(latex_to_mathml("a < b", true).0.contains("<annotation encoding=\"application/x-tex\">a &lt; b</annotation>"));
+    }
+
+    #[test]
+    fn test_environments() {
+        assert_eq!(
+            body(r"\begin{pmatrix} 1 & 0 \\ 0 & 1 \end{pmatrix}", false),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mtable>\
+             <mtr><mtd><mn>1</mn></mtd><mtd><mn>0</mn></mtd></mtr>\
+             <mtr><mtd><mn>0</mn></mtd><mtd><mn>1</mn></mtd></mtr>\
+             </mtable><mo fence=\"true\" stretchy=\"true\">)</mo></mrow>"
+        );
+
+        let cases = body(r"f(x) = \begin{cases} 1 & x > 0 \\ 0 & \text{otherwise} \\ \end{cases}", true);
+        assert_eq!(cases.matches("<mtr>").count(), 2);
+        assert!(cases.contains("<mtable columnalign=\"left left\">"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = |latex: &str| latex_to_mathml(latex, false).1.iter().map(MathError::to_string).collect::<Vec<_>>();
+
+        assert!(errors(r"\frac{a}{b} + \left( x \right)").is_empty());
+        assert!(errors(r"f'^2 + \begin{cases} 1 \\ 0 \end{cases}").is_empty());
+        assert!(errors(r"\boxed{x} = \textcolor{#f00}{\frac12} + \underbrace{a + b}_{n}").is_empty());
+        assert_eq!(errors(r"\color{a;b} x"), vec!["Invalid color for `\\color` at character 0"]);
+        assert_eq!(errors(r"α + \foo"), vec!["Unknown command `\\foo` at character 4"]);
+        assert_eq!(errors(r"\frac{a}"), vec!["Missing argument for `\\frac` at character 0"]);
+        assert_eq!(errors(r"x^2^3"), vec!["Double superscript `^` at character 3"]);
+        assert_eq!(errors(r"{a + b"), vec!["Missing closing brace for `{` at character 0"]);
+        assert_eq!(errors(r"a} + b"), vec!["Unmatched closing brace `}` at character 1"]);
+        assert_eq!(errors(r"\left( x"), vec!["Missing \\right for `\\left` at character 0"]);
+        assert_eq!(
+            errors(r"\begin{matrix} 1 \end{pmatrix}"),
+            vec!["Mismatched `\\end{pmatrix}` at character 17"]
+        );
+
+        // Deep nesting is cut off instead of overflowing the stack
+        let nested = "{".repeat(50_000);
+        assert_eq!(errors(&nested), vec!["Too deeply nested `{` at character 100"]);
+        assert_eq!(errors(&format!("x^{}", nested)).len(), 1);
+        assert_eq!(errors(&r"\frac{".repeat(20_000)).len(), 1);
+
+        let html = math_html(r"x^", false);
+        assert!(html.starts_with("<span class=\"math-error\" title=\"Missing argument for `^` at character 1\"><math"));
+        assert!(math_html("x", false).starts_with("<math"));
+    }
+}
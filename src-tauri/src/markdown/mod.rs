@@ -8,21 +8,51 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 mod block_detection;
+mod blocks;
+mod diagram;
+mod document;
+mod frontmatter;
+mod highlight;
 mod inline_rendering;
+mod links;
+mod math;
+mod outline;
+mod quote_rendering;
+mod sanitize;
+mod table_rendering;
+mod tags;
+mod wiki_links;
 
-use block_detection::{is_in_code_block, is_in_math_block};
+use block_detection::{block_state_at, block_states, BlockPosition, BlockScanner, LineBlockState};
+pub use block_detection::ColumnAlignment;
+pub use blocks::{document_blocks, Block, TableBlockRow, TextRun, TextStyle};
+use diagram::{diagram_html, DiagramKind};
+pub use document::{create_document_store, DocumentStoreHandle, DocumentUpdate, LineEdit};
+pub use frontmatter::{parse_properties, replace_properties, NoteProperties};
+pub use highlight::{CodeToken, Highlighter, TokenKind};
+pub use links::{extract_links, LinkKind, NoteLink};
+pub use math::math_html;
+pub use outline::{extract_outline, slugify, update_toc, OutlineHeading};
+pub use sanitize::SanitizePolicy;
+pub use tags::{extract_tags, is_valid_tag, tag_matches, NoteTag};
+pub use wiki_links::NoteIndex;
 use inline_rendering::{render_inline_markdown, render_inline_markdown_with_markers};
+use quote_rendering::render_quote_line;
+use table_rendering::render_table_line;
 
 // Pre-compiled regex patterns for block-level elements
 static LANG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^```(\w+)?").unwrap());
 static HR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(---+|\*\*\*+|___+)$").unwrap());
 static HEADER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.+)$").unwrap());
 static LIST_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*)([-*+]|\d+\.)\s+(.+)$").unwrap());
-static BLOCKQUOTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^>\s*(.+)$").unwrap());
+static TASK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[([ xX])\](?:\s+|$)").unwrap());
+static CALLOUT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[!([A-Za-z][\w-]*)\]([+-])?(?:\s+(.*))?$").unwrap());
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LineRenderResult {
     pub html: String,
     pub is_code_block_boundary: bool,
@@ -36,20 +66,207 @@ pub struct RenderRequest {
     pub is_editing: bool,
 }
 
+/// Settings that affect how every line is rendered
+#[derive(Debug, Clone, Default)]
+pub struct RenderContext {
+    pub sanitize: SanitizePolicy,
+    /// Snapshot of the workspace note names, for resolving wiki-links
+    pub notes: Arc<NoteIndex>,
+    /// Replacement `src` for image destinations as written in the note,
+    /// used by exports that embed or copy images
+    pub image_sources: HashMap<String, String>,
+    /// Bumped whenever the shared context changes, so cached renders know they are stale
+    pub revision: u64,
+}
+
+// Global state wrapped in Arc<Mutex<>> for thread-safe access
+pub type RenderContextHandle = Arc<Mutex<RenderContext>>;
+
+pub fn create_render_context() -> RenderContextHandle {
+    Arc::new(Mutex::new(RenderContext::default()))
+}
+
+/// Checkbox of a task list item (`- [ ] item` / `- [x] item`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskMarker {
+    /// Byte offset of the character between the brackets
+    pub state_offset: usize,
+    pub checked: bool,
+}
+
+/// Parse the task checkbox of a list line, if it has one
+pub fn parse_task_marker(line: &str) -> Option<TaskMarker> {
+    let text = LIST_RE.captures(line)?.get(3)?;
+    let cap = TASK_RE.captures(text.as_str())?;
+    let state = cap.get(1)?;
+
+    Some(TaskMarker {
+        state_offset: text.start() + state.start(),
+        checked: state.as_str() != " ",
+    })
+}
+
+/// Lines inside frontmatter, code or math blocks, where markdown syntax is not interpreted
+pub fn literal_line_mask(lines: &[&str]) -> Vec<bool> {
+    let mut scanner = BlockScanner::for_document(lines);
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let state = scanner.advance(line, lines.get(i + 1).copied());
+            state.frontmatter.is_some() || state.code.in_block || state.math.in_block
+        })
+        .collect()
+}
+
+/// Title of a note: its frontmatter `title`, or else its first heading
+pub fn note_title(content: &str) -> Option<String> {
+    if let Some(title) = parse_properties(content).ok().and_then(|p| p.title) {
+        return Some(title);
+    }
+
+    extract_outline(content)
+        .into_iter()
+        .map(|heading| heading.text)
+        .find(|text| !text.is_empty())
+}
+
 /// Escape HTML entities
 fn escape_html(text: &str) -> String {
     html_escape::encode_text(text).to_string()
 }
 
 /// Render a single markdown line to HTML
-pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
-    let line = &request.line;
-    let line_index = request.line_index;
-    let all_lines = &request.all_lines;
-    let is_editing = request.is_editing;
+pub fn render_markdown_line(request: RenderRequest, context: &RenderContext) -> LineRenderResult {
+    let state = block_state_at(request.line_index, &request.all_lines);
+    render_line(&request.line, &state, request.is_editing, context)
+}
+
+/// Render a batch of lines of one document to HTML
+///
+/// Superseded by document sessions, which only receive edits; kept for older
+/// frontends. A batch covers a single document, so the document of the first
+/// request is scanned once for every line. Large batches render in parallel.
+pub fn render_markdown_batch(requests: Vec<RenderRequest>, context: &RenderContext) -> Vec<LineRenderResult> {
+    use rayon::prelude::*;
+
+    let states = requests.first().map(|request| block_states(&request.all_lines)).unwrap_or_default();
+    let render = |request: &RenderRequest| {
+        // Past the end, `block_state_at` leaves the state of the last line
+        let state = states.get(request.line_index).or(states.last()).cloned().unwrap_or_default();
+        render_line(&request.line, &state, request.is_editing, context)
+    };
+
+    // Use parallel iterator for large batches (>50 lines)
+    if requests.len() > 50 {
+        requests.par_iter().map(render).collect()
+    } else {
+        // For small batches, sequential is faster (no thread overhead)
+        requests.iter().map(render).collect()
+    }
+}
+
+/// Render a whole note for reading outside the editor
+///
+/// Lines render as in the preview, each wrapped in `<div class="editor-line">`,
+/// except that a code block becomes one highlighted `<pre>`, a diagram block
+/// one SVG drawing and a `$$` block one display formula. Frontmatter is left out.
+pub fn render_document(content: &str, context: &RenderContext) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut scanner = BlockScanner::for_document(&lines);
+    let mut html = String::new();
+    let mut code: Option<Highlighter> = None;
+    let mut diagram: Option<(DiagramKind, Vec<&str>)> = None;
+    let mut math: Option<Vec<&str>> = None;
+
+    let math_block = |latex: &[&str]| {
+        format!("<div class=\"math-block\">{}</div>\n", math_html(&latex.join("\n"), true))
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = scanner.advance(line, lines.get(i + 1).copied());
+
+        if state.frontmatter.is_some() {
+            continue;
+        }
+
+        if state.code.is_start {
+            let lang = LANG_RE
+                .captures(line.trim())
+                .and_then(|cap| cap.get(1))
+                .map(|m| m.as_str())
+                .unwrap_or("");
+            if let Some(kind) = DiagramKind::from_lang(lang) {
+                diagram = Some((kind, Vec::new()));
+                continue;
+            }
+            html.push_str(&format!("<pre class=\"code-block\" data-lang=\"{}\"><code>", lang));
+            code = Some(Highlighter::new(lang));
+            continue;
+        }
+        if state.code.is_end {
+            match diagram.take() {
+                Some((kind, source)) => html.push_str(&format!("{}\n", diagram_html(kind, &source.join("\n")))),
+                None => html.push_str("</code></pre>\n"),
+            }
+            code = None;
+            continue;
+        }
+        if let Some((_, source)) = diagram.as_mut() {
+            source.push(line);
+            continue;
+        }
+        if let Some(highlighter) = code.as_mut() {
+            html.push_str(&highlighter.highlight_line(line));
+            html.push('\n');
+            continue;
+        }
+
+        if state.math.is_start {
+            math = Some(Vec::new());
+            continue;
+        }
+        if state.math.is_end {
+            html.push_str(&math_block(&math.take().unwrap_or_default()));
+            continue;
+        }
+        if let Some(latex) = math.as_mut() {
+            latex.push(line);
+            continue;
+        }
+
+        let rendered = render_line(line, &state, false, context);
+        html.push_str(&format!("<div class=\"editor-line\">{}</div>\n", rendered.html));
+    }
+
+    // Blocks left open at the end of the note
+    if code.is_some() {
+        html.push_str("</code></pre>\n");
+    }
+    if let Some((kind, source)) = diagram {
+        html.push_str(&format!("{}\n", diagram_html(kind, &source.join("\n"))));
+    }
+    if let Some(latex) = math {
+        html.push_str(&math_block(&latex));
+    }
+
+    html
+}
+
+/// Render a line whose block context has already been determined
+fn render_line(
+    line: &str,
+    state: &LineBlockState,
+    is_editing: bool,
+    context: &RenderContext,
+) -> LineRenderResult {
+    if let Some((format, position)) = state.frontmatter {
+        return frontmatter::render_frontmatter_line(line, format, position, is_editing);
+    }
 
     // Check if this line is part of a code block
-    let (in_block, is_start, is_end) = is_in_code_block(line_index, all_lines);
+    let BlockPosition { in_block, is_start, is_end } = state.code;
 
     if is_start {
         // Starting ``` line - extract language if present
@@ -84,8 +301,13 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
                 is_code_block_boundary: true,
             };
         } else {
+            // Diagram blocks show the drawing after their source
+            let diagram = match &state.diagram {
+                Some((kind, source)) => diagram_html(*kind, source),
+                None => String::new(),
+            };
             return LineRenderResult {
-                html: "<span class=\"code-block-end\"></span>".to_string(),
+                html: format!("<span class=\"code-block-end\"></span>{}", diagram),
                 is_code_block_boundary: true,
             };
         }
@@ -99,15 +321,23 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
                 is_code_block_boundary: false,
             };
         } else {
+            let html = match state.highlight {
+                Some(mut highlighter) => highlighter.highlight_line(line),
+                None => escape_html(line),
+            };
             return LineRenderResult {
-                html: format!("<code class=\"code-block-line\">{}</code>", escape_html(line)),
+                html: format!("<code class=\"code-block-line\">{}</code>", html),
                 is_code_block_boundary: false,
             };
         }
     }
 
     // Check if this line is part of a math block
-    let (in_math_block, is_math_start, is_math_end) = is_in_math_block(line_index, all_lines);
+    let BlockPosition {
+        in_block: in_math_block,
+        is_start: is_math_start,
+        is_end: is_math_end,
+    } = state.math;
 
     if is_math_start {
         // Starting $$ line
@@ -132,8 +362,13 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
                 is_code_block_boundary: true,
             };
         } else {
+            // The closing line shows the typeset formula of the whole block
+            let formula = state.math_source.as_deref().unwrap_or_default();
             return LineRenderResult {
-                html: "<span class=\"math-block-end\"></span>".to_string(),
+                html: format!(
+                    "<span class=\"math-block-end\"></span><div class=\"math-block\">{}</div>",
+                    math_html(formula, true)
+                ),
                 is_code_block_boundary: true,
             };
         }
@@ -154,6 +389,11 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
         }
     }
 
+    // Table rows (header, delimiter and body)
+    if let Some(table) = &state.table {
+        return render_table_line(line, table, is_editing, context);
+    }
+
     // Empty line
     if line.trim().is_empty() {
         return LineRenderResult {
@@ -182,17 +422,23 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
         let level = cap.get(1).unwrap().as_str().len();
         let hashes = cap.get(1).unwrap().as_str();
         let text = cap.get(2).unwrap().as_str();
+        // Anchor for `#slug` links, e.g. `[see below](#design-decisions)`
+        let id_attr = state
+            .heading_id
+            .as_ref()
+            .map(|id| format!(" id=\"{}\"", html_escape::encode_double_quoted_attribute(id)))
+            .unwrap_or_default();
 
         if is_editing {
-            let processed_text = render_inline_markdown_with_markers(text);
+            let processed_text = render_inline_markdown_with_markers(text, context);
             return LineRenderResult {
-                html: format!("<span class=\"heading h{}\">{} {}</span>", level, hashes, processed_text),
+                html: format!("<span class=\"heading h{}\"{}>{} {}</span>", level, id_attr, hashes, processed_text),
                 is_code_block_boundary: false,
             };
         } else {
-            let processed_text = render_inline_markdown(text);
+            let processed_text = render_inline_markdown(text, context);
             return LineRenderResult {
-                html: format!("<span class=\"heading h{}\">{}</span>", level, processed_text),
+                html: format!("<span class=\"heading h{}\"{}>{}</span>", level, id_attr, processed_text),
                 is_code_block_boundary: false,
             };
         }
@@ -207,8 +453,43 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
         let is_ordered = marker.chars().next().unwrap().is_numeric();
         let marker_class = if is_ordered { "ordered" } else { "unordered" };
 
+        // Task list items render the checkbox in place of the bullet
+        if let Some(cap) = TASK_RE.captures(text) {
+            let checked = &cap[1] != " ";
+            let task_text = &text[cap.get(0).unwrap().end()..];
+
+            if is_editing {
+                let processed_text = render_inline_markdown_with_markers(task_text, context);
+                return LineRenderResult {
+                    html: format!(
+                        "<span class=\"list-item task-item\">{}{} <span class=\"task-marker\">{}</span>{}</span>",
+                        indent_spaces,
+                        marker,
+                        escape_html(cap.get(0).unwrap().as_str()),
+                        processed_text
+                    ),
+                    is_code_block_boundary: false,
+                };
+            } else {
+                let processed_text = render_inline_markdown(task_text, context);
+                return LineRenderResult {
+                    html: format!(
+                        "<span class=\"list-item task-item{}\" style=\"padding-left: {}px\">\
+                        <input type=\"checkbox\" class=\"task-checkbox\"{} />\
+                        <span class=\"task-text\">{}</span>\
+                        </span>",
+                        if checked { " task-done" } else { "" },
+                        indent * 20,
+                        if checked { " checked" } else { "" },
+                        processed_text
+                    ),
+                    is_code_block_boundary: false,
+                };
+            }
+        }
+
         if is_editing {
-            let processed_text = render_inline_markdown_with_markers(text);
+            let processed_text = render_inline_markdown_with_markers(text, context);
             return LineRenderResult {
                 html: format!(
                     "<span class=\"list-item\">{}{} {}</span>",
@@ -217,7 +498,7 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
                 is_code_block_boundary: false,
             };
         } else {
-            let processed_text = render_inline_markdown(text);
+            let processed_text = render_inline_markdown(text, context);
             let display_marker = if is_ordered { marker } else { "•" };
             return LineRenderResult {
                 html: format!(
@@ -235,34 +516,20 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
         }
     }
 
-    // Blockquote
-    if let Some(cap) = BLOCKQUOTE_RE.captures(line) {
-        let text = cap.get(1).unwrap().as_str();
-
-        if is_editing {
-            let processed_text = render_inline_markdown_with_markers(text);
-            return LineRenderResult {
-                html: format!("<span class=\"blockquote\">&gt; {}</span>", processed_text),
-                is_code_block_boundary: false,
-            };
-        } else {
-            let processed_text = render_inline_markdown(text);
-            return LineRenderResult {
-                html: format!("<span class=\"blockquote\">{}</span>", processed_text),
-                is_code_block_boundary: false,
-            };
-        }
+    // Blockquotes and callouts
+    if let Some(quote) = &state.quote {
+        return render_quote_line(line, quote, is_editing, context);
     }
 
     // Regular paragraph - process inline markdown
     if is_editing {
         LineRenderResult {
-            html: render_inline_markdown_with_markers(line),
+            html: render_inline_markdown_with_markers(line, context),
             is_code_block_boundary: false,
         }
     } else {
         LineRenderResult {
-            html: render_inline_markdown(line),
+            html: render_inline_markdown(line, context),
             is_code_block_boundary: false,
         }
     }
@@ -280,7 +547,7 @@ mod tests {
             all_lines: vec!["# Hello World".to_string()],
             is_editing: false,
         };
-        let result = render_markdown_line(request);
+        let result = render_markdown_line(request, &RenderContext::default());
         assert!(result.html.contains("heading h1"));
         assert!(result.html.contains("Hello World"));
     }
@@ -298,7 +565,7 @@ mod tests {
             line_index: 0,
             all_lines: lines.clone(),
             is_editing: false,
-        });
+        }, &RenderContext::default());
         assert!(result0.html.contains("code-block-start"));
 
         let result1 = render_markdown_line(RenderRequest {
@@ -306,8 +573,122 @@ mod tests {
             line_index: 1,
             all_lines: lines.clone(),
             is_editing: false,
-        });
-        assert!(result1.html.contains("code-block-line"));
-        assert!(result1.html.contains("fn main() {}"));
+        }, &RenderContext::default());
+        assert_eq!(
+            result1.html,
+            "<code class=\"code-block-line\"><span class=\"tok-keyword\">fn</span> \
+             <span class=\"tok-function\">main</span>() {}</code>"
+        );
+    }
+
+    #[test]
+    fn test_batch_matches_line_rendering() {
+        let lines: Vec<String> = ["---", "title: x", "---", "# A", "```c", "/* a", "b */", "```", "# A", "$$", "x", "$$"]
+            .map(String::from)
+            .to_vec();
+        let requests: Vec<RenderRequest> = (0..lines.len() + 1)
+            .map(|i| RenderRequest {
+                line: lines.get(i).cloned().unwrap_or_default(),
+                line_index: i,
+                all_lines: lines.clone(),
+                is_editing: i == 3,
+            })
+            .collect();
+
+        let context = RenderContext::default();
+        let expected: Vec<_> = requests.iter().map(|request| render_markdown_line(request.clone(), &context)).collect();
+        assert_eq!(render_markdown_batch(requests, &context), expected);
+    }
+
+    #[test]
+    fn test_table_rendering() {
+        let lines = vec![
+            "| A | B |".to_string(),
+            "| --- | :-: |".to_string(),
+            "| 1 | 2 |".to_string(),
+        ];
+
+        let render = |index: usize, is_editing: bool| {
+            render_markdown_line(RenderRequest {
+                line: lines[index].clone(),
+                line_index: index,
+                all_lines: lines.clone(),
+                is_editing,
+            }, &RenderContext::default())
+        };
+
+        assert!(render(0, false).html.contains("table-header"));
+        assert!(render(1, false).html.contains("table-delimiter"));
+        assert!(render(2, false).html.contains("text-align: center\">2</span>"));
+        assert!(render(2, true).html.contains("table-pipe"));
+    }
+
+    #[test]
+    fn test_task_list_rendering() {
+        let context = RenderContext::default();
+        let render = |line: &str, is_editing: bool| {
+            let state = block_state_at(0, &[line.to_string()]);
+            render_line(line, &state, is_editing, &context).html
+        };
+
+        let open = render("- [ ] buy milk", false);
+        assert!(open.contains("<input type=\"checkbox\" class=\"task-checkbox\" />"));
+        assert!(open.contains("<span class=\"task-text\">buy milk</span>"));
+
+        let done = render("  * [x] **done**", false);
+        assert!(done.contains("task-item task-done"));
+        assert!(done.contains("class=\"task-checkbox\" checked />"));
+        assert!(done.contains("<strong>done</strong>"));
+
+        let editing = render("- [X] item", true);
+        assert!(editing.contains("- <span class=\"task-marker\">[X] </span>item"));
+
+        // Not a task: no space inside the brackets, or link syntax
+        assert!(!render("- [] item", false).contains("checkbox"));
+        assert!(!render("- [x](url)", false).contains("checkbox"));
+    }
+
+    #[test]
+    fn test_parse_task_marker() {
+        assert_eq!(
+            parse_task_marker("  - [ ] todo"),
+            Some(TaskMarker { state_offset: 5, checked: false })
+        );
+        assert_eq!(
+            parse_task_marker("1. [x] done"),
+            Some(TaskMarker { state_offset: 4, checked: true })
+        );
+        assert_eq!(parse_task_marker("[ ] not a list"), None);
+        assert_eq!(parse_task_marker("- plain item"), None);
+    }
+
+    #[test]
+    fn test_note_title() {
+        assert_eq!(note_title("---\ntitle: From Properties\n---\n# Heading"), Some("From Properties".to_string()));
+        assert_eq!(note_title("```\n# comment\n```\nText\n## First heading ##"), Some("First heading".to_string()));
+        assert_eq!(note_title("No headings here"), None);
+    }
+
+    #[test]
+    fn test_render_document() {
+        let context = RenderContext::default();
+        let html = render_document("---\ntitle: x\n---\n# Intro\n```rust\nlet s = \"/*\";\n```\n$$\nx^2\n$$\nSee $a$", &context);
+        let lines: Vec<&str> = html.lines().collect();
+
+        assert_eq!(lines[0], "<div class=\"editor-line\"><span class=\"heading h1\" id=\"intro\">Intro</span></div>");
+        assert_eq!(
+            lines[1],
+            "<pre class=\"code-block\" data-lang=\"rust\"><code><span class=\"tok-keyword\">let</span> s = \
+             <span class=\"tok-string\">\"/*\"</span>;"
+        );
+        assert_eq!(lines[2], "</code></pre>");
+        assert!(lines[3].starts_with("<div class=\"math-block\"><math") && lines[3].contains("<msup><mi>x</mi><mn>2</mn></msup>"));
+        assert!(lines[4].starts_with("<div class=\"editor-line\">See <math") && lines[4].contains("display=\"inline\""));
+        assert_eq!(lines.len(), 5);
+
+        let html = render_document("```mermaid\ngraph TD\n  A --> B\n```\n```dot\ndigraph {\n", &context);
+        let lines: Vec<&str> = html.lines().collect();
+        assert!(lines[0].starts_with("<div class=\"diagram\"><svg") && lines[0].matches("class=\"diagram-node\"").count() == 2);
+        assert_eq!(lines[1], "<div class=\"diagram diagram-error\">Expected `}`</div>");
     }
 }
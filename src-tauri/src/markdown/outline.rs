@@ -0,0 +1,243 @@
+/**
+ * Document outline
+ *
+ * Lists a note's ATX headings with their level, plain text, line and slug
+ * anchor, ignoring anything inside frontmatter, code or math blocks. The
+ * anchors are the ids rendered headings get, assigned by the block scanner.
+ * The outline also generates the `[TOC]` block: a nested list of links to
+ * the headings placed right after a `[TOC]` line, rebuilt on every refresh.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::block_detection::BlockScanner;
+use super::inline_rendering::{parse_inline, InlineKind, InlineNode};
+use super::{literal_line_mask, HEADER_RE};
+
+/// Marker line the generated table of contents follows
+const TOC_MARKER: &str = "[TOC]";
+
+// Everything GitHub drops when turning heading text into an anchor
+static SLUG_STRIP_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^\p{L}\p{N}\p{M}\p{Pc}\- ]").unwrap());
+// A line of a generated table of contents: a list item linking to an anchor
+static TOC_ENTRY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*[-*] \[.*\]\(#[^)]*\)\s*$").unwrap());
+
+/// A heading of a note
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutlineHeading {
+    pub level: u8,
+    /// Heading text with its markdown formatting removed
+    pub text: String,
+    /// Zero-based line index
+    pub line: usize,
+    /// Anchor id, unique within the note
+    pub slug: String,
+}
+
+/// GitHub-style anchor for a heading's text: lowercased, punctuation
+/// removed and spaces turned into dashes
+pub fn slugify(text: &str) -> String {
+    SLUG_STRIP_RE.replace_all(&text.to_lowercase(), "").replace(' ', "-")
+}
+
+/// Hands out unique slugs within a document the way GitHub does: the
+/// second `Setup` heading becomes `setup-1`, the third `setup-2`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SlugGenerator {
+    occurrences: HashMap<String, usize>,
+}
+
+impl SlugGenerator {
+    /// Slug for a heading whose text slugifies to `base`
+    pub fn unique(&mut self, base: String) -> String {
+        let mut slug = base.clone();
+
+        while self.occurrences.contains_key(&slug) {
+            let count = self.occurrences.entry(base.clone()).or_default();
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+
+        self.occurrences.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+/// Text of inline markdown as it reads once rendered
+fn plain_text(nodes: &[InlineNode], source: &str, text: &mut String) {
+    for node in nodes {
+        match &node.kind {
+            InlineKind::Text(t) | InlineKind::Code(t) => text.push_str(t),
+            InlineKind::Math => text.push_str(&source[node.range.clone()]),
+            InlineKind::WikiLink(link) => text.push_str(&link.display_text()),
+            InlineKind::Break { .. } => text.push(' '),
+            InlineKind::Html(_) => {}
+            InlineKind::Emphasis
+            | InlineKind::Strong
+            | InlineKind::Strikethrough
+            | InlineKind::Link { .. }
+            | InlineKind::Image { .. } => plain_text(&node.children, source, text),
+        }
+    }
+}
+
+/// Level and raw text of an ATX heading line
+fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    let cap = HEADER_RE.captures(line)?;
+    let raw = cap.get(2)?.as_str();
+    // A closing run of #s is not part of the text
+    let trimmed = raw.trim_end_matches('#');
+    let raw = if trimmed.is_empty() || trimmed.ends_with(char::is_whitespace) { trimmed } else { raw };
+
+    Some((cap[1].len() as u8, raw.trim()))
+}
+
+/// Level and plain text of an ATX heading line
+pub(super) fn heading_text(line: &str) -> Option<(u8, String)> {
+    let (level, raw) = parse_heading(line)?;
+    let mut text = String::new();
+    plain_text(&parse_inline(raw), raw, &mut text);
+    Some((level, text.trim().to_string()))
+}
+
+/// Every heading of a note, in document order
+pub fn extract_outline(content: &str) -> Vec<OutlineHeading> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut scanner = BlockScanner::for_document(&lines);
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let slug = scanner.advance(line, lines.get(i + 1).copied()).heading_id?;
+            let (level, text) = heading_text(line)?;
+            Some(OutlineHeading { level, text, line: i, slug })
+        })
+        .collect()
+}
+
+/// Markdown list linking to every heading, indented by level
+fn toc_lines(outline: &[OutlineHeading]) -> Vec<String> {
+    let top = outline.iter().map(|h| h.level).min().unwrap_or(1);
+
+    outline
+        .iter()
+        .map(|heading| {
+            let indent = "  ".repeat((heading.level - top) as usize);
+            // Brackets would end the link text early
+            let text = heading.text.replace('[', "\\[").replace(']', "\\]");
+            format!("{}- [{}](#{})", indent, text, heading.slug)
+        })
+        .collect()
+}
+
+/// Refresh every `[TOC]` block of a note, or insert one
+///
+/// The list of links following each `[TOC]` line is replaced with one built
+/// from the current headings. When the note has no `[TOC]` line, a new
+/// block is inserted before `insert_line` (zero-based; past the end
+/// appends it); without `insert_line` that is an error.
+pub fn update_toc(content: &str, insert_line: Option<usize>) -> Result<String, String> {
+    let line_ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<&str> = content.lines().collect();
+    let literal = literal_line_mask(&lines);
+
+    let markers: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| !literal[*i] && line.trim() == TOC_MARKER)
+        .map(|(i, _)| i)
+        .collect();
+
+    let toc = toc_lines(&extract_outline(content));
+    let mut updated: Vec<&str> = Vec::with_capacity(lines.len() + toc.len());
+
+    if markers.is_empty() {
+        let Some(insert_line) = insert_line else {
+            return Err("The document has no [TOC] line".to_string());
+        };
+        let at = insert_line.min(lines.len());
+        let rest = lines.split_off(at);
+
+        updated.extend(lines);
+        updated.push(TOC_MARKER);
+        updated.extend(toc.iter().map(String::as_str));
+        // Keep the list from running into the following paragraph
+        if rest.first().is_some_and(|line| !line.trim().is_empty()) {
+            updated.push("");
+        }
+        updated.extend(rest);
+    } else {
+        let mut i = 0;
+        while i < lines.len() {
+            updated.push(lines[i]);
+            if markers.contains(&i) {
+                updated.extend(toc.iter().map(String::as_str));
+                i += 1;
+                // Drop the previously generated list
+                while i < lines.len() && TOC_ENTRY_RE.is_match(lines[i]) {
+                    i += 1;
+                }
+                continue;
+            }
+            i += 1;
+        }
+    }
+
+    let mut result = updated.join(line_ending);
+    if content.ends_with('\n') || content.is_empty() {
+        result.push_str(line_ending);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_outline() {
+        let content = "---\ntitle: x\n---\n# Design **Decisions**\n\n```\n# not a heading\n```\n\
+                       $$\n# nor this\n$$\n## Setup ##\n## Setup\n### [[Other note|Links]] & `code`\n#hashtag\n## Setup-1\n";
+        let outline = extract_outline(content);
+        let summary: Vec<(u8, &str, usize, &str)> = outline
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.line, h.slug.as_str()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (1, "Design Decisions", 3, "design-decisions"),
+                (2, "Setup", 11, "setup"),
+                (2, "Setup", 12, "setup-1"),
+                (3, "Links & code", 13, "links--code"),
+                (2, "Setup-1", 15, "setup-1-1"),
+            ]
+        );
+        assert_eq!(slugify("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(slugify("Ünïcode Heading_1"), "ünïcode-heading_1");
+    }
+
+    #[test]
+    fn test_update_toc() {
+        let content = "# Title\r\nIntro\r\n## A [draft]\r\n### B\r\n";
+        let inserted = update_toc(content, Some(1)).unwrap();
+        assert_eq!(
+            inserted,
+            "# Title\r\n[TOC]\r\n- [Title](#title)\r\n  - [A \\[draft\\]](#a-draft)\r\n    - [B](#b)\r\n\r\n\
+             Intro\r\n## A [draft]\r\n### B\r\n"
+        );
+
+        // Refreshing replaces the old list and keeps everything else
+        let edited = inserted.replace("### B", "### C");
+        let refreshed = update_toc(&edited, None).unwrap();
+        assert_eq!(refreshed, inserted.replace("B](#b)", "C](#c)").replace("### B", "### C"));
+        assert_eq!(update_toc(&refreshed, None).unwrap(), refreshed);
+
+        assert!(update_toc("# No marker", None).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+/**
+ * Blockquote and callout rendering
+ *
+ * Each quote level of a line becomes a nested span, so `> > text` shows two
+ * bars. Callouts (`> [!NOTE] Title`) replace the level they open with a box
+ * colored by type: the header shows an icon and the title, and the lines
+ * below it belong to the box until the quote ends. The body of a callout
+ * whose type is followed by `-` is hidden until the line is edited.
+ */
+
+use super::block_detection::{parse_callout_header, parse_quote, CalloutFold, QuoteLine};
+use super::inline_rendering::{render_inline_markdown, render_inline_markdown_with_markers};
+use super::{escape_html, LineRenderResult, RenderContext};
+
+/// Callout types with the color they take and their icon; colors match
+/// the theme's `callout-*` variables, and other types render as notes
+const CALLOUT_TYPES: &[(&str, &str, &str)] = &[
+    ("note", "note", "ℹ"),
+    ("info", "note", "ℹ"),
+    ("todo", "note", "☐"),
+    ("abstract", "note", "≡"),
+    ("summary", "note", "≡"),
+    ("tldr", "note", "≡"),
+    ("quote", "note", "❝"),
+    ("cite", "note", "❝"),
+    ("tip", "tip", "★"),
+    ("hint", "tip", "★"),
+    ("success", "tip", "✓"),
+    ("check", "tip", "✓"),
+    ("done", "tip", "✓"),
+    ("important", "important", "!"),
+    ("question", "important", "?"),
+    ("help", "important", "?"),
+    ("faq", "important", "?"),
+    ("example", "important", "☰"),
+    ("warning", "warning", "⚠"),
+    ("attention", "warning", "⚠"),
+    ("caution", "caution", "⊘"),
+    ("failure", "caution", "✗"),
+    ("fail", "caution", "✗"),
+    ("missing", "caution", "✗"),
+    ("danger", "caution", "⚡"),
+    ("error", "caution", "⚡"),
+    ("bug", "caution", "✱"),
+];
+
+/// Color class and icon of a callout type
+pub fn callout_style(kind: &str) -> (&'static str, &'static str) {
+    CALLOUT_TYPES
+        .iter()
+        .find(|(name, _, _)| *name == kind)
+        .map_or(("note", "ℹ"), |&(_, color, icon)| (color, icon))
+}
+
+/// Title of a callout header, defaulting to its type: `[!tip]` is "Tip"
+pub fn callout_title(kind: &str, title: &str) -> String {
+    if !title.is_empty() {
+        return title.to_string();
+    }
+    let mut chars = kind.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect())
+        .unwrap_or_default()
+}
+
+/// Render a line starting with `>`
+pub fn render_quote_line(line: &str, quote: &QuoteLine, is_editing: bool, context: &RenderContext) -> LineRenderResult {
+    let text = parse_quote(line).map_or("", |(_, text)| text);
+
+    let content = if is_editing {
+        let markers = &line[..line.len() - text.len()];
+        format!(
+            "<span class=\"quote-marker\">{}</span>{}",
+            escape_html(markers),
+            render_inline_markdown_with_markers(text, context)
+        )
+    } else if quote.is_callout_start {
+        let (kind, fold, title) = parse_callout_header(text).unwrap_or(("note", CalloutFold::None, ""));
+        let fold_class = match fold {
+            CalloutFold::None => "",
+            CalloutFold::Expanded => " callout-foldable",
+            CalloutFold::Collapsed => " callout-foldable callout-collapsed",
+        };
+        format!(
+            "<span class=\"callout-title{}\"><span class=\"callout-icon\" aria-hidden=\"true\">{}</span>{}</span>",
+            fold_class,
+            callout_style(&kind.to_lowercase()).1,
+            render_inline_markdown(&callout_title(kind, title), context)
+        )
+    } else if text.is_empty() {
+        "<br>".to_string()
+    } else {
+        render_inline_markdown(text, context)
+    };
+
+    // One span per quote level; the level a callout opens is its box
+    let mut html = String::new();
+    for level in 1..=quote.depth {
+        let folded = if level == 1 && quote.folded && !is_editing { " callout-folded" } else { "" };
+        match quote.callouts.iter().find(|callout| callout.depth == level) {
+            Some(callout) => html.push_str(&format!(
+                "<span class=\"callout callout-{}{}\" data-callout=\"{}\">",
+                callout_style(&callout.kind).0,
+                folded,
+                html_escape::encode_double_quoted_attribute(&callout.kind)
+            )),
+            None => html.push_str(&format!("<span class=\"blockquote{}\">", folded)),
+        }
+    }
+    html.push_str(&content);
+    html.push_str(&"</span>".repeat(quote.depth));
+
+    LineRenderResult {
+        html,
+        is_code_block_boundary: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::block_detection::block_state_at;
+
+    fn render(lines: &[&str], index: usize, is_editing: bool) -> String {
+        let lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
+        let quote = block_state_at(index, &lines).quote.unwrap();
+        render_quote_line(&lines[index], &quote, is_editing, &RenderContext::default()).html
+    }
+
+    #[test]
+    fn test_callout_rendering() {
+        let lines = ["> [!WARNING]- Mind **this**", "> body", "> > nested"];
+        assert_eq!(
+            render(&lines, 0, false),
+            "<span class=\"callout callout-warning\" data-callout=\"warning\"><span class=\"callout-title \
+             callout-foldable callout-collapsed\"><span class=\"callout-icon\" aria-hidden=\"true\">⚠</span>\
+             Mind <strong>this</strong></span></span>"
+        );
+        assert_eq!(
+            render(&lines, 1, false),
+            "<span class=\"callout callout-warning callout-folded\" data-callout=\"warning\">body</span>"
+        );
+        assert_eq!(
+            render(&lines, 2, true),
+            "<span class=\"callout callout-warning\" data-callout=\"warning\"><span class=\"blockquote\">\
+             <span class=\"quote-marker\">&gt; &gt; </span>nested</span></span>"
+        );
+
+        assert!(render(&["> [!faq]"], 0, false).contains("?</span>Faq</span>"));
+        assert_eq!(callout_style("custom"), ("note", "ℹ"));
+    }
+
+    #[test]
+    fn test_nested_blockquote() {
+        assert_eq!(
+            render(&[">> deep"], 0, false),
+            "<span class=\"blockquote\"><span class=\"blockquote\">deep</span></span>"
+        );
+    }
+}
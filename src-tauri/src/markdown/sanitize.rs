@@ -0,0 +1,205 @@
+/**
+ * HTML sanitization for rendered markdown
+ *
+ * Markdown allows raw HTML and arbitrary link destinations. Notes can come
+ * from other people, so every user-authored tag and URL is checked against
+ * an allowlist before it reaches the webview. Anything not allowed is shown
+ * as escaped text instead of being interpreted.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static TAG_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^<(/?)([A-Za-z][A-Za-z0-9-]*)((?:\s+[^\s"'>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(/?)>$"#).unwrap()
+});
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"([^\s"'>/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap()
+});
+static SCHEME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([A-Za-z][A-Za-z0-9+.-]*):").unwrap());
+static DRIVE_PATH_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z]:[\\/]").unwrap());
+
+/// Attributes whose values are URLs and must pass the scheme check
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite", "action", "formaction", "poster", "background"];
+
+/// Allowlist of HTML that may appear in rendered notes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SanitizePolicy {
+    /// Disable only for fully trusted workspaces
+    pub enabled: bool,
+    pub allowed_tags: Vec<String>,
+    pub allowed_attributes: Vec<String>,
+    pub allowed_url_schemes: Vec<String>,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        let to_strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+
+        Self {
+            enabled: true,
+            allowed_tags: to_strings(&[
+                "a", "abbr", "b", "br", "code", "del", "details", "em", "i", "img", "ins", "kbd",
+                "mark", "q", "s", "small", "span", "strong", "sub", "summary", "sup", "u",
+            ]),
+            allowed_attributes: to_strings(&["alt", "height", "href", "src", "title", "width"]),
+            allowed_url_schemes: to_strings(&["http", "https", "mailto", "tel", "asset"]),
+        }
+    }
+}
+
+impl SanitizePolicy {
+    fn allows_tag(&self, name: &str) -> bool {
+        self.allowed_tags.iter().any(|t| t.eq_ignore_ascii_case(name))
+    }
+
+    fn allows_attribute(&self, name: &str) -> bool {
+        // Event handlers are never allowed, whatever the configuration says
+        !name.to_ascii_lowercase().starts_with("on")
+            && self.allowed_attributes.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    /// Check a link or image destination
+    ///
+    /// Relative paths, fragments and local file paths are always allowed.
+    /// Image sources may also use `data:image/...` URLs.
+    pub fn is_safe_url(&self, url: &str, is_image: bool) -> bool {
+        if !self.enabled {
+            return true;
+        }
+
+        // Browsers ignore whitespace and control characters inside schemes ("java\tscript:")
+        let normalized: String = url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_control()).collect();
+
+        if DRIVE_PATH_RE.is_match(&normalized) {
+            return true;
+        }
+
+        match SCHEME_RE.captures(&normalized) {
+            Some(cap) => {
+                let scheme = cap[1].to_ascii_lowercase();
+                if is_image && scheme == "data" {
+                    return normalized[5..].to_ascii_lowercase().starts_with("image/");
+                }
+                self.allowed_url_schemes.iter().any(|s| s.eq_ignore_ascii_case(&scheme))
+            }
+            None => true,
+        }
+    }
+
+    /// Sanitize a single raw HTML tag (as produced by the inline parser)
+    ///
+    /// Returns the rebuilt tag with disallowed attributes removed, or `None`
+    /// if the tag itself is not allowed and should be shown as text.
+    pub fn sanitize_tag(&self, raw: &str) -> Option<String> {
+        if !self.enabled {
+            return Some(raw.to_string());
+        }
+
+        let cap = TAG_RE.captures(raw.trim())?;
+        let is_closing = !cap[1].is_empty();
+        let name = cap[2].to_ascii_lowercase();
+
+        if !self.allows_tag(&name) {
+            return None;
+        }
+
+        if is_closing {
+            return Some(format!("</{}>", name));
+        }
+
+        let mut tag = format!("<{}", name);
+        for attr in ATTR_RE.captures_iter(&cap[3]) {
+            let attr_name = attr[1].to_ascii_lowercase();
+            if !self.allows_attribute(&attr_name) {
+                continue;
+            }
+
+            let raw_value = attr.get(2).or(attr.get(3)).or(attr.get(4)).map_or("", |m| m.as_str());
+            let value = html_escape::decode_html_entities(raw_value);
+
+            if URL_ATTRIBUTES.contains(&attr_name.as_str()) && !self.is_safe_url(&value, name == "img") {
+                continue;
+            }
+
+            tag.push_str(&format!(
+                " {}=\"{}\"",
+                attr_name,
+                html_escape::encode_double_quoted_attribute(&value)
+            ));
+        }
+
+        if !cap[4].is_empty() {
+            tag.push_str(" /");
+        }
+        tag.push('>');
+
+        Some(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_allowlist() {
+        let policy = SanitizePolicy::default();
+        assert_eq!(policy.sanitize_tag("<b>"), Some("<b>".to_string()));
+        assert_eq!(policy.sanitize_tag("</B>"), Some("</b>".to_string()));
+        assert_eq!(policy.sanitize_tag("<script>"), None);
+        assert_eq!(policy.sanitize_tag("<iframe src=\"https://x\">"), None);
+        assert_eq!(policy.sanitize_tag("<!-- comment -->"), None);
+    }
+
+    #[test]
+    fn test_event_handlers_are_stripped() {
+        let policy = SanitizePolicy::default();
+        assert_eq!(
+            policy.sanitize_tag("<img src=x onerror=alert(1)>"),
+            Some("<img src=\"x\">".to_string())
+        );
+        assert_eq!(
+            policy.sanitize_tag("<span ONMOUSEOVER='alert(1)' title=\"a\">"),
+            Some("<span title=\"a\">".to_string())
+        );
+
+        let mut permissive = SanitizePolicy::default();
+        permissive.allowed_attributes.push("onclick".to_string());
+        assert_eq!(permissive.sanitize_tag("<b onclick=\"x()\">"), Some("<b>".to_string()));
+    }
+
+    #[test]
+    fn test_url_schemes() {
+        let policy = SanitizePolicy::default();
+        assert!(policy.is_safe_url("https://example.com", false));
+        assert!(policy.is_safe_url("notes/other.md#setup", false));
+        assert!(policy.is_safe_url("C:\\Users\\me\\image.png", true));
+        assert!(policy.is_safe_url("data:image/png;base64,AAAA", true));
+
+        assert!(!policy.is_safe_url("javascript:alert(1)", false));
+        assert!(!policy.is_safe_url("JaVaScRiPt:alert(1)", false));
+        assert!(!policy.is_safe_url("java\tscript:alert(1)", false));
+        assert!(!policy.is_safe_url(" vbscript:msgbox(1)", false));
+        assert!(!policy.is_safe_url("data:text/html,<script>alert(1)</script>", false));
+        assert!(!policy.is_safe_url("data:image/png;base64,AAAA", false));
+    }
+
+    #[test]
+    fn test_entity_encoded_scheme_in_attribute() {
+        let policy = SanitizePolicy::default();
+        assert_eq!(
+            policy.sanitize_tag("<a href=\"&#106;avascript:alert(1)\">"),
+            Some("<a>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_disabled_policy_passes_through() {
+        let policy = SanitizePolicy { enabled: false, ..Default::default() };
+        assert_eq!(policy.sanitize_tag("<script>"), Some("<script>".to_string()));
+        assert!(policy.is_safe_url("javascript:alert(1)", false));
+    }
+}
@@ -0,0 +1,131 @@
+/**
+ * GFM table rendering
+ *
+ * Tables are rendered one line at a time like everything else. Each row
+ * becomes a grid with one track per column, so cells line up across rows
+ * without needing a single <table> element spanning several editor lines.
+ */
+
+use super::block_detection::{table_cell_ranges, ColumnAlignment, TableLine, TableRow};
+use super::inline_rendering::{render_inline_markdown, render_inline_markdown_with_markers};
+use super::{escape_html, LineRenderResult, RenderContext};
+
+fn alignment_style(alignment: ColumnAlignment) -> &'static str {
+    match alignment {
+        ColumnAlignment::Left => " style=\"text-align: left\"",
+        ColumnAlignment::Center => " style=\"text-align: center\"",
+        ColumnAlignment::Right => " style=\"text-align: right\"",
+        ColumnAlignment::None => "",
+    }
+}
+
+/// Render a line that belongs to a table
+pub fn render_table_line(
+    line: &str,
+    table: &TableLine,
+    is_editing: bool,
+    context: &RenderContext,
+) -> LineRenderResult {
+    let html = if is_editing {
+        render_table_line_editing(line, table, context)
+    } else {
+        render_table_line_preview(line, table, context)
+    };
+
+    LineRenderResult {
+        html,
+        is_code_block_boundary: false,
+    }
+}
+
+fn render_table_line_preview(line: &str, table: &TableLine, context: &RenderContext) -> String {
+    if table.row == TableRow::Delimiter {
+        return "<span class=\"table-delimiter\"></span>".to_string();
+    }
+
+    let (row_class, cell_class) = match table.row {
+        TableRow::Header => ("table-row table-header", "table-cell table-header-cell"),
+        _ => ("table-row", "table-cell"),
+    };
+
+    let cells = table_cell_ranges(line);
+    let mut html = format!(
+        "<span class=\"{}\" style=\"grid-template-columns: repeat({}, minmax(0, 1fr))\">",
+        row_class,
+        table.alignments.len()
+    );
+
+    // Missing cells are padded and extra cells dropped, as in GFM
+    for (i, alignment) in table.alignments.iter().enumerate() {
+        let content = cells
+            .get(i)
+            .map(|range| render_inline_markdown(line[range.clone()].trim(), context))
+            .unwrap_or_default();
+        html.push_str(&format!(
+            "<span class=\"{}\"{}>{}</span>",
+            cell_class,
+            alignment_style(*alignment),
+            content
+        ));
+    }
+
+    html.push_str("</span>");
+    html
+}
+
+/// Editing mode keeps the pipes visible and renders each cell with its markers
+fn render_table_line_editing(line: &str, table: &TableLine, context: &RenderContext) -> String {
+    if table.row == TableRow::Delimiter {
+        return format!("<span class=\"table-delimiter-editing\">{}</span>", escape_html(line));
+    }
+
+    let mut html = String::from("<span class=\"table-row-editing\">");
+    let mut cursor = 0;
+
+    for range in table_cell_ranges(line) {
+        html.push_str(&render_pipes(&line[cursor..range.start]));
+        html.push_str(&render_inline_markdown_with_markers(&line[range.clone()], context));
+        cursor = range.end;
+    }
+    html.push_str(&render_pipes(&line[cursor..]));
+
+    html.push_str("</span>");
+    html
+}
+
+fn render_pipes(separator: &str) -> String {
+    escape_html(separator).replace('|', "<span class=\"table-pipe\">|</span>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(row: TableRow) -> TableLine {
+        TableLine {
+            row,
+            alignments: vec![ColumnAlignment::Left, ColumnAlignment::Right],
+        }
+    }
+
+    #[test]
+    fn test_preview_cells_are_aligned() {
+        let context = RenderContext::default();
+        let result = render_table_line("| **a** | 1 | extra |", &table(TableRow::Body), false, &context);
+        assert!(result.html.contains("repeat(2, minmax(0, 1fr))"));
+        assert!(result.html.contains("<span class=\"table-cell\" style=\"text-align: left\"><strong>a</strong></span>"));
+        assert!(result.html.contains("<span class=\"table-cell\" style=\"text-align: right\">1</span>"));
+        assert!(!result.html.contains("extra"));
+    }
+
+    #[test]
+    fn test_editing_keeps_pipes() {
+        let context = RenderContext::default();
+        let result = render_table_line("| *a* | b |", &table(TableRow::Header), true, &context);
+        assert_eq!(
+            result.html,
+            "<span class=\"table-row-editing\"><span class=\"table-pipe\">|</span> <em>*a*</em> \
+             <span class=\"table-pipe\">|</span> b <span class=\"table-pipe\">|</span></span>"
+        );
+    }
+}
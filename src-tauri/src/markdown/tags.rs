@@ -0,0 +1,234 @@
+/**
+ * Tag extraction
+ *
+ * Notes are tagged inline with `#tag` or through the frontmatter `tags`
+ * property. Tags are hierarchical: `#project/alpha` is nested under
+ * `project`. Inline tags are only looked for in plain text, so code,
+ * link destinations and heading markers are never mistaken for tags.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::ops::Range;
+
+use super::frontmatter::find_frontmatter;
+use super::inline_rendering::{parse_inline, InlineKind, InlineNode};
+use super::literal_line_mask;
+
+static INLINE_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"#(\w[\w/-]*)").unwrap());
+// Values of the frontmatter tags property, with or without a leading #
+static PROPERTY_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"#?(\w[\w/-]*)").unwrap());
+static TAGS_PROPERTY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^tags?\s*[:=]").unwrap());
+
+/// A tag found in a note
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteTag {
+    /// Tag as written, without the `#`
+    pub name: String,
+    /// Zero-based line index
+    pub line: usize,
+    /// Byte range of the name within the line
+    pub range: Range<usize>,
+    pub line_text: String,
+}
+
+/// Whether `name` can be used as a tag: word characters, `-` and `/`
+/// separators, and not purely numeric (`#123` is an issue number)
+pub fn is_valid_tag(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('/').all(|part| !part.is_empty())
+        && name.chars().all(|c| c.is_alphanumeric() || "_-/".contains(c))
+        && name.chars().any(|c| !c.is_ascii_digit() && c != '/')
+}
+
+/// Whether a tag is `tag` itself or nested under it, ignoring case
+pub fn tag_matches(name: &str, tag: &str) -> bool {
+    let name = name.to_lowercase();
+    let tag = tag.to_lowercase();
+    name == tag || name.strip_prefix(&tag).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Collect the inline and frontmatter tags of a note
+pub fn extract_tags(content: &str) -> Vec<NoteTag> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut tags = frontmatter_tags(content, &lines);
+    let literal = literal_line_mask(&lines);
+
+    for (i, line) in lines.iter().enumerate() {
+        if literal[i] || !line.contains('#') {
+            continue;
+        }
+        collect_inline_tags(&parse_inline(line), i, line, &mut tags);
+    }
+
+    tags
+}
+
+fn collect_inline_tags(nodes: &[InlineNode], line_index: usize, line: &str, tags: &mut Vec<NoteTag>) {
+    for node in nodes {
+        match &node.kind {
+            // Escaped text no longer matches its source, so only plain text is scanned
+            InlineKind::Text(text) if line.get(node.range.clone()) == Some(text.as_str()) => {
+                for cap in INLINE_TAG_RE.captures_iter(text) {
+                    let hash = node.range.start + cap.get(0).unwrap().start();
+                    // Emphasis markers don't count: `**#tag**` is a tag
+                    let starts_word = line[..hash]
+                        .chars()
+                        .next_back()
+                        .is_none_or(|c| c.is_whitespace() || "([{,;:!?\"'*_~".contains(c));
+                    let name = cap[1].trim_end_matches('/');
+
+                    if starts_word && is_valid_tag(name) {
+                        tags.push(NoteTag {
+                            name: name.to_string(),
+                            line: line_index,
+                            range: hash + 1..hash + 1 + name.len(),
+                            line_text: line.to_string(),
+                        });
+                    }
+                }
+            }
+            InlineKind::Emphasis | InlineKind::Strong | InlineKind::Strikethrough => {
+                collect_inline_tags(&node.children, line_index, line, tags);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tags listed in the frontmatter `tags` (or `tag`) property
+///
+/// The property is scanned as text rather than parsed so that every tag
+/// keeps its position, which lets a rename edit it in place.
+fn frontmatter_tags(content: &str, lines: &[&str]) -> Vec<NoteTag> {
+    let Some(block) = find_frontmatter(content) else {
+        return Vec::new();
+    };
+
+    let property_lines = content[block.content].lines().count();
+    let mut in_tags = false;
+    let mut tags = Vec::new();
+
+    // Line 0 is the opening delimiter
+    for (i, line) in lines.iter().enumerate().skip(1).take(property_lines) {
+        let values_start = if let Some(key) = TAGS_PROPERTY_RE.find(line) {
+            in_tags = true;
+            key.end()
+        } else if in_tags && line.starts_with([' ', '\t', '-', ']']) {
+            0 // List items and wrapped arrays continue the property
+        } else {
+            in_tags = false;
+            continue;
+        };
+
+        for range in property_value_tags(line, values_start) {
+            tags.push(NoteTag {
+                name: line[range.clone()].to_string(),
+                line: i,
+                range,
+                line_text: line.to_string(),
+            });
+        }
+    }
+
+    tags
+}
+
+/// Byte ranges of the tags in a property value, from `start` to the end of the line
+///
+/// Follows YAML where it matters for tags: a `#` after whitespace starts a
+/// comment, and a quoted scalar is a single value that only counts when it
+/// is a tag as a whole.
+fn property_value_tags(line: &str, start: usize) -> Vec<Range<usize>> {
+    let bytes = line.as_bytes();
+    let mut ranges = Vec::new();
+    let mut plain_start = start;
+    let mut pos = start;
+
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'#' if pos == 0 || bytes[pos - 1].is_ascii_whitespace() => break,
+            quote @ (b'"' | b'\'') => {
+                push_plain_tags(line, plain_start..pos, &mut ranges);
+                let close = line[pos + 1..].find(quote as char).map_or(line.len(), |i| pos + 1 + i);
+                let value = &line[pos + 1..close];
+                let name = value.trim().trim_start_matches('#').trim_end_matches('/');
+                if is_valid_tag(name) {
+                    let name_start = pos + 1 + value.find(name).unwrap_or(0);
+                    ranges.push(name_start..name_start + name.len());
+                }
+                pos = close + 1;
+                plain_start = pos;
+                continue;
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+
+    push_plain_tags(line, plain_start..pos.min(line.len()), &mut ranges);
+    ranges
+}
+
+/// Tags in unquoted property text, where several may be listed
+fn push_plain_tags(line: &str, span: Range<usize>, ranges: &mut Vec<Range<usize>>) {
+    for cap in PROPERTY_TAG_RE.captures_iter(&line[span.clone()]) {
+        let name_match = cap.get(1).unwrap();
+        let name = name_match.as_str().trim_end_matches('/');
+        if is_valid_tag(name) {
+            let name_start = span.start + name_match.start();
+            ranges.push(name_start..name_start + name.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_tags() {
+        let content = "---\ntags: [project/alpha, \"#urgent\"]\naliases:\n  - notatag\n---\n\
+                       # Heading #meeting\n\
+                       Plain #todo, **#bold** and `#code` or [#link](page#frag)\n\
+                       ```\n#fenced\n```\n\
+                       Issue #123 and a#b but (#inner/nested/) too";
+        let tags = extract_tags(content);
+        let found: Vec<(&str, usize)> = tags
+            .iter()
+            .map(|t| (t.name.as_str(), t.line))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                ("project/alpha", 1),
+                ("urgent", 1),
+                ("meeting", 5),
+                ("todo", 6),
+                ("bold", 6),
+                ("inner/nested", 10),
+            ]
+        );
+
+        let tags = extract_tags("Yes #a/b/");
+        assert_eq!(&tags[0].line_text[tags[0].range.clone()], "a/b");
+
+        // Comments and multi-word quoted values are not tags
+        let content = "---\ntags: [a, \"two words\", 'c#d'] # old stuff\ntag:\n  - e # was f\n  # - g\n---\n";
+        let names: Vec<String> = extract_tags(content).into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["a", "e"]);
+    }
+
+    #[test]
+    fn test_tag_matching() {
+        assert!(tag_matches("Project/Alpha", "project"));
+        assert!(tag_matches("project", "project"));
+        assert!(!tag_matches("projects", "project"));
+
+        assert!(is_valid_tag("project/alpha-2"));
+        assert!(!is_valid_tag("2024"));
+        assert!(!is_valid_tag("a//b"));
+        assert!(!is_valid_tag("has space"));
+    }
+}
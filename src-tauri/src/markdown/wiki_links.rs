@@ -0,0 +1,196 @@
+/**
+ * Wiki-link parsing and resolution
+ *
+ * `[[Target]]`, `[[Target|alias]]` and `[[Target#Heading]]` refer to notes
+ * by name rather than by path. Names are resolved against an index of the
+ * workspace's markdown files that the backend keeps up to date, so
+ * rendering never has to touch the filesystem.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// [[...]] without nested brackets or line breaks
+pub static WIKI_LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[([^\[\]\n]+?)\]\]").unwrap());
+
+/// Parsed contents of a `[[...]]` link
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikiLink {
+    pub target: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+}
+
+impl WikiLink {
+    /// Parse the text between the double brackets
+    pub fn parse(inner: &str) -> Option<Self> {
+        let (link, alias) = match inner.split_once('|') {
+            Some((link, alias)) => (link, Some(alias.trim()).filter(|a| !a.is_empty())),
+            None => (inner, None),
+        };
+        let (target, heading) = match link.split_once('#') {
+            Some((target, heading)) => (target, Some(heading.trim()).filter(|h| !h.is_empty())),
+            None => (link, None),
+        };
+
+        let target = target.trim();
+        if target.is_empty() && heading.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            target: target.to_string(),
+            heading: heading.map(str::to_string),
+            alias: alias.map(str::to_string),
+        })
+    }
+
+    /// Text shown in place of the link
+    pub fn display_text(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        match (&self.heading, self.target.is_empty()) {
+            (Some(heading), true) => heading.clone(),
+            (Some(heading), false) => format!("{} > {}", self.target, heading),
+            (None, _) => self.target.clone(),
+        }
+    }
+}
+
+/// Lowercased name a note is linked by, without the `.md` extension
+fn note_key(name: &str) -> String {
+    let name = name.trim().trim_start_matches("./").trim_start_matches('/');
+    let lower = name.replace('\\', "/").to_lowercase();
+    lower.strip_suffix(".md").map(str::to_string).unwrap_or(lower)
+}
+
+/// Index of workspace notes by name
+#[derive(Debug, Clone, Default)]
+pub struct NoteIndex {
+    root: Option<PathBuf>,
+    /// File stem (lowercased) -> paths relative to the root, shortest first
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl NoteIndex {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: Some(root.to_path_buf()),
+            by_name: HashMap::new(),
+        }
+    }
+
+    /// Workspace-relative path with forward slashes, if `path` is inside the workspace
+    pub fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(self.root.as_ref()?).ok()?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        Some(parts.join("/"))
+    }
+
+    pub fn insert(&mut self, relative_path: &str) {
+        let key = note_key(relative_path.rsplit('/').next().unwrap_or(relative_path));
+        let paths = self.by_name.entry(key).or_default();
+
+        if !paths.iter().any(|p| p == relative_path) {
+            paths.push(relative_path.to_string());
+            // Obsidian-style preference: the least nested note wins
+            paths.sort_by(|a, b| a.matches('/').count().cmp(&b.matches('/').count()).then_with(|| a.cmp(b)));
+        }
+    }
+
+    /// Remove a note, or every note under a folder
+    pub fn remove(&mut self, relative_path: &str) {
+        let folder_prefix = format!("{}/", relative_path);
+        for paths in self.by_name.values_mut() {
+            paths.retain(|p| p != relative_path && !p.starts_with(&folder_prefix));
+        }
+        self.by_name.retain(|_, paths| !paths.is_empty());
+    }
+
+    pub fn contains(&self, relative_path: &str) -> bool {
+        let key = note_key(relative_path.rsplit('/').next().unwrap_or(relative_path));
+        self.by_name.get(&key).is_some_and(|paths| paths.iter().any(|p| p == relative_path))
+    }
+
+    /// The note at a path, or every note under a folder
+    pub fn paths_under(&self, relative_path: &str) -> Vec<String> {
+        let folder_prefix = format!("{}/", relative_path);
+        self.by_name
+            .values()
+            .flatten()
+            .filter(|p| *p == relative_path || p.starts_with(&folder_prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Resolve a link target to a workspace-relative path
+    ///
+    /// A bare name matches any note with that file name; a target containing
+    /// `/` must also match the end of the note's path.
+    pub fn resolve(&self, target: &str) -> Option<&str> {
+        let key = note_key(target);
+        let name = key.rsplit('/').next().unwrap_or(&key);
+        let candidates = self.by_name.get(name)?;
+
+        candidates
+            .iter()
+            .find(|path| {
+                let path_key = note_key(path);
+                path_key == key || path_key.ends_with(&format!("/{}", key))
+            })
+            .map(String::as_str)
+    }
+
+    /// Absolute path of a resolved note
+    pub fn absolute_path(&self, relative_path: &str) -> Option<PathBuf> {
+        Some(self.root.as_ref()?.join(relative_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wiki_link() {
+        assert_eq!(
+            WikiLink::parse("Note Name"),
+            Some(WikiLink { target: "Note Name".to_string(), heading: None, alias: None })
+        );
+        assert_eq!(
+            WikiLink::parse("Note#Setup|the setup"),
+            Some(WikiLink {
+                target: "Note".to_string(),
+                heading: Some("Setup".to_string()),
+                alias: Some("the setup".to_string()),
+            })
+        );
+        assert_eq!(WikiLink::parse("#Local").unwrap().display_text(), "Local");
+        assert_eq!(WikiLink::parse("Note#Setup").unwrap().display_text(), "Note > Setup");
+        assert_eq!(WikiLink::parse(" | "), None);
+    }
+
+    #[test]
+    fn test_resolve_prefers_shallow_notes() {
+        let mut index = NoteIndex::new(Path::new("/vault"));
+        index.insert("projects/archive/Plan.md");
+        index.insert("projects/Plan.md");
+        index.insert("Inbox.md");
+
+        assert_eq!(index.resolve("plan"), Some("projects/Plan.md"));
+        assert_eq!(index.resolve("archive/Plan"), Some("projects/archive/Plan.md"));
+        assert_eq!(index.resolve("Inbox.md"), Some("Inbox.md"));
+        assert_eq!(index.resolve("other/Plan"), None);
+        assert_eq!(index.resolve("Missing"), None);
+
+        index.remove("projects");
+        assert_eq!(index.resolve("Plan"), None);
+        assert_eq!(index.resolve("Inbox"), Some("Inbox.md"));
+    }
+}
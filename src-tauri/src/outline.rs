@@ -0,0 +1,131 @@
+use crate::markdown::{extract_outline, slugify, update_toc, OutlineHeading};
+use crate::workspace::{percent_decode, resolve_relative_link, WorkspaceState, WorkspaceStateHandle};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Where a link with an anchor leads
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorTarget {
+    pub file_path: String,
+    /// Zero-based line of the heading; `None` when the link has no anchor
+    /// or no heading matches it
+    pub line: Option<usize>,
+    pub heading: Option<String>,
+}
+
+/// Outline of a note's content: its headings with level, text, line and anchor
+#[tauri::command]
+pub fn get_outline(content: String) -> Vec<OutlineHeading> {
+    extract_outline(&content)
+}
+
+/// Outline of a note on disk
+#[tauri::command]
+pub fn get_file_outline(path: String) -> Result<Vec<OutlineHeading>, String> {
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    Ok(extract_outline(&content))
+}
+
+/// Regenerate the `[TOC]` blocks of a note's content, returning the new content
+///
+/// Without a `[TOC]` line, one is inserted before `insert_line` if given.
+#[tauri::command]
+pub fn refresh_toc(content: String, insert_line: Option<usize>) -> Result<String, String> {
+    update_toc(&content, insert_line)
+}
+
+/// Note a link destination (without its anchor) points to from `source`
+fn link_target(workspace: &WorkspaceState, source: &Path, destination: &str) -> Result<PathBuf, String> {
+    if destination.is_empty() {
+        return Ok(source.to_path_buf());
+    }
+
+    let notes = workspace.notes();
+    let source_path = notes
+        .relative_path(source)
+        .ok_or_else(|| format!("{} is not in the open folder", source.display()))?;
+    let relative = resolve_relative_link(&source_path, destination)
+        .ok_or_else(|| format!("{} is not a link to a note", destination))?;
+
+    // A bare note name (`other#setup`) is looked up like a wiki-link target
+    notes
+        .absolute_path(&relative)
+        .filter(|path| path.is_file())
+        .or_else(|| notes.absolute_path(notes.resolve(destination)?))
+        .ok_or_else(|| format!("Link target not found: {}", destination))
+}
+
+/// Resolve a link such as `#design-decisions` or `other.md#setup` found in
+/// `source` to the file and heading line it leads to
+pub fn resolve_anchor(workspace: &WorkspaceState, link: &str, source: &Path) -> Result<AnchorTarget, String> {
+    let (destination, anchor) = link.split_once('#').unwrap_or((link, ""));
+    let anchor = percent_decode(anchor);
+    let target = link_target(workspace, source, destination)?;
+
+    let content = fs::read_to_string(&target)
+        .map_err(|e| format!("Failed to read {}: {}", target.display(), e))?;
+
+    // Wiki-links name the heading rather than its anchor, so accept either
+    let heading = (!anchor.is_empty())
+        .then(|| {
+            let wanted = slugify(&anchor);
+            extract_outline(&content)
+                .into_iter()
+                .find(|h| h.slug == anchor || h.slug == wanted)
+        })
+        .flatten();
+
+    Ok(AnchorTarget {
+        file_path: target.to_string_lossy().to_string(),
+        line: heading.as_ref().map(|h| h.line),
+        heading: heading.map(|h| h.text),
+    })
+}
+
+/// Resolve a `file#anchor` link from a note to a file path and heading line
+#[tauri::command]
+pub fn resolve_anchor_link(
+    link: String,
+    source_path: String,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<AnchorTarget, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    resolve_anchor(&workspace, &link, Path::new(&source_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_anchor() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("index.md"), "# Index\n## Design Decisions\n## Setup\n## Setup").unwrap();
+        fs::write(root.join("docs/other.md"), "intro\n## Setup Guide").unwrap();
+
+        let mut workspace = WorkspaceState::new();
+        workspace.open(root);
+        let index = root.join("index.md");
+        let resolve = |link: &str| resolve_anchor(&workspace, link, &index).map(|t| (t.line, t.heading));
+
+        assert_eq!(resolve("#design-decisions"), Ok((Some(1), Some("Design Decisions".to_string()))));
+        assert_eq!(resolve("#setup-1"), Ok((Some(3), Some("Setup".to_string()))));
+        assert_eq!(resolve("docs/other.md#setup-guide"), Ok((Some(1), Some("Setup Guide".to_string()))));
+        assert_eq!(resolve("other#Setup%20Guide"), Ok((Some(1), Some("Setup Guide".to_string()))));
+        assert_eq!(resolve("#missing"), Ok((None, None)));
+        assert!(resolve("nowhere.md#setup").is_err());
+        assert!(resolve("https://example.com#setup").is_err());
+
+        let target = resolve_anchor(&workspace, "docs/other.md", &index).unwrap();
+        assert_eq!(PathBuf::from(target.file_path), root.join("docs/other.md"));
+    }
+}
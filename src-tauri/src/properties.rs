@@ -0,0 +1,47 @@
+use crate::markdown::{parse_properties, replace_properties, NoteProperties};
+use crate::tasks::content_hash;
+use crate::write_file_atomic;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertiesUpdateResult {
+    pub new_content: String,
+    pub content_hash: String,
+}
+
+/// Parse the frontmatter of a note's content into JSON properties
+#[tauri::command]
+pub fn get_note_properties(content: String) -> Result<NoteProperties, String> {
+    parse_properties(&content)
+}
+
+/// Write a note's properties back to its frontmatter on disk
+///
+/// The note body is left byte-for-byte as it was. As with `toggle_task`,
+/// `expected_hash` must match the file's current content.
+#[tauri::command]
+pub fn update_note_properties(
+    path: String,
+    properties: Map<String, Value>,
+    expected_hash: String,
+) -> Result<PropertiesUpdateResult, String> {
+    let file_path = Path::new(&path);
+    let content = fs::read_to_string(file_path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    if content_hash(&content) != expected_hash {
+        return Err("File has changed on disk since it was loaded".to_string());
+    }
+
+    let new_content = replace_properties(&content, &properties)?;
+    write_file_atomic(file_path, &new_content)?;
+
+    Ok(PropertiesUpdateResult {
+        content_hash: content_hash(&new_content),
+        new_content,
+    })
+}
@@ -0,0 +1,287 @@
+use crate::workspace::{WorkspaceState, WorkspaceStateHandle};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// Results returned when the caller doesn't ask for a number
+const DEFAULT_LIMIT: usize = 50;
+
+// Fuzzy scoring weights
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 8;
+const BONUS_PATH_SEGMENT: i64 = 10;
+const BONUS_WORD_START: i64 = 8;
+const BONUS_CAMEL_CASE: i64 = 7;
+const BONUS_FILE_NAME: i64 = 4;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTEND: i64 = 1;
+
+/// Matched characters `start..end`, counted in characters rather than bytes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickOpenMatch {
+    pub file_path: String,
+    /// Path relative to the workspace, with `/` separators
+    pub relative_path: String,
+    /// Frontmatter title or first heading
+    pub title: Option<String>,
+    pub score: i64,
+    /// Matched parts of `relative_path`
+    pub path_ranges: Vec<MatchRange>,
+    /// Matched parts of `title`
+    pub title_ranges: Vec<MatchRange>,
+}
+
+/// A fuzzy match of a query against some text
+#[derive(Debug, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Character index in the text of each query character
+    pub positions: Vec<usize>,
+}
+
+/// Bonus for a query character matching at `text[i]`: matches starting a
+/// path segment, a word or a camelCase hump count for more
+fn position_bonus(text: &[char], i: usize) -> i64 {
+    let Some(&prev) = i.checked_sub(1).and_then(|p| text.get(p)) else {
+        return BONUS_PATH_SEGMENT;
+    };
+    let current = text[i];
+
+    if prev == '/' || prev == '\\' {
+        BONUS_PATH_SEGMENT
+    } else if !prev.is_alphanumeric() {
+        BONUS_WORD_START
+    } else if prev.is_lowercase() && current.is_uppercase() {
+        BONUS_CAMEL_CASE
+    } else {
+        0
+    }
+}
+
+/// Match `query` against `text` as a subsequence, finding the best scoring
+/// alignment
+///
+/// The match ignores case unless the query has an uppercase letter.
+/// Whitespace in the query is ignored.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).map(fold).collect();
+    let text: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = text.iter().map(|&c| fold(c)).collect();
+
+    if query.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+
+    // Most candidates don't contain the query at all; rule them out cheaply
+    let mut remaining = folded.iter();
+    if !query.iter().all(|q| remaining.any(|c| c == q)) {
+        return None;
+    }
+
+    // best[i][j]: best score with query[i] matched at text[j], and where
+    // query[i - 1] was matched to get it
+    let (n, m) = (query.len(), text.len());
+    let mut best: Vec<Vec<Option<(i64, usize)>>> = vec![vec![None; m]; n];
+
+    for (j, &c) in folded.iter().enumerate() {
+        if c == query[0] {
+            best[0][j] = Some((SCORE_MATCH + 2 * position_bonus(&text, j), 0));
+        }
+    }
+
+    for i in 1..n {
+        // Best predecessor at least two characters back, with the gap
+        // penalty's dependency on its position factored out
+        let mut gapped: Option<(i64, usize)> = None;
+
+        for j in i..m {
+            if j >= 2 {
+                if let Some((score, _)) = best[i - 1][j - 2] {
+                    let candidate = score + PENALTY_GAP_EXTEND * (j - 2) as i64;
+                    if gapped.is_none_or(|(best_score, _)| candidate > best_score) {
+                        gapped = Some((candidate, j - 2));
+                    }
+                }
+            }
+
+            if folded[j] != query[i] {
+                continue;
+            }
+
+            let consecutive = best[i - 1][j - 1].map(|(score, _)| (score + BONUS_CONSECUTIVE, j - 1));
+            let after_gap = gapped.map(|(score, k)| {
+                (score - PENALTY_GAP_EXTEND * (j - 2) as i64 - PENALTY_GAP_START, k)
+            });
+            let previous = match (consecutive, after_gap) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+
+            if let Some((score, k)) = previous {
+                best[i][j] = Some((score + SCORE_MATCH + position_bonus(&text, j), k));
+            }
+        }
+    }
+
+    let (mut j, (score, _)) = best[n - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, cell)| cell.map(|cell| (j, cell)))
+        .max_by_key(|(j, (score, _))| (*score, std::cmp::Reverse(*j)))?;
+
+    let mut positions = vec![0; n];
+    for i in (0..n).rev() {
+        positions[i] = j;
+        j = best[i][j].map_or(0, |(_, k)| k);
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Merge matched character positions into ranges
+fn match_ranges(positions: &[usize]) -> Vec<MatchRange> {
+    let mut ranges: Vec<MatchRange> = Vec::new();
+    for &position in positions {
+        match ranges.last_mut() {
+            Some(range) if range.end == position => range.end += 1,
+            _ => ranges.push(MatchRange { start: position, end: position + 1 }),
+        }
+    }
+    ranges
+}
+
+/// Score a note's path for quick open, favouring matches in the file name
+fn match_path(query: &str, relative_path: &str) -> Option<FuzzyMatch> {
+    let mut path_match = fuzzy_match(query, relative_path)?;
+    let name_start = relative_path
+        .rfind('/')
+        .map_or(0, |i| relative_path[..=i].chars().count());
+
+    let in_name = path_match.positions.iter().filter(|&&p| p >= name_start).count();
+    path_match.score += BONUS_FILE_NAME * in_name as i64;
+    Some(path_match)
+}
+
+/// Notes matching a quick open query, best first
+pub fn rank_notes(workspace: &WorkspaceState, query: &str, limit: usize) -> Vec<QuickOpenMatch> {
+    let notes: Vec<(&String, Option<&String>)> = workspace.note_titles().collect();
+
+    let mut matches: Vec<QuickOpenMatch> = notes
+        .par_iter()
+        .filter_map(|&(relative_path, title)| {
+            let path_match = match_path(query, relative_path);
+            let title_match = title.and_then(|title| fuzzy_match(query, title));
+            let score = match (&path_match, &title_match) {
+                (None, None) => return None,
+                (path, title) => path.iter().chain(title).map(|m| m.score).max().unwrap_or_default(),
+            };
+
+            Some(QuickOpenMatch {
+                file_path: workspace
+                    .notes()
+                    .absolute_path(relative_path)?
+                    .to_string_lossy()
+                    .to_string(),
+                relative_path: relative_path.clone(),
+                title: title.cloned(),
+                score,
+                path_ranges: path_match.map(|m| match_ranges(&m.positions)).unwrap_or_default(),
+                title_ranges: title_match.map(|m| match_ranges(&m.positions)).unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    // Equal scores go to the shorter path, so `note` finds `note.md` before `notes/old/note.md`
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.relative_path.len().cmp(&b.relative_path.len()))
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+/// Find notes for a quick open (Ctrl+P) query
+///
+/// Matches the query fuzzily against each note's workspace-relative path
+/// and its title, using the workspace's note list, which the file watcher
+/// keeps current. An empty query lists every note, up to the limit.
+#[tauri::command]
+pub fn quick_open(
+    query: String,
+    limit: Option<usize>,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<Vec<QuickOpenMatch>, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    Ok(rank_notes(&workspace, &query, limit.unwrap_or(DEFAULT_LIMIT)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_fuzzy_match() {
+        // The best alignment is found, not the first one
+        let m = fuzzy_match("mn", "my-meeting/minutes.md").unwrap();
+        assert_eq!(m.positions, vec![11, 13]);
+
+        assert_eq!(
+            match_ranges(&fuzzy_match("rn", "projects/readme-notes.md").unwrap().positions),
+            vec![MatchRange { start: 9, end: 10 }, MatchRange { start: 16, end: 17 }]
+        );
+        assert_eq!(
+            match_ranges(&fuzzy_match("read", "projects/readme.md").unwrap().positions),
+            vec![MatchRange { start: 9, end: 13 }]
+        );
+
+        // Smart case: an uppercase letter makes the match case-sensitive
+        assert!(fuzzy_match("RM", "readme.md").is_none());
+        assert!(fuzzy_match("rm", "README.md").is_some());
+        assert!(fuzzy_match("xyz", "readme.md").is_none());
+
+        // Word starts beat scattered letters
+        let boundary = fuzzy_match("dn", "daily-notes.md").unwrap().score;
+        let scattered = fuzzy_match("dn", "dragonfruit.md").unwrap().score;
+        assert!(boundary > scattered);
+    }
+
+    #[test]
+    fn test_rank_notes() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("archive")).unwrap();
+        fs::write(root.join("plan.md"), "# Roadmap 2026").unwrap();
+        fs::write(root.join("archive/plan.md"), "").unwrap();
+        fs::write(root.join("pineapple.md"), "---\ntitle: Fruit\n---\n").unwrap();
+
+        let mut workspace = WorkspaceState::new();
+        workspace.open(root);
+
+        let paths = |query: &str| -> Vec<String> {
+            rank_notes(&workspace, query, 10).into_iter().map(|m| m.relative_path).collect()
+        };
+        assert_eq!(paths("plan"), vec!["plan.md", "archive/plan.md"]);
+        assert_eq!(paths("road"), vec!["plan.md"]);
+        assert_eq!(paths("").len(), 3);
+
+        let fruit = &rank_notes(&workspace, "fruit", 1)[0];
+        assert_eq!(fruit.title.as_deref(), Some("Fruit"));
+        assert_eq!(fruit.title_ranges, vec![MatchRange { start: 0, end: 5 }]);
+        assert!(fruit.path_ranges.is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+use crate::config::get_loom_dir;
+use crate::search::{build_search_regex, checked_search_dir, search_candidates, SearchOptions};
+use crate::search_index::SearchIndexHandle;
+use crate::tasks::content_hash;
+use crate::{rewrite_files_atomic, write_file_atomic, FileRewrite};
+use rayon::prelude::*;
+use regex::{NoExpand, Regex, Replacer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+/// Folder under `.loom/` holding the undo journal
+const JOURNAL_DIR: &str = "replace-journal";
+/// Older replaces are dropped from the journal beyond this many
+const JOURNAL_LIMIT: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceEdit {
+    /// Index of the match within its file, used to select it
+    pub id: usize,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub text: String,
+    /// Replacement with any `$1` style groups expanded
+    pub replacement: String,
+    pub line_text: String,
+    /// The line with just this match replaced
+    pub new_line_text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReplacePreview {
+    pub file_path: String,
+    /// Hash of the content the preview was computed from
+    pub content_hash: String,
+    pub edits: Vec<ReplaceEdit>,
+}
+
+/// Matches of one file chosen to be replaced
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReplaceSelection {
+    pub file_path: String,
+    pub content_hash: String,
+    pub match_ids: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceSummary {
+    /// Pass to `undo_replace` to revert this replace
+    pub journal_id: String,
+    pub files_changed: usize,
+    pub replaced_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JournalEntry {
+    file_path: String,
+    original: String,
+    /// Hash of the content written, to tell whether the file was edited since
+    updated_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplaceJournal {
+    id: String,
+    query: String,
+    replacement: String,
+    files: Vec<JournalEntry>,
+}
+
+/// A match in a file, with its replacement
+struct PlannedMatch {
+    /// Byte range in the whole content
+    start: usize,
+    end: usize,
+    line: usize,
+    line_text: String,
+    /// Byte range within the line
+    column: usize,
+    replacement: String,
+}
+
+/// Find every match line by line, as `search_in_content` does, and work out
+/// its replacement
+fn plan_matches(re: &Regex, content: &str, replacement: &str, expand: bool) -> Vec<PlannedMatch> {
+    let mut matches = Vec::new();
+    let mut line_start = 0;
+
+    for (line_num, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line = raw_line.trim_end_matches('\n').trim_end_matches('\r');
+
+        for caps in re.captures_iter(line) {
+            let found = caps.get(0).unwrap();
+            let mut expanded = String::new();
+            if expand {
+                caps.expand(replacement, &mut expanded);
+            } else {
+                NoExpand(replacement).replace_append(&caps, &mut expanded);
+            }
+
+            matches.push(PlannedMatch {
+                start: line_start + found.start(),
+                end: line_start + found.end(),
+                line: line_num,
+                line_text: line.to_string(),
+                column: found.start(),
+                replacement: expanded,
+            });
+        }
+
+        line_start += raw_line.len();
+    }
+
+    matches
+}
+
+/// Content with the selected matches replaced
+fn apply_matches(content: &str, matches: &[PlannedMatch], selected: &HashSet<usize>) -> String {
+    let mut updated = String::with_capacity(content.len());
+    let mut cursor = 0;
+
+    for (id, planned) in matches.iter().enumerate() {
+        if selected.contains(&id) {
+            updated.push_str(&content[cursor..planned.start]);
+            updated.push_str(&planned.replacement);
+            cursor = planned.end;
+        }
+    }
+
+    updated.push_str(&content[cursor..]);
+    updated
+}
+
+fn preview_file(re: &Regex, path: &Path, replacement: &str, expand: bool) -> Option<FileReplacePreview> {
+    let content = fs::read_to_string(path).ok()?; // Skip files we can't read
+    let matches = plan_matches(re, &content, replacement, expand);
+    if matches.is_empty() {
+        return None;
+    }
+
+    let edits = matches
+        .into_iter()
+        .enumerate()
+        .map(|(id, planned)| {
+            let length = planned.end - planned.start;
+            let new_line_text = format!(
+                "{}{}{}",
+                &planned.line_text[..planned.column],
+                planned.replacement,
+                &planned.line_text[planned.column + length..]
+            );
+            ReplaceEdit {
+                id,
+                line: planned.line + 1,
+                column: planned.column + 1,
+                length,
+                text: planned.line_text[planned.column..planned.column + length].to_string(),
+                replacement: planned.replacement,
+                line_text: planned.line_text,
+                new_line_text,
+            }
+        })
+        .collect();
+
+    Some(FileReplacePreview {
+        file_path: path.to_string_lossy().to_string(),
+        content_hash: content_hash(&content),
+        edits,
+    })
+}
+
+fn journal_dir(dir_path: &str) -> Result<PathBuf, String> {
+    Ok(get_loom_dir(Some(dir_path.to_string()))?.join(JOURNAL_DIR))
+}
+
+/// Journal files, oldest first
+fn journal_files(journal_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(journal_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Ids are zero-padded timestamps, so names sort by age
+    files.sort();
+    files
+}
+
+/// Whether an id has the form `write_journal` gives out, so it can't name
+/// a file outside the journal
+fn is_journal_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Save a journal under a new id and return its path
+///
+/// Ids are zero-padded millisecond timestamps. The journal file is created
+/// exclusively, so a replace in the same millisecond (or from another window)
+/// takes the next free id instead of overwriting.
+fn write_journal(journal_dir: &Path, journal: &mut ReplaceJournal) -> Result<PathBuf, String> {
+    fs::create_dir_all(journal_dir)
+        .map_err(|e| format!("Failed to create replace journal directory: {}", e))?;
+
+    let mut created = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    let path = loop {
+        let path = journal_dir.join(format!("{:020}.json", created));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => break path,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => created += 1,
+            Err(e) => return Err(format!("Failed to create replace journal: {}", e)),
+        }
+    };
+    journal.id = format!("{:020}", created);
+
+    let json = serde_json::to_string(journal)
+        .map_err(|e| format!("Failed to serialize replace journal: {}", e))?;
+    if let Err(e) = write_file_atomic(&path, &json) {
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+
+    let files = journal_files(journal_dir);
+    for old in files.iter().take(files.len().saturating_sub(JOURNAL_LIMIT)) {
+        let _ = fs::remove_file(old);
+    }
+
+    Ok(path)
+}
+
+/// Preview a replace across every note in a directory
+///
+/// Returns each file's matches with the line before and after, plus the
+/// hash of the content they were computed from.
+#[tauri::command]
+pub fn preview_replace_in_directory(
+    query: String,
+    replacement: String,
+    dir_path: String,
+    options: SearchOptions,
+    search_index: State<SearchIndexHandle>,
+) -> Result<Vec<FileReplacePreview>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let path = checked_search_dir(&dir_path)?;
+    let re = build_search_regex(&query, &options)?;
+    let candidates = search_candidates(Some(&query), path, &options, &search_index)?;
+
+    Ok(candidates
+        .par_iter()
+        .filter_map(|candidate| preview_file(&re, candidate, &replacement, options.use_regex))
+        .collect())
+}
+
+/// Replace the selected matches of a previewed replace
+///
+/// Every file must still have the content it was previewed from. All files
+/// are written as one step, and the original contents are journaled under
+/// `.loom/` so `undo_replace` can revert the whole replace.
+#[tauri::command]
+pub fn replace_in_directory(
+    query: String,
+    replacement: String,
+    dir_path: String,
+    options: SearchOptions,
+    files: Vec<FileReplaceSelection>,
+) -> Result<ReplaceSummary, String> {
+    // An empty pattern matches between every two characters
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+
+    let journal_dir = journal_dir(&dir_path)?;
+    let re = build_search_regex(&query, &options)?;
+
+    // A file selected twice (under the same or another spelling of its path)
+    // is replaced once, with the matches of both selections
+    let mut selections: Vec<(PathBuf, &FileReplaceSelection, HashSet<usize>)> = Vec::new();
+    for selection in files.iter().filter(|selection| !selection.match_ids.is_empty()) {
+        let path = fs::canonicalize(&selection.file_path)
+            .map_err(|e| format!("Failed to read {}: {}", selection.file_path, e))?;
+        match selections.iter_mut().find(|(seen, _, _)| *seen == path) {
+            Some((_, _, ids)) => ids.extend(selection.match_ids.iter().copied()),
+            None => selections.push((path, selection, selection.match_ids.iter().copied().collect())),
+        }
+    }
+
+    let mut originals = Vec::new();
+    let mut replaced_count = 0;
+    for (path, selection, match_ids) in selections {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", selection.file_path, e))?;
+        if content_hash(&content) != selection.content_hash {
+            return Err(format!("{} has changed since the preview", selection.file_path));
+        }
+
+        let matches = plan_matches(&re, &content, &replacement, options.use_regex);
+        let selected: HashSet<usize> = match_ids.into_iter().filter(|id| *id < matches.len()).collect();
+        replaced_count += selected.len();
+        let updated = apply_matches(&content, &matches, &selected);
+        originals.push((PathBuf::from(&selection.file_path), content, updated));
+    }
+
+    let mut journal = ReplaceJournal {
+        id: String::new(),
+        query,
+        replacement,
+        files: originals
+            .iter()
+            .map(|(path, original, updated)| JournalEntry {
+                file_path: path.to_string_lossy().to_string(),
+                original: original.clone(),
+                updated_hash: content_hash(updated),
+            })
+            .collect(),
+    };
+
+    // Journal first, so there is never a replace on disk that can't be undone
+    let journal_path = write_journal(&journal_dir, &mut journal)?;
+
+    let rewrites: Vec<FileRewrite> = originals
+        .iter()
+        .map(|(path, original, updated)| FileRewrite { path, original, updated })
+        .collect();
+    if let Err(e) = rewrite_files_atomic(&rewrites) {
+        let _ = fs::remove_file(&journal_path);
+        return Err(e);
+    }
+
+    Ok(ReplaceSummary {
+        journal_id: journal.id,
+        files_changed: originals.len(),
+        replaced_count,
+    })
+}
+
+/// Revert a replace, or the most recent one when no id is given
+///
+/// Refuses if any of its files was edited after the replace, rather than
+/// losing those edits. Returns the paths restored.
+#[tauri::command]
+pub fn undo_replace(dir_path: String, journal_id: Option<String>) -> Result<Vec<String>, String> {
+    let journal_dir = journal_dir(&dir_path)?;
+    let journal_path = match journal_id {
+        Some(id) if is_journal_id(&id) => journal_dir.join(format!("{}.json", id)),
+        Some(id) => return Err(format!("Invalid replace journal id: {}", id)),
+        None => journal_files(&journal_dir)
+            .pop()
+            .ok_or_else(|| "Nothing to undo".to_string())?,
+    };
+
+    let json = fs::read_to_string(&journal_path)
+        .map_err(|e| format!("Failed to read replace journal: {}", e))?;
+    let journal: ReplaceJournal = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse replace journal: {}", e))?;
+
+    let mut current = Vec::new();
+    for entry in &journal.files {
+        let content = fs::read_to_string(&entry.file_path)
+            .map_err(|e| format!("Failed to read {}: {}", entry.file_path, e))?;
+        if content_hash(&content) != entry.updated_hash {
+            return Err(format!("{} has been edited since the replace", entry.file_path));
+        }
+        current.push((PathBuf::from(&entry.file_path), content));
+    }
+
+    let rewrites: Vec<FileRewrite> = current
+        .iter()
+        .zip(&journal.files)
+        .map(|((path, content), entry)| FileRewrite {
+            path,
+            original: content,
+            updated: &entry.original,
+        })
+        .collect();
+    rewrite_files_atomic(&rewrites)?;
+
+    fs::remove_file(&journal_path)
+        .map_err(|e| format!("Failed to remove replace journal: {}", e))?;
+
+    Ok(journal.files.into_iter().map(|entry| entry.file_path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(use_regex: bool) -> SearchOptions {
+        SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        let content = "foo bar foo\r\nFoo\n";
+        let re = build_search_regex("foo", &options(false)).unwrap();
+        let matches = plan_matches(&re, content, "$x", false);
+        assert_eq!(matches.len(), 3);
+        assert_eq!((matches[2].line, matches[2].column), (1, 0));
+
+        let selected: HashSet<usize> = [0, 2].into_iter().collect();
+        assert_eq!(apply_matches(content, &matches, &selected), "$x bar foo\r\n$x\n");
+
+        let re = build_search_regex(r"(\w+)@(\w+)", &options(true)).unwrap();
+        let matches = plan_matches(&re, "me@home", "$2 at $1", true);
+        assert_eq!(matches[0].replacement, "home at me");
+    }
+
+    #[test]
+    fn test_replace_and_undo() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let a = root.join("a.md");
+        let b = root.join("b.md");
+        fs::write(&a, "old one\nold two\n").unwrap();
+        fs::write(&b, "old three").unwrap();
+
+        let re = build_search_regex("old", &options(false)).unwrap();
+        let preview = preview_file(&re, &a, "new", false).unwrap();
+        assert_eq!(preview.edits[1].new_line_text, "new two");
+
+        let selections = vec![
+            FileReplaceSelection {
+                file_path: preview.file_path.clone(),
+                content_hash: preview.content_hash.clone(),
+                match_ids: vec![1],
+            },
+            FileReplaceSelection {
+                file_path: b.to_string_lossy().to_string(),
+                content_hash: content_hash("old three"),
+                match_ids: vec![0],
+            },
+        ];
+        let dir_path = root.to_string_lossy().to_string();
+        let summary = replace_in_directory("old".into(), "new".into(), dir_path.clone(), options(false), selections).unwrap();
+        assert_eq!((summary.files_changed, summary.replaced_count), (2, 2));
+        assert_eq!(fs::read_to_string(&a).unwrap(), "old one\nnew two\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "new three");
+
+        // A stale preview is refused
+        let stale = vec![FileReplaceSelection {
+            file_path: preview.file_path,
+            content_hash: preview.content_hash,
+            match_ids: vec![0],
+        }];
+        assert!(replace_in_directory("old".into(), "new".into(), dir_path.clone(), options(false), stale).is_err());
+
+        let restored = undo_replace(dir_path.clone(), None).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read_to_string(&a).unwrap(), "old one\nold two\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "old three");
+        assert!(undo_replace(dir_path, None).is_err());
+    }
+
+    #[test]
+    fn test_replace_guards() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let a = root.join("a.md");
+        fs::write(&a, "ab ab").unwrap();
+        let dir_path = root.to_string_lossy().to_string();
+        let select = |ids: Vec<usize>| FileReplaceSelection {
+            file_path: a.to_string_lossy().to_string(),
+            content_hash: content_hash(&fs::read_to_string(&a).unwrap()),
+            match_ids: ids,
+        };
+
+        assert!(replace_in_directory("".into(), "x".into(), dir_path.clone(), options(false), vec![select(vec![0])]).is_err());
+
+        // The same file selected twice is replaced once
+        let twice = vec![select(vec![0]), select(vec![0, 1])];
+        let first = replace_in_directory("ab".into(), "abab".into(), dir_path.clone(), options(false), twice).unwrap();
+        assert_eq!((first.files_changed, first.replaced_count), (1, 2));
+        assert_eq!(fs::read_to_string(&a).unwrap(), "abab abab");
+
+        // Back-to-back replaces get their own journals
+        let second = replace_in_directory("abab".into(), "c".into(), dir_path.clone(), options(false), vec![select(vec![0])])
+            .unwrap();
+        assert_ne!(first.journal_id, second.journal_id);
+
+        assert!(undo_replace(dir_path.clone(), Some("../../a".into())).is_err());
+        undo_replace(dir_path.clone(), Some(second.journal_id)).unwrap();
+        undo_replace(dir_path, Some(first.journal_id)).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "ab ab");
+    }
+}
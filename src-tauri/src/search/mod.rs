@@ -0,0 +1,620 @@
+use crate::search_index::SearchIndexHandle;
+use crate::search_scope::SearchScope;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter, State};
+use walkdir::WalkDir;
+
+mod query;
+
+use query::SearchQuery;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub use_regex: bool,
+    /// Read the query as a search query with field filters (see `SearchQuery`)
+    #[serde(default)]
+    pub use_query: bool,
+    /// Which files directory searches cover; unset options fall back to
+    /// the folder's `SearchDefaults`
+    #[serde(default)]
+    pub include: Option<Vec<String>>,
+    #[serde(default)]
+    pub exclude: Option<Vec<String>>,
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    #[serde(default)]
+    pub respect_ignore_files: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub text: String,
+    pub line_text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSearchResult {
+    pub file_path: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceResult {
+    pub new_content: String,
+    pub replaced_count: usize,
+}
+
+/// Build the regex for a query and its options
+pub(crate) fn build_search_regex(query: &str, options: &SearchOptions) -> Result<Regex, String> {
+    if options.use_query {
+        return Err("Search queries can only be used to find notes".to_string());
+    }
+
+    // Build the search pattern
+    let pattern = if options.use_regex {
+        query.to_string()
+    } else if options.whole_word {
+        format!(r"\b{}\b", regex::escape(query))
+    } else {
+        regex::escape(query)
+    };
+
+    // Create regex with appropriate flags
+    let regex_pattern = if options.case_sensitive {
+        pattern
+    } else {
+        format!("(?i){}", pattern)
+    };
+
+    Regex::new(&regex_pattern).map_err(|e| e.to_string())
+}
+
+/// Find every match of a search regex, line by line
+fn find_matches(re: &Regex, content: &str) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        for mat in re.find_iter(line) {
+            matches.push(SearchMatch {
+                line: line_num + 1,
+                column: mat.start() + 1,
+                length: mat.end() - mat.start(),
+                text: mat.as_str().to_string(),
+                line_text: line.to_string(),
+            });
+        }
+    }
+
+    matches
+}
+
+/// Search for a query in text content
+#[tauri::command]
+pub fn search_in_content(
+    query: String,
+    content: String,
+    options: SearchOptions,
+) -> Result<Vec<SearchMatch>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if options.use_query {
+        let query = SearchQuery::parse(&query, &options)?;
+        return Ok(query.highlights(&query::QueryNote::new(String::new(), &content, None)));
+    }
+
+    let re = build_search_regex(&query, &options)?;
+    Ok(find_matches(&re, &content))
+}
+
+/// Replace all occurrences in content
+#[tauri::command]
+pub fn replace_in_content(
+    query: String,
+    replacement: String,
+    content: String,
+    options: SearchOptions,
+) -> Result<ReplaceResult, String> {
+    if query.is_empty() {
+        return Ok(ReplaceResult {
+            new_content: content,
+            replaced_count: 0,
+        });
+    }
+
+    let re = build_search_regex(&query, &options)?;
+
+    // Count matches before replacement
+    let count = re.find_iter(&content).count();
+
+    // Perform replacement
+    let new_content = re.replace_all(&content, replacement.as_str()).to_string();
+
+    Ok(ReplaceResult {
+        new_content,
+        replaced_count: count,
+    })
+}
+
+/// Walk a directory and yield every markdown file in it
+pub fn markdown_files(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        // Only .md files are notes
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+}
+
+/// Files to search for a query, best candidates first when the index can tell
+///
+/// Only files in the search's scope are returned. Plain-text queries use the
+/// folder's search index once it is built, ranked with BM25, and only read
+/// the notes whose words contain the query's words: whole words for
+/// whole-word searches, anywhere inside an indexed word ("ust" in "rust")
+/// otherwise. Files the index doesn't cover (other extensions, hidden
+/// folders) are always read. Regex queries, and any search before the index
+/// is ready, scan every file in scope.
+///
+/// `index_query` is the text every match contains, if known.
+pub(crate) fn search_candidates(
+    index_query: Option<&str>,
+    dir: &Path,
+    options: &SearchOptions,
+    search_index: &SearchIndexHandle,
+) -> Result<Vec<PathBuf>, String> {
+    let scope = SearchScope::for_options(dir, options)?;
+    let files = scope.files();
+
+    let candidates = match index_query {
+        Some(query) if !options.use_regex => {
+            let state = search_index.lock()
+                .map_err(|e| format!("Failed to acquire search index lock: {}", e))?;
+            state
+                .rank(dir, query, !options.whole_word)
+                .map(|ranked| order_candidates(&files, &ranked, |path| state.is_indexed(path)))
+        }
+        _ => None,
+    };
+
+    Ok(candidates.unwrap_or(files))
+}
+
+/// Ranked files in scope first, then the files in scope the index doesn't cover
+fn order_candidates(files: &[PathBuf], ranked: &[PathBuf], is_indexed: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let in_scope: HashSet<&PathBuf> = files.iter().collect();
+
+    ranked
+        .iter()
+        .filter(|path| in_scope.contains(path))
+        .chain(files.iter().filter(|path| !is_indexed(path)))
+        .cloned()
+        .collect()
+}
+
+/// How a directory search decides which files match
+enum FileMatcher {
+    Pattern(Regex),
+    Query(SearchQuery),
+}
+
+impl FileMatcher {
+    fn new(query: &str, options: &SearchOptions) -> Result<Self, String> {
+        if options.use_query {
+            SearchQuery::parse(query, options).map(FileMatcher::Query)
+        } else {
+            build_search_regex(query, options).map(FileMatcher::Pattern)
+        }
+    }
+
+    /// Text to look up in the search index
+    fn index_query(&self, query: &str) -> Option<String> {
+        match self {
+            FileMatcher::Pattern(_) => Some(query.to_string()),
+            FileMatcher::Query(query) => query.required_text(),
+        }
+    }
+
+    /// Search one file of the folder `root`, skipping files we can't read
+    fn search_file(&self, path: &Path, root: &Path) -> Option<FileSearchResult> {
+        let re = match self {
+            FileMatcher::Pattern(re) => re,
+            FileMatcher::Query(query) => return query.search_file(path, root),
+        };
+
+        let content = fs::read_to_string(path).ok()?;
+        let matches = find_matches(re, &content);
+
+        (!matches.is_empty()).then(|| FileSearchResult {
+            file_path: path.to_string_lossy().to_string(),
+            matches,
+        })
+    }
+}
+
+pub(crate) fn checked_search_dir(dir_path: &str) -> Result<&Path, String> {
+    let path = Path::new(dir_path);
+    if !path.exists() || !path.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+    Ok(path)
+}
+
+/// Search across all files in a directory
+#[tauri::command]
+pub fn search_in_directory(
+    query: String,
+    dir_path: String,
+    options: SearchOptions,
+    search_index: State<SearchIndexHandle>,
+) -> Result<Vec<FileSearchResult>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let path = checked_search_dir(&dir_path)?;
+    let matcher = FileMatcher::new(&query, &options)?;
+    let index_query = matcher.index_query(&query);
+    let candidates = search_candidates(index_query.as_deref(), path, &options, &search_index)?;
+
+    Ok(candidates.iter().filter_map(|entry_path| matcher.search_file(entry_path, path)).collect())
+}
+
+/// A file's matches, sent while a streaming search runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultEvent {
+    pub search_id: u64,
+    /// Position of the file among the candidates; results arrive out of
+    /// order, and indexed searches put the best matches first
+    pub rank: usize,
+    pub result: FileSearchResult,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchProgressEvent {
+    pub search_id: u64,
+    pub scanned: usize,
+    pub total: usize,
+    /// Set on the last event of a search
+    pub done: bool,
+    pub cancelled: bool,
+}
+
+/// Report progress after this many files
+const PROGRESS_INTERVAL: usize = 50;
+
+/// Cancellation flags of the streaming searches still running
+pub struct SearchTasks {
+    next_id: u64,
+    running: HashMap<u64, Arc<AtomicBool>>,
+}
+
+impl SearchTasks {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            running: HashMap::new(),
+        }
+    }
+
+    /// Register a new search, cancelling the ones still running
+    fn start(&mut self) -> (u64, Arc<AtomicBool>) {
+        self.cancel_all();
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running.insert(id, Arc::clone(&cancelled));
+        (id, cancelled)
+    }
+
+    fn cancel(&mut self, id: u64) {
+        if let Some(cancelled) = self.running.remove(&id) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn cancel_all(&mut self) {
+        for (_, cancelled) in self.running.drain() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// Global state wrapped in Arc<Mutex<>> for thread-safe access
+pub type SearchTasksHandle = Arc<Mutex<SearchTasks>>;
+
+pub fn create_search_tasks() -> SearchTasksHandle {
+    Arc::new(Mutex::new(SearchTasks::new()))
+}
+
+/// Search files in parallel, handing each file's matches to `on_result` as
+/// soon as it is found and the number of files scanned to `on_progress`
+///
+/// Stops early once `cancelled` is set; returns the number of files scanned.
+fn stream_search(
+    matcher: &FileMatcher,
+    root: &Path,
+    candidates: &[PathBuf],
+    cancelled: &AtomicBool,
+    on_result: impl Fn(usize, FileSearchResult) + Sync,
+    on_progress: impl Fn(usize) + Sync,
+) -> usize {
+    let scanned = AtomicUsize::new(0);
+
+    candidates.par_iter().enumerate().for_each(|(rank, path)| {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if let Some(result) = matcher.search_file(path, root) {
+            on_result(rank, result);
+        }
+
+        let count = scanned.fetch_add(1, Ordering::Relaxed) + 1;
+        if count.is_multiple_of(PROGRESS_INTERVAL) {
+            on_progress(count);
+        }
+    });
+
+    scanned.into_inner()
+}
+
+/// Start a search across a directory that reports back through events
+///
+/// Each file's matches are emitted as `search-result` and progress as
+/// `search-progress`, ending with one marked `done`. Starting a search
+/// cancels any still running, so a new keystroke replaces the previous
+/// query. Returns the id carried by this search's events.
+#[tauri::command]
+pub fn start_directory_search(
+    query: String,
+    dir_path: String,
+    options: SearchOptions,
+    app_handle: AppHandle,
+    search_index: State<SearchIndexHandle>,
+    search_tasks: State<SearchTasksHandle>,
+) -> Result<u64, String> {
+    let path = checked_search_dir(&dir_path)?;
+    let matcher = if query.is_empty() {
+        None
+    } else {
+        Some(FileMatcher::new(&query, &options)?)
+    };
+    let candidates = match &matcher {
+        Some(matcher) => {
+            let index_query = matcher.index_query(&query);
+            search_candidates(index_query.as_deref(), path, &options, &search_index)?
+        }
+        None => Vec::new(),
+    };
+    let root = path.to_path_buf();
+
+    let (search_id, cancelled) = search_tasks.lock()
+        .map_err(|e| format!("Failed to acquire search lock: {}", e))?
+        .start();
+    let search_tasks = Arc::clone(search_tasks.inner());
+
+    thread::spawn(move || {
+        let total = candidates.len();
+        let emit_progress = |scanned: usize, done: bool| {
+            let progress = SearchProgressEvent {
+                search_id,
+                scanned,
+                total,
+                done,
+                cancelled: cancelled.load(Ordering::Relaxed),
+            };
+            if let Err(e) = app_handle.emit("search-progress", progress) {
+                eprintln!("Failed to emit search progress: {}", e);
+            }
+        };
+
+        emit_progress(0, false);
+        // An empty query has nothing to look for
+        let scanned = match &matcher {
+            Some(matcher) => stream_search(
+                matcher,
+                &root,
+                &candidates,
+                &cancelled,
+                |rank, result| {
+                    let event = SearchResultEvent { search_id, rank, result };
+                    if let Err(e) = app_handle.emit("search-result", event) {
+                        eprintln!("Failed to emit search result: {}", e);
+                    }
+                },
+                |scanned| emit_progress(scanned, false),
+            ),
+            None => 0,
+        };
+
+        if let Ok(mut tasks) = search_tasks.lock() {
+            tasks.running.remove(&search_id);
+        }
+        emit_progress(scanned, true);
+    });
+
+    Ok(search_id)
+}
+
+/// Stop a streaming search; results already sent stay valid
+#[tauri::command]
+pub fn cancel_directory_search(
+    search_id: u64,
+    search_tasks: State<SearchTasksHandle>,
+) -> Result<(), String> {
+    let mut tasks = search_tasks.lock()
+        .map_err(|e| format!("Failed to acquire search lock: {}", e))?;
+
+    tasks.cancel(search_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_case_sensitive() {
+        let content = "Hello World\nhello world".to_string();
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: false,
+            use_regex: false,
+            ..Default::default()
+        };
+
+        let matches = search_in_content("Hello".to_string(), content, options).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, 1);
+    }
+
+    #[test]
+    fn test_search_case_insensitive() {
+        let content = "Hello World\nhello world".to_string();
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            ..Default::default()
+        };
+
+        let matches = search_in_content("hello".to_string(), content, options).unwrap();
+        assert_eq!(matches.len(), 2);
+    }
+
+    #[test]
+    fn test_search_whole_word() {
+        let content = "hello helloworld".to_string();
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: true,
+            use_regex: false,
+            ..Default::default()
+        };
+
+        let matches = search_in_content("hello".to_string(), content, options).unwrap();
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[test]
+    fn test_replace() {
+        let content = "Hello World\nHello Universe".to_string();
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            ..Default::default()
+        };
+
+        let result = replace_in_content(
+            "Hello".to_string(),
+            "Hi".to_string(),
+            content,
+            options,
+        ).unwrap();
+
+        assert_eq!(result.replaced_count, 2);
+        assert_eq!(result.new_content, "Hi World\nHi Universe");
+    }
+
+    #[test]
+    fn test_substring_candidates_skip_unmatched_notes() {
+        use crate::search_index::{create_search_index_state, SearchIndex};
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("rust.md"), "learning rust").unwrap();
+        fs::write(dir.join("other.md"), "nothing here").unwrap();
+        fs::write(dir.join("data.txt"), "rust").unwrap();
+
+        let mut index = SearchIndex::default();
+        index.sync_with_disk(dir);
+        let handle = create_search_index_state();
+        handle.lock().unwrap().set_index(dir, index);
+
+        let extensions = Some(vec!["md".to_string(), "txt".to_string()]);
+        let options = SearchOptions { extensions: extensions.clone(), ..Default::default() };
+        let candidates = |query: &str, options: &SearchOptions| {
+            let mut candidates = search_candidates(Some(query), dir, options, &handle).unwrap();
+            candidates.sort();
+            candidates
+        };
+
+        // "ust" falls inside "rust"; other.md can't match, so it is never read
+        assert_eq!(candidates("ust", &options), vec![dir.join("data.txt"), dir.join("rust.md")]);
+        assert_eq!(candidates("zzz", &options), vec![dir.join("data.txt")]);
+        let whole_word = SearchOptions { whole_word: true, extensions, ..Default::default() };
+        assert_eq!(candidates("ust", &whole_word), vec![dir.join("data.txt")]);
+    }
+
+    #[test]
+    fn test_stream_search() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let candidates: Vec<PathBuf> = (0..120)
+            .map(|i| {
+                let path = dir.join(format!("note-{}.md", i));
+                let content = if i % 3 == 0 { "needle here" } else { "nothing" };
+                fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            ..Default::default()
+        };
+        let matcher = FileMatcher::new("needle", &options).unwrap();
+        let found = Mutex::new(Vec::new());
+        let progress = AtomicUsize::new(0);
+
+        let scanned = stream_search(
+            &matcher,
+            dir,
+            &candidates,
+            &AtomicBool::new(false),
+            |rank, result| found.lock().unwrap().push((rank, result.matches.len())),
+            |_| {
+                progress.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        let mut found = found.into_inner().unwrap();
+        found.sort();
+        assert_eq!(scanned, 120);
+        assert_eq!(found.len(), 40);
+        assert_eq!(found[1], (3, 1));
+        assert_eq!(progress.into_inner(), 120 / PROGRESS_INTERVAL);
+
+        // A cancelled search stops before reading anything
+        let scanned = stream_search(&matcher, dir, &candidates, &AtomicBool::new(true), |_, _| panic!(), |_| {});
+        assert_eq!(scanned, 0);
+    }
+}
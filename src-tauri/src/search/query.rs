@@ -0,0 +1,549 @@
+use super::{build_search_regex, find_matches, FileSearchResult, SearchMatch, SearchOptions};
+use crate::markdown::{extract_outline, extract_tags, parse_properties, tag_matches, NoteTag, OutlineHeading};
+use once_cell::sync::Lazy;
+use once_cell::unsync::OnceCell;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+use chrono::{DateTime, Datelike, Local};
+
+static FIELD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([A-Za-z_][\w.-]*):(.*)$").unwrap());
+static DATE_FILTER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(>=|<=|>|<|=)?(\d{4})-(\d{2})-(\d{2})$").unwrap());
+
+/// A search query in the query language
+///
+/// ```text
+/// tag:#meeting path:projects/ "exact phrase" -draft modified:>2026-01-01 heading:Decisions
+/// ```
+///
+/// Terms separated by spaces must all match; `OR` (or `AND`, the default)
+/// combines terms and parentheses group them. `-term` or `NOT term`
+/// excludes notes; a `-` before anything but a letter, `(` or `"` is part
+/// of the word, as in `-5` or `--force`. A `field:value` term filters on
+/// note metadata:
+///
+/// - `tag:` a tag or one nested under it, with or without the `#`
+/// - `path:` part of the note's path relative to the searched folder
+/// - `heading:` part of one of the note's headings
+/// - `modified:` the local modification date, as `YYYY-MM-DD` with an
+///   optional `>`, `>=`, `<`, `<=` or `=`
+/// - any other field is a frontmatter property, matched against part of
+///   its value (or any list item); an empty value matches notes that set
+///   the property at all. Notes containing the term as text match too, so
+///   `note:foo` still finds that text
+///
+/// A colon followed by `//`, as in `https://example.com`, is not a field.
+///
+/// Everything else is looked for in the note's content, following the
+/// case-sensitive and whole-word options. Quote a term to search for it
+/// as written, spaces, colons and all.
+#[derive(Debug)]
+pub struct SearchQuery {
+    expr: QueryExpr,
+}
+
+#[derive(Debug)]
+enum QueryExpr {
+    Text { source: String, pattern: Regex },
+    Tag(String),
+    Path(String),
+    Heading(Regex),
+    Modified(Ordering, bool, i64),
+    Property { key: String, value: String },
+    Not(Box<QueryExpr>),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Quoted(String),
+    Field(String, String),
+}
+
+/// Split a query into tokens
+fn tokenize_query(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    let mut depth = 0usize;
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                depth += 1;
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                depth = depth.saturating_sub(1);
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Quoted(read_quoted(&mut chars)));
+            }
+            // Only before something that starts a term, so `-5` and `--force` are words
+            '-' if chars.clone().nth(1).is_some_and(|next| next.is_alphabetic() || "(\"".contains(next)) => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    // A closing parenthesis only ends a word inside a group
+                    if c.is_whitespace() || (c == ')' && depth > 0) {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' && word.ends_with(':') && FIELD_RE.is_match(&word) {
+                        word.push_str(&read_quoted(&mut chars));
+                        break;
+                    }
+                    word.push(c);
+                }
+
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => match FIELD_RE.captures(&word) {
+                        Some(cap) if !cap[2].starts_with("//") => Token::Field(cap[1].to_string(), cap[2].to_string()),
+                        _ => Token::Word(word),
+                    },
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Read up to the closing quote, or the end of an unfinished query
+fn read_quoted(chars: &mut Peekable<Chars>) -> String {
+    chars.by_ref().take_while(|&c| c != '"').collect()
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    options: &'a SearchOptions,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr, String> {
+        let mut terms = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            terms.push(self.parse_and()?);
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { QueryExpr::Or(terms) })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr, String> {
+        let mut terms = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(_) => {}
+            }
+            terms.push(self.parse_unary()?);
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { QueryExpr::And(terms) })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(QueryExpr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("Missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Word(text)) | Some(Token::Quoted(text)) => self.text(text),
+            Some(Token::Field(field, value)) => self.field(&field, value),
+            Some(Token::Close) => Err("Unexpected closing parenthesis".to_string()),
+            Some(Token::And) | Some(Token::Or) | None => {
+                Err("Expected a search term".to_string())
+            }
+        }
+    }
+
+    fn text(&self, text: String) -> Result<QueryExpr, String> {
+        let options = SearchOptions {
+            use_regex: false,
+            use_query: false,
+            ..self.options.clone()
+        };
+        let pattern = build_search_regex(&text, &options)?;
+        Ok(QueryExpr::Text { source: text, pattern })
+    }
+
+    fn field(&self, field: &str, value: String) -> Result<QueryExpr, String> {
+        let key = field.to_lowercase();
+        Ok(match key.as_str() {
+            "tag" => QueryExpr::Tag(value.trim_start_matches('#').trim_end_matches('/').to_string()),
+            "path" => QueryExpr::Path(value.replace('\\', "/").to_lowercase()),
+            "heading" => QueryExpr::Heading(
+                Regex::new(&format!("(?i){}", regex::escape(&value))).map_err(|e| e.to_string())?,
+            ),
+            "modified" => {
+                let cap = DATE_FILTER_RE
+                    .captures(&value)
+                    .ok_or_else(|| format!("Invalid date filter: {} (expected e.g. >2026-01-01)", value))?;
+                let day = days_from_civil(
+                    cap[2].parse().unwrap_or_default(),
+                    cap[3].parse().unwrap_or_default(),
+                    cap[4].parse().unwrap_or_default(),
+                )
+                .ok_or_else(|| format!("Invalid date: {}", &value[cap.get(2).unwrap().start()..]))?;
+
+                match cap.get(1).map_or("=", |op| op.as_str()) {
+                    ">" => QueryExpr::Modified(Ordering::Greater, false, day),
+                    ">=" => QueryExpr::Modified(Ordering::Greater, true, day),
+                    "<" => QueryExpr::Modified(Ordering::Less, false, day),
+                    "<=" => QueryExpr::Modified(Ordering::Less, true, day),
+                    _ => QueryExpr::Modified(Ordering::Equal, true, day),
+                }
+            }
+            // A colon in ordinary text looks like a property filter, so match the term as text too
+            _ => QueryExpr::Or(vec![
+                QueryExpr::Property { key: key.clone(), value: value.to_lowercase() },
+                self.text(format!("{}:{}", field, value))?,
+            ]),
+        })
+    }
+}
+
+/// Days since 1970-01-01 of a calendar date, if it exists
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = [31, if leap { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if !(1..=12).contains(&month) || day == 0 || day > month_days[month as usize - 1] {
+        return None;
+    }
+
+    // Howard Hinnant's days_from_civil
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let m = month as i64;
+    let day_of_year = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// What a query can look at in one note; metadata is only worked out if
+/// the query asks for it
+pub struct QueryNote<'a> {
+    pub relative_path: String,
+    pub content: &'a str,
+    /// Days since 1970-01-01 (UTC) of the last modification
+    pub modified_day: Option<i64>,
+    tags: OnceCell<Vec<NoteTag>>,
+    headings: OnceCell<Vec<OutlineHeading>>,
+    properties: OnceCell<Map<String, Value>>,
+}
+
+impl<'a> QueryNote<'a> {
+    pub fn new(relative_path: String, content: &'a str, modified_day: Option<i64>) -> Self {
+        Self {
+            relative_path,
+            content,
+            modified_day,
+            tags: OnceCell::new(),
+            headings: OnceCell::new(),
+            properties: OnceCell::new(),
+        }
+    }
+
+    fn tags(&self) -> &[NoteTag] {
+        self.tags.get_or_init(|| extract_tags(self.content))
+    }
+
+    fn headings(&self) -> &[OutlineHeading] {
+        self.headings.get_or_init(|| extract_outline(self.content))
+    }
+
+    fn properties(&self) -> &Map<String, Value> {
+        // Notes with broken frontmatter just have no properties
+        self.properties.get_or_init(|| {
+            parse_properties(self.content).map(|p| p.properties).unwrap_or_default()
+        })
+    }
+}
+
+fn property_matches(value: &Value, needle: &str) -> bool {
+    match value {
+        Value::String(s) => s.to_lowercase().contains(needle),
+        Value::Array(items) => items.iter().any(|item| property_matches(item, needle)),
+        Value::Number(n) => n.to_string().contains(needle),
+        Value::Bool(b) => b.to_string() == needle,
+        Value::Null => needle.is_empty(),
+        Value::Object(_) => needle.is_empty(),
+    }
+}
+
+impl QueryExpr {
+    fn matches(&self, note: &QueryNote) -> bool {
+        match self {
+            QueryExpr::Text { pattern, .. } => pattern.is_match(note.content),
+            QueryExpr::Tag(tag) => note.tags().iter().any(|t| tag_matches(&t.name, tag)),
+            QueryExpr::Path(part) => note.relative_path.to_lowercase().contains(part.as_str()),
+            QueryExpr::Heading(pattern) => note.headings().iter().any(|h| pattern.is_match(&h.text)),
+            QueryExpr::Modified(ordering, or_equal, day) => note
+                .modified_day
+                .is_some_and(|modified| modified.cmp(day) == *ordering || (*or_equal && modified == *day)),
+            QueryExpr::Property { key, value } => note
+                .properties()
+                .iter()
+                .any(|(k, v)| k.eq_ignore_ascii_case(key) && (value.is_empty() || property_matches(v, value))),
+            QueryExpr::Not(expr) => !expr.matches(note),
+            QueryExpr::And(exprs) => exprs.iter().all(|expr| expr.matches(note)),
+            QueryExpr::Or(exprs) => exprs.iter().any(|expr| expr.matches(note)),
+        }
+    }
+
+    /// Matches to show for the terms that aren't negated
+    fn collect_highlights(&self, note: &QueryNote, matches: &mut Vec<SearchMatch>) {
+        match self {
+            QueryExpr::Text { pattern, .. } => matches.extend(find_matches(pattern, note.content)),
+            QueryExpr::Tag(tag) => matches.extend(
+                note.tags()
+                    .iter()
+                    .filter(|t| tag_matches(&t.name, tag))
+                    .map(|t| SearchMatch {
+                        line: t.line + 1,
+                        column: t.range.start + 1,
+                        length: t.range.len(),
+                        text: t.name.clone(),
+                        line_text: t.line_text.clone(),
+                    }),
+            ),
+            QueryExpr::Heading(pattern) => {
+                let lines: Vec<&str> = note.content.lines().collect();
+                for heading in note.headings() {
+                    let line = lines[heading.line];
+                    // Formatting moves the text around; highlight within the raw line if it's there
+                    let (text, offset) = match line.find(heading.text.as_str()) {
+                        Some(offset) => (heading.text.as_str(), offset),
+                        None => (line, 0),
+                    };
+                    if let Some(m) = pattern.find(text) {
+                        matches.push(SearchMatch {
+                            line: heading.line + 1,
+                            column: offset + m.start() + 1,
+                            length: m.len(),
+                            text: m.as_str().to_string(),
+                            line_text: line.to_string(),
+                        });
+                    }
+                }
+            }
+            QueryExpr::And(exprs) | QueryExpr::Or(exprs) => {
+                for expr in exprs {
+                    expr.collect_highlights(note, matches);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn collect_required_words(&self, words: &mut Vec<String>) {
+        match self {
+            QueryExpr::Text { source, .. } => words.push(source.clone()),
+            QueryExpr::And(exprs) => {
+                for expr in exprs {
+                    expr.collect_required_words(words);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Parse a query; text terms follow the case-sensitive and whole-word options
+    pub fn parse(query: &str, options: &SearchOptions) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize_query(query),
+            pos: 0,
+            options,
+        };
+        let expr = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err("Unexpected closing parenthesis".to_string());
+        }
+
+        Ok(Self { expr })
+    }
+
+    /// Whether a note matches the query
+    pub fn matches(&self, note: &QueryNote) -> bool {
+        self.expr.matches(note)
+    }
+
+    /// Where the query's terms match a note's content, in document order
+    pub fn highlights(&self, note: &QueryNote) -> Vec<SearchMatch> {
+        let mut matches = Vec::new();
+        self.expr.collect_highlights(note, &mut matches);
+        matches.sort_by_key(|m| (m.line, m.column, m.length));
+        matches.dedup_by_key(|m| (m.line, m.column, m.length));
+        matches
+    }
+
+    /// Text every matching note has to contain, for narrowing the files to
+    /// read with the search index; `None` when any note might match
+    pub fn required_text(&self) -> Option<String> {
+        let mut words = Vec::new();
+        self.expr.collect_required_words(&mut words);
+        (!words.is_empty()).then(|| words.join(" "))
+    }
+
+    /// Search one file of a folder, skipping files we can't read
+    pub fn search_file(&self, path: &Path, root: &Path) -> Option<FileSearchResult> {
+        let content = fs::read_to_string(path).ok()?;
+        // Dates in queries are local dates, so the day is taken in local time too
+        let modified_day = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| {
+                let date = DateTime::<Local>::from(time).date_naive();
+                days_from_civil(date.year() as i64, date.month(), date.day())
+            });
+        let relative_path = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let note = QueryNote::new(relative_path, &content, modified_day);
+        self.matches(&note).then(|| FileSearchResult {
+            file_path: path.to_string_lossy().to_string(),
+            matches: self.highlights(&note),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> SearchQuery {
+        SearchQuery::parse(query, &SearchOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_query() {
+        assert_eq!(
+            tokenize_query(r#"heading:"Next steps" -(a OR "b c") f(x) modified:>2026-01-01"#),
+            vec![
+                Token::Field("heading".to_string(), "Next steps".to_string()),
+                Token::Not,
+                Token::Open,
+                Token::Word("a".to_string()),
+                Token::Or,
+                Token::Quoted("b c".to_string()),
+                Token::Close,
+                Token::Word("f(x)".to_string()),
+                Token::Field("modified".to_string(), ">2026-01-01".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize_query("https://example.com"),
+            vec![Token::Word("https://example.com".to_string())]
+        );
+        assert_eq!(
+            tokenize_query("-5 --force - -tag:x"),
+            vec![
+                Token::Word("-5".to_string()),
+                Token::Word("--force".to_string()),
+                Token::Word("-".to_string()),
+                Token::Not,
+                Token::Field("tag".to_string(), "x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_matching() {
+        let content = "---\nstatus: In Progress\nowners: [ana, ben]\n---\n\
+                       # Meeting notes #meeting/weekly\n\n\
+                       ## Decisions\nShip the exact phrase today.\n\
+                       ```\n# not a heading\n```\n\
+                       See note:foo at https://example.com/x\n";
+        let day = days_from_civil(2026, 3, 1).unwrap();
+        let note = QueryNote::new("projects/alpha.md".to_string(), content, Some(day));
+
+        let cases = [
+            (r#"tag:#meeting path:projects/ "exact phrase" -draft modified:>2026-01-01 heading:Decisions"#, true),
+            ("tag:meeting/weekly heading:decisions", true),
+            ("heading:\"not a heading\"", false),
+            ("ship -today", false),
+            ("draft OR (ship AND status:progress)", true),
+            ("NOT owners:carl owners:BEN", true),
+            ("status: priority:", false),
+            ("modified:2026-03-01 modified:<=2026-03-01", true),
+            ("modified:<2026-03-01", false),
+            ("path:archive/ OR tag:meetings", false),
+            ("note:foo https://example.com/x", true),
+            ("note:bar", false),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(parse(query).matches(&note), expected, "{}", query);
+        }
+
+        let highlights = parse("tag:meeting heading:Decisions -notes phrase").highlights(&note);
+        let found: Vec<(usize, usize, &str)> = highlights
+            .iter()
+            .map(|m| (m.line, m.column, m.text.as_str()))
+            .collect();
+        assert_eq!(found, vec![(5, 18, "meeting/weekly"), (7, 4, "Decisions"), (8, 16, "phrase")]);
+
+        assert_eq!(parse("a -b (c OR d) \"e f\"").required_text(), Some("a e f".to_string()));
+        assert_eq!(parse("tag:x").required_text(), None);
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let options = SearchOptions::default();
+        assert!(SearchQuery::parse("(a OR b", &options).is_err());
+        assert!(SearchQuery::parse("a OR", &options).is_err());
+        assert!(SearchQuery::parse("modified:>2026-02-30", &options).is_err());
+        assert!(SearchQuery::parse("modified:yesterday", &options).is_err());
+        assert!(SearchQuery::parse("", &options).is_err());
+        assert_eq!(days_from_civil(1970, 1, 1), Some(0));
+        assert_eq!(days_from_civil(2000, 3, 1), Some(11_017));
+    }
+}
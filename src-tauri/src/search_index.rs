@@ -0,0 +1,568 @@
+use crate::atomic_temp_path;
+use crate::config::get_loom_dir;
+use crate::search::markdown_files;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Saved ahead of the index; bump when the layout changes so older indexes are rebuilt
+const INDEX_VERSION: u32 = 2;
+const INDEX_FILE: &str = "search-index.bin";
+/// How often a changed index is written back to `.loom/`
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Lowercased words of a text, as stored in the index
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Modification time (ms) and size of a file when it was indexed
+type FileStamp = (u64, u64);
+/// Note path with its word counts, ready to be inserted
+type CountedNote = (String, HashMap<String, u32>, FileStamp);
+
+fn word_counts(content: &str) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for word in tokenize(content) {
+        *counts.entry(word).or_default() += 1;
+    }
+    counts
+}
+
+/// Modification time and size of a file, used to spot stale index entries
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((modified.as_millis() as u64, metadata.len()))
+}
+
+/// Workspace-relative path, skipping hidden files and folders (`.loom` included)
+fn visible_relative_path(root: &Path, path: &Path) -> Option<String> {
+    let parts: Vec<String> = path
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    let is_hidden = parts.iter().any(|part| part.starts_with('.'));
+    (!is_hidden && !parts.is_empty()).then(|| parts.join("/"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedNote {
+    /// Workspace-relative path
+    path: String,
+    /// Number of words, for length normalization
+    length: u32,
+    stamp: FileStamp,
+    /// Distinct words of the note, whose postings list it
+    words: Vec<String>,
+}
+
+/// Inverted index over the words of every note in a folder
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// Note id -> note; removed notes leave a gap that is reused
+    notes: Vec<Option<IndexedNote>>,
+    /// Word -> (note id, occurrences), sorted by note id
+    postings: BTreeMap<String, Vec<(u32, u32)>>,
+    total_length: u64,
+    #[serde(skip)]
+    ids: HashMap<String, u32>,
+    #[serde(skip)]
+    free_ids: Vec<u32>,
+}
+
+impl SearchIndex {
+    /// Load a saved index, or `None` if it is missing, unreadable or outdated
+    pub fn load(path: &Path) -> Option<Self> {
+        let bytes = fs::read(path).ok()?;
+        let (version, mut index): (u32, Self) = bincode::deserialize(&bytes).ok()?;
+        if version != INDEX_VERSION {
+            return None;
+        }
+
+        for (id, note) in index.notes.iter().enumerate() {
+            match note {
+                Some(note) => {
+                    index.ids.insert(note.path.clone(), id as u32);
+                }
+                None => index.free_ids.push(id as u32),
+            }
+        }
+        Some(index)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = bincode::serialize(&(INDEX_VERSION, self))
+            .map_err(|e| format!("Failed to serialize search index: {}", e))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create .loom directory: {}", e))?;
+        }
+
+        let temp_path = atomic_temp_path(path)?;
+        fs::write(&temp_path, bytes)
+            .map_err(|e| format!("Failed to write search index: {}", e))?;
+        fs::rename(&temp_path, path).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            format!("Failed to write search index: {}", e)
+        })
+    }
+
+    fn is_current(&self, relative_path: &str, stamp: FileStamp) -> bool {
+        self.ids
+            .get(relative_path)
+            .and_then(|id| self.notes[*id as usize].as_ref())
+            .is_some_and(|note| note.stamp == stamp)
+    }
+
+    /// Add or replace a note
+    pub fn index_note(&mut self, relative_path: &str, content: &str, stamp: FileStamp) {
+        self.remove_notes(&[relative_path.to_string()]);
+        self.insert_note(relative_path, word_counts(content), stamp);
+    }
+
+    /// Add a note that isn't in the index yet
+    fn insert_note(&mut self, relative_path: &str, counts: HashMap<String, u32>, stamp: FileStamp) {
+        let id = match self.free_ids.pop() {
+            Some(id) => id,
+            None => {
+                self.notes.push(None);
+                (self.notes.len() - 1) as u32
+            }
+        };
+
+        let length = counts.values().sum();
+        let words = counts.keys().cloned().collect();
+        for (word, count) in counts {
+            let postings = self.postings.entry(word).or_default();
+            let at = postings.partition_point(|(note, _)| *note < id);
+            postings.insert(at, (id, count));
+        }
+
+        self.notes[id as usize] = Some(IndexedNote {
+            path: relative_path.to_string(),
+            length,
+            stamp,
+            words,
+        });
+        self.ids.insert(relative_path.to_string(), id);
+        self.total_length += length as u64;
+    }
+
+    /// Remove a note, or every note under a folder
+    pub fn remove_path(&mut self, relative_path: &str) {
+        let folder_prefix = format!("{}/", relative_path);
+        let removed: Vec<String> = self
+            .ids
+            .keys()
+            .filter(|path| *path == relative_path || path.starts_with(&folder_prefix))
+            .cloned()
+            .collect();
+        self.remove_notes(&removed);
+    }
+
+    fn remove_notes(&mut self, relative_paths: &[String]) {
+        for path in relative_paths {
+            let Some(id) = self.ids.remove(path) else {
+                continue;
+            };
+            self.free_ids.push(id);
+            let Some(note) = self.notes[id as usize].take() else {
+                continue;
+            };
+            self.total_length -= note.length as u64;
+
+            // Posting lists are sorted by note id
+            for word in note.words {
+                let Some(postings) = self.postings.get_mut(&word) else {
+                    continue;
+                };
+                if let Ok(at) = postings.binary_search_by_key(&id, |(note, _)| *note) {
+                    postings.remove(at);
+                }
+                if postings.is_empty() {
+                    self.postings.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Bring the index in line with the notes on disk, re-reading only the
+    /// notes whose modification time or size changed; returns whether
+    /// anything changed
+    pub fn sync_with_disk(&mut self, root: &Path) -> bool {
+        let on_disk: Vec<(String, PathBuf, FileStamp)> = markdown_files(root)
+            .filter_map(|path| {
+                let relative_path = visible_relative_path(root, &path)?;
+                let stamp = file_stamp(&path)?;
+                Some((relative_path, path, stamp))
+            })
+            .collect();
+
+        let present: HashSet<&str> = on_disk.iter().map(|(relative_path, _, _)| relative_path.as_str()).collect();
+        let missing: Vec<String> = self.ids.keys().filter(|path| !present.contains(path.as_str())).cloned().collect();
+        self.remove_notes(&missing);
+
+        // Reading and counting dominates, so do it in parallel
+        let changed: Vec<CountedNote> = on_disk
+            .par_iter()
+            .filter(|(relative_path, _, stamp)| !self.is_current(relative_path, *stamp))
+            .filter_map(|(relative_path, path, stamp)| {
+                let content = fs::read_to_string(path).ok()?; // Unreadable notes aren't searchable
+                Some((relative_path.clone(), word_counts(&content), *stamp))
+            })
+            .collect();
+
+        let changed_paths: Vec<String> = changed.iter().map(|(relative_path, _, _)| relative_path.clone()).collect();
+        self.remove_notes(&changed_paths);
+        for (relative_path, counts, stamp) in changed {
+            self.insert_note(&relative_path, counts, stamp);
+        }
+        !missing.is_empty() || !changed_paths.is_empty()
+    }
+
+    /// Re-examine a changed path on disk
+    pub fn update_path(&mut self, root: &Path, path: &Path) {
+        let Some(relative_path) = visible_relative_path(root, path) else {
+            return;
+        };
+
+        self.remove_path(&relative_path);
+        if path.is_dir() {
+            self.sync_folder(root, path);
+        } else if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            if let (Ok(content), Some(stamp)) = (fs::read_to_string(path), file_stamp(path)) {
+                self.index_note(&relative_path, &content, stamp);
+            }
+        }
+    }
+
+    fn sync_folder(&mut self, root: &Path, dir: &Path) {
+        for path in markdown_files(dir) {
+            let (Some(relative_path), Some(stamp)) = (visible_relative_path(root, &path), file_stamp(&path)) else {
+                continue;
+            };
+            if let Ok(content) = fs::read_to_string(&path) {
+                self.index_note(&relative_path, &content, stamp);
+            }
+        }
+    }
+
+    /// Notes containing every word of the query, best BM25 score first
+    ///
+    /// With `substring`, a query word also matches longer words containing
+    /// it, so partly typed words and substring searches still find every
+    /// note they could match.
+    pub fn rank(&self, query: &str, substring: bool) -> Vec<(String, f64)> {
+        let mut words: Vec<String> = tokenize(query).collect();
+        words.sort();
+        words.dedup();
+        if words.is_empty() || self.ids.is_empty() {
+            return Vec::new();
+        }
+
+        let note_count = self.ids.len() as f64;
+        let average_length = (self.total_length as f64 / note_count).max(1.0);
+        // Note id -> (score, query words matched)
+        let mut scores: HashMap<u32, (f64, usize)> = HashMap::new();
+
+        for word in &words {
+            let matching: Vec<&Vec<(u32, u32)>> = if substring {
+                self.postings
+                    .iter()
+                    .filter(|(indexed, _)| indexed.contains(word.as_str()))
+                    .map(|(_, postings)| postings)
+                    .collect()
+            } else {
+                self.postings.get(word).into_iter().collect()
+            };
+
+            let mut frequencies: HashMap<u32, u32> = HashMap::new();
+            for (id, count) in matching.into_iter().flatten() {
+                *frequencies.entry(*id).or_default() += count;
+            }
+
+            let document_frequency = frequencies.len() as f64;
+            let idf = ((note_count - document_frequency + 0.5) / (document_frequency + 0.5) + 1.0).ln();
+
+            for (id, frequency) in frequencies {
+                let length = self.notes[id as usize].as_ref().map_or(0, |note| note.length) as f64;
+                let frequency = frequency as f64;
+                let score = idf * frequency * (K1 + 1.0)
+                    / (frequency + K1 * (1.0 - B + B * length / average_length));
+
+                let entry = scores.entry(id).or_default();
+                entry.0 += score;
+                entry.1 += 1;
+            }
+        }
+
+        let mut ranked: Vec<(String, f64)> = scores
+            .into_iter()
+            .filter(|(_, (_, matched))| *matched == words.len())
+            .filter_map(|(id, (score, _))| Some((self.notes[id as usize].as_ref()?.path.clone(), score)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+/// Search index of the open folder, built and saved in the background
+pub struct SearchIndexState {
+    root: Option<PathBuf>,
+    /// `None` while the index is being built; searches scan files meanwhile.
+    /// Shared with the flush thread while it saves a snapshot.
+    index: Option<Arc<SearchIndex>>,
+    /// Paths changed while the index was being built
+    pending: Vec<PathBuf>,
+    /// Changed since it was last saved
+    dirty: bool,
+    /// Incremented per opened folder, so background work for an old one stops
+    generation: u64,
+}
+
+impl SearchIndexState {
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            index: None,
+            pending: Vec::new(),
+            dirty: false,
+            generation: 0,
+        }
+    }
+
+    /// Ranked notes under `dir` for a plain-text query, or `None` if the
+    /// index can't answer it (still building, another folder, no words)
+    pub fn rank(&self, dir: &Path, query: &str, substring: bool) -> Option<Vec<PathBuf>> {
+        let root = self.root.as_ref()?;
+        let index = self.index.as_ref()?;
+        if !dir.starts_with(root) || tokenize(query).next().is_none() {
+            return None;
+        }
+
+        Some(
+            index
+                .rank(query, substring)
+                .into_iter()
+                .map(|(relative_path, _)| root.join(relative_path))
+                .filter(|path| path.starts_with(dir))
+                .collect(),
+        )
+    }
+
+    /// Use a ready-built index for the folder `root`
+    #[cfg(test)]
+    pub fn set_index(&mut self, root: &Path, index: SearchIndex) {
+        self.root = Some(root.to_path_buf());
+        self.index = Some(Arc::new(index));
+    }
+
+    /// Whether the index has an entry for `path`; searches read the files
+    /// it doesn't cover in full
+    pub fn is_indexed(&self, path: &Path) -> bool {
+        let (Some(root), Some(index)) = (&self.root, &self.index) else {
+            return false;
+        };
+        visible_relative_path(root, path).is_some_and(|relative| index.ids.contains_key(&relative))
+    }
+
+    /// Apply file system changes to the index
+    pub fn update_paths(&mut self, paths: &[PathBuf]) {
+        let Some(root) = &self.root else {
+            return;
+        };
+
+        match &mut self.index {
+            Some(index) => {
+                // Copies the index only while a save still holds the old one
+                let index = Arc::make_mut(index);
+                for path in paths {
+                    index.update_path(root, path);
+                }
+                self.dirty = true;
+            }
+            None => self.pending.extend_from_slice(paths),
+        }
+    }
+}
+
+// Global state wrapped in Arc<Mutex<>> for thread-safe access
+pub type SearchIndexHandle = Arc<Mutex<SearchIndexState>>;
+
+pub fn create_search_index_state() -> SearchIndexHandle {
+    Arc::new(Mutex::new(SearchIndexState::new()))
+}
+
+fn index_file_path(root: &Path) -> Option<PathBuf> {
+    get_loom_dir(Some(root.to_string_lossy().to_string()))
+        .ok()
+        .map(|loom_dir| loom_dir.join(INDEX_FILE))
+}
+
+/// Start indexing a newly opened folder in the background
+pub fn open_search_index(handle: &SearchIndexHandle, root: &Path) -> Result<(), String> {
+    let generation = {
+        let mut state = handle.lock()
+            .map_err(|e| format!("Failed to acquire search index lock: {}", e))?;
+
+        state.generation += 1;
+        state.root = Some(root.to_path_buf());
+        state.index = None;
+        state.pending.clear();
+        state.dirty = false;
+        state.generation
+    };
+
+    let handle = Arc::clone(handle);
+    let root = root.to_path_buf();
+    thread::spawn(move || build_and_flush(handle, root, generation));
+    Ok(())
+}
+
+/// Load or build the index, then save it periodically while the folder is open
+///
+/// Changes made since the last save are caught up from file modification
+/// times the next time the folder is opened.
+fn build_and_flush(handle: SearchIndexHandle, root: PathBuf, generation: u64) {
+    let index_path = index_file_path(&root);
+    let mut index = index_path
+        .as_deref()
+        .and_then(SearchIndex::load)
+        .unwrap_or_default();
+    let mut changed = index.sync_with_disk(&root);
+
+    {
+        let Ok(mut state) = handle.lock() else {
+            return;
+        };
+        if state.generation != generation {
+            return;
+        }
+
+        for path in std::mem::take(&mut state.pending) {
+            index.update_path(&root, &path);
+            changed = true;
+        }
+        state.index = Some(Arc::new(index));
+        state.dirty = changed;
+    }
+
+    loop {
+        // Save a snapshot outside the lock, so searches and updates don't wait on the disk
+        let snapshot = {
+            let Ok(mut state) = handle.lock() else {
+                return;
+            };
+            if state.generation != generation {
+                return;
+            }
+
+            let snapshot = state.index.clone().filter(|_| state.dirty);
+            state.dirty = false;
+            snapshot
+        };
+
+        if let (Some(index), Some(index_path)) = (snapshot, &index_path) {
+            if let Err(e) = index.save(index_path) {
+                eprintln!("{}", e);
+            }
+        }
+
+        thread::sleep(FLUSH_INTERVAL);
+    }
+}
+
+/// Update the search index after a file system event
+pub fn sync_search_index(app_handle: &AppHandle, paths: &[PathBuf]) {
+    let search_index = app_handle.state::<SearchIndexHandle>();
+
+    let Ok(mut state) = search_index.lock() else {
+        eprintln!("Failed to acquire search index lock");
+        return;
+    };
+
+    state.update_paths(paths);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_and_remove() {
+        let mut index = SearchIndex::default();
+        index.index_note("a.md", "Rust search index. Rust everywhere.", (1, 1));
+        index.index_note("b.md", "A long note that mentions rust once among many other words here", (1, 1));
+        index.index_note("c.md", "Nothing relevant", (1, 1));
+
+        let ranked: Vec<String> = index.rank("rust", false).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(ranked, vec!["a.md", "b.md"]);
+
+        // Every word must match; prefixes only with `prefix`
+        assert_eq!(index.rank("rust words", false).len(), 1);
+        assert!(index.rank("ind", false).is_empty());
+        assert_eq!(index.rank("ind", true)[0].0, "a.md");
+        assert_eq!(index.rank("dex", true)[0].0, "a.md");
+
+        index.index_note("a.md", "Now about something else", (2, 1));
+        assert_eq!(index.rank("rust", false).len(), 1);
+        index.remove_path("b.md");
+        assert!(index.rank("rust", false).is_empty());
+        assert_eq!(index.ids.len(), 2);
+        // Words only the removed notes had are gone from the vocabulary
+        assert!(!index.postings.contains_key("rust") && !index.postings.contains_key("mentions"));
+    }
+
+    #[test]
+    fn test_sync_and_persist() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::create_dir_all(root.join(".loom")).unwrap();
+        fs::write(root.join("notes/One.md"), "alpha beta").unwrap();
+        fs::write(root.join("Two.md"), "beta gamma").unwrap();
+        fs::write(root.join(".loom/Hidden.md"), "beta").unwrap();
+
+        let mut index = SearchIndex::default();
+        assert!(index.sync_with_disk(root));
+        assert!(!index.sync_with_disk(root));
+        assert_eq!(index.rank("beta", false).len(), 2);
+
+        let index_path = root.join(".loom").join(INDEX_FILE);
+        index.save(&index_path).unwrap();
+        let mut loaded = SearchIndex::load(&index_path).unwrap();
+        assert_eq!(loaded.ids.len(), 2);
+
+        fs::remove_file(root.join("Two.md")).unwrap();
+        fs::write(root.join("notes/One.md"), "alpha beta delta").unwrap();
+        loaded.sync_with_disk(root);
+        loaded.update_path(root, &root.join("notes/One.md"));
+        assert_eq!(loaded.rank("beta", false).len(), 1);
+        assert_eq!(loaded.rank("delta", false)[0].0, "notes/One.md");
+
+        let mut state = SearchIndexState::new();
+        state.root = Some(root.to_path_buf());
+        state.index = Some(Arc::new(loaded));
+        assert_eq!(state.rank(&root.join("notes"), "alpha", true), Some(vec![root.join("notes/One.md")]));
+        assert_eq!(state.rank(root, "--", true), None);
+        assert!(state.is_indexed(&root.join("notes/One.md")));
+        assert!(!state.is_indexed(&root.join("Two.md")));
+    }
+}
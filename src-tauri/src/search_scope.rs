@@ -0,0 +1,226 @@
+use crate::config::load_app_config;
+use crate::search::SearchOptions;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the workspace-specific ignore file, read like `.gitignore`
+const LOOM_IGNORE_FILE: &str = ".loomignore";
+
+/// Which files a directory search looks at, stored in config.json
+///
+/// Searches can override any of these through their `SearchOptions`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchDefaults {
+    /// File extensions searched, without the dot
+    pub extensions: Vec<String>,
+    /// Globs a file must match to be searched; empty searches everything
+    pub include: Vec<String>,
+    /// Globs of files and folders never searched
+    pub exclude: Vec<String>,
+    /// Larger files are skipped; 0 searches files of any size
+    pub max_file_size: u64,
+    /// Skip what `.gitignore` and `.loomignore` files ignore
+    pub respect_ignore_files: bool,
+}
+
+impl Default for SearchDefaults {
+    fn default() -> Self {
+        Self {
+            extensions: vec!["md".to_string()],
+            include: Vec::new(),
+            exclude: vec!["node_modules".to_string()],
+            max_file_size: 2 * 1024 * 1024,
+            respect_ignore_files: true,
+        }
+    }
+}
+
+impl SearchDefaults {
+    /// The search defaults configured for a folder
+    pub fn load(dir: &Path) -> Self {
+        load_app_config(Some(dir.to_string_lossy().to_string()))
+            .map(|config| config.search)
+            .unwrap_or_default()
+    }
+
+    /// Apply a search's own options on top of the defaults
+    ///
+    /// Exclude globs are added to the configured ones; every other option
+    /// replaces its default.
+    pub fn with_options(&self, options: &SearchOptions) -> Self {
+        let mut exclude = self.exclude.clone();
+        exclude.extend(options.exclude.iter().flatten().cloned());
+
+        Self {
+            extensions: options.extensions.clone().unwrap_or_else(|| self.extensions.clone()),
+            include: options.include.clone().unwrap_or_else(|| self.include.clone()),
+            exclude,
+            max_file_size: options.max_file_size.unwrap_or(self.max_file_size),
+            respect_ignore_files: options.respect_ignore_files.unwrap_or(self.respect_ignore_files),
+        }
+    }
+}
+
+/// The files under a directory that a search covers
+pub struct SearchScope {
+    root: PathBuf,
+    settings: SearchDefaults,
+    extensions: Vec<String>,
+    include: Option<Override>,
+    exclude: Override,
+}
+
+impl SearchScope {
+    /// Scope for searching `dir` with the given settings
+    ///
+    /// Fails when one of the globs is invalid.
+    pub fn new(dir: &Path, settings: SearchDefaults) -> Result<Self, String> {
+        // Extensions are written with or without the dot and matched ignoring case
+        let extensions = settings
+            .extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+
+        let include = if settings.include.is_empty() {
+            None
+        } else {
+            Some(build_globs(dir, settings.include.iter().map(|glob| glob.to_string()))?)
+        };
+        let exclude = build_globs(dir, settings.exclude.iter().map(|glob| format!("!{}", glob)))?;
+
+        Ok(Self {
+            root: dir.to_path_buf(),
+            settings,
+            extensions,
+            include,
+            exclude,
+        })
+    }
+
+    /// Scope for a search, using the folder's configured defaults
+    pub fn for_options(dir: &Path, options: &SearchOptions) -> Result<Self, String> {
+        Self::new(dir, SearchDefaults::load(dir).with_options(options))
+    }
+
+    /// Whether a file has one of the searched extensions
+    pub fn has_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+    }
+
+    /// Every file in scope, in directory order
+    ///
+    /// Hidden files and folders (including `.loom`) are never searched.
+    /// Excluded folders aren't descended into.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let respect = self.settings.respect_ignore_files;
+        let mut walker = WalkBuilder::new(&self.root);
+        walker
+            .hidden(true)
+            .follow_links(false)
+            .parents(respect)
+            .ignore(false)
+            .git_ignore(respect)
+            .git_global(respect)
+            .git_exclude(respect)
+            // Workspaces are often not repositories, but their .gitignore still counts
+            .require_git(false)
+            .overrides(self.exclude.clone())
+            .max_filesize((self.settings.max_file_size > 0).then_some(self.settings.max_file_size));
+        if respect {
+            walker.add_custom_ignore_filename(LOOM_IGNORE_FILE);
+        }
+
+        walker
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path())
+            .filter(|path| self.has_extension(path))
+            .filter(|path| {
+                self.include
+                    .as_ref()
+                    .is_none_or(|include| include.matched(path, false).is_whitelist())
+            })
+            .collect()
+    }
+}
+
+fn build_globs(root: &Path, globs: impl Iterator<Item = String>) -> Result<Override, String> {
+    let mut builder = OverrideBuilder::new(root);
+    for glob in globs {
+        builder.add(&glob)
+            .map_err(|e| format!("Invalid glob {}: {}", glob.trim_start_matches('!'), e))?;
+    }
+    builder.build().map_err(|e| format!("Failed to build search globs: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_search_scope_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        for dir in ["notes/drafts", "node_modules/pkg", ".loom", "archive"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "a.md", "b.MARKDOWN", "c.txt", "d.rs", "notes/e.md", "notes/drafts/f.md",
+            "node_modules/pkg/g.md", ".loom/h.md", "archive/i.md",
+        ] {
+            fs::write(root.join(file), "text").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "drafts/\n").unwrap();
+        fs::write(root.join(".loomignore"), "archive\n").unwrap();
+        fs::write(root.join("big.md"), "x".repeat(64)).unwrap();
+
+        let relative = |scope: SearchScope| {
+            let mut files: Vec<String> = scope
+                .files()
+                .iter()
+                .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            files.sort();
+            files
+        };
+
+        let settings = SearchDefaults {
+            extensions: vec!["md".to_string(), ".markdown".to_string()],
+            max_file_size: 32,
+            ..SearchDefaults::default()
+        };
+        assert_eq!(
+            relative(SearchScope::new(root, settings.clone()).unwrap()),
+            vec!["a.md", "b.MARKDOWN", "notes/e.md"]
+        );
+
+        let options = SearchOptions {
+            include: Some(vec!["notes/**".to_string()]),
+            respect_ignore_files: Some(false),
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            relative(SearchScope::new(root, settings.with_options(&options)).unwrap()),
+            vec!["notes/drafts/f.md", "notes/e.md"]
+        );
+
+        let options = SearchOptions {
+            extensions: Some(vec!["txt".to_string()]),
+            exclude: Some(vec!["*.txt".to_string()]),
+            ..SearchOptions::default()
+        };
+        assert!(relative(SearchScope::new(root, settings.with_options(&options)).unwrap()).is_empty());
+
+        let bad = SearchDefaults { include: vec!["{".to_string()], ..SearchDefaults::default() };
+        assert!(SearchScope::new(root, bad).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::markdown::{extract_tags, is_valid_tag, tag_matches, NoteTag};
+use crate::search::{FileSearchResult, SearchMatch};
+use crate::workspace::{WorkspaceState, WorkspaceStateHandle};
+use crate::{rewrite_files_atomic, FileRewrite};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tauri::State;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSummary {
+    /// Full tag name, e.g. `project/alpha`
+    pub name: String,
+    /// Notes using exactly this tag
+    pub count: usize,
+    /// Notes using this tag or any tag nested under it
+    pub nested_count: usize,
+}
+
+/// Tag given by the user, with an optional leading `#`
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').trim_end_matches('/').to_string()
+}
+
+/// Every tag in the workspace with note counts, sorted by name
+///
+/// Parents of nested tags are listed even when no note uses them directly,
+/// so the result can be shown as a tree. Tags differing only in case are
+/// counted together.
+pub fn summarize_tags(workspace: &WorkspaceState) -> Vec<TagSummary> {
+    let mut sources: Vec<(&String, &Vec<NoteTag>)> = workspace.note_tags().collect();
+    sources.sort_by(|a, b| a.0.cmp(b.0));
+
+    // Lowercased name -> (name as first written, direct count, nested count)
+    let mut summaries: BTreeMap<String, (String, usize, usize)> = BTreeMap::new();
+
+    for (_, tags) in sources {
+        let mut direct = HashSet::new();
+        let mut nested = HashSet::new();
+
+        for tag in tags {
+            direct.insert(tag.name.to_lowercase());
+            for (i, _) in tag.name.match_indices('/').chain([(tag.name.len(), "")]) {
+                let name = &tag.name[..i];
+                if nested.insert(name.to_lowercase()) {
+                    summaries.entry(name.to_lowercase()).or_insert_with(|| (name.to_string(), 0, 0)).2 += 1;
+                }
+            }
+        }
+
+        for key in direct {
+            if let Some(summary) = summaries.get_mut(&key) {
+                summary.1 += 1;
+            }
+        }
+    }
+
+    summaries
+        .into_values()
+        .map(|(name, count, nested_count)| TagSummary { name, count, nested_count })
+        .collect()
+}
+
+/// Notes tagged with a tag or a tag nested under it, with each occurrence
+pub fn tagged_files(workspace: &WorkspaceState, tag: &str) -> Vec<FileSearchResult> {
+    let mut sources: Vec<(&String, &Vec<NoteTag>)> = workspace.note_tags().collect();
+    sources.sort_by(|a, b| a.0.cmp(b.0));
+
+    sources
+        .into_iter()
+        .filter_map(|(source, tags)| {
+            let matches: Vec<SearchMatch> = tags
+                .iter()
+                .filter(|t| tag_matches(&t.name, tag))
+                .map(|t| SearchMatch {
+                    line: t.line + 1,
+                    column: t.range.start + 1,
+                    length: t.range.len(),
+                    text: t.name.clone(),
+                    line_text: t.line_text.clone(),
+                })
+                .collect();
+
+            if matches.is_empty() {
+                return None;
+            }
+            Some(FileSearchResult {
+                file_path: workspace.notes().absolute_path(source)?.to_string_lossy().to_string(),
+                matches,
+            })
+        })
+        .collect()
+}
+
+/// Rename a tag (and the tags nested under it) within a note's content
+///
+/// Returns `None` when the note doesn't use the tag. Only the tag names
+/// are replaced; everything else is left as it was.
+pub fn rename_tag_in_content(content: &str, old_tag: &str, new_tag: &str) -> Option<String> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+
+    let mut renamed: Vec<(usize, NoteTag)> = extract_tags(content)
+        .into_iter()
+        .filter(|tag| tag_matches(&tag.name, old_tag))
+        .map(|tag| (line_starts[tag.line] + tag.range.start, tag))
+        .collect();
+
+    if renamed.is_empty() {
+        return None;
+    }
+
+    // Replace from the end so earlier offsets stay valid
+    renamed.sort_by_key(|(start, _)| *start);
+    let depth = old_tag.split('/').count();
+    let mut updated = content.to_string();
+    for (start, tag) in renamed.iter().rev() {
+        // Matching ignores case, so keep the nested part by segment rather than by length
+        let new_name = match tag.name.splitn(depth + 1, '/').nth(depth) {
+            Some(nested) => format!("{}/{}", new_tag, nested),
+            None => new_tag.to_string(),
+        };
+        updated.replace_range(*start..start + tag.name.len(), &new_name);
+    }
+
+    Some(updated)
+}
+
+/// List every tag in the workspace with note counts
+#[tauri::command]
+pub fn list_tags(workspace: State<WorkspaceStateHandle>) -> Result<Vec<TagSummary>, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    Ok(summarize_tags(&workspace))
+}
+
+/// Find the notes using a tag, including tags nested under it
+#[tauri::command]
+pub fn get_files_for_tag(
+    tag: String,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<Vec<FileSearchResult>, String> {
+    let workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    Ok(tagged_files(&workspace, &normalize_tag(&tag)))
+}
+
+/// Rename a tag in every note using it, all or nothing
+///
+/// Every note is read before anything is written, and the new contents are
+/// swapped in together, so a failed write leaves no note renamed.
+pub fn rename_tag_in_workspace(
+    workspace: &mut WorkspaceState,
+    old_tag: &str,
+    new_tag: &str,
+) -> Result<Vec<String>, String> {
+    let candidates: Vec<PathBuf> = tagged_files(workspace, old_tag)
+        .into_iter()
+        .map(|file| PathBuf::from(file.file_path))
+        .collect();
+
+    let mut updates = Vec::new();
+    for path in candidates {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if let Some(updated) = rename_tag_in_content(&content, old_tag, new_tag) {
+            updates.push((path, content, updated));
+        }
+    }
+
+    let rewrites: Vec<FileRewrite> = updates
+        .iter()
+        .map(|(path, original, updated)| FileRewrite { path, original, updated })
+        .collect();
+    rewrite_files_atomic(&rewrites)?;
+
+    Ok(updates
+        .iter()
+        .map(|(path, _, _)| {
+            workspace.refresh_path(path);
+            path.to_string_lossy().to_string()
+        })
+        .collect())
+}
+
+/// Rename a tag across the workspace, returning the paths of the notes changed
+///
+/// Nested tags move along: renaming `project` to `work` turns
+/// `#project/alpha` into `#work/alpha`.
+#[tauri::command]
+pub fn rename_tag(
+    old_tag: String,
+    new_tag: String,
+    workspace: State<WorkspaceStateHandle>,
+) -> Result<Vec<String>, String> {
+    let old_tag = normalize_tag(&old_tag);
+    let new_tag = normalize_tag(&new_tag);
+    if !is_valid_tag(&new_tag) {
+        return Err(format!("Invalid tag name: {}", new_tag));
+    }
+
+    let mut workspace = workspace.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    rename_tag_in_workspace(&mut workspace, &old_tag, &new_tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_tag_in_content() {
+        let content = "---\r\ntags:\r\n  - project/alpha\r\n  - projects\r\n---\r\n\
+                       Working on #Project and #project/beta, not `#project`\r\n";
+        let updated = rename_tag_in_content(content, "project", "work").unwrap();
+
+        assert_eq!(
+            updated,
+            "---\r\ntags:\r\n  - work/alpha\r\n  - projects\r\n---\r\n\
+             Working on #work and #work/beta, not `#project`\r\n"
+        );
+        assert_eq!(rename_tag_in_content("No tags here", "project", "work"), None);
+
+        // YAML comments are left alone
+        assert_eq!(
+            rename_tag_in_content("---\ntags: [project] # project notes\n---\n", "project", "work").unwrap(),
+            "---\ntags: [work] # project notes\n---\n"
+        );
+    }
+
+    #[test]
+    fn test_summarize_tags() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::write(root.join("a.md"), "#project/alpha #project/alpha #todo").unwrap();
+        fs::write(root.join("b.md"), "---\ntags: [Project]\n---\n#project/beta").unwrap();
+
+        let mut workspace = WorkspaceState::new();
+        workspace.open(root);
+
+        let summary: Vec<(String, usize, usize)> = summarize_tags(&workspace)
+            .into_iter()
+            .map(|t| (t.name, t.count, t.nested_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("project".to_string(), 1, 2),
+                ("project/alpha".to_string(), 1, 1),
+                ("project/beta".to_string(), 1, 1),
+                ("todo".to_string(), 1, 1),
+            ]
+        );
+
+        let files = tagged_files(&workspace, "PROJECT");
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].matches.len(), 2);
+    }
+
+    #[test]
+    fn test_rename_tag_rolls_back_on_failed_write() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::write(root.join("a.md"), "#project").unwrap();
+        fs::write(root.join("b.md"), "#project/beta").unwrap();
+
+        let mut workspace = WorkspaceState::new();
+        workspace.open(root);
+
+        // A directory in the way of b.md's staging file makes its write fail
+        fs::create_dir(root.join(".b.md.loom-tmp")).unwrap();
+        assert!(rename_tag_in_workspace(&mut workspace, "project", "work").is_err());
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "#project");
+        assert_eq!(fs::read_to_string(root.join("b.md")).unwrap(), "#project/beta");
+
+        fs::remove_dir(root.join(".b.md.loom-tmp")).unwrap();
+        let changed = rename_tag_in_workspace(&mut workspace, "project", "work").unwrap();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(fs::read_to_string(root.join("b.md")).unwrap(), "#work/beta");
+        assert_eq!(tagged_files(&workspace, "project").len(), 0);
+    }
+}
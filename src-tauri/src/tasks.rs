@@ -0,0 +1,204 @@
+use crate::markdown::{literal_line_mask, parse_task_marker};
+use crate::search::markdown_files;
+use crate::write_file_atomic;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskItem {
+    pub line: usize,
+    pub text: String,
+    pub line_text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTaskResult {
+    pub file_path: String,
+    pub tasks: Vec<TaskItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskToggleResult {
+    pub new_content: String,
+    pub content_hash: String,
+    pub checked: bool,
+}
+
+/// Hash of a file's content, used to detect edits made since it was read
+///
+/// Not used for security; it only has to change when the content does.
+/// The digest is stable across builds, since replace journals keep it on disk.
+pub fn content_hash(content: &str) -> String {
+    Md5::digest(content.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Flip the checkbox on one line, keeping every other byte as it was
+fn toggle_task_in_content(content: &str, line_index: usize) -> Result<(String, bool), String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let line = lines
+        .get(line_index)
+        .ok_or_else(|| format!("Line {} is out of range", line_index + 1))?;
+
+    // Checkbox syntax inside a code block is just text
+    let is_literal = literal_line_mask(&lines)[line_index];
+    let marker = parse_task_marker(line)
+        .filter(|_| !is_literal)
+        .ok_or_else(|| format!("Line {} is not a task list item", line_index + 1))?;
+
+    let line_start: usize = content
+        .split_inclusive('\n')
+        .take(line_index)
+        .map(str::len)
+        .sum();
+    let offset = line_start + marker.state_offset;
+    let state = if marker.checked { " " } else { "x" };
+
+    let mut new_content = content.to_string();
+    new_content.replace_range(offset..offset + 1, state);
+
+    Ok((new_content, !marker.checked))
+}
+
+/// Collect unchecked task items from a note
+pub fn find_open_tasks(content: &str) -> Vec<TaskItem> {
+    let lines: Vec<&str> = content.lines().collect();
+    let literal = literal_line_mask(&lines);
+
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !literal[*i])
+        .filter_map(|(i, line)| {
+            let marker = parse_task_marker(line)?;
+            if marker.checked {
+                return None;
+            }
+
+            Some(TaskItem {
+                line: i + 1,
+                text: line[marker.state_offset + 2..].trim().to_string(),
+                line_text: line.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Hash content the same way `toggle_task` checks it
+#[tauri::command]
+pub fn get_content_hash(content: String) -> String {
+    content_hash(&content)
+}
+
+/// Toggle the task checkbox on a line of a file on disk
+///
+/// `expected_hash` must match the file's current content, otherwise the file
+/// was changed elsewhere and the toggle is refused rather than overwriting it.
+#[tauri::command]
+pub fn toggle_task(
+    path: String,
+    line_index: usize,
+    expected_hash: String,
+) -> Result<TaskToggleResult, String> {
+    let file_path = Path::new(&path);
+    let content = fs::read_to_string(file_path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    if content_hash(&content) != expected_hash {
+        return Err("File has changed on disk since it was loaded".to_string());
+    }
+
+    let (new_content, checked) = toggle_task_in_content(&content, line_index)?;
+    write_file_atomic(file_path, &new_content)?;
+
+    Ok(TaskToggleResult {
+        content_hash: content_hash(&new_content),
+        new_content,
+        checked,
+    })
+}
+
+/// List unchecked tasks across all notes in a directory
+#[tauri::command]
+pub fn find_open_tasks_in_directory(dir_path: String) -> Result<Vec<FileTaskResult>, String> {
+    let path = Path::new(&dir_path);
+    if !path.exists() || !path.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+
+    let mut results = Vec::new();
+
+    for entry_path in markdown_files(path) {
+        let content = match fs::read_to_string(&entry_path) {
+            Ok(c) => c,
+            Err(_) => continue, // Skip files we can't read
+        };
+
+        let tasks = find_open_tasks(&content);
+        if !tasks.is_empty() {
+            results.push(FileTaskResult {
+                file_path: entry_path.to_string_lossy().to_string(),
+                tasks,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_preserves_other_bytes() {
+        let content = "# Todo\r\n- [ ] first\r\n  - [x] second\r\n";
+
+        let (checked, state) = toggle_task_in_content(content, 1).unwrap();
+        assert!(state);
+        assert_eq!(checked, "# Todo\r\n- [x] first\r\n  - [x] second\r\n");
+
+        let (unchecked, state) = toggle_task_in_content(content, 2).unwrap();
+        assert!(!state);
+        assert_eq!(unchecked, "# Todo\r\n- [ ] first\r\n  - [ ] second\r\n");
+    }
+
+    #[test]
+    fn test_toggle_rejects_non_tasks() {
+        let content = "- item\n```\n- [ ] in code\n```";
+        assert!(toggle_task_in_content(content, 0).is_err());
+        assert!(toggle_task_in_content(content, 2).is_err());
+        assert!(toggle_task_in_content(content, 9).is_err());
+    }
+
+    #[test]
+    fn test_toggle_checks_hash() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let file = dir.join("note.md");
+        fs::write(&file, "- [ ] task\n").unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        assert!(toggle_task(path.clone(), 0, content_hash("stale")).is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "- [ ] task\n");
+
+        let result = toggle_task(path, 0, content_hash("- [ ] task\n")).unwrap();
+        assert!(result.checked);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "- [x] task\n");
+        assert_eq!(result.content_hash, content_hash(&result.new_content));
+        assert_eq!(content_hash(""), "d41d8cd98f00b204e9800998ecf8427e");
+    }
+
+    #[test]
+    fn test_find_open_tasks() {
+        let content = "- [ ] open one\n- [x] done\n```\n- [ ] code\n```\n1. [ ]  open two ";
+        let tasks = find_open_tasks(content);
+
+        let found: Vec<(usize, &str)> = tasks.iter().map(|t| (t.line, t.text.as_str())).collect();
+        assert_eq!(found, vec![(1, "open one"), (6, "open two")]);
+    }
+}
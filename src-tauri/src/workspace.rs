@@ -0,0 +1,419 @@
+use crate::markdown::{
+    extract_links, extract_tags, note_title, LinkKind, NoteIndex, NoteLink, NoteTag, RenderContextHandle,
+};
+use crate::search::{markdown_files, FileSearchResult, SearchMatch};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+/// Indexes over the notes of the open folder
+pub struct WorkspaceState {
+    notes: NoteIndex,
+    /// Outgoing links of every note, keyed by workspace-relative path
+    links: HashMap<String, Vec<NoteLink>>,
+    /// Inline and frontmatter tags of every note, keyed the same way
+    tags: HashMap<String, Vec<NoteTag>>,
+    /// Titles of the notes that have one, keyed the same way
+    titles: HashMap<String, String>,
+    /// Paths whose note names changed since the last publish
+    unpublished: Vec<String>,
+    /// A folder was opened, so the renderer needs the whole index
+    republish_all: bool,
+}
+
+/// What the workspace keeps from reading a note
+struct ParsedNote {
+    links: Vec<NoteLink>,
+    tags: Vec<NoteTag>,
+    title: Option<String>,
+}
+
+impl WorkspaceState {
+    pub fn new() -> Self {
+        Self {
+            notes: NoteIndex::default(),
+            links: HashMap::new(),
+            tags: HashMap::new(),
+            titles: HashMap::new(),
+            unpublished: Vec::new(),
+            republish_all: false,
+        }
+    }
+
+    /// Index every note under a newly opened folder
+    pub fn open(&mut self, root: &Path) {
+        self.notes = NoteIndex::new(root);
+        self.links.clear();
+        self.tags.clear();
+        self.titles.clear();
+        self.index_notes_under(root);
+        self.unpublished.clear();
+        self.republish_all = true;
+    }
+
+    fn index_notes_under(&mut self, dir: &Path) {
+        let notes: Vec<(String, PathBuf)> = markdown_files(dir)
+            .filter_map(|path| Some((self.visible_relative_path(&path)?, path)))
+            .collect();
+
+        // Reading and parsing dominates, so do it in parallel
+        let parsed: Vec<(String, ParsedNote)> = notes
+            .par_iter()
+            .map(|(relative_path, path)| (relative_path.clone(), read_note(path)))
+            .collect();
+
+        for (relative_path, note) in parsed {
+            self.insert_note(relative_path, note);
+        }
+    }
+
+    fn insert_note(&mut self, relative_path: String, note: ParsedNote) {
+        self.notes.insert(&relative_path);
+        if let Some(title) = note.title {
+            self.titles.insert(relative_path.clone(), title);
+        }
+        self.tags.insert(relative_path.clone(), note.tags);
+        self.links.insert(relative_path, note.links);
+    }
+
+    /// Relative path of a workspace file, skipping hidden files and folders
+    /// the same way `read_directory` does
+    fn visible_relative_path(&self, path: &Path) -> Option<String> {
+        let relative_path = self.notes.relative_path(path)?;
+        let is_hidden = relative_path.split('/').any(|part| part.starts_with('.'));
+        (!is_hidden).then_some(relative_path)
+    }
+
+    /// Bring the indexes in line with the current state of a changed path
+    ///
+    /// Watcher events are coarse (a rename may arrive as separate from/to
+    /// events), so the path is simply re-examined on disk.
+    pub fn refresh_path(&mut self, path: &Path) {
+        let Some(relative_path) = self.notes.relative_path(path) else {
+            return;
+        };
+
+        let was_note = self.notes.contains(&relative_path);
+        self.notes.remove(&relative_path);
+        let folder_prefix = format!("{}/", relative_path);
+        self.links.retain(|source, _| *source != relative_path && !source.starts_with(&folder_prefix));
+        self.tags.retain(|source, _| *source != relative_path && !source.starts_with(&folder_prefix));
+        self.titles.retain(|source, _| *source != relative_path && !source.starts_with(&folder_prefix));
+
+        if path.is_dir() {
+            self.index_notes_under(path);
+        } else if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            if let Some(relative_path) = self.visible_relative_path(path) {
+                self.insert_note(relative_path, read_note(path));
+            }
+        }
+
+        // Saving a note only changes its content, which the renderer doesn't keep
+        if path.is_dir() || was_note != self.notes.contains(&relative_path) {
+            self.unpublished.push(relative_path);
+        }
+    }
+
+    pub fn notes(&self) -> &NoteIndex {
+        &self.notes
+    }
+
+    /// Indexed notes (workspace-relative paths) with their outgoing links
+    pub fn link_sources(&self) -> impl Iterator<Item = (&String, &Vec<NoteLink>)> {
+        self.links.iter()
+    }
+
+    /// Indexed notes (workspace-relative paths) with their tags
+    pub fn note_tags(&self) -> impl Iterator<Item = (&String, &Vec<NoteTag>)> {
+        self.tags.iter()
+    }
+
+    /// Indexed notes (workspace-relative paths) with their titles, if any
+    pub fn note_titles(&self) -> impl Iterator<Item = (&String, Option<&String>)> {
+        self.links.keys().map(|path| (path, self.titles.get(path)))
+    }
+
+    /// Workspace-relative path a link points to, if it points into the workspace
+    fn link_destination(&self, source: &str, link: &NoteLink) -> Option<String> {
+        match link.kind {
+            LinkKind::Wiki => self.notes.resolve(&link.target).map(str::to_string),
+            LinkKind::Markdown | LinkKind::Image => resolve_relative_link(source, &link.target),
+        }
+    }
+
+    /// Every link pointing at a note, grouped by the note containing it
+    pub fn backlinks(&self, path: &Path) -> Vec<FileSearchResult> {
+        let Some(target) = self.notes.relative_path(path) else {
+            return Vec::new();
+        };
+
+        let mut sources: Vec<&String> = self.links.keys().filter(|source| **source != target).collect();
+        sources.sort();
+
+        sources
+            .into_iter()
+            .filter_map(|source| {
+                let matches: Vec<SearchMatch> = self.links[source]
+                    .iter()
+                    .filter(|link| self.link_destination(source, link).as_deref() == Some(target.as_str()))
+                    .map(|link| SearchMatch {
+                        line: link.line + 1,
+                        column: link.range.start + 1,
+                        length: link.range.len(),
+                        text: link.text().to_string(),
+                        line_text: link.line_text.clone(),
+                    })
+                    .collect();
+
+                if matches.is_empty() {
+                    return None;
+                }
+                Some(FileSearchResult {
+                    file_path: self.notes.absolute_path(source)?.to_string_lossy().to_string(),
+                    matches,
+                })
+            })
+            .collect()
+    }
+
+    /// Hand the note names that changed since the last publish to the renderer
+    pub fn publish(&mut self, render_context: &RenderContextHandle) -> Result<(), String> {
+        if !self.republish_all && self.unpublished.is_empty() {
+            return Ok(());
+        }
+
+        let mut context = render_context.lock()
+            .map_err(|e| format!("Failed to acquire render context lock: {}", e))?;
+
+        if self.republish_all {
+            context.notes = Arc::new(self.notes.clone());
+        } else {
+            // Update the renderer's copy in place rather than cloning the index
+            let notes = Arc::make_mut(&mut context.notes);
+            for relative_path in &self.unpublished {
+                notes.remove(relative_path);
+                for note in self.notes.paths_under(relative_path) {
+                    notes.insert(&note);
+                }
+            }
+        }
+
+        self.unpublished.clear();
+        self.republish_all = false;
+        context.revision += 1;
+        Ok(())
+    }
+}
+
+fn read_note(path: &Path) -> ParsedNote {
+    // Unreadable files simply have no links, tags or title
+    let content = fs::read_to_string(path).unwrap_or_default();
+    ParsedNote {
+        links: extract_links(&content),
+        tags: extract_tags(&content),
+        title: note_title(&content),
+    }
+}
+
+/// Decode `%XX` escapes in a link destination
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Resolve a markdown link destination against the note containing it
+///
+/// External URLs return `None`. A leading `/` is relative to the workspace
+/// root, and a destination without an extension is taken to be a note.
+pub fn resolve_relative_link(source: &str, destination: &str) -> Option<String> {
+    let destination = percent_decode(destination.split('?').next().unwrap_or(""));
+    let has_scheme = destination.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+    });
+    if destination.is_empty() || has_scheme {
+        return None;
+    }
+
+    let destination = destination.replace('\\', "/");
+    let mut parts: Vec<String> = match destination.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => source.split('/').map(str::to_string).collect(),
+    };
+    // Drop the source file name, leaving its folder
+    if !destination.starts_with('/') {
+        parts.pop();
+    }
+
+    for component in Path::new(destination.trim_start_matches('/')).components() {
+        match component {
+            Component::ParentDir => {
+                // Links escaping the workspace don't point at a note in it
+                parts.pop()?;
+            }
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            _ => {}
+        }
+    }
+
+    let mut resolved = parts.join("/");
+    if Path::new(&resolved).extension().is_none() {
+        resolved.push_str(".md");
+    }
+    Some(resolved)
+}
+
+// Global state wrapped in Arc<Mutex<>> for thread-safe access
+pub type WorkspaceStateHandle = Arc<Mutex<WorkspaceState>>;
+
+pub fn create_workspace_state() -> WorkspaceStateHandle {
+    Arc::new(Mutex::new(WorkspaceState::new()))
+}
+
+/// Update the workspace indexes after a file system event
+pub fn sync_changed_paths(app_handle: &AppHandle, paths: &[PathBuf]) {
+    let workspace = app_handle.state::<WorkspaceStateHandle>();
+    let render_context = app_handle.state::<RenderContextHandle>();
+
+    let Ok(mut state) = workspace.lock() else {
+        eprintln!("Failed to acquire workspace lock");
+        return;
+    };
+
+    for path in paths {
+        state.refresh_path(path);
+    }
+
+    if let Err(e) = state.publish(&render_context) {
+        eprintln!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_and_refresh() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("projects")).unwrap();
+        fs::write(root.join("Inbox.md"), "").unwrap();
+        fs::write(root.join("projects/Plan.md"), "").unwrap();
+        fs::write(root.join("projects/diagram.png"), "").unwrap();
+        fs::create_dir_all(root.join(".trash")).unwrap();
+        fs::write(root.join(".trash/Old.md"), "").unwrap();
+
+        let mut state = WorkspaceState::new();
+        state.open(root);
+        assert_eq!(state.notes().resolve("Plan"), Some("projects/Plan.md"));
+        assert_eq!(state.notes().resolve("diagram"), None);
+        assert_eq!(state.notes().resolve("Old"), None);
+
+        // Folder renamed: the old path is gone, the new one has the notes
+        fs::rename(root.join("projects"), root.join("archive")).unwrap();
+        state.refresh_path(&root.join("projects"));
+        assert_eq!(state.notes().resolve("Plan"), None);
+        state.refresh_path(&root.join("archive"));
+        assert_eq!(state.notes().resolve("Plan"), Some("archive/Plan.md"));
+
+        fs::remove_file(root.join("Inbox.md")).unwrap();
+        state.refresh_path(&root.join("Inbox.md"));
+        assert_eq!(state.notes().resolve("Inbox"), None);
+    }
+
+    #[test]
+    fn test_backlinks() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("projects")).unwrap();
+        fs::write(root.join("projects/Plan.md"), "# Plan\nSee [[Plan#Goals]] above").unwrap();
+        fs::write(root.join("Inbox.md"), "- [[Plan]]\n- [plan](projects/Plan.md)\n- [web](https://x.y/Plan.md)").unwrap();
+        fs::write(root.join("projects/Notes.md"), "Back to [the plan](./Plan.md#goals)").unwrap();
+
+        let mut state = WorkspaceState::new();
+        state.open(root);
+
+        let backlinks = state.backlinks(&root.join("projects/Plan.md"));
+        let found: Vec<(String, usize, usize, &str)> = backlinks
+            .iter()
+            .flat_map(|file| {
+                let name = Path::new(&file.file_path).file_name().unwrap().to_string_lossy().to_string();
+                file.matches.iter().map(move |m| (name.clone(), m.line, m.column, m.text.as_str()))
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Inbox.md".to_string(), 1, 3, "[[Plan]]"),
+                ("Inbox.md".to_string(), 2, 3, "[plan](projects/Plan.md)"),
+                ("Notes.md".to_string(), 1, 9, "[the plan](./Plan.md#goals)"),
+            ]
+        );
+
+        // Edits are picked up when the watcher reports the file
+        fs::write(root.join("Inbox.md"), "nothing here").unwrap();
+        state.refresh_path(&root.join("Inbox.md"));
+        assert_eq!(state.backlinks(&root.join("projects/Plan.md")).len(), 1);
+    }
+
+    #[test]
+    fn test_publish_only_changed_names() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::write(root.join("Inbox.md"), "").unwrap();
+        let render_context = crate::markdown::create_render_context();
+
+        let mut state = WorkspaceState::new();
+        state.open(root);
+        state.publish(&render_context).unwrap();
+        let revision = render_context.lock().unwrap().revision;
+
+        // Saving an existing note leaves the renderer's index alone
+        fs::write(root.join("Inbox.md"), "edited").unwrap();
+        state.refresh_path(&root.join("Inbox.md"));
+        state.publish(&render_context).unwrap();
+        assert_eq!(render_context.lock().unwrap().revision, revision);
+
+        fs::write(root.join("Plan.md"), "").unwrap();
+        state.refresh_path(&root.join("Plan.md"));
+        state.publish(&render_context).unwrap();
+        let context = render_context.lock().unwrap();
+        assert_eq!(context.revision, revision + 1);
+        assert_eq!(context.notes.resolve("Plan"), Some("Plan.md"));
+        assert_eq!(context.notes.resolve("Inbox"), Some("Inbox.md"));
+        drop(context);
+    }
+
+    #[test]
+    fn test_resolve_relative_link() {
+        assert_eq!(resolve_relative_link("a/b/Note.md", "../Other.md"), Some("a/Other.md".to_string()));
+        assert_eq!(resolve_relative_link("a/Note.md", "Sub%20Folder/Page"), Some("a/Sub Folder/Page.md".to_string()));
+        assert_eq!(resolve_relative_link("a/Note.md", "/Top.md"), Some("Top.md".to_string()));
+        assert_eq!(resolve_relative_link("Note.md", "../outside.md"), None);
+        assert_eq!(resolve_relative_link("Note.md", "mailto:me@example.com"), None);
+    }
+}
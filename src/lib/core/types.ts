@@ -44,16 +44,6 @@ export interface FileEntry {
   children?: FileEntry[];
 }
 
-/**
- * Request for rendering a markdown line (sent to Rust backend)
- */
-export interface RenderRequest {
-  line: string;
-  line_index: number;
-  all_lines: string[];
-  is_editing: boolean;
-}
-
 /**
  * Result of rendering a markdown line (received from Rust backend)
  */
@@ -62,6 +52,23 @@ export interface LineRenderResult {
   is_code_block_boundary: boolean;
 }
 
+/**
+ * Replacement of a range of lines in an open document (sent to Rust backend)
+ */
+export interface LineEdit {
+  start: number;
+  delete_count: number;
+  insert: string[];
+}
+
+/**
+ * Lines whose HTML changed after a document update (received from Rust backend)
+ */
+export interface DocumentUpdate {
+  line_count: number;
+  changed: { line_index: number; result: LineRenderResult }[];
+}
+
 /**
  * Theme configuration
  */
@@ -82,6 +89,29 @@ export interface AppConfig {
   confirm_folder_delete?: boolean;
   keybinds?: Record<string, string>;
   custom_settings?: Record<string, unknown>;
+  sanitize?: SanitizePolicy;
+  search?: SearchDefaults;
+}
+
+/**
+ * Allowlist applied to raw HTML and link URLs in rendered notes, stored in config.json
+ */
+export interface SanitizePolicy {
+  enabled: boolean;
+  allowed_tags: string[];
+  allowed_attributes: string[];
+  allowed_url_schemes: string[];
+}
+
+/**
+ * Files covered by directory search, stored in config.json
+ */
+export interface SearchDefaults {
+  extensions: string[];
+  include: string[];
+  exclude: string[];
+  max_file_size: number;
+  respect_ignore_files: boolean;
 }
 
 /**
@@ -5,7 +5,7 @@
 
 import { editor } from "../core/dom";
 import { state } from "../core/state";
-import { renderMarkdownLine, getAllLines } from "./rendering";
+import { syncDocument, getAllLines } from "./rendering";
 import { updateCursorPosition } from "../ui/ui";
 import { getFirstTextNode, isLineInsideBlock } from "./editor-utils";
 import { saveCursorPosition } from "../utils/cursor-utils";
@@ -42,10 +42,10 @@ export async function handleCursorChange() {
     const oldLine = state.currentLine;
     state.currentLine = lineNum;
 
-    // Get all lines for code block detection
+    // Get all lines for block detection
     const allLines = getAllLines();
 
-    // Re-render the old line if it exists
+    // Keep the old line's edited text if it exists
     if (oldLine !== null && oldLine < editor.childNodes.length) {
       const oldLineDiv = editor.childNodes[oldLine] as HTMLElement;
       if (oldLineDiv) {
@@ -67,28 +67,21 @@ export async function handleCursorChange() {
           if (!isSpecialBlock) {
             const currentText = oldLineDiv.textContent || "";
             oldLineDiv.setAttribute("data-raw", currentText);
-            allLines[oldLine] = currentText;
           }
         }
-
-        const rawText = oldLineDiv.getAttribute("data-raw") || "";
-        const html = await renderMarkdownLine(rawText, false, oldLine, allLines);
-        oldLineDiv.innerHTML = html;
         oldLineDiv.classList.remove("editing");
       }
     }
 
-    // Update current line to show raw
+    // Save cursor position before the line's innerHTML is replaced
     const currentLineDiv = editor.childNodes[lineNum] as HTMLElement;
-    if (currentLineDiv) {
-      const rawText = currentLineDiv.getAttribute("data-raw") || "";
+    const cursorOffset = currentLineDiv ? saveCursorPosition(currentLineDiv) : 0;
 
-      // Save cursor position before modifying innerHTML
-      const cursorOffset = saveCursorPosition(currentLineDiv);
+    // Re-render the old line as HTML and the current line as raw markdown
+    const redraw = oldLine !== null ? [oldLine, lineNum] : [lineNum];
+    await syncDocument(lineNum, redraw);
 
-      // Update the line to show raw markdown
-      const html = await renderMarkdownLine(rawText, true, lineNum, allLines);
-      currentLineDiv.innerHTML = html;
+    if (currentLineDiv) {
       currentLineDiv.classList.add("editing");
 
       // Use requestAnimationFrame to ensure DOM layout is complete
@@ -5,9 +5,10 @@
 
 import { editor, editModeToggle, editorContainer } from "../core/dom";
 import { state } from "../core/state";
-import { renderAllLines, getAllLines, renderMarkdownLine } from "./rendering";
+import { renderAllLines, syncDocument, getEditorContent } from "./rendering";
 import { saveFile } from "../file-operations";
-import { closeActiveTab } from "../tabs/tabs";
+import { closeActiveTab, markCurrentTabDirty, updateCurrentTabContent } from "../tabs/tabs";
+import { updateStatistics } from "../ui/ui";
 import { getFirstTextNode } from "./editor-utils";
 import { handleEnterKey, handleBackspaceKey, handleDeleteKey, handleTabKey } from "./editor-keys";
 import { handleImagePaste, handleImageDrop } from "./image-handling";
@@ -20,6 +21,34 @@ import { toggleSearchModal } from "../search";
 export { handleCursorChange } from "./cursor-management";
 export { handleInput } from "./editor-input";
 
+// Task list line: indent, bullet, then the `[ ]` / `[x]` checkbox
+const TASK_LINE_RE = /^(\s*(?:[-*+]|\d+\.)\s+\[)([ xX])(\](?:\s|$))/;
+
+/**
+ * Toggle a task checkbox by flipping `[ ]` / `[x]` in its line's markdown
+ */
+async function toggleTaskCheckbox(checkbox: HTMLInputElement) {
+  const lineElement = checkbox.closest(".editor-line") as HTMLElement | null;
+  if (!lineElement) return;
+
+  const rawText = lineElement.getAttribute("data-raw") || "";
+  const match = rawText.match(TASK_LINE_RE);
+  if (!match) return;
+
+  const newState = match[2] === " " ? "x" : " ";
+  const newText = match[1] + newState + rawText.substring(match[1].length + 1);
+  lineElement.setAttribute("data-raw", newText);
+
+  // Re-render the line, keeping whichever line is being edited
+  const lineNum = parseInt(lineElement.getAttribute("data-line") || "0");
+  await syncDocument(state.editMode ? state.currentLine : null, [lineNum]);
+
+  state.content = getEditorContent();
+  updateStatistics(state.content);
+  updateCurrentTabContent(state.content);
+  markCurrentTabDirty();
+}
+
 /**
  * Handle paste events - detects if it's image or text and routes accordingly
  */
@@ -95,6 +124,21 @@ export function initEditorEvents() {
   editor.addEventListener("click", async (e) => {
     const target = e.target as HTMLElement;
 
+    // Clicking a task checkbox toggles it in the markdown source
+    if (target instanceof HTMLInputElement && target.classList.contains("task-checkbox")) {
+      e.preventDefault();
+      e.stopPropagation();
+      await toggleTaskCheckbox(target);
+      return;
+    }
+
+    // Collapse or expand the frontmatter properties
+    if (target.classList.contains("frontmatter-toggle")) {
+      e.preventDefault();
+      editor.classList.toggle("frontmatter-collapsed");
+      return;
+    }
+
     // Handle image clicks - switch to edit mode to edit markdown
     if (target.tagName === "IMG" && target.classList.contains("markdown-image")) {
       e.preventDefault();
@@ -118,9 +162,7 @@ export function initEditorEvents() {
 
       if (imageMatch) {
         // Render line in edit mode
-        const allLines = getAllLines();
-        const html = await renderMarkdownLine(rawText, true, lineNum, allLines);
-        lineElement.innerHTML = html;
+        await syncDocument(lineNum, [lineNum]);
         lineElement.classList.add("editing");
 
         // Position cursor at the start of the image markdown
@@ -152,6 +194,12 @@ export function initEditorEvents() {
   editor.addEventListener("mousedown", (e) => {
     const target = e.target as HTMLElement;
 
+    // Keep the caret where it is when a task checkbox is clicked
+    if (target.classList.contains("task-checkbox")) {
+      e.preventDefault();
+      return;
+    }
+
     if (target === editor || target.classList.contains("editor-container")) {
       e.preventDefault();
 
@@ -5,7 +5,7 @@
 
 import { editor } from "../core/dom";
 import { state } from "../core/state";
-import { renderMarkdownLine, getAllLines, getEditorContent } from "./rendering";
+import { syncDocument, getEditorContent } from "./rendering";
 import { updateStatistics } from "../ui/ui";
 import { markCurrentTabDirty, updateCurrentTabContent } from "../tabs/tabs";
 import { getCurrentLineNumber } from "../ui/ui";
@@ -26,14 +26,7 @@ export async function handleInput() {
     const cursorOffset = saveCursorPosition(lineDiv);
 
     // Re-render the line with editing mode
-    const allLines = getAllLines();
-    const html = await renderMarkdownLine(
-      rawText,
-      true,
-      currentLineNum,
-      allLines
-    );
-    lineDiv.innerHTML = html;
+    await syncDocument(currentLineNum, [currentLineNum]);
 
     // Restore cursor position
     try {
@@ -5,14 +5,8 @@
 
 import { editor } from "../core/dom";
 import { state, markDirty } from "../core/state";
-import {
-  renderMarkdownLine,
-  renderMarkdownBatch,
-  getAllLines,
-  getEditorContent,
-} from "./rendering";
+import { syncDocument, getEditorContent } from "./rendering";
 import { updateStatistics, getCurrentLineNumber } from "../ui/ui";
-import { RenderRequest } from "../core/types";
 import { markCurrentTabDirty, updateCurrentTabContent } from "../tabs/tabs";
 import { getFirstTextNode } from "./editor-utils";
 
@@ -46,7 +40,6 @@ export async function handleEnterKey(e: KeyboardEvent) {
 
   // Update current line
   currentLine.setAttribute("data-raw", beforeCursor);
-  currentLine.innerHTML = await renderMarkdownLine(beforeCursor, false);
   currentLine.classList.remove("editing");
 
   // Create new line
@@ -70,38 +63,8 @@ export async function handleEnterKey(e: KeyboardEvent) {
     line.setAttribute("data-line", String(i));
   }
 
-  // Re-render affected lines
-  const allLines = getAllLines();
-  const requests: RenderRequest[] = [];
-  for (let i = currentLineNum; i < editor.childNodes.length; i++) {
-    const lineDiv = editor.childNodes[i] as HTMLElement;
-    const rawText = lineDiv.getAttribute("data-raw") || "";
-    const isEditing = i === currentLineNum + 1;
-    requests.push({
-      line: rawText,
-      line_index: i,
-      all_lines: allLines,
-      is_editing: isEditing,
-    });
-  }
-
-  const results = await renderMarkdownBatch(requests);
-  let resultIndex = 0;
-  for (let i = currentLineNum; i < editor.childNodes.length; i++) {
-    const lineDiv = editor.childNodes[i] as HTMLElement;
-    const isEditing = i === currentLineNum + 1;
-
-    if (results[resultIndex]) {
-      lineDiv.innerHTML = results[resultIndex].html;
-    }
-
-    if (isEditing) {
-      lineDiv.classList.add("editing");
-    } else {
-      lineDiv.classList.remove("editing");
-    }
-    resultIndex++;
-  }
+  // Re-render affected lines (the session sends back only lines whose HTML changed)
+  await syncDocument(currentLineNum + 1);
 
   // Move cursor to beginning of new line
   const newRange = document.createRange();
@@ -156,7 +119,6 @@ export async function handleBackspaceKey(e: KeyboardEvent) {
 
     // Update previous line
     prevLine.setAttribute("data-raw", mergedText);
-    prevLine.innerHTML = await renderMarkdownLine(mergedText, true);
     prevLine.classList.add("editing");
 
     // Remove current line
@@ -168,38 +130,8 @@ export async function handleBackspaceKey(e: KeyboardEvent) {
       line.setAttribute("data-line", String(i));
     }
 
-    // Re-render affected lines
-    const allLines = getAllLines();
-    const requests: RenderRequest[] = [];
-    for (let i = currentLineNum - 1; i < editor.childNodes.length; i++) {
-      const lineDiv = editor.childNodes[i] as HTMLElement;
-      const rawText = lineDiv.getAttribute("data-raw") || "";
-      const isEditing = i === currentLineNum - 1;
-      requests.push({
-        line: rawText,
-        line_index: i,
-        all_lines: allLines,
-        is_editing: isEditing,
-      });
-    }
-
-    const results = await renderMarkdownBatch(requests);
-    let resultIndex = 0;
-    for (let i = currentLineNum - 1; i < editor.childNodes.length; i++) {
-      const lineDiv = editor.childNodes[i] as HTMLElement;
-      const isEditing = i === currentLineNum - 1;
-
-      if (results[resultIndex]) {
-        lineDiv.innerHTML = results[resultIndex].html;
-      }
-
-      if (isEditing) {
-        lineDiv.classList.add("editing");
-      } else {
-        lineDiv.classList.remove("editing");
-      }
-      resultIndex++;
-    }
+    // Re-render affected lines (the session sends back only lines whose HTML changed)
+    await syncDocument(currentLineNum - 1);
 
     // Move cursor to merge point
     const textNode = getFirstTextNode(prevLine);
@@ -256,7 +188,6 @@ export async function handleDeleteKey(e: KeyboardEvent) {
 
     // Update current line
     currentLine.setAttribute("data-raw", mergedText);
-    currentLine.innerHTML = await renderMarkdownLine(mergedText, true);
     currentLine.classList.add("editing");
 
     // Remove next line
@@ -268,38 +199,8 @@ export async function handleDeleteKey(e: KeyboardEvent) {
       line.setAttribute("data-line", String(i));
     }
 
-    // Re-render affected lines
-    const allLines = getAllLines();
-    const requests: RenderRequest[] = [];
-    for (let i = currentLineNum; i < editor.childNodes.length; i++) {
-      const lineDiv = editor.childNodes[i] as HTMLElement;
-      const rawText = lineDiv.getAttribute("data-raw") || "";
-      const isEditing = i === currentLineNum;
-      requests.push({
-        line: rawText,
-        line_index: i,
-        all_lines: allLines,
-        is_editing: isEditing,
-      });
-    }
-
-    const results = await renderMarkdownBatch(requests);
-    let resultIndex = 0;
-    for (let i = currentLineNum; i < editor.childNodes.length; i++) {
-      const lineDiv = editor.childNodes[i] as HTMLElement;
-      const isEditing = i === currentLineNum;
-
-      if (results[resultIndex]) {
-        lineDiv.innerHTML = results[resultIndex].html;
-      }
-
-      if (isEditing) {
-        lineDiv.classList.add("editing");
-      } else {
-        lineDiv.classList.remove("editing");
-      }
-      resultIndex++;
-    }
+    // Re-render affected lines (the session sends back only lines whose HTML changed)
+    await syncDocument(currentLineNum);
 
     // Keep cursor at same position
     const textNode = currentLine.firstChild;
@@ -7,7 +7,7 @@ import { editor } from "../core/dom";
 import { state } from "../core/state";
 import { invoke } from "@tauri-apps/api/core";
 import { getSettings } from "../settings/settings-manager";
-import { renderMarkdownLine, getAllLines, getEditorContent } from "./rendering";
+import { syncDocument, getEditorContent } from "./rendering";
 import { updateStatistics } from "../ui/ui";
 import { markCurrentTabDirty, updateCurrentTabContent } from "../tabs/tabs";
 import { getCurrentLineNumber } from "../ui/ui";
@@ -72,9 +72,7 @@ export async function insertImageAtCursor(imagePath: string, altText: string = "
   currentLine.setAttribute("data-raw", newText);
 
   // Re-render the line
-  const allLines = getAllLines();
-  const html = await renderMarkdownLine(newText, true, currentLineNum, allLines);
-  currentLine.innerHTML = html;
+  await syncDocument(currentLineNum, [currentLineNum]);
   currentLine.classList.add("editing");
 
   // Position cursor after inserted image markdown
@@ -1,13 +1,12 @@
 /**
  * Markdown rendering functions
  *
- * This module handles rendering markdown to HTML using the Rust backend
- * and KaTeX for LaTeX math rendering on the frontend.
+ * This module handles rendering markdown to HTML using the Rust backend,
+ * which also typesets LaTeX math as MathML.
  */
 
 import { invoke, convertFileSrc } from "@tauri-apps/api/core";
-import katex from "katex";
-import { RenderRequest, LineRenderResult } from "../core/types";
+import { LineRenderResult, LineEdit, DocumentUpdate } from "../core/types";
 import { editor } from "../core/dom";
 
 /**
@@ -21,39 +20,6 @@ export function escapeHtml(text: string): string {
   return div.innerHTML;
 }
 
-/**
- * Parse and render LaTeX expressions in text
- * @param text - Text containing LaTeX expressions
- * @returns Text with LaTeX rendered as HTML
- */
-export function renderLatex(text: string): string {
-  // Replace display math $$ ... $$
-  text = text.replace(/\$\$([\s\S]+?)\$\$/g, (match, latex) => {
-    try {
-      return katex.renderToString(latex.trim(), {
-        displayMode: true,
-        throwOnError: false,
-      });
-    } catch (e) {
-      return match;
-    }
-  });
-
-  // Replace inline math $ ... $ (but not $$)
-  text = text.replace(/\$([^\$\n]+?)\$/g, (match, latex) => {
-    try {
-      return katex.renderToString(latex.trim(), {
-        displayMode: false,
-        throwOnError: false,
-      });
-    } catch (e) {
-      return match;
-    }
-  });
-
-  return text;
-}
-
 /**
  * Convert image file paths to Tauri asset protocol URLs
  * @param html - HTML string containing img tags
@@ -95,123 +61,144 @@ export function convertImagePaths(html: string): string {
   );
 }
 
+// Document session shown in the editor; the backend caches its rendered lines
+const SCRATCH_DOCUMENT_ID = "scratch";
+let documentId: string | null = null;
+let documentLines: string[] = [];
+let documentHtml: string[] = [];
+let documentEditingLine: number | null = null;
+
 /**
- * Post-process HTML to render LaTeX (frontend-only since we use KaTeX)
- * Optimized: only process if line contains LaTeX markers or is a math block
- * @param html - HTML string to process
- * @returns HTML with LaTeX rendered
+ * Open a document session in the backend and render all of its lines once
+ * @param docId - Session id (the tab id)
+ * @param lines - Raw lines of the document
+ * @param editingLine - Line shown as raw markdown, if any
+ * @returns HTML for every line
  */
-export function renderLatexInHtml(html: string): string {
-  // Check for math block lines and render them with KaTeX in display mode
-  if (html.includes('class="math-block-line"')) {
-    return html.replace(
-      /<span class="math-block-line">([\s\S]*?)<\/span>/g,
-      (match, content) => {
-        try {
-          // Decode HTML entities
-          const tempDiv = document.createElement('div');
-          tempDiv.innerHTML = content;
-          const latex = tempDiv.textContent || tempDiv.innerText || '';
+export async function openDocument(
+  docId: string,
+  lines: string[],
+  editingLine: number | null
+): Promise<string[]> {
+  documentId = docId;
+  documentLines = [...lines];
+  documentEditingLine = editingLine;
 
-          const rendered = katex.renderToString(latex.trim(), {
-            displayMode: true,
-            throwOnError: false,
-          });
-          return `<span class="math-block-line">${rendered}</span>`;
-        } catch (e) {
-          console.error("Error rendering LaTeX:", e);
-          return match;
-        }
-      }
-    );
+  try {
+    const results = await invoke<LineRenderResult[]>("open_document", {
+      docId,
+      lines,
+      editingLine,
+    });
+    documentHtml = results.map((result) => convertImagePaths(result.html));
+  } catch (error) {
+    console.error("Error opening document:", error);
+    documentHtml = lines.map((line) => escapeHtml(line));
   }
 
-  // Quick check: if no $ symbol, skip LaTeX processing entirely
-  if (!html.includes("$")) {
-    return html;
-  }
-  return renderLatex(html);
+  return documentHtml;
 }
 
 /**
- * Convert a single markdown line to HTML using the Rust backend
- * @param line - The markdown line to render
- * @param isEditing - Whether the line is being edited
- * @param lineIndex - Optional line index for context
- * @param allLines - Optional array of all lines for context
- * @returns Rendered HTML string
+ * Close a document session and release its cached render state
+ * @param docId - Session id (the tab id)
  */
-export async function renderMarkdownLine(
-  line: string,
-  isEditing: boolean,
-  lineIndex?: number,
-  allLines?: string[]
-): Promise<string> {
-  const safeLineIndex = lineIndex ?? 0;
-  const safeAllLines = allLines ?? [line];
-
-  const request: RenderRequest = {
-    line,
-    line_index: safeLineIndex,
-    all_lines: safeAllLines,
-    is_editing: isEditing,
-  };
+export async function closeDocument(docId: string) {
+  if (docId === documentId) {
+    documentId = null;
+    documentLines = [];
+    documentHtml = [];
+    documentEditingLine = null;
+  }
 
   try {
-    const result = await invoke<LineRenderResult>("render_markdown", {
-      request,
-    });
-
-    let html = result.html;
+    await invoke("close_document", { docId });
+  } catch (error) {
+    console.error("Error closing document:", error);
+  }
+}
 
-    // Convert image paths to Tauri asset URLs
-    html = convertImagePaths(html);
+/**
+ * Find the single edit that turns the old lines into the new ones
+ * (unchanged prefix and suffix are left out)
+ */
+function diffLines(oldLines: string[], newLines: string[]): LineEdit[] {
+  let start = 0;
+  while (
+    start < oldLines.length &&
+    start < newLines.length &&
+    oldLines[start] === newLines[start]
+  ) {
+    start++;
+  }
 
-    // Only render LaTeX when NOT editing to preserve the original $ markers
-    if (isEditing) {
-      return html;
-    }
+  let oldEnd = oldLines.length;
+  let newEnd = newLines.length;
+  while (oldEnd > start && newEnd > start && oldLines[oldEnd - 1] === newLines[newEnd - 1]) {
+    oldEnd--;
+    newEnd--;
+  }
 
-    return renderLatexInHtml(html);
-  } catch (error) {
-    console.error("Error rendering markdown:", error);
-    return escapeHtml(line);
+  if (start === oldEnd && start === newEnd) {
+    return [];
   }
+
+  return [{ start, delete_count: oldEnd - start, insert: newLines.slice(start, newEnd) }];
 }
 
 /**
- * Batch render multiple markdown lines for better performance
- * @param requests - Array of render requests
- * @returns Array of render results
+ * Send the edits made in the editor since the last sync to the backend and
+ * apply the lines whose HTML changed
+ * @param editingLine - Line shown as raw markdown, if any
+ * @param refresh - Lines whose DOM was modified directly and must be redrawn
  */
-export async function renderMarkdownBatch(
-  requests: RenderRequest[]
-): Promise<LineRenderResult[]> {
-  try {
-    const results = await invoke<LineRenderResult[]>("render_markdown_batch", {
-      requests,
-    });
+export async function syncDocument(editingLine: number | null, refresh: number[] = []) {
+  const lines = getAllLines();
+  let redraw = [...refresh];
+
+  if (documentId === null) {
+    await openDocument(SCRATCH_DOCUMENT_ID, lines, editingLine);
+    redraw = lines.map((_, i) => i);
+  } else {
+    const edits = diffLines(documentLines, lines);
+    if (edits.length === 0 && editingLine === documentEditingLine && refresh.length === 0) {
+      return;
+    }
 
-    // Post-process all results to convert image paths and add LaTeX rendering
-    return results.map((result: LineRenderResult, index: number) => {
-      let html = convertImagePaths(result.html);
+    // Record the new content before awaiting so overlapping syncs diff correctly
+    const docId = documentId;
+    documentLines = lines;
+    documentEditingLine = editingLine;
+    for (const edit of edits) {
+      documentHtml.splice(edit.start, edit.delete_count, ...edit.insert.map(() => ""));
+      for (let i = 0; i < edit.insert.length; i++) {
+        redraw.push(edit.start + i);
+      }
+    }
 
-      // Only for non-editing lines, also render LaTeX
-      if (!requests[index].is_editing) {
-        html = renderLatexInHtml(html);
+    try {
+      const update = await invoke<DocumentUpdate>("update_document", {
+        docId,
+        edits,
+        editingLine,
+      });
+      for (const changed of update.changed) {
+        documentHtml[changed.line_index] = convertImagePaths(changed.result.html);
+        redraw.push(changed.line_index);
       }
+    } catch (error) {
+      // The session is out of step with the editor: start it over
+      console.error("Error updating document:", error);
+      await openDocument(docId, lines, editingLine);
+      redraw = lines.map((_, i) => i);
+    }
+  }
 
-      return {
-        ...result,
-        html,
-      };
-    });
-  } catch (error) {
-    console.error("Error batch rendering markdown:", error);
-    return requests.map((req) => ({
-      html: escapeHtml(req.line),
-      is_code_block_boundary: false,
-    }));
+  for (const index of redraw) {
+    const lineDiv = editor.childNodes[index] as HTMLElement | undefined;
+    if (lineDiv && documentHtml[index] !== undefined) {
+      lineDiv.innerHTML = documentHtml[index];
+    }
   }
 }
 
@@ -239,33 +226,26 @@ export function getEditorContent(): string {
 }
 
 /**
- * Set editor content from plain text
+ * Set editor content from plain text and open it as a document session
  * @param text - Text content to set
+ * @param docId - Session id (the tab id)
  */
-export async function setEditorContent(text: string) {
+export async function setEditorContent(text: string, docId: string) {
   const lines = text.split(/\r?\n/).map((line: string) => line.trimEnd());
   editor.innerHTML = "";
 
-  // Create requests for batch rendering
-  const requests: RenderRequest[] = lines.map((line, index) => ({
-    line,
-    line_index: index,
-    all_lines: lines,
-    is_editing: false,
-  }));
-
-  // Batch render all lines
-  const results = await renderMarkdownBatch(requests);
+  // Render all lines once; later edits only send the lines that changed
+  const html = await openDocument(docId, lines, null);
 
   // Use DocumentFragment for efficient DOM operations (single reflow)
   const fragment = document.createDocumentFragment();
 
-  results.forEach((result, index) => {
+  html.forEach((lineHtml, index) => {
     const lineDiv = document.createElement("div");
     lineDiv.className = "editor-line";
     lineDiv.setAttribute("data-raw", lines[index]);
     lineDiv.setAttribute("data-line", String(index));
-    lineDiv.innerHTML = result.html;
+    lineDiv.innerHTML = lineHtml;
     fragment.appendChild(lineDiv);
   });
 
@@ -277,29 +257,14 @@ export async function setEditorContent(text: string) {
  * Render all lines in the editor
  */
 export async function renderAllLines(currentLine: number | null, editMode: boolean) {
-  const allLines = getAllLines();
-
-  // Create requests for batch rendering
-  const requests: RenderRequest[] = allLines.map((line, i) => ({
-    line,
-    line_index: i,
-    all_lines: allLines,
-    is_editing: i === currentLine && editMode,
-  }));
+  const editingLine = editMode ? currentLine : null;
 
-  // Batch render all lines
-  const results = await renderMarkdownBatch(requests);
+  // Redraw every line, since search highlights may have been drawn into them
+  await syncDocument(editingLine, Array.from(editor.childNodes, (_, i) => i));
 
-  // Update DOM
   for (let i = 0; i < editor.childNodes.length; i++) {
     const lineDiv = editor.childNodes[i] as HTMLElement;
-    const isCurrentLine = i === currentLine && editMode;
-
-    if (results[i]) {
-      lineDiv.innerHTML = results[i].html;
-    }
-
-    if (isCurrentLine) {
+    if (i === editingLine) {
       lineDiv.classList.add("editing");
     } else {
       lineDiv.classList.remove("editing");
@@ -5,7 +5,7 @@
 
 import { editor } from "../core/dom";
 import { state } from "../core/state";
-import { renderMarkdownLine, renderMarkdownBatch, getAllLines, getEditorContent } from "./rendering";
+import { syncDocument, getEditorContent } from "./rendering";
 import { updateStatistics } from "../ui/ui";
 import { markCurrentTabDirty, updateCurrentTabContent } from "../tabs/tabs";
 import { getCurrentLineNumber } from "../ui/ui";
@@ -41,9 +41,7 @@ export async function handleTextPaste(pastedText: string) {
     currentLine.setAttribute("data-raw", newText);
 
     // Re-render the line
-    const allLines = getAllLines();
-    const html = await renderMarkdownLine(newText, true, currentLineNum, allLines);
-    currentLine.innerHTML = html;
+    await syncDocument(currentLineNum, [currentLineNum]);
     currentLine.classList.add("editing");
 
     // Position cursor after pasted text
@@ -63,7 +61,6 @@ export async function handleTextPaste(pastedText: string) {
     // Update current line with first pasted line
     const firstLineText = beforeCursor + pastedLines[0];
     currentLine.setAttribute("data-raw", firstLineText);
-    currentLine.innerHTML = await renderMarkdownLine(firstLineText, false);
     currentLine.classList.remove("editing");
 
     // Create new lines for remaining pasted lines
@@ -102,38 +99,8 @@ export async function handleTextPaste(pastedText: string) {
       line.setAttribute("data-line", String(i));
     }
 
-    // Re-render all affected lines
-    const allLines = getAllLines();
-    const requests: any[] = [];
-    for (let i = currentLineNum; i < editor.childNodes.length; i++) {
-      const lineDiv = editor.childNodes[i] as HTMLElement;
-      const rawText = lineDiv.getAttribute("data-raw") || "";
-      const isEditing = i === currentLineNum + numNewLines;
-      requests.push({
-        line: rawText,
-        line_index: i,
-        all_lines: allLines,
-        is_editing: isEditing,
-      });
-    }
-
-    const results = await renderMarkdownBatch(requests);
-    let resultIndex = 0;
-    for (let i = currentLineNum; i < editor.childNodes.length; i++) {
-      const lineDiv = editor.childNodes[i] as HTMLElement;
-      const isEditing = i === currentLineNum + numNewLines;
-
-      if (results[resultIndex]) {
-        lineDiv.innerHTML = results[resultIndex].html;
-      }
-
-      if (isEditing) {
-        lineDiv.classList.add("editing");
-      } else {
-        lineDiv.classList.remove("editing");
-      }
-      resultIndex++;
-    }
+    // Re-render all affected lines (the session sends back only lines whose HTML changed)
+    await syncDocument(currentLineNum + numNewLines);
 
     // Position cursor at end of last pasted line (before afterCursor text)
     const lastPastedLineNum = currentLineNum + numNewLines;
@@ -0,0 +1,61 @@
+/**
+ * Import module
+ * Converts HTML, Word, Evernote and Notion exports into notes in the workspace
+ */
+
+import { open } from "@tauri-apps/plugin-dialog";
+import { invoke } from "@tauri-apps/api/core";
+import { state } from "../core/state";
+import { refreshFileTree } from "../file-tree/file-tree-core";
+
+interface ImportedNote {
+  source: string;
+  path: string | null;
+  warnings: string[];
+  error: string | null;
+}
+
+/**
+ * Pick files to import and convert them into notes in the open folder
+ */
+export async function importFiles(): Promise<void> {
+  try {
+    if (!state.currentFolder) {
+      alert("Please open a folder first before importing.");
+      return;
+    }
+
+    const selected = await open({
+      multiple: true,
+      filters: [{
+        name: "Documents",
+        extensions: ["html", "htm", "docx", "enex", "zip"]
+      }]
+    });
+
+    if (!selected || selected.length === 0) {
+      return; // User cancelled
+    }
+
+    const results = await invoke<ImportedNote[]>("import_files", {
+      paths: selected,
+      folderPath: state.currentFolder,
+    });
+    await refreshFileTree();
+
+    const imported = results.filter((result) => result.path).length;
+    const problems = results.flatMap((result) => [
+      ...(result.error ? [`${result.source}: ${result.error}`] : []),
+      ...result.warnings.map((warning) => `${result.source}: ${warning}`),
+    ]);
+
+    if (problems.length > 0) {
+      alert(`Imported ${imported} note(s) with warnings:\n${problems.join("\n")}`);
+    } else {
+      alert(`Successfully imported ${imported} note(s)`);
+    }
+  } catch (error) {
+    console.error("Error importing files:", error);
+    alert(`Failed to import: ${error}`);
+  }
+}
@@ -4,32 +4,91 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type Event } from '@tauri-apps/api/event';
 import type { FileSearchResult, SearchOptions } from './search-state';
 import { setMultiFileResults } from './search-state';
 import { state } from '../core/state';
 
+interface SearchResultEvent {
+  searchId: number;
+  rank: number;
+  result: FileSearchResult;
+}
+
+export interface SearchProgress {
+  searchId: number;
+  scanned: number;
+  total: number;
+  done: boolean;
+  cancelled: boolean;
+}
+
 /**
  * Search across all files in the current folder
+ *
+ * Results stream in from the backend while it searches; `onUpdate` is called
+ * with the results so far (best matches first) and the latest progress.
+ * Starting a new search cancels the previous one, which then resolves with
+ * whatever it had found.
  */
-export async function searchInFolder(query: string, options: SearchOptions): Promise<FileSearchResult[]> {
+export async function searchInFolder(
+  query: string,
+  options: SearchOptions,
+  onUpdate?: (results: FileSearchResult[], progress: SearchProgress | null) => void,
+): Promise<FileSearchResult[]> {
   if (!query || !state.currentFolder) {
     setMultiFileResults([]);
     return [];
   }
 
+  const ranked = new Map<number, FileSearchResult>();
+  const sortedResults = () =>
+    [...ranked.entries()].sort((a, b) => a[0] - b[0]).map(([, result]) => result);
+
+  // Events can arrive before the search id is returned, so they are queued
+  let searchId: number | null = null;
+  const queued: Array<SearchResultEvent | SearchProgress> = [];
+  let finish: () => void = () => {};
+  const finished = new Promise<void>((resolve) => (finish = resolve));
+
+  const handle = (payload: SearchResultEvent | SearchProgress) => {
+    if (searchId === null) {
+      queued.push(payload);
+      return;
+    }
+    if (payload.searchId !== searchId) return;
+
+    if ('result' in payload) {
+      ranked.set(payload.rank, payload.result);
+      onUpdate?.(sortedResults(), null);
+    } else {
+      onUpdate?.(sortedResults(), payload);
+      if (payload.done) finish();
+    }
+  };
+
+  const unlistenResult = await listen('search-result', (event: Event<SearchResultEvent>) => handle(event.payload));
+  const unlistenProgress = await listen('search-progress', (event: Event<SearchProgress>) => handle(event.payload));
+
   try {
-    const results = await invoke<FileSearchResult[]>('search_in_directory', {
+    searchId = await invoke<number>('start_directory_search', {
       query,
       dirPath: state.currentFolder,
       options,
     });
+    queued.splice(0).forEach(handle);
+    await finished;
 
+    const results = sortedResults();
     setMultiFileResults(results);
     return results;
   } catch (error) {
     console.error('Multi-file search failed:', error);
     setMultiFileResults([]);
     return [];
+  } finally {
+    unlistenResult();
+    unlistenProgress();
   }
 }
 
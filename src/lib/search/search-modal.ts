@@ -15,9 +15,11 @@ let replaceInput: HTMLInputElement | null = null;
 let caseSensitiveCheckbox: HTMLInputElement | null = null;
 let wholeWordCheckbox: HTMLInputElement | null = null;
 let regexCheckbox: HTMLInputElement | null = null;
+let queryModeCheckbox: HTMLInputElement | null = null;
 let searchInAllFilesCheckbox: HTMLInputElement | null = null;
 let matchCountSpan: HTMLElement | null = null;
 let multiFileResultsDiv: HTMLElement | null = null;
+let latestFolderSearch = 0;
 
 /**
  * Create the search modal UI
@@ -73,6 +75,10 @@ export function createSearchModal(): void {
         <input type="checkbox" class="search-regex" />
         <span>Regex</span>
       </label>
+      <label style="display: flex; align-items: center; gap: 4px; font-size: 12px; cursor: pointer;" title="tag:#meeting path:projects/ &quot;exact phrase&quot; -draft modified:>2026-01-01 heading:Decisions">
+        <input type="checkbox" class="search-query-mode" />
+        <span>Query</span>
+      </label>
       <label style="display: flex; align-items: center; gap: 4px; font-size: 12px; cursor: pointer;">
         <input type="checkbox" class="search-all-files" />
         <span>All files</span>
@@ -100,6 +106,7 @@ export function createSearchModal(): void {
   caseSensitiveCheckbox = searchModal.querySelector('.search-case-sensitive');
   wholeWordCheckbox = searchModal.querySelector('.search-whole-word');
   regexCheckbox = searchModal.querySelector('.search-regex');
+  queryModeCheckbox = searchModal.querySelector('.search-query-mode');
   searchInAllFilesCheckbox = searchModal.querySelector('.search-all-files');
   matchCountSpan = searchModal.querySelector('.search-match-count');
   multiFileResultsDiv = searchModal.querySelector('.search-multi-file-results');
@@ -168,6 +175,11 @@ function attachEventListeners(): void {
     performSearch();
   });
 
+  queryModeCheckbox?.addEventListener('change', () => {
+    updateSearchOptions({ useQuery: queryModeCheckbox!.checked });
+    performSearch();
+  });
+
   searchInAllFilesCheckbox?.addEventListener('change', () => {
     const searchInAllFiles = searchInAllFilesCheckbox!.checked;
     updateSearchState({ searchInAllFiles });
@@ -210,8 +222,12 @@ async function performSearch(): Promise<void> {
 
   if (searchState.searchInAllFiles) {
     // Multi-file search
-    const results = await searchInFolder(searchState.query, searchState.options);
-    displayMultiFileResults(results);
+    // A newer search cancels this one; don't let its results replace the newer ones
+    const searchToken = ++latestFolderSearch;
+    const results = await searchInFolder(searchState.query, searchState.options, (partial) => {
+      if (searchToken === latestFolderSearch) displayMultiFileResults(partial);
+    });
+    if (searchToken === latestFolderSearch) displayMultiFileResults(results);
   } else {
     // Current file search
     await searchInCurrentFile(searchState.query, searchState.options);
@@ -7,6 +7,14 @@ export interface SearchOptions {
   caseSensitive: boolean;
   wholeWord: boolean;
   useRegex: boolean;
+  // Query language with tag:, path:, heading:, modified: and property filters
+  useQuery: boolean;
+  // Directory search scope; unset options use the folder's config.json defaults
+  include?: string[];
+  exclude?: string[];
+  extensions?: string[];
+  maxFileSize?: number;
+  respectIgnoreFiles?: boolean;
 }
 
 export interface SearchMatch {
@@ -52,6 +60,7 @@ let searchState: SearchState = {
     caseSensitive: false,
     wholeWord: false,
     useRegex: false,
+    useQuery: false,
   },
   currentFileMatches: [],
   multiFileResults: [],
@@ -87,6 +96,7 @@ export function resetSearchState(): void {
       caseSensitive: false,
       wholeWord: false,
       useRegex: false,
+      useQuery: false,
     },
     currentFileMatches: [],
     multiFileResults: [],
@@ -120,7 +120,8 @@ export async function reinitializeSettingsForFolder(): Promise<void> {
  */
 export async function saveSettings(customSettings?: any): Promise<void> {
   try {
-    // Get current config to preserve custom_settings if not provided
+    // Get current config to preserve custom_settings if not provided, and the
+    // sanitize and search settings that are only edited in config.json
     const currentConfig = await getSettings();
 
     await invoke("update_config", {
@@ -131,7 +132,9 @@ export async function saveSettings(customSettings?: any): Promise<void> {
         confirm_file_delete: state.confirmFileDelete,
         confirm_folder_delete: state.confirmFolderDelete,
         keybinds: state.keybinds,
-        custom_settings: customSettings !== undefined ? customSettings : currentConfig.custom_settings || {}
+        custom_settings: customSettings !== undefined ? customSettings : currentConfig.custom_settings || {},
+        sanitize: currentConfig.sanitize,
+        search: currentConfig.search
       }
     });
   } catch (error) {
@@ -19,6 +19,7 @@ const settingsStatusBarToggle = document.getElementById("settings-status-bar-tog
 const settingsImageFolderInput = document.getElementById("settings-image-folder") as HTMLInputElement;
 const settingsConfirmFileDeleteToggle = document.getElementById("settings-confirm-file-delete") as HTMLInputElement;
 const settingsConfirmFolderDeleteToggle = document.getElementById("settings-confirm-folder-delete") as HTMLInputElement;
+const settingsPdfTocToggle = document.getElementById("settings-pdf-toc") as HTMLInputElement;
 const keybindsList = document.getElementById("keybinds-list") as HTMLElement;
 
 /**
@@ -106,6 +107,13 @@ export function setupSettingsEventListeners(): void {
     });
   }
 
+  // PDF table of contents toggle
+  if (settingsPdfTocToggle) {
+    settingsPdfTocToggle.addEventListener("change", async () => {
+      await updateCustomSetting("pdfTableOfContents", settingsPdfTocToggle.checked);
+    });
+  }
+
   // Escape key to close
   document.addEventListener("keydown", (e) => {
     if (e.key === "Escape" && !settingsModal.classList.contains("hidden")) {
@@ -129,12 +137,15 @@ export async function openSettings(): Promise<void> {
   // Update status bar toggle
   settingsStatusBarToggle.checked = state.statusBarVisible;
 
-  // Load and update image folder setting
+  // Load and update image folder and export settings
+  const config = await getSettings();
   if (settingsImageFolderInput) {
-    const config = await getSettings();
     const imageSaveFolder = config.custom_settings?.imageSaveFolder || ".";
     settingsImageFolderInput.value = imageSaveFolder;
   }
+  if (settingsPdfTocToggle) {
+    settingsPdfTocToggle.checked = config.custom_settings?.pdfTableOfContents === true;
+  }
 
   // Update delete confirmation toggles
   if (settingsConfirmFileDeleteToggle) {
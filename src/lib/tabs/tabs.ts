@@ -4,7 +4,7 @@
 
 import { state, markDirty, updateTitle } from "../core/state";
 import { editor } from "../core/dom";
-import { setEditorContent } from "../editor/rendering";
+import { setEditorContent, closeDocument } from "../editor/rendering";
 import { saveFile } from "../file-operations";
 import { createNewWindow } from "../ui/window-controls";
 import { getFilename } from "../utils/path-utils";
@@ -62,7 +62,7 @@ async function loadTabState(tab: Tab): Promise<void> {
 
   editor.blur();
   editor.innerHTML = "";
-  await setEditorContent(tab.content);
+  await setEditorContent(tab.content, tab.id);
   updateTitle();
   updateTabBar();
 }
@@ -172,8 +172,9 @@ export async function closeTab(index: number): Promise<void> {
     }
   }
 
-  // Remove the tab
+  // Remove the tab and release its render session
   tabs.splice(index, 1);
+  await closeDocument(tab.id);
 
   // Update active tab index
   if (tabs.length === 0) {
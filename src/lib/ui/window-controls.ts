@@ -11,6 +11,7 @@ import { openFolder, toggleSidebar } from "../file-tree/file-tree";
 import { switchTheme, importTheme, getAvailableThemes } from "../settings/theme";
 import { state } from "../core/state";
 import { exportToHTML, exportToPDF } from "../export/export";
+import { importFiles } from "../import/import";
 
 /**
  * Get the main application window
@@ -221,6 +222,13 @@ export function initWindowControls() {
       fileMenu?.classList.add("hidden");
     });
 
+  document
+    .getElementById("file-menu-import")
+    ?.addEventListener("click", async () => {
+      await importFiles();
+      fileMenu?.classList.add("hidden");
+    });
+
   // Export menu items
   document
     .getElementById("file-menu-export-html")